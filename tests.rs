@@ -0,0 +1,256 @@
+// Tests for the engine library. Build the library first, then run them with
+//   $ rustc -L . --test tests.rs
+//   $ ./tests
+
+extern crate rand;
+extern crate tetris_engine;
+
+use std::vec_ng::Vec;
+use tetris_engine::{board, piece_getter, scoring, gravity, engine};
+use tetris_engine::pieces;
+use tetris_engine::pieces::{Block, Piece, Red, Blue, White, PieceType, I, J, L, O, S, T, Z};
+use tetris_engine::piece_getter::PieceGetter;
+use tetris_engine::scoring::Scoring;
+use tetris_engine::engine::{GameEngine, RotateClockwise, ShiftLeft, ShiftRight, HardDrop, Fall};
+use rand::{Rng, SeedableRng, Isaac64Rng};
+
+fn fill_row(board: &mut board::Board, row: i8) {
+  for col in range(1, 11i8) {
+    board.set(Block{row: row, column: col, color: Red, ty: None});
+  }
+}
+
+#[test]
+fn new_board_is_empty() {
+  let board = board::new();
+  assert_eq!(board.block_count(), 0);
+  assert!(board.rows().all(|row| row.iter().all(|cell| cell.is_none())));
+}
+
+#[test]
+fn cells_outside_the_board_are_empty() {
+  let mut board = board::new();
+  fill_row(&mut board, 1);
+  fill_row(&mut board, 20);
+  assert!(board.get(0, 1).is_none());
+  assert!(board.get(21, 1).is_none());
+  assert!(board.get(1, 0).is_none());
+  assert!(board.get(1, 11).is_none());
+  assert!(!board.has_block(0, 5));
+  assert!(!board.has_block(21, 5));
+  board.remove(21, 5);
+  assert_eq!(board.block_count(), 20);
+}
+
+#[test]
+#[should_fail]
+fn setting_a_block_outside_the_board_fails() {
+  let mut board = board::new();
+  board.set(Block{row: 0, column: 1, color: Red, ty: None});
+}
+
+#[test]
+fn set_get_and_remove() {
+  let mut board = board::new();
+  board.set(Block{row: 5, column: 3, color: Blue, ty: None});
+  let block = board.get(5, 3).unwrap();
+  assert_eq!((block.row, block.column), (5, 3));
+  assert!(block.color == Blue);
+  assert!(board.has_block(5, 3));
+  board.remove(5, 3);
+  assert!(!board.has_block(5, 3));
+}
+
+#[test]
+fn rows_go_from_top_to_bottom() {
+  let mut board = board::new();
+  board.set(Block{row: 20, column: 10, color: White, ty: None});
+  let rows: ~[&[Option<tetris_engine::pieces::Color>]] = board.rows().collect();
+  assert_eq!(rows.len(), 20);
+  assert!(rows[19][9].is_some());
+  assert!(rows[0].iter().all(|cell| cell.is_none()));
+}
+
+#[test]
+fn full_rows() {
+  let mut board = board::new();
+  fill_row(&mut board, 18);
+  fill_row(&mut board, 20);
+  board.set(Block{row: 19, column: 1, color: Red, ty: None});
+  assert!(board.is_row_full(18));
+  assert!(!board.is_row_full(19));
+  assert!(!board.is_row_full(0));
+  assert!(!board.is_row_full(21));
+  assert_eq!(board.full_rows().as_slice(), &[18i8, 20]);
+}
+
+#[test]
+fn clearing_rows_drops_the_rows_above() {
+  let mut board = board::new();
+  board.set(Block{row: 17, column: 2, color: Blue, ty: None});
+  fill_row(&mut board, 18);
+  board.set(Block{row: 19, column: 4, color: Blue, ty: None});
+  fill_row(&mut board, 20);
+  assert_eq!(board.clear_full_rows(), 2);
+  assert_eq!(board.block_count(), 2);
+  assert!(board.has_block(19, 2));
+  assert!(board.has_block(20, 4));
+  assert_eq!(board.full_rows().len(), 0);
+}
+
+#[test]
+fn pushing_up_reports_blocks_pushed_off_the_top() {
+  let mut board = board::new();
+  board.set(Block{row: 20, column: 1, color: Red, ty: None});
+  assert!(!board.push_up());
+  assert!(board.has_block(19, 1));
+  assert!(!board.has_block(20, 1));
+  
+  board.set(Block{row: 1, column: 5, color: Red, ty: None});
+  assert!(board.push_up());
+  assert!(!board.has_block(1, 5));
+}
+
+// Simulated games: the engine is driven by a script with no display and no clock, so the
+// rules can be checked move by move. In a script < and > shift the falling piece, r rotates
+// it, d drops it to the bottom and s is a drop step (spaces are ignored).
+fn play(pieceGetter: ~PieceGetter, script: &str) -> GameEngine {
+  let levels = scoring::default_levels();
+  let gravity = gravity::new(gravity::LevelTable, levels.as_slice());
+  let mut game = engine::new(pieceGetter, scoring::new(scoring::Standard, levels, scoring::defaultBonusDecay), gravity);
+  for c in script.chars() {
+    match c {
+      '<' => { game.apply_action(ShiftLeft); }
+      '>' => { game.apply_action(ShiftRight); }
+      'r' => { game.apply_action(RotateClockwise); }
+      'd' => { game.apply_action(HardDrop); }
+      's' => { game.step(); }
+      ' ' => (),
+      _   => fail!("unknown move {} in the script", c)
+    }
+  }
+  game
+}
+
+// the set blocks of a row, each as its piece's letter, or . for an empty cell
+fn row_text(game: &GameEngine, row: i8) -> ~str {
+  range(1, board::width + 1).map(|col| match game.board.get(row, col) {
+    Some(Block{ty: Some(ty), ..}) => "IJLOSTZ".char_at(ty as uint),
+    Some(_)                       => '#',
+    None                          => '.'
+  }).collect()
+}
+
+fn scripted(types: &[PieceType]) -> ~PieceGetter {
+  piece_getter::scripted(Vec::from_slice(types))
+}
+
+#[test]
+fn simulated_single_clear() {
+  // two flat I pieces at the sides and an O in the middle fill the bottom row
+  let game = play(scripted(&[I, I, O]), "<<<ds >>>ds ds s");
+  assert_eq!(row_text(&game, 20), ~"....OO....");
+  assert_eq!(row_text(&game, 19), ~"..........");
+  let score = game.scoring.get_score();
+  assert_eq!(score.lines, 1);
+  assert_eq!(score.score, 10);
+  assert_eq!(score.bonus, 2);
+  assert!(game.state == Fall);
+}
+
+#[test]
+fn simulated_tetris() {
+  // eight O pieces fill columns 1 to 8 of the bottom four rows, then two upright I pieces
+  // fill columns 9 and 10
+  let layer = "<<<<ds <<ds ds >>ds ";
+  let script = format!("{}{}r>>>>ds", layer, layer);
+  let game = play(scripted(&[O, O, O, O, O, O, O, O, I, I]), script.as_slice());
+  assert_eq!(row_text(&game, 17), ~"OOOOOOOOI.");
+  assert_eq!(row_text(&game, 20), ~"OOOOOOOOI.");
+  
+  let game = play(scripted(&[O, O, O, O, O, O, O, O, I, I]), format!("{} r>>>>>ds s", script).as_slice());
+  for row in range(1, board::height + 1) {
+    assert_eq!(row_text(&game, row), ~"..........");
+  }
+  let score = game.scoring.get_score();
+  assert_eq!(score.lines, 4);
+  assert_eq!(score.tetrises, 1);
+  assert_eq!(score.score, 80);
+  assert_eq!(score.bonus, 8);
+}
+
+#[test]
+fn simulated_games_with_the_same_seed_match() {
+  let script = "<<ds ds >>>ds rds r<ds >ds rr>>>>ds <<<<ds s ds ds";
+  let first = play(piece_getter::new(42), script);
+  let second = play(piece_getter::new(42), script);
+  for row in range(1, board::height + 1) {
+    assert_eq!(row_text(&first, row), row_text(&second, row));
+  }
+  assert_eq!(first.scoring.get_score().score, second.scoring.get_score().score);
+  assert_eq!(first.pieces, second.pieces);
+}
+
+// Rotation checks, on pieces of every type turned and moved to random places, so a typo in
+// the rotation table shows up
+static allTypes: [PieceType, ..7] = [I, J, L, O, S, T, Z];
+
+static rotationTrials: uint = 1000;
+
+fn random_piece(rng: &mut Isaac64Rng) -> Piece {
+  let mut piece = pieces::new(allTypes[rng.gen_range(0u, 7)]);
+  for _ in range(0, rng.gen_range(0u, 4)) {
+    piece = pieces::rotate_clockwise(&piece);
+  }
+  pieces::translate(&piece, rng.gen_range(0i8, 16), rng.gen_range(-3i8, 4))
+}
+
+fn same_cells(a: &Piece, b: &Piece) -> bool {
+  a.blocks.iter().zip(b.blocks.iter()).all(|(x, y)| x.row == y.row && x.column == y.column)
+}
+
+#[test]
+fn four_clockwise_rotations_are_a_full_turn() {
+  let mut rng: Isaac64Rng = SeedableRng::from_seed(&[2157u64]);
+  for _ in range(0, rotationTrials) {
+    let piece = random_piece(&mut rng);
+    let mut turned = piece;
+    for _ in range(0, 4) {
+      turned = pieces::rotate_clockwise(&turned);
+    }
+    assert!(same_cells(&piece, &turned), "{:?} doesn't come back after four turns", piece.ty);
+    assert_eq!(turned.rotate, piece.rotate);
+  }
+}
+
+#[test]
+fn counter_clockwise_undoes_clockwise() {
+  let mut rng: Isaac64Rng = SeedableRng::from_seed(&[2157u64]);
+  for _ in range(0, rotationTrials) {
+    let piece = random_piece(&mut rng);
+    let there = pieces::rotate_clockwise(&piece);
+    let back = pieces::rotate_counter_clockwise(&there);
+    assert!(same_cells(&piece, &back), "{:?} rotation {} isn't undone", piece.ty, piece.rotate);
+    assert!(same_cells(&piece, &pieces::rotate_clockwise(&pieces::rotate_counter_clockwise(&piece))));
+  }
+}
+
+#[test]
+fn rotations_keep_the_color_and_four_separate_blocks() {
+  let mut rng: Isaac64Rng = SeedableRng::from_seed(&[2157u64]);
+  for _ in range(0, rotationTrials) {
+    let piece = random_piece(&mut rng);
+    let color = pieces::new(piece.ty).blocks[0].color;
+    for turned in [pieces::rotate_clockwise(&piece), pieces::rotate_counter_clockwise(&piece)].iter() {
+      assert!(turned.ty == piece.ty);
+      for (i, block) in turned.blocks.iter().enumerate() {
+        assert!(block.color == color);
+        assert!(block.ty == Some(piece.ty));
+        for other in turned.blocks.slice_from(i + 1).iter() {
+          assert!(block.row != other.row || block.column != other.column,
+                  "{:?} rotation {} has two blocks in one cell", turned.ty, turned.rotate);
+        }
+      }
+    }
+  }
+}
@@ -1,12 +1,17 @@
 extern crate rand;
 extern crate serialize;
 extern crate time;
+extern crate tetris_engine;
+
+use tetris_engine::{pieces, board, piece_getter, scoring, gravity, engine};
 
 use std::io::{print, println};
+use std::io::File;
 use std::os;
+use std::ascii::StrAsciiExt;
 
 mod terminal_control {
-  use std::libc::{c_int, c_uint, c_uchar};
+  use std::libc::{c_int, c_uint, c_uchar, c_ushort, c_ulong};
   
   // Linux specifc termios structure definition
   //
@@ -26,10 +31,78 @@ mod terminal_control {
     c_ospeed: c_uint,          // output speed
   }
 
+  #[allow(non_camel_case_types)]
+  struct winsize {
+    ws_row:    c_ushort,
+    ws_col:    c_ushort,
+    ws_xpixel: c_ushort,
+    ws_ypixel: c_ushort
+  }
+
   extern {
     fn tcgetattr(filedes: c_int, termptr: *mut termios) -> c_int;
     fn tcsetattr(filedes: c_int, opt: c_int, termptr: *termios) -> c_int;
     fn cfmakeraw(termptr: *mut termios);
+    fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> uint;
+    fn ioctl(filedes: c_int, request: c_ulong, ...) -> c_int;
+    fn isatty(filedes: c_int) -> c_int;
+  }
+  
+  // Linux's request number for the window size
+  static tiocgwinsz: c_ulong = 0x5413;
+  
+  // false when the game's output is going to a file or a pipe
+  pub fn stdout_is_terminal() -> bool {
+    unsafe { isatty(1) == 1 }
+  }
+  
+  // false when keys can't be read, because standard input is a file or a pipe
+  pub fn stdin_is_terminal() -> bool {
+    unsafe { isatty(0) == 1 }
+  }
+  
+  // the terminal's columns and rows, if standard input is a terminal
+  pub fn terminal_size() -> Option<(uint, uint)> {
+    let mut size = winsize{ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0};
+    let err = unsafe { ioctl(0, tiocgwinsz, &mut size as *mut winsize) };
+    if err != 0 || size.ws_col == 0 {
+      None
+    } else {
+      Some((size.ws_col as uint, size.ws_row as uint))
+    }
+  }
+  
+  // SIGHUP is sent when the terminal goes away (the window is closed, or an SSH connection drops)
+  static sighup: c_int = 1;
+  
+  static mut hungUp: bool = false;
+  
+  extern "C" fn on_hangup(_signum: c_int) {
+    unsafe {
+      hungUp = true;
+    }
+  }
+  
+  // Instead of being killed by SIGHUP, just remember that it happened. The signal also
+  // interrupts any poll() in progress, so the game loop gets a chance to check hung_up().
+  pub fn catch_hangup() {
+    unsafe {
+      signal(sighup, on_hangup);
+    }
+  }
+  
+  pub fn hung_up() -> bool {
+    unsafe {
+      hungUp
+    }
+  }
+  
+  // The terminal can't be written to any more, which is as good as it being gone, so the
+  // game carries on as if it hung up
+  pub fn lose_terminal() {
+    unsafe {
+      hungUp = true;
+    }
   }
 
   fn get_terminal_attr() -> (termios, c_int) {
@@ -94,17 +167,115 @@ mod terminal_control {
   }
 }
 
+mod trace {
+  use std::libc::{c_char, c_int, c_void, size_t, ssize_t};
+  use std::io::{IoResult, IoError};
+  use time;
+  
+  extern {
+    fn open(path: *c_char, flags: c_int, mode: c_int) -> c_int;
+    fn write(fd: c_int, buf: *c_void, count: size_t) -> ssize_t;
+    fn close(fd: c_int) -> c_int;
+  }
+  
+  static oWrOnly: c_int = 1;
+  static oCreat: c_int = 0x40;
+  static oTrunc: c_int = 0x200;
+  static logMode: c_int = 0x1A4;  // rw-r--r--
+  
+  // The debug log written with --log, to work out timing problems from a user's report.
+  // Every line starts with the milliseconds since the log was started. It's written
+  // straight to the file with no buffering, so nothing is lost if the game crashes.
+  // A Log is handed to everything that writes to it, and off writes nothing.
+  pub struct Log {
+    fd:      c_int,
+    startNs: u64
+  }
+  
+  pub static off: Log = Log{fd: -1, startNs: 0};
+  
+  // the log is closed when this is dropped
+  pub struct LogFile {
+    log: Log
+  }
+  
+  pub fn start(path: &Path) -> IoResult<LogFile> {
+    let fd = path.with_c_str(|p| unsafe { open(p, oWrOnly | oCreat | oTrunc, logMode) });
+    if fd < 0 {
+      return Err(IoError::last_error());
+    }
+    Ok(LogFile{log: Log{fd: fd, startNs: time::precise_time_ns()}})
+  }
+  
+  impl Log {
+    pub fn is_on(&self) -> bool {
+      self.fd >= 0
+    }
+    
+    pub fn log(&self, message: &str) {
+      if !self.is_on() {
+        return;
+      }
+      let line = format!("{:10.3f} {}\n", (time::precise_time_ns() - self.startNs) as f64 / 1000000.0, message);
+      unsafe {
+        write(self.fd, line.as_ptr() as *c_void, line.len() as size_t);
+      }
+    }
+  }
+  
+  impl Drop for LogFile {
+    fn drop(&mut self) {
+      unsafe {
+        close(self.log.fd);
+      }
+    }
+  }
+}
+
 mod input_reader {
-  use std::libc::{c_int, c_short, c_long};
+  use std::libc::{c_int, c_short, c_long, EINTR};
   use std::cast::transmute;
+  use std::io::{IoResult, IoError};
+  use std::os;
+  use std::vec_ng::Vec;
+  use pieces::{PieceType, I, J, L, O, S, T, Z};
+  use time;
+  use trace::Log;
   
   pub enum PollResult {
     PollReady,
     PollTimeout,
+    PollInterrupted  // a signal arrived before there was any input
   }
   
   pub enum ReadResult {
-    Up, Down, Right, Left, Other
+    Up, Down, Right, Left,
+    FocusIn, FocusOut,  // the terminal gained or lost focus, once it's been asked to say (ESC [ I and ESC [ O)
+    Key(u8),  // any other single byte key
+    Other
+  }
+  
+  // What the player asked the game to do
+  #[deriving(Eq)]
+  pub enum Action {
+    Rotate, Drop, MoveRight, MoveLeft, Hint,
+    Stats,            // show or hide the statistics HUD
+    Spawn(PieceType), // sandbox: swap the falling piece for one of these
+    ClearBoard,       // sandbox: take every set block off the board
+    StepGame,         // stepping: move the game on by one drop step
+    DumpState,        // stepping: write the engine's state to the debug log
+    Pause,            // pause or resume the game, with the settings menu over the board
+    FocusLost,        // the terminal lost focus, so no one's looking at the game
+    FocusGained,      // the terminal has focus again
+    Quit
+  }
+  
+  // an action along with when it was read, from time::precise_time_ns(), and which
+  // player it's for (always 0 unless two people are playing)
+  pub struct ActionEvent {
+    action: Action,
+    timeNs: u64,
+    player: uint
   }
   
   #[allow(non_camel_case_types)]
@@ -131,6 +302,8 @@ mod input_reader {
         return PollReady
       } else if pr == 0 {
         return PollTimeout;
+      } else if os::errno() == EINTR as int {
+        return PollInterrupted;
       } else {
         fail!("error polling standard input");
       }
@@ -138,6 +311,14 @@ mod input_reader {
   }
   
   pub fn read_stdin() -> ReadResult {
+    match try_read_stdin() {
+      Ok(result) => result,
+      Err(_)     => fail!("error reading standard input")
+    }
+  }
+  
+  // read_stdin for callers that have something better to do than fail when the read does
+  pub fn try_read_stdin() -> IoResult<ReadResult> {
     unsafe {
       // Reading bytes into storage for an unsigned integer for easy comparison of
       // input byte sequence (we only care about arrow keys) to integer constants
@@ -161,1304 +342,11578 @@ mod input_reader {
       // first parameter is file descriptor number, 0 ==> standard input
       let numRead = read(0, bufAddr, 8);
       if numRead < 0 {
-        fail!("error reading standard input");
+        return Err(IoError::last_error());
       }
-      match buf {
+      Ok(match buf {
         0x415B1B => Up,
         0x425B1B => Down,
         0x435B1B => Right,
         0x445B1B => Left,
+        _ if numRead == 1 => Key(buf as u8),
         _        => Other
-      }
+      })
     }
-  }  
-}
-
-mod graphics {
-  use std::io::stdio;
-  use std::io::print;
-  use pieces::{Block, Black, Piece, O, S};
-  use scoring::Score;
+  }
   
-  fn csi() {
-    print!("{}[", '\x1B');
+  // Reads the terminal's answers to queries: everything that arrives on standard input until
+  // that many answers (each ended by BEL or ESC \) are in, or nothing comes for timeoutMillis
+  pub fn read_reply(timeoutMillis: c_int, answers: uint) -> Vec<u8> {
+    let mut reply = Vec::new();
+    let mut buf = [0u8, ..256];
+    let mut ended = 0;
+    while ended < answers {
+      match poll_stdin(timeoutMillis) {
+        PollReady => (),
+        _         => break
+      }
+      let numRead = unsafe { read(0, buf.as_mut_ptr(), buf.len() as u64) };
+      if numRead <= 0 {
+        break;
+      }
+      for &byte in buf.slice_to(numRead as uint).iter() {
+        if byte == 0x07 || (byte == 0x5C && reply.last() == Some(&0x1Bu8)) {
+          ended += 1;
+        }
+        reply.push(byte);
+      }
+    }
+    reply
   }
   
-  fn clear_terminal() {
-    csi();
-    print("2J");
+  // Reads everything waiting on standard input and splits it into keys, since more than one
+  // key can arrive in a single read when keys are pressed quickly (or by two people at once).
+  // Arrow keys and focus reports are 3 byte sequences like the ones described above, anything
+  // else is taken a byte at a time.
+  pub fn read_keys(log: Log) -> Vec<ReadResult> {
+    let mut buf = [0u8, ..64];
+    
+    // first parameter is file descriptor number, 0 ==> standard input
+    let numRead = unsafe { read(0, buf.as_mut_ptr(), buf.len() as u64) };
+    if numRead < 0 {
+      fail!("error reading standard input");
+    }
+    let bytes = buf.slice_to(numRead as uint);
+    if log.is_on() {
+      let hex: Vec<~str> = bytes.iter().map(|b| format!("{:02x}", *b)).collect();
+      log.log(format!("read {} bytes: {}", bytes.len(), hex.connect(" ")));
+    }
+    
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+      let sequence = if i + 2 < bytes.len() && bytes[i] == 0x1B && bytes[i + 1] == 0x5B {
+        match bytes[i + 2] {
+          0x41 => Some(Up),
+          0x42 => Some(Down),
+          0x43 => Some(Right),
+          0x44 => Some(Left),
+          0x49 => Some(FocusIn),
+          0x4F => Some(FocusOut),
+          _    => None
+        }
+      } else {
+        None
+      };
+      match sequence {
+        Some(key) => {
+          keys.push(key);
+          i += 3;
+        }
+        None => {
+          keys.push(Key(bytes[i]));
+          i += 1;
+        }
+      }
+    }
+    keys
   }
-
-  fn reset_graphics() {
-    csi();
-    print("0m");
+  
+  // Keys that can be used as well as the arrow keys, set in the config file. They're
+  // kept lower case, and match whichever case is pressed.
+  //
+  // In the sandbox, 1 to 7 pick the piece (in the order I, J, L, O, S, T, Z) and 0 clears
+  // the board, and when stepping through a game, n moves it on by a step and l writes its
+  // state to the log. Otherwise they quit, like any other key.
+  pub struct Keys {
+    rotate:    Option<u8>,
+    drop:      Option<u8>,
+    moveLeft:  Option<u8>,
+    moveRight: Option<u8>,
+    hint:      Option<u8>,
+    stats:     Option<u8>,
+    pause:     Option<u8>
   }
-
-  fn hide_cursor() {
-    csi();
-    print("?25l");
+  
+  pub static defaultKeys: Keys = Keys{rotate: None, drop: None, moveLeft: None, moveRight: None,
+                                      hint: Some(0x68), stats: Some(0x69), pause: Some(0x70)};  // h, i, p
+  
+  static sandboxPieces: [PieceType, ..7] = [I, J, L, O, S, T, Z];
+  
+  fn to_lower(byte: u8) -> u8 {
+    if byte >= 0x41 && byte <= 0x5A { byte + 0x20 } else { byte }
   }
   
-  fn show_cursor() {
-    csi();
-    print("?25h");
+  fn is_key(key: Option<u8>, byte: u8) -> bool {
+    key == Some(to_lower(byte))
   }
   
-  fn move_cursor(rowCol: (i8, i8)) {
-    let (row, col) = rowCol;
-    csi();
-    print!("{};{}H", row, col);
+  // Down is a hard drop rather than a soft drop that keeps going while the key is held. The
+  // terminal only sends key presses (repeated at the keyboard's own rate when held), never
+  // releases, so there's no telling when a key is let go.
+  pub fn read_actions(keys: &Keys, sandbox: bool, stepping: bool, log: Log) -> Vec<ActionEvent> {
+    let timeNs = time::precise_time_ns();
+    read_keys(log).iter().map(|key| {
+      ActionEvent{action: key_action(*key, keys, sandbox, stepping), timeNs: timeNs, player: 0}
+    }).collect()
   }
   
-  fn set_background_color(offset: u8) {
-    csi();
-    print!("{}m", 40 + offset);
+  // what a key read by read_keys asks the game to do
+  pub fn key_action(key: ReadResult, keys: &Keys, sandbox: bool, stepping: bool) -> Action {
+    match key {
+      Up    => Rotate,
+      Down  => Drop,
+      Right => MoveRight,
+      Left  => MoveLeft,
+      FocusIn  => FocusGained,
+      FocusOut => FocusLost,
+      Key(b) if is_key(keys.rotate, b)    => Rotate,
+      Key(b) if is_key(keys.drop, b)      => Drop,
+      Key(b) if is_key(keys.moveLeft, b)  => MoveLeft,
+      Key(b) if is_key(keys.moveRight, b) => MoveRight,
+      Key(b) if is_key(keys.hint, b)      => Hint,
+      Key(b) if is_key(keys.stats, b)     => Stats,
+      Key(b) if is_key(keys.pause, b)     => Pause,
+      Key(b) if sandbox && b >= 0x31 && b <= 0x37 => Spawn(sandboxPieces[b - 0x31]),  // 1 to 7
+      Key(0x30) if sandbox                => ClearBoard,
+      Key(b) if stepping && to_lower(b) == 0x6E => StepGame,   // n
+      Key(b) if stepping && to_lower(b) == 0x6C => DumpState,  // l
+      _     => Quit
+    }
   }
   
-  fn print_borders(rows: i8, cols: i8, rowOffset: i8, columnOffset: i8) {
-    reset_graphics();
+  // Two players on one keyboard: W, A, S and D for the first player, the arrow keys for the second
+  pub fn read_two_player_actions(log: Log) -> Vec<ActionEvent> {
+    let timeNs = time::precise_time_ns();
+    read_keys(log).iter().map(|key| {
+      let (action, player) = match *key {
+        Key(0x77) | Key(0x57) => (Rotate, 0),     // w
+        Key(0x73) | Key(0x53) => (Drop, 0),       // s
+        Key(0x64) | Key(0x44) => (MoveRight, 0),  // d
+        Key(0x61) | Key(0x41) => (MoveLeft, 0),   // a
+        Up                    => (Rotate, 1),
+        Down                  => (Drop, 1),
+        Right                 => (MoveRight, 1),
+        Left                  => (MoveLeft, 1),
+        _                     => (Quit, 0)
+      };
+      ActionEvent{action: action, timeNs: timeNs, player: player}
+    }).collect()
+  }
+}
 
-    // sides
-    let mut row = 1;
-    while row <= rows + 1 {
-      move_cursor((row + rowOffset, 1 + columnOffset));
-      print("<!");
-      move_cursor((row + rowOffset, 3 + cols + columnOffset));
-      print("!>");
-      row += 1;
-    }
-    
-    // bottom
-    move_cursor((rows + rowOffset + 1, 3 + columnOffset));
-    let mut col = 1;
-    while col <= cols {
-      print("=");
-      col += 1;
-    }
-    move_cursor((rows + rowOffset + 2, 3 + columnOffset));
-    col = 1;
-    while col <= cols - 1 {
-      print("\\/");
-      col += 2;
-    }
+mod output {
+  use std::io::{IoResult, Writer};
+  use std::io::stdio;
+  use std::io::stdio::StdWriter;
+  use std::vec_ng::Vec;
+  use time;
+  use trace::Log;
+  use terminal_control;
+  
+  // Receives a copy of every frame sent to the terminal
+  pub trait FrameSink {
+    fn frame(&mut self, bytes: &[u8]);
   }
   
-  // convert from game level row and column to terminal row/col
-  trait Converter {
-    fn to_terminal(&self, row: i8, col: i8) -> (i8, i8);
+  // Everything the game prints goes through a FrameWriter installed as the task's stdout.
+  // Output is held until a flush, then sent to the terminal with a single write, so each
+  // flush corresponds to one "frame" of terminal output.
+  //
+  // Frames are sent at most 60 times a second. A flush that comes sooner after the last
+  // frame is held back, and whatever is drawn until the next frame is due goes out with it,
+  // so a burst of key presses is one frame rather than one for each key.
+  struct FrameWriter {
+    out:   StdWriter,
+    buf:   Vec<u8>,
+    sinks: Vec<~FrameSink>,
+    log:   Log
   }
   
-  // game level rows for the information area (displaying score info, next piece)
-  static levelRow: i8 = 2;
-  static bonusRow: i8 = 4;
-  static scoreRow: i8 = 6;
-  static nextRow: i8 = 10;
+  static minFrameIntervalNs: u64 = 16666667;
   
-  // base game level column for the information area
-  // Display implemenations may use an offset from this
-  static baseInfoCol: i8 = 14;
+  // number of bytes sent to the terminal by the most recent flush
+  static mut lastFrameBytes: uint = 0;
   
-  fn init<T: Converter>(converter: T,
-                        terminalRows: i8,
-                        terminalCols: i8,
-                        terminalRowOffset: i8,
-                        terminalColumnOffset: i8,
-                        infoCol: i8) {
-      clear_terminal();
-      hide_cursor();
-      print_borders(terminalRows, terminalCols, terminalRowOffset, terminalColumnOffset);
-      
-      move_cursor(converter.to_terminal(levelRow, infoCol));
-      print("Level:");
-      
-      move_cursor(converter.to_terminal(bonusRow, infoCol));
-      print("Bonus:");
-      
-      move_cursor(converter.to_terminal(scoreRow, infoCol));
-      print("Score:");
-      
-      move_cursor(converter.to_terminal(nextRow, infoCol));
-      print("Next:");
-      
-      stdio::flush();
-  }
+  // when the last frame was sent, and whether a flush has been held back since
+  static mut lastFrameNs: u64 = 0;
+  static mut held: bool = false;
   
-  fn close<T: Converter>(converter: T, cursorMoveGameRow: i8) {
-    reset_graphics();
-    show_cursor();
-    move_cursor(converter.to_terminal(cursorMoveGameRow, 1));
-  }
+  // set once a frame couldn't be written, after which nothing more is sent
+  static mut lost: bool = false;
   
-  fn print_score<T: Converter>(converter: T, infoCol: i8, score: Score) {
-      reset_graphics();
-      
-      move_cursor(converter.to_terminal(levelRow, infoCol));
-      print!("{}   ", score.level);
-      
-      move_cursor(converter.to_terminal(bonusRow, infoCol));
-      print!("{}    ", score.bonus);
+  impl FrameWriter {
+    fn send(&mut self) -> IoResult<()> {
+      unsafe {
+        lastFrameBytes = self.buf.len();
+        lastFrameNs = time::precise_time_ns();
+        held = false;
+      }
+      if self.log.is_on() {
+        self.log.log(format!("flush {} bytes", self.buf.len()));
+      }
+      for sink in self.sinks.mut_iter() {
+        sink.frame(self.buf.as_slice());
+      }
+      if unsafe { lost } {
+        self.buf.clear();
+        return Ok(());
+      }
+      let result = self.out.write(self.buf.as_slice());
+      self.buf.clear();
       
-      move_cursor(converter.to_terminal(scoreRow, infoCol));
-      print!("{}    ", score.score);
-  }
-  
-  pub trait Display {
-    fn init(&self);
-    fn close(&self);
-    fn print_score(&self, score: Score);
-    fn print_block(&self, block: Block);
-    fn print_next_piece(&self, piece: &Piece);
-
-    fn print_piece(&self, piece: &Piece) {
-      for block in piece.blocks.iter() {
-        self.print_block(*block);
+      // A terminal that's gone away (EPIPE, or EIO once it's closed) would otherwise fail the
+      // print! that happened to flush, deep in the drawing code. Instead it's treated as a
+      // hangup, so the game saves itself (or keeps the score if it was over) and stops drawing.
+      match result {
+        Ok(()) => Ok(()),
+        Err(e) => {
+          self.log.log(format!("can't write to the terminal: {}", e));
+          unsafe { lost = true; }
+          terminal_control::lose_terminal();
+          Ok(())
+        }
       }
     }
-        
-    fn flush(&self) {
-      stdio::flush();
-    }
-    
-    fn erase_block(&self, row: i8, col: i8) {
-      self.print_block(Block{row: row, column: col, color: Black});
+  }
+  
+  impl Writer for FrameWriter {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+      self.buf.push_all(buf);
+      Ok(())
     }
     
-    fn erase_piece(&self, piece: &Piece) {
-      for block in piece.blocks.iter() {
-        self.erase_block(block.row, block.column);
+    fn flush(&mut self) -> IoResult<()> {
+      if self.buf.len() == 0 {
+        return Ok(());
       }
+      if time::precise_time_ns() - unsafe { lastFrameNs } < minFrameIntervalNs {
+        unsafe {
+          held = true;
+        }
+        return Ok(());
+      }
+      self.send()
     }
-    
-    fn erase_next_piece(&self, piece: &Piece) {
-      let erase = Piece {
-                    ty:     piece.ty,
-                    rotate: piece.rotate,
-                    blocks: [Block{row:    piece.blocks[0].row,
-                                   column: piece.blocks[0].column,
-                                   color:  Black},
-
-                             Block{row:    piece.blocks[1].row,
-                                   column: piece.blocks[1].column,
-                                   color:  Black},
-
-                             Block{row:    piece.blocks[2].row,
-                                   column: piece.blocks[2].column,
-                                   color:  Black},
-
-                             Block{row:    piece.blocks[3].row,
-                                   column: piece.blocks[3].column,
-                                   color:  Black}]
-      };
-      self.print_next_piece(&erase);
+  }
+  
+  // everything still buffered goes out, however soon after the last frame
+  impl Drop for FrameWriter {
+    fn drop(&mut self) {
+      if self.buf.len() > 0 {
+        let _ = self.send();
+      }
     }
   }
   
-  pub struct StandardDisplay;
-
-  // terminal level row/column offsets for everything (Blocks, borders, ...)
-  static stdRowOffset: i8 = 2i8;
-  static stdColumnOffset: i8 = 3i8;
+  pub fn install(sinks: Vec<~FrameSink>, log: Log) {
+    stdio::set_stdout(~FrameWriter{out: stdio::stdout_raw(), buf: Vec::new(), sinks: sinks, log: log} as ~Writer);
+  }
   
-  // terminal level number of columns a left/right border takes
-  static stdBorderColumns: i8 = 2i8;
+  // put back the standard line buffered stdout, flushing anything still buffered
+  pub fn uninstall() {
+    stdio::set_stdout(~stdio::stdout() as ~Writer);
+  }
   
-  impl StandardDisplay {
-    #[inline(always)]
-    fn to_terminal(row: i8, col: i8) -> (i8, i8) {
-      (row + stdRowOffset, 2 * col + stdBorderColumns - 1 + stdColumnOffset)
+  pub fn last_frame_bytes() -> uint {
+    unsafe {
+      lastFrameBytes
     }
   }
   
-  impl Converter for StandardDisplay {
-    fn to_terminal(&self, row: i8, col: i8) -> (i8, i8) {
-      StandardDisplay::to_terminal(row, col)
+  // when the held back frame can be sent, if there is one
+  pub fn held_frame_due() -> Option<u64> {
+    unsafe {
+      if held { Some(lastFrameNs + minFrameIntervalNs) } else { None }
     }
   }
   
-  impl Display for StandardDisplay {
-    fn init(&self) {
-      init(*self, 20, 20, stdRowOffset, stdColumnOffset, baseInfoCol);
-    }
-
-    fn close(&self) {
-      close(*self, 23);
+  // sends the held back frame if it's due
+  pub fn flush_held() {
+    if unsafe { held } {
+      stdio::flush();
     }
-    
-    fn print_score(&self, score: Score) {
-      print_score(*self, baseInfoCol + 4, score);
-    }
-    
-    fn print_block(&self, block: Block) {
-      if block.row < 1 || block.column < 1 {
-        return;
-      }
-      move_cursor(StandardDisplay::to_terminal(block.row, block.column));
-      set_background_color(block.color as u8);
-      print("  ");
-    }
-    
-    fn print_next_piece(&self, piece: &Piece) {
-      let colOffset = match piece.ty {
-        O | S => 13,
-        _     => 14
-      };
-      for block in piece.blocks.iter() {
-        move_cursor(StandardDisplay::to_terminal(nextRow + block.row, colOffset + block.column));
-        set_background_color(block.color as u8);
-        print("  ");
-      }
+  }
+  
+  // sends everything drawn so far right away, before waiting on something other than the main loop
+  pub fn flush_now() {
+    unsafe {
+      lastFrameNs = 0;
     }
+    stdio::flush();
   }
+}
+
+mod cast_recorder {
+  use std::io::{File, IoResult};
+  use std::str;
+  use serialize::json;
+  use time;
+  use output::FrameSink;
   
-  pub struct DoubleDisplay;
-  
-  static dblRowOffset: i8 = 2i8;
-  static dblColumnOffset: i8 = 30i8;
-  static dblBorderColumns: i8 = 2i8;
-  
-  impl DoubleDisplay {
-    #[inline(always)]
-    fn to_terminal(row: i8, col: i8) -> (i8, i8) {
-      (2 * row + dblRowOffset, 4 * col - 3 + dblBorderColumns + dblColumnOffset)
-    }
+  // Writes frames to an asciinema v2 .cast file: a JSON header line, followed by
+  // one [seconds, "o", data] JSON array per frame
+  pub struct CastRecorder {
+    file:    File,
+    startNs: u64
   }
   
-  impl Converter for DoubleDisplay {
-    fn to_terminal(&self, row: i8, col: i8) -> (i8, i8) {
-      DoubleDisplay::to_terminal(row, col)
-    }
+  pub fn new(path: &Path, size: (uint, uint)) -> IoResult<CastRecorder> {
+    let (width, height) = size;
+    let mut file = try!(File::create(path));
+    try!(file.write_line(format!("\\{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}, \"title\": \"tetris1\"\\}",
+                                 width, height, time::get_time().sec)));
+    Ok(CastRecorder{file: file, startNs: time::precise_time_ns()})
   }
   
-  impl Display for DoubleDisplay {
-    fn init(&self) {
-      init(*self, 40, 40, dblRowOffset, dblColumnOffset, baseInfoCol - 1);
-    }
-  
-    fn close(&self) {
-      close(*self, 22);
-    }
-  
-    fn print_score(&self, score: Score) {
-      print_score(*self, baseInfoCol + 1, score);
-    }
-  
-    fn print_block(&self, block: Block) {
-       if block.row < 1 || block.column < 1 {
-        return;
-      }
-      move_cursor(DoubleDisplay::to_terminal(block.row, block.column));
-      set_background_color(block.color as u8);
-      print("    ");
-      move_cursor((2 * block.row - 1 + dblRowOffset, 4 * block.column - 3 + dblBorderColumns + dblColumnOffset));
-      print("    ");
-    }
-    
-    fn print_next_piece(&self, piece: &Piece) {
-      let colOffset = match piece.ty {
-        O | S => 10,
-        _     => 11
-      };
-      for block in piece.blocks.iter() {
-        move_cursor(DoubleDisplay::to_terminal(nextRow + block.row, colOffset + block.column));
-        set_background_color(block.color as u8);
-        print("    ");
-        move_cursor((2 * (nextRow + block.row) - 1 + dblRowOffset,
-                     4 * (colOffset + block.column) - 3 + dblBorderColumns + dblColumnOffset));
-        print("    ");
-      }
+  impl FrameSink for CastRecorder {
+    fn frame(&mut self, bytes: &[u8]) {
+      let seconds = (time::precise_time_ns() - self.startNs) as f64 / 1000000000.0;
+      let data = json::String(str::from_utf8_lossy(bytes).into_owned()).to_str();
+      let _ = self.file.write_line(format!("[{:.6f}, \"o\", {}]", seconds, data));
     }
   }
 }
 
-mod pieces {
-  #[deriving(Eq)]
-  pub enum Color {
-    Black = 0, Red, Green, Yellow, Blue, Magenta, Cyan, White
-  }
-
-  pub struct Block {
-    row:    i8,
-    column: i8,
-    color:  Color
-  }
-
-  #[deriving(Clone)]
-  pub enum PieceType {
-    I = 0, J, L, O, S, T, Z
-  }
-
-  pub struct Piece {
-    ty:     PieceType,
-    rotate: u8,
-    blocks: [Block, ..4]
-  }
-
-
-  /*
-  Pieces Table:
-
-    | | | | |     | |0| | |     | | | | |     | |3| | |
-    | | | | |     | |1| | |     | | | | |     | |2| | |
-  I | | | | | --> | |2| | | --> | | | | | --> | |1| | |
-    |0|1|2|3|     | |3| | |     |3|2|1|0|     | |0| | |
-
-    | | | | |     | | | | |     | | | | |     | | | | |
-    | | | | |     | |1|0| |     | | | | |     | |3| | |
-  J |0| | | | --> | |2| | | --> |3|2|1| | --> | |2| | |
-    |1|2|3| |     | |3| | |     | | |0| |     |0|1| | |
-
-    | | | | |     | | | | |     | | | | |     | | | | |
-    | | | | |     |0| | | |     | | | | |     |3|2| | |
-  L | | |3| | --> |1| | | | --> |2|1|0| | --> | |1| | |
-    |0|1|2| |     |2|3| | |     |3| | | |     | |0| | |
-
-    | | | | |     | | | | |     | | | | |     | | | | |
-    | | | | |     | | | | |     | | | | |     | | | | |
-  O |0|1| | | --> |0|1| | | --> |0|1| | | --> |0|1| | |
-    |2|3| | |     |2|3| | |     |2|3| | |     |2|3| | |
-
-    | | | | |     | | | | |     | | | | |     | | | | |
-    | | | | |     |0| | | |     | | | | |     |3| | | |
-  S | |2|3| | --> |1|2| | | --> | |1|0| | --> |2|1| | |
-    |0|1| | |     | |3| | |     |3|2| | |     | |0| | |
-
-    | | | | |     | | | | |     | | | | |     | | | | |
-    | | | | |     |0| | | |     | | | | |     | |2| | |
-  T | |3| | | --> |1|3| | | --> |2|1|0| | --> |3|1| | |
-    |0|1|2| |     |2| | | |     | |3| | |     | |0| | |
-
-    | | | | |     | | | | |     | | | | |     | | | | |
-    | | | | |     | |0| | |     | | | | |     | |3| | |
-  Z |0|1| | | --> |2|1| | | --> |3|2| | | --> |1|2| | |
-    | |2|3| |     |3| | | |     | |1|0| |     |0| | | |
-  */
-
-  static pieceInitial: [Piece, ..7] = 
-  [
-    Piece{ty:     I,
-          rotate: 0,
-          blocks: [Block{row: 0, column: 4, color: Cyan},
-                   Block{row: 0, column: 5, color: Cyan},
-                   Block{row: 0, column: 6, color: Cyan},
-                   Block{row: 0, column: 7, color: Cyan}]},
-    
-    Piece{ty:     J,
-          rotate: 0,
-          blocks: [Block{row: -1, column: 4, color: Blue},
-                   Block{row:  0, column: 4, color: Blue},
-                   Block{row:  0, column: 5, color: Blue},
-                   Block{row:  0, column: 6, color: Blue}]},
-    
-    Piece{ty:     L,
-          rotate: 0,
-          blocks: [Block{row:  0, column: 4, color: White},
-                   Block{row:  0, column: 5, color: White},
-                   Block{row:  0, column: 6, color: White},
-                   Block{row: -1, column: 6, color: White}]},
-
-    Piece{ty:     O,
-          rotate: 0,
-          blocks: [Block{row: -1, column: 5, color: Yellow},
-                   Block{row: -1, column: 6, color: Yellow},
-                   Block{row:  0, column: 5, color: Yellow},
-                   Block{row:  0, column: 6, color: Yellow}]},
-    
-    Piece{ty:     S,
-          rotate: 0,
-          blocks: [Block{row:  0, column: 5, color: Green},
-                   Block{row:  0, column: 6, color: Green},
-                   Block{row: -1, column: 6, color: Green},
-                   Block{row: -1, column: 7, color: Green}]},
-    
-    Piece{ty:     T,
-          rotate: 0,
-          blocks: [Block{row:  0, column: 4, color: Magenta},
-                   Block{row:  0, column: 5, color: Magenta},
-                   Block{row:  0, column: 6, color: Magenta},
-                   Block{row: -1, column: 5, color: Magenta}]},
-
-    Piece{ty:     Z,
-          rotate: 0,
-          blocks: [Block{row: -1, column: 4, color: Red},
-                   Block{row: -1, column: 5, color: Red},
-                   Block{row:  0, column: 5, color: Red},
-                   Block{row:  0, column: 6, color: Red}]}
-  ];
-
-  pub fn new(ty: PieceType) -> Piece {
-    pieceInitial[ty as int]
-  }
+// Mirrors the player's board to a small file after every frame, for stream overlays and
+// LED matrices that can't read escape sequences. The file is always 216 bytes and is
+// written over in place, so it can be mmap'd and read at any time:
+//
+//   0    "T1FC"
+//   4    the layout's version (1), the columns (10) and rows (20), and a 0, a byte each
+//   8    the frame number, 4 bytes little endian, counting up from 1
+//   12   200 cells, a byte each, row by row from the top: 0 is empty, 1 to 7 the block colors
+//        red, green, yellow, blue, magenta, cyan and white (before the theme), and 8 garbage,
+//        with 0x80 added for an outline (the hint, or the trail of a hard drop)
+//   212  the frame number again
+//
+// A reader that finds the two frame numbers different caught a frame half written, and
+// should read it again.
+mod frame_capture {
+  use std::io::{File, IoResult, SeekSet};
+  use std::comm::Receiver;
+  use std::vec_ng::Vec;
+  use output::FrameSink;
   
-  static pieceRotate: [[[(i8, i8), ..4], ..4], ..7] =
-  [
-    // I
-    [[(-3,1),(-2,0),(-1,-1),(0,-2)], [(3,2),(2,1),(1,0),(0,-1)], [(0,-2),(-1,-1),(-2,0),(-3,1)], [(0,-1),(1,0),(2,1),(3,2)]],
-
-    // J
-    [[(-1,2),(-2,1),(-1,0),(0,-1)], [(2,0),(1,1),(0,0),(-1,-1)], [(0,-2),(1,-1),(0,0),(-1,1)], [(-1,0),(0,-1),(1,0),(2,1)]],
-
-    // L
-    [[(-2,0),(-1,-1),(0,-2),(1,-1)], [(1,2),(0,1),(-1,0),(0,-1)], [(1,-1),(0,0),(-1,1),(-2,0)], [(0,-1),(1,0),(2,1),(1,2)]],
-
-    // O
-    [[(0,0),(0,0),(0,0),(0,0)], [(0,0),(0,0),(0,0),(0,0)], [(0,0),(0,0),(0,0),(0,0)], [(0,0),(0,0),(0,0),(0,0)]],
-
-    // S
-    [[(-2,0),(-1,-1),(0,0),(1,-1)], [(1,2),(0,1),(1,0),(0,-1)], [(1,-1),(0,0),(-1,-1),(-2,0)], [(0,-1),(1,0),(0,1),(1,2)]],
-
-    // T
-    [[(-2,0),(-1,-1),(0,-2),(0,0)], [(1,2),(0,1),(-1,0),(1,0)], [(1,-1),(0,0),(-1,1),(-1,-1)], [(0,-1),(1,0),(2,1),(0,1)]],
-
-    // Z
-    [[(-1,1),(0,0),(-1,-1),(0,-2)], [(2,1),(1,0),(0,1),(-1,0)], [(0,-2),(-1,-1),(0,0),(-1,1)], [(-1,0),(0,1),(1,0),(2,1)]]
-  ];
-
-  trait Offset {
-    fn row(self) -> i8;
-    fn col(self) -> i8;
-  }
-
-  impl Offset for (i8, i8) {
-    fn row(self) -> i8 {
-      let (row, _) = self;
-      row
-    }
-    
-    fn col(self) -> i8 {
-      let (_, col) = self;
-      col
-    }
+  static version: u8 = 1;
+  
+  pub struct FrameCapture {
+    file:     File,
+    frame:    u32,
+    mirrored: Receiver<[u8, ..200]>,  // the board from the display, every time it's flushed
+    cells:    [u8, ..200]             // the last board from the display
   }
   
-  fn transform_blocks(clockwise: bool, blocks: &[Block, ..4], transform: [(i8, i8), ..4]) -> [Block, ..4] {
-    let s = if clockwise { 1i8 } else { -1i8 };
-    [
-      Block{row:    blocks[0].row    + s * transform[0].row(),
-            column: blocks[0].column + s * transform[0].col(),
-            color:  blocks[0].color},
-    
-      Block{row:    blocks[1].row    + s * transform[1].row(),
-            column: blocks[1].column + s * transform[1].col(),
-            color:  blocks[1].color},
-    
-      Block{row:    blocks[2].row    + s * transform[2].row(),
-            column: blocks[2].column + s * transform[2].col(),
-            color:  blocks[2].color},
-    
-      Block{row:    blocks[3].row    + s * transform[3].row(),
-            column: blocks[3].column + s * transform[3].col(),
-            color:  blocks[3].color}
-    ]
+  pub fn new(path: &Path, mirrored: Receiver<[u8, ..200]>) -> IoResult<FrameCapture> {
+    let mut capture = FrameCapture{file: try!(File::create(path)), frame: 0, mirrored: mirrored, cells: [0, ..200]};
+    try!(capture.write());
+    Ok(capture)
   }
-
-  pub fn rotate_clockwise(piece: &Piece) -> Piece {
-    Piece {
-      ty:     piece.ty,
-      rotate: (piece.rotate + 1) % 4,
-      blocks: transform_blocks(true, &piece.blocks, pieceRotate[piece.ty as int][piece.rotate])
+  
+  fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    for i in range(0, 4) {
+      bytes.push((value >> (8 * i)) as u8);
     }
   }
-
-  pub fn rotate_counter_clockwise(piece: &Piece) -> Piece {
-    Piece {
-      ty:     piece.ty,
-      rotate: (piece.rotate + 3) % 4,
-      blocks: transform_blocks(false, &piece.blocks, pieceRotate[piece.ty as int][(piece.rotate + 3) % 4])
+  
+  impl FrameCapture {
+    fn write(&mut self) -> IoResult<()> {
+      self.frame += 1;
+      let mut bytes = Vec::from_slice("T1FC".as_bytes());
+      bytes.push_all(&[version, 10, 20, 0]);
+      push_u32(&mut bytes, self.frame);
+      loop {
+        match self.mirrored.try_recv() {
+          Ok(cells) => self.cells = cells,
+          Err(_)    => break
+        }
+      }
+      bytes.push_all(self.cells.as_slice());
+      push_u32(&mut bytes, self.frame);
+      try!(self.file.seek(0, SeekSet));
+      try!(self.file.write(bytes.as_slice()));
+      self.file.flush()
     }
   }
   
-  pub fn translate(piece: &Piece, rowOffset: i8, columnOffset: i8) -> Piece {
-    Piece {
-      ty:     piece.ty,
-      rotate: piece.rotate,
-      blocks: [Block{row:    piece.blocks[0].row    + rowOffset,
-                     column: piece.blocks[0].column + columnOffset,
-                     color:  piece.blocks[0].color},
-                     
-               Block{row:    piece.blocks[1].row    + rowOffset,
-                     column: piece.blocks[1].column + columnOffset,
-                     color:  piece.blocks[1].color},
-                     
-               Block{row:    piece.blocks[2].row    + rowOffset,
-                     column: piece.blocks[2].column + columnOffset,
-                     color:  piece.blocks[2].color},
-               
-               Block{row:    piece.blocks[3].row + rowOffset,
-                     column: piece.blocks[3].column + columnOffset,
-                     color:  piece.blocks[3].color}]
+  impl FrameSink for FrameCapture {
+    fn frame(&mut self, _bytes: &[u8]) {
+      let _ = self.write();
     }
   }
 }
 
-mod set_blocks {
-  use pieces::Block;
+// Sends the player's board to an RGB LED matrix whenever a frame changes it, through a
+// serial device (set up first with something like "stty -F /dev/ttyACM0 115200 raw") or
+// a UNIX socket. Each frame is "T1", then the red, green and blue of each of the 200 cells
+// (600 bytes, row by row from the top), then a checksum byte: those 600 bytes added up,
+// modulo 256. Empty cells are off, garbage is a dim gray and outlines are at a quarter of
+// their color's brightness. If the device goes away the game carries on without it.
+mod led_matrix {
+  use std::io;
+  use std::io::{File, IoResult, Open, Write, Writer};
+  use std::io::fs;
+  use std::io::net::unix::UnixStream;
+  use std::comm::Receiver;
+  use std::vec_ng::Vec;
+  use graphics;
+  use output::FrameSink;
+  use trace::Log;
   
-  pub trait SetBlocks {
-    fn has_block(&self, row: i8, col: i8) -> bool;  
-    fn get(&self, row: i8, col: i8) -> Option<Block>;
-    fn remove(&mut self, row: i8, col: i8);
-    fn set(&mut self, block: Block);
+  // black, the block colors from red to white, then garbage
+  static ledColors: [(u8, u8, u8), ..9] = [(0, 0, 0), (255, 0, 0), (0, 255, 0), (255, 255, 0), (0, 0, 255),
+                                          (255, 0, 255), (0, 255, 255), (255, 255, 255), (48, 48, 48)];
+  
+  pub struct LedMatrix {
+    out:      Option<~Writer>,
+    sent:     Option<[u8, ..200]>,  // the cells in the last frame sent
+    mirrored: Receiver<[u8, ..200]>,  // the board from the display, every time it's flushed
+    cells:    [u8, ..200],            // the last board from the display
+    log:      Log
   }
-
-  #[inline(always)]
-  fn index(row: i8, col: i8) -> int {
-    return 10 * ((row as int) - 1) + (col as int) - 1;
+  
+  // a UNIX socket is connected to, anything else is written to like a file
+  pub fn new(path: &Path, mirrored: Receiver<[u8, ..200]>, log: Log) -> IoResult<LedMatrix> {
+    let out = match fs::stat(path) {
+      Ok(stat) if stat.kind == io::TypeUnknown => ~try!(UnixStream::connect(path)) as ~Writer,
+      _                                        => ~try!(File::open_mode(path, Open, Write)) as ~Writer
+    };
+    Ok(LedMatrix{out: Some(out), sent: None, mirrored: mirrored, cells: [0, ..200], log: log})
   }
-
-  impl SetBlocks for [Option<Block>, ..200] {
-    fn has_block(&self, row: i8, col: i8) -> bool {
-      if row < 1 || row > 20 || col < 1 || col > 10 {
-        return false;
+  
+  fn frame_bytes(cells: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::from_slice("T1".as_bytes());
+    let mut sum = 0u8;
+    for &cell in cells.iter() {
+      let (red, green, blue) = ledColors[(cell & !graphics::outlineCell) as uint];
+      let shift = if cell & graphics::outlineCell != 0 { 2 } else { 0 };
+      for &value in [red >> shift, green >> shift, blue >> shift].iter() {
+        bytes.push(value);
+        sum += value;
       }
-      return self[index(row, col)].is_some();
-    }
-    
-    fn get(&self, row: i8, col: i8) -> Option<Block> {
-      return self[index(row, col)];
     }
-    
-    fn remove(&mut self, row: i8, col: i8) {
-      self[index(row, col)] = None;
-    }
-    
-    fn set(&mut self, block: Block) {
-      if block.row < 1 || block.row > 20 || block.column < 1 || block.column > 10 {
-        fail!("can't add out of bounds block to set blocks");
+    bytes.push(sum);
+    bytes
+  }
+  
+  impl FrameSink for LedMatrix {
+    fn frame(&mut self, _bytes: &[u8]) {
+      loop {
+        match self.mirrored.try_recv() {
+          Ok(cells) => self.cells = cells,
+          Err(_)    => break
+        }
+      }
+      let cells = self.cells;
+      if self.sent.map_or(false, |sent| sent.as_slice() == cells.as_slice()) {
+        return;
+      }
+      self.sent = Some(cells);
+      let failed = match self.out {
+        Some(ref mut out) => {
+          let bytes = frame_bytes(cells.as_slice());
+          out.write(bytes.as_slice()).is_err() || out.flush().is_err()
+        }
+        None => false
+      };
+      if failed {
+        self.log.log(~"the LED matrix went away, the board isn't sent any more");
+        self.out = None;
       }
-      self[index(block.row, block.column)] = Some(block);
     }
   }
 }
 
-mod piece_getter {
-  use pieces;
-  use pieces::{Piece, I, J, L, O, S, T, Z};
-  use rand::Rng;
-  use rand::os::OSRng;
-
-  pub trait PieceGetter {
-    fn next_piece(&mut self) -> Piece;
-  }
+mod spectator {
+  use std::libc::{c_int, c_void, size_t, ssize_t};
+  use std::io::{IoResult, IoError};
+  use std::io::stdio;
+  use std::io::net::addrinfo;
+  use std::io::net::ip::{SocketAddr, Ipv4Addr};
+  use std::io::net::tcp::TcpStream;
+  use std::comm::Receiver;
+  use std::mem;
+  use std::ptr;
+  use std::str;
+  use std::vec_ng::Vec;
+  use output::FrameSink;
+  use commentary;
+  use strings;
   
-  pub fn new() -> ~PieceGetter {
-    return ~RandomPieceGetter{rng: OSRng::new()} as ~PieceGetter;
+  // Linux specific struct sockaddr_in, with the port and address in network byte order
+  #[allow(non_camel_case_types)]
+  struct sockaddr_in {
+    sin_family: u16,
+    sin_port:   u16,
+    sin_addr:   u32,
+    sin_zero:   [u8, ..8]
   }
   
-  struct RandomPieceGetter {
-    rng: OSRng
+  extern {
+    fn socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int;
+    fn setsockopt(fd: c_int, level: c_int, name: c_int, value: *c_void, len: u32) -> c_int;
+    fn bind(fd: c_int, addr: *sockaddr_in, len: u32) -> c_int;
+    fn listen(fd: c_int, backlog: c_int) -> c_int;
+    fn accept(fd: c_int, addr: *mut c_void, len: *mut u32) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, arg: c_int) -> c_int;
+    fn send(fd: c_int, buf: *c_void, len: size_t, flags: c_int) -> ssize_t;
+    fn close(fd: c_int) -> c_int;
   }
   
-  impl PieceGetter for RandomPieceGetter {
-    fn next_piece(&mut self) -> Piece {
-      let pieceType = self.rng.choose(&[I, J, L, O, S, T, Z]);
-      return pieces::new(pieceType);
-    }
-  }
-}
-
-mod scoring {
-  use std::libc::c_int;
-
-  struct Level {
-    time:     c_int,
-    score:    int,
-    count:    int,
-    bonusInc: int
-  }
+  static afInet: c_int = 2;
+  static sockStream: c_int = 1;
+  static solSocket: c_int = 1;
+  static soReuseAddr: c_int = 2;
+  static fSetFl: c_int = 4;
+  static oNonBlock: c_int = 0x800;
   
-  static levels : [Level, ..11] = [Level{time: 1000, score: 0,  count: 3, bonusInc: 1},
-                                   Level{time: 900,  score: 5,  count: 3, bonusInc: 1},
-                                   Level{time: 800,  score: 10, count: 3, bonusInc: 2},
-                                   Level{time: 700,  score: 15, count: 3, bonusInc: 2},
-                                   Level{time: 600,  score: 20, count: 3, bonusInc: 3},
-                                   Level{time: 500,  score: 30, count: 4, bonusInc: 4},
-                                   Level{time: 400,  score: 40, count: 4, bonusInc: 4},
-                                   Level{time: 350,  score: 45, count: 5, bonusInc: 5},
-                                   Level{time: 300,  score: 50, count: 6, bonusInc: 10},
-                                   Level{time: 250,  score: 60, count: 6, bonusInc: 20},
-                                   Level{time: 200,  score: 70, count: 4, bonusInc: 20}];
+  // don't wait for a slow spectator, and don't get killed by SIGPIPE when one goes away
+  static msgDontWait: c_int = 0x40;
+  static msgNoSignal: c_int = 0x4000;
   
-  #[inline(always)]
-  fn get_level(level: u16) -> &'static Level {
-    &levels[level - 1]
+  // Serves the game's frames to anyone who connects, so they can watch with "nc HOST PORT"
+  // or "tetris1 --watch HOST:PORT". Sockets are never waited on: new spectators are picked up
+  // when a frame is drawn, and a spectator who can't keep up is disconnected. Spectators also
+  // get the commentary on the game, on a line of its own under it.
+  pub struct SpectatorServer {
+    listener: c_int,
+    clients:  Vec<c_int>,
+    screen:   Screen,   // what's on the screen now, for new spectators
+    commentaryRow: uint,
+    remarks:  Receiver<commentary::Remarks>,  // from the game's commentator
+    latest:   Option<commentary::Remarks>     // None until the first piece of the game is set
   }
   
-  #[deriving(Encodable, Decodable)]
-  pub struct Score {
-    level: u16,
-    bonus: int,
-    score: int
+  fn last_error<T>() -> IoResult<T> {
+    Err(IoError::last_error())
   }
   
-  pub trait Scoring {
-    fn get_score(&self) -> Score;
-    fn update(&mut self, setRows: int) -> Score;
-    fn get_time(&self) -> c_int;
+  // Escape sequences are read a byte at a time, since one can be split between frames
+  enum ParseState {
+    Text,
+    Escape,        // after ESC
+    Control,       // after ESC [, with the parameters read so far in params
+    Command,       // after ESC ], up to BEL or ESC \ (like the window title), which doesn't draw anything
+    CommandEscape  // an ESC in a command
   }
   
-  pub fn new() -> ~Scoring {
-    ~StdScoring{level:     1,
-                score:     0,
-                bonus:     1,
-                count:     0,
-                bonusDrop: bonusDropReset} as ~Scoring
+  // The screen as the game has drawn it, kept from the frames so a new spectator can be sent
+  // what's on it now rather than everything drawn since the game started (the game only
+  // draws what changes). It understands what the displays use: moving the cursor, colors,
+  // clearing and text.
+  struct Screen {
+    cells:  Vec<Vec<(uint, char)>>,  // the style and character of each cell, row by row
+    styles: Vec<~str>,  // the SGR parameters of each style seen so far, the first is plain
+    row:    uint,       // where the cursor is, from 0
+    col:    uint,
+    style:  uint,
+    state:  ParseState,
+    params: ~str,
+    utf8:   Vec<u8>     // the bytes of a character read so far
   }
   
-  struct StdScoring {
-    level:     u16,
-    score:     int,
-    bonus:     int,
-    count:     int,
-    bonusDrop: int,
+  // more than any display needs, anything drawn outside is left out
+  static screenRows: uint = 60;
+  static screenCols: uint = 200;
+  
+  fn new_screen() -> Screen {
+    Screen{cells:  Vec::from_fn(screenRows, |_| Vec::from_elem(screenCols, (0u, ' '))),
+           styles: vec!(~""),
+           row:    0,
+           col:    0,
+           style:  0,
+           state:  Text,
+           params: ~"",
+           utf8:   Vec::new()}
   }
   
-  // control how many pieces drop without completing any rows before the bonus is decremented
-  static bonusDropReset: int = 1;
+  // the escape sequence that sets a style from scratch
+  fn sgr(style: &str) -> ~str {
+    if style.len() == 0 { ~"\x1B[0m" } else { format!("\x1B[0;{}m", style) }
+  }
   
-  impl StdScoring {
-    fn update_some_set_rows(&mut self, setRows: int) -> Score {
-      let level = get_level(self.level);
-      
-      let baseScore = 10 * (1 << (setRows - 1));
-      let levelScore = level.score;
-      
-      self.score += (baseScore + levelScore) * self.bonus;
-      
-      self.count += 1;
-      
-      // add to the bonus when a level is cleared
-      let bonusInc = if self.count > level.count { level.bonusInc } else { 0 };
-      
-      if self.count > level.count {
-        if self.level < levels.len() as u16 {
-          self.level += 1;
+  impl Screen {
+    fn read(&mut self, bytes: &[u8]) {
+      for &byte in bytes.iter() {
+        match self.state {
+          Text => match byte {
+            0x1B => self.state = Escape,
+            0x0D => self.col = 0,
+            0x0A => self.row += 1,
+            0x08 => if self.col > 0 { self.col -= 1 },
+            _ if byte < 0x20 => (),
+            _ => self.put_byte(byte)
+          },
+          Escape => self.state = match byte {
+            0x5B => {
+              self.params = ~"";
+              Control
+            }
+            0x5D => Command,
+            _    => Text
+          },
+          Control if byte >= 0x40 && byte <= 0x7E => {
+            self.control(byte as char);
+            self.state = Text;
+          }
+          Control => self.params.push_char(byte as char),
+          Command => self.state = match byte {
+            0x07 => Text,
+            0x1B => CommandEscape,
+            _    => Command
+          },
+          CommandEscape => self.state = if byte == 0x5C { Text } else { Command }
         }
-        self.count = 0;
-      }
-      
-      if self.bonus == 1 {
-        self.bonus = 2 * setRows;
-      } else {
-        self.bonus += 2 * setRows;
       }
-      self.bonus += bonusInc;
-      
-      self.bonusDrop = bonusDropReset;
-      
-      self.get_score()
     }
     
-    fn update_no_set_rows(&mut self) -> Score {
-      if self.bonus > 1 {
-        self.bonusDrop -= 1;
-        if self.bonusDrop == 0 {
-          self.bonus -= 1;
-          self.bonusDrop = bonusDropReset;
+    // a byte of text, which is drawn once it's a whole UTF-8 character
+    fn put_byte(&mut self, byte: u8) {
+      self.utf8.push(byte);
+      let c = str::from_utf8(self.utf8.as_slice()).map(|text| text.char_at(0));
+      match c {
+        Some(c) => {
+          self.utf8.clear();
+          if self.row < screenRows && self.col < screenCols {
+            *self.cells.get_mut(self.row).get_mut(self.col) = (self.style, c);
+          }
+          self.col += 1;
         }
+        None if self.utf8.len() >= 4 => self.utf8.clear(),
+        None                         => ()
       }
-      self.get_score()
     }
-  }
-  
-  impl Scoring for StdScoring {
-    fn get_score(&self) -> Score {
-      Score{level: self.level, bonus: self.bonus, score: self.score}
+    
+    // ESC [ params command
+    fn control(&mut self, command: char) {
+      let params = self.params.clone();
+      let numbers: Vec<uint> = params.split(';').map(|n| from_str::<uint>(n).unwrap_or(0)).collect();
+      let count = if *numbers.get(0) > 0 { *numbers.get(0) } else { 1 };
+      match command {
+        'H' | 'f' => {
+          self.row = count - 1;
+          self.col = if numbers.len() > 1 && *numbers.get(1) > 0 { *numbers.get(1) - 1 } else { 0 };
+        }
+        'A' => self.row = if self.row > count { self.row - count } else { 0 },
+        'B' => self.row += count,
+        'C' => self.col += count,
+        'D' => self.col = if self.col > count { self.col - count } else { 0 },
+        'm' => self.set_style(params.as_slice()),
+        'J' if params.as_slice() == "2" => {
+          for row in self.cells.mut_iter() {
+            for cell in row.mut_iter() {
+              *cell = (0, ' ');
+            }
+          }
+        }
+        'K' if self.row < screenRows => {
+          let style = self.style;
+          for cell in self.cells.get_mut(self.row).mut_iter().skip(self.col) {
+            *cell = (style, ' ');
+          }
+        }
+        _ => ()
+      }
     }
     
-    fn update(&mut self, setRows: int) -> Score {
-      if setRows > 0 {
-        self.update_some_set_rows(setRows)
+    // SGR parameters add to the style, unless they start by resetting it
+    fn set_style(&mut self, params: &str) {
+      let style = if params == "" || params == "0" {
+        ~""
+      } else if params.starts_with("0;") {
+        params.slice_from(2).to_owned()
+      } else if self.style == 0 {
+        params.to_owned()
       } else {
-        self.update_no_set_rows()
-      }
+        format!("{};{}", *self.styles.get(self.style), params)
+      };
+      self.style = match self.styles.iter().position(|other| *other == style) {
+        Some(index) => index,
+        None        => {
+          self.styles.push(style);
+          self.styles.len() - 1
+        }
+      };
     }
     
-    fn get_time(&self) -> c_int {
-      get_level(self.level).time
+    // what a terminal has to be sent to show the screen as it is, with the cursor and the
+    // style where the game left them
+    fn contents(&self) -> ~str {
+      let mut text = ~"\x1B[0m\x1B[2J\x1B[?25l";
+      for (row, cells) in self.cells.iter().enumerate() {
+        // blank cells at the end of a row aren't sent
+        let end = match cells.iter().rposition(|&(style, c)| style != 0 || c != ' ') {
+          Some(col) => col + 1,
+          None      => continue
+        };
+        text.push_str(format!("\x1B[{};1H", row + 1));
+        let mut style = 0;
+        for &(cellStyle, c) in cells.slice_to(end).iter() {
+          if cellStyle != style {
+            text.push_str(sgr(*self.styles.get(cellStyle)));
+            style = cellStyle;
+          }
+          text.push_char(c);
+        }
+      }
+      text.push_str(format!("\x1B[{};{}H", self.row + 1, self.col + 1));
+      text.push_str(sgr(*self.styles.get(self.style)));
+      text
     }
   }
-}
-
-mod score_keeper {
-  use serialize::json;
-  use serialize::{Encodable, Decodable};
-  use scoring::Score;
-  use std::io::File;
-  use std::vec_ng::Vec;
-  use time;
   
-  pub trait ScoreKeeper {
-    fn store_score(&self, tm: &time::Tm, score: Score);
-    fn get_scores(&self) -> ScoreStorage;
-  }
-    
-  #[deriving(Encodable, Decodable)]
-  pub struct ScoreStorage {
-    highScores:   Vec<(time::Tm, Score)>,
-    recentScores: Vec<(time::Tm, Score)>
+  pub fn new(port: u16, commentaryRow: uint, remarks: Receiver<commentary::Remarks>) -> IoResult<SpectatorServer> {
+    unsafe {
+      let fd = socket(afInet, sockStream, 0);
+      if fd < 0 {
+        return last_error();
+      }
+      let on: c_int = 1;
+      setsockopt(fd, solSocket, soReuseAddr, &on as *c_int as *c_void, mem::size_of::<c_int>() as u32);
+      
+      let addr = sockaddr_in {
+        sin_family: afInet as u16,
+        sin_port:   mem::to_be16(port as i16) as u16,
+        sin_addr:   0,  // any address
+        sin_zero:   [0, ..8]
+      };
+      if bind(fd, &addr, mem::size_of::<sockaddr_in>() as u32) < 0 ||
+         listen(fd, 8) < 0 ||
+         fcntl(fd, fSetFl, oNonBlock) < 0 {
+        let error = last_error();
+        close(fd);
+        return error;
+      }
+      Ok(SpectatorServer{listener: fd, clients: Vec::new(), screen: new_screen(), commentaryRow: commentaryRow,
+                         remarks: remarks, latest: None})
+    }
   }
   
-  pub fn get() -> &ScoreKeeper {
-    &myFileScoreKeeper as &ScoreKeeper
+  // sends all of bytes, or returns false if the client couldn't take them
+  fn send_all(fd: c_int, bytes: &[u8]) -> bool {
+    unsafe {
+      let sent = send(fd, bytes.as_ptr() as *c_void, bytes.len() as size_t, msgDontWait | msgNoSignal);
+      sent == bytes.len() as ssize_t
+    }
   }
-
-  
-  struct FileScoreKeeper;
   
-  static myFileScoreKeeper: FileScoreKeeper = FileScoreKeeper;  
-  static maxScores : uint = 5;
-  
-  impl ScoreKeeper for FileScoreKeeper {
-    fn store_score(&self, tm: &time::Tm, score: Score) {
-      // zero scores aren't worth keeping
-      if score.score <= 0 {
-        return;
+  impl SpectatorServer {
+    fn accept_clients(&mut self) {
+      loop {
+        let fd = unsafe { accept(self.listener, ptr::mut_null(), ptr::mut_null()) };
+        if fd < 0 {
+          return;
+        }
+        if send_all(fd, self.screen.contents().as_bytes()) {
+          self.clients.push(fd);
+        } else {
+          unsafe { close(fd); }
+        }
       }
+    }
+  }
+  
+  impl FrameSink for SpectatorServer {
+    fn frame(&mut self, bytes: &[u8]) {
+      self.screen.read(bytes);
       
-      let mut scores = self.get_scores();
+      self.accept_clients();
       
-      scores.highScores.insert(0, (tm.clone(), score));
-      scores.highScores.sort_by(|&(_, s1), &(_, s2)| s2.score.cmp(&s1.score));
-      if scores.highScores.len() > maxScores {
-        scores.highScores.pop();
+      loop {
+        match self.remarks.try_recv() {
+          Ok(remarks) => self.latest = Some(remarks),
+          Err(_)      => break
+        }
       }
       
-      scores.recentScores.insert(0, (tm.clone(), score));
-      if scores.recentScores.len() > maxScores {
-        scores.recentScores.pop();
+      // the commentary is written over the last line every frame, then the cursor is put back
+      let mut frame = Vec::from_slice(bytes);
+      match self.latest {
+        Some(remarks) => {
+          let line = format!("\x1B7\x1B[{};1H\x1B[0m{}\x1B[K\x1B8", self.commentaryRow, commentary::describe(remarks));
+          frame.push_all(line.as_bytes());
+        }
+        None => ()
       }
       
-      let mut scoresFile = File::create(&Path::new("scores.json"));
-      let mut encoder = json::PrettyEncoder::new(&mut scoresFile);
-      scores.encode(&mut encoder);
+      let mut kept = Vec::new();
+      for fd in self.clients.iter() {
+        if send_all(*fd, frame.as_slice()) {
+          kept.push(*fd);
+        } else {
+          unsafe { close(*fd); }
+        }
+      }
+      self.clients = kept;
     }
-    
-    fn get_scores(&self) -> ScoreStorage {
-      let emptyStorage = ScoreStorage {
-        highScores:   vec!(),
-        recentScores: vec!()
-      };
-      
-      let storageFile = File::open(&Path::new("scores.json"));
-      if storageFile.is_err() {
-        return emptyStorage;
+  }
+  
+  impl Drop for SpectatorServer {
+    fn drop(&mut self) {
+      unsafe {
+        for fd in self.clients.iter() {
+          close(*fd);
+        }
+        close(self.listener);
       }
-      
-      let storageObject = json::from_reader(&mut storageFile.unwrap());
-      if storageObject.is_err() {
-        return emptyStorage;
+    }
+  }
+  
+  // The address of "HOST:PORT", an IPv4 one if the host has one
+  pub fn resolve(address: &str) -> Result<SocketAddr, ~str> {
+    let (host, port) = match address.rfind(':') {
+      Some(i) => match from_str::<u16>(address.slice_from(i + 1)) {
+        Some(port) => (address.slice_to(i), port),
+        None       => return Err(strings::fill(strings::get().errBadPort, &[address]))
+      },
+      None => return Err(strings::fill(strings::get().errHostPort, &[address]))
+    };
+    let ips = match addrinfo::get_host_addresses(host) {
+      Ok(ips) => ips,
+      Err(_)  => return Err(strings::fill(strings::get().errCantFind, &[host]))
+    };
+    let ip = match ips.iter().find(|ip| match **ip { Ipv4Addr(..) => true, _ => false }) {
+      Some(ip)              => *ip,
+      None if ips.len() > 0 => ips[0],
+      None                  => return Err(strings::fill(strings::get().errCantFind, &[host]))
+    };
+    Ok(SocketAddr{ip: ip, port: port})
+  }
+  
+  // Connects to a game being served at "HOST:PORT" and shows it until the game is over
+  pub fn watch(address: &str) -> Result<(), ~str> {
+    let mut stream = match TcpStream::connect(try!(resolve(address))) {
+      Ok(stream) => stream,
+      Err(e)     => return Err(strings::fill(strings::get().errCantConnect, &[address, e.to_str().as_slice()]))
+    };
+    
+    let mut out = stdio::stdout_raw();
+    let mut buf = [0u8, ..4096];
+    loop {
+      match stream.read(buf) {
+        Ok(n)  => { let _ = out.write(buf.slice_to(n)); }
+        Err(_) => break
       }
-      
-      let mut decoder = json::Decoder::new(storageObject.unwrap());
-      Decodable::decode(&mut decoder)
     }
+    
+    // the game's colors and hidden cursor may be left over if it ended abruptly
+    let _ = out.write_str("\x1B[0m\x1B[?25h\n");
+    Ok(())
   }
 }
 
-mod tetris {
-  use time;
-  use std::libc::c_int;
+// What spectators are told about the game as it goes: how fast it's played, how many garbage
+// rows its clears have been worth (what they'd have sent to another player) and how close the
+// stack is to the top. A Commentator listens in on the game and sends its remarks to the
+// spectator server, which draws them, so the player doesn't see them.
+mod commentary {
+  use std::comm::Sender;
+  use board;
+  use engine::GameEngine;
+  use modes::GarbageRules;
+  use strings;
   
-  use terminal_control;
-  use input_reader;
-  use pieces;
-  use pieces::{Block, Piece};
-  use graphics::Display;
-  use piece_getter;
-  use piece_getter::PieceGetter;
-  use scoring;
-  use scoring::Scoring;
-  use score_keeper;
-  use score_keeper::ScoreKeeper;
-  use set_blocks::SetBlocks;
+  pub struct Remarks {
+    piecesPerSecond: f64,
+    attack:          int,   // garbage rows sent
+    height:          uint   // rows from the bottom of the board to the top of the stack
+  }
   
-  trait GameHandler {
-    fn init(&self);
-    fn handle_step(&mut self) -> Option<c_int>;
-    fn handle_input(&mut self, input: input_reader::ReadResult);
-    fn handle_quit(&self);
+  // stacks up to these heights are a low, medium or high danger, anything higher critical
+  static dangerHeights: [uint, ..3] = [9, 13, 16];
+  
+  pub fn describe(remarks: Remarks) -> ~str {
+    let text = strings::get();
+    let danger = dangerHeights.iter().position(|top| remarks.height <= *top).unwrap_or(dangerHeights.len());
+    strings::fill(text.commentary, &[format!("{:.2f}", remarks.piecesPerSecond).as_slice(),
+                                     remarks.attack.to_str().as_slice(),
+                                     text.dangerLevels[danger]])
   }
-
-  enum State {
-    Fall = 0, Clear, GameOver
+  
+  pub struct Commentator {
+    startNs: u64,
+    sent:    [int, ..4],  // garbage rows clearing 1, 2, 3 or 4 rows at once would send
+    attack:  int,
+    remarks: Sender<Remarks>  // where the remarks go each time a piece is set
   }
-
-  struct TetrisGame<'a> {
-    display:     &'a Display,
-    pieceGetter: &'a mut PieceGetter,
-    scoring:     &'a mut Scoring,
-    scoreKeeper: &'a ScoreKeeper,
-    state:       State,
-    piece:       Piece,
-    nextPiece:   Piece,
-    setBlocks:   [Option<Block>, ..200]
+  
+  pub fn new(startNs: u64, garbage: GarbageRules, remarks: Sender<Remarks>) -> Commentator {
+    Commentator{startNs: startNs, sent: garbage.sent, attack: 0, remarks: remarks}
   }
-
-  impl<'a> TetrisGame<'a> {  
-    fn collides_with_set_blocks(&self, piece: &Piece) -> bool {
-      piece.blocks.iter().any(|block| self.setBlocks.has_block(block.row, block.column))
+  
+  impl Commentator {
+    pub fn rows_cleared(&mut self, rows: uint) {
+      self.attack += self.sent[rows - 1];
     }
     
-    fn in_bounds_bottom_row(piece: &Piece) -> bool {
-      piece.blocks.iter().all(|block| block.row <= 20)
+    pub fn piece_set(&mut self, timeNs: u64, engine: &GameEngine) {
+      let seconds = (timeNs - self.startNs) as f64 / 1000000000.0;
+      let top = range(1, board::height + 1).find(|row| engine.board.row_count(*row) > 0);
+      let remarks = Remarks{piecesPerSecond: if seconds > 0.0 { engine.pieces as f64 / seconds } else { 0.0 },
+                            attack:          self.attack,
+                            height:          top.map_or(0, |row| (board::height - row + 1) as uint)};
+      self.remarks.send(remarks);
     }
-    
-    fn in_bounds_cols(piece: &Piece) -> bool {
-      piece.blocks.iter().all(|block| block.column >= 1 && block.column <= 10)
+  }
+}
+
+// Versus matches over the network go through a relay ("tetris1 --serve PORT"), so neither
+// player has to be reachable by the other, and both can be behind NAT. Players connect to
+// it and are paired, then it passes on every line one of them sends to the other. Each
+// message is a line of text:
+//   player to relay:  "play" to be paired with the next player who asks, or "join CODE" to
+//                     be paired with the player who joins the room with the same code
+//   relay to player:  "wait" until there's an opponent, with "lobby PLAYERS MATCHES" (how
+//                     many players are connected and matches going on) whenever that changes,
+//                     then "start SEED SIDE" (both games use the pieces of SEED, SIDE is 0 for
+//                     the player who waited and 1 for the other), and "left" if the opponent goes
+//   player to player: "board MS MASKS" (the masks of the 20 rows, see print_mini_board),
+//                     "garbage MS ROWS" and "over MS" once their game is, each with how many
+//                     milliseconds the game had gone on for, "ping MS" answered at once
+//                     with "pong MS THEIRS" (the game time of the ping, and of the answer) to
+//                     measure the lag, and "chat TEXT" for a line they typed. The player who
+//                     waited starts with "rules SINGLE DOUBLE TRIPLE TETRIS CANCEL" (the
+//                     garbage rows sent for each clear from their config file, and 1 if the
+//                     rows sent cancel out garbage coming in), and both games keep to those.
+// Lines that aren't understood are ignored, so newer players can add messages.
+mod relay {
+  use std::libc::{c_int, c_long, c_short, c_void, size_t, ssize_t};
+  use std::io::IoError;
+  use std::ascii::StrAsciiExt;
+  use std::mem;
+  use std::os;
+  use std::ptr;
+  use std::str;
+  use std::vec_ng::Vec;
+  use rand;
+  use modes::GarbageRules;
+  use remote_scores;
+  use spectator;
+  use strings;
+  
+  pub enum Message {
+    Play,
+    Join(~str),        // the room code
+    Wait,
+    Lobby(uint, uint), // players connected and matches going on
+    Start(u64, uint),  // the seed and the player's side
+    Left,
+    // the events of a game come with its game time in milliseconds
+    Board(u64, Vec<u16>),
+    Garbage(u64, int),
+    Over(u64),
+    Ping(u64),
+    Pong(u64, u64),    // the game time of the ping, and of the answer
+    Rules(GarbageRules),
+    Chat(~str)         // a line the player typed
+  }
+  
+  pub fn encode(message: &Message) -> ~str {
+    match *message {
+      Play                    => ~"play",
+      Join(ref code)          => format!("join {}", *code),
+      Wait                    => ~"wait",
+      Lobby(players, matches) => format!("lobby {} {}", players, matches),
+      Start(seed, side)       => format!("start {} {}", seed, side),
+      Left                    => ~"left",
+      Board(ms, ref rows)     => {
+        let masks: Vec<~str> = rows.iter().map(|mask| mask.to_str()).collect();
+        format!("board {} {}", ms, masks.connect(","))
+      }
+      Garbage(ms, rows)       => format!("garbage {} {}", ms, rows),
+      Over(ms)                => format!("over {}", ms),
+      Ping(ms)                => format!("ping {}", ms),
+      Pong(ms, theirs)        => format!("pong {} {}", ms, theirs),
+      Rules(rules)            => format!("rules {} {} {} {} {}", rules.sent[0], rules.sent[1], rules.sent[2],
+                                         rules.sent[3], if rules.cancel { 1 } else { 0 }),
+      Chat(ref text)          => format!("chat {}", *text)
     }
-    
-    fn all_in_bounds(piece: &Piece) -> bool {
-      piece.blocks.iter().all(|block| block.row >= 1 && block.row <= 20 && block.column >= 1 && block.column <= 10)
+  }
+  
+  pub fn decode(line: &str) -> Option<Message> {
+    // the text of a chat line is kept as it was typed, spaces and all
+    if line.starts_with("chat ") {
+      let text = chat_text(line.slice_from(5));
+      return if text.len() > 0 { Some(Chat(text)) } else { None };
     }
-    
-    fn can_move_rows(&self, piece: &Piece, rowOffset: i8) -> bool {
-      let moved =  pieces::translate(piece, rowOffset, 0);
-      return TetrisGame::in_bounds_bottom_row(&moved) && !self.collides_with_set_blocks(&moved);
+    let words: Vec<&str> = line.words().collect();
+    if words.is_empty() {
+      return None;
     }
-
-    fn is_row_set(&self, row: i8) -> bool {
-      let mut col = 1;
-      while self.setBlocks.has_block(row, col) {
-        col += 1;
+    let args = words.slice_from(1);
+    match (*words.get(0), args.len()) {
+      ("play", 0)    => Some(Play),
+      ("join", 1)    => {
+        let code = args[0].to_ascii_upper();
+        if valid_room_code(code.as_slice()) { Some(Join(code)) } else { None }
       }
-      return col == 11i8;
-    }
-    
-    fn set_row_count(&self) -> int {
-      let mut count = 0;
-      for row in range(1, 21i8) {
-        if self.is_row_set(row) {
-          count += 1;
+      ("wait", 0)    => Some(Wait),
+      ("lobby", 2)   => match (from_str::<uint>(args[0]), from_str::<uint>(args[1])) {
+        (Some(players), Some(matches)) => Some(Lobby(players, matches)),
+        _                              => None
+      },
+      ("start", 2)   => match (from_str::<u64>(args[0]), from_str::<uint>(args[1])) {
+        (Some(seed), Some(side)) if side < 2 => Some(Start(seed, side)),
+        _                                    => None
+      },
+      ("left", 0)    => Some(Left),
+      ("board", 2)   => {
+        let rows: Vec<Option<u16>> = args[1].split(',').map(|mask| from_str::<u16>(mask)).collect();
+        match from_str::<u64>(args[0]) {
+          Some(ms) if rows.len() == 20 && rows.iter().all(|mask| mask.is_some()) => {
+            Some(Board(ms, rows.iter().map(|mask| mask.unwrap()).collect()))
+          }
+          _                                                                      => None
         }
       }
-      return count;
-    }
-    
-    fn erase_row(&self, row: i8) {
-      for col in range(1, 11i8) {
-        self.display.erase_block(row, col);
-      }
-    }
-    
-    fn erase_set_rows(&self) {
-      for row in range(1, 21i8) {
-        if self.is_row_set(row) {
-          self.erase_row(row);
+      ("garbage", 2) => match (from_str::<u64>(args[0]), from_str::<int>(args[1])) {
+        (Some(ms), Some(rows)) if rows > 0 => Some(Garbage(ms, rows)),
+        _                                  => None
+      },
+      ("over", 1)    => from_str::<u64>(args[0]).map(|ms| Over(ms)),
+      ("ping", 1)    => from_str::<u64>(args[0]).map(|ms| Ping(ms)),
+      ("pong", 2)    => match (from_str::<u64>(args[0]), from_str::<u64>(args[1])) {
+        (Some(ms), Some(theirs)) => Some(Pong(ms, theirs)),
+        _                        => None
+      },
+      ("rules", 5)   => {
+        // as many rows as the config file allows
+        let sent: Vec<Option<int>> = args.slice_to(4).iter().map(|rows| from_str::<int>(*rows)).collect();
+        if sent.iter().all(|rows| rows.map_or(false, |rows| rows >= 0 && rows <= 20)) && (args[4] == "0" || args[4] == "1") {
+          let mut rules = GarbageRules{sent: [0, ..4], cancel: args[4] == "1"};
+          for (i, rows) in sent.iter().enumerate() {
+            rules.sent[i] = rows.unwrap();
+          }
+          Some(Rules(rules))
+        } else {
+          None
         }
       }
+      _              => None
     }
-    
-    fn erase_all_set_blocks(&self) {
-      for row in range(1, 21i8) {
-        for col in range(1, 11i8) {
-          match self.setBlocks.get(row, col) {
-            None    => (),
-            Some(_) => self.display.erase_block(row, col)
-          }
-        }
+  }
+  
+  // characters a chat line is cut to, so it fits next to the board with who said it
+  pub static chatMaxLength: uint = 22;
+  
+  // The printable ASCII characters of text, up to chatMaxLength of them, so nothing the
+  // opponent sends can move the cursor or change colors
+  fn chat_text(text: &str) -> ~str {
+    text.chars().filter(|c| *c >= ' ' && *c <= '~').take(chatMaxLength).collect()
+  }
+  
+  // letters in a room code
+  static roomCodeLength: uint = 4;
+  
+  // Room codes are made up by the players, and given in either case. They're kept upper case.
+  pub fn valid_room_code(code: &str) -> bool {
+    code.len() == roomCodeLength && code.chars().all(|c| (c >= 'A' && c <= 'Z') || (c >= 'a' && c <= 'z'))
+  }
+  
+  // Linux specific struct sockaddr_in and struct pollfd, as in mod spectator and mod remote_scores
+  #[allow(non_camel_case_types)]
+  struct sockaddr_in {
+    sin_family: u16,
+    sin_port:   u16,
+    sin_addr:   u32,
+    sin_zero:   [u8, ..8]
+  }
+  
+  #[allow(non_camel_case_types)]
+  struct pollfd {
+    fd:      c_int,
+    events:  c_short,
+    revents: c_short
+  }
+  
+  extern {
+    fn socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int;
+    fn setsockopt(fd: c_int, level: c_int, name: c_int, value: *c_void, len: u32) -> c_int;
+    fn bind(fd: c_int, addr: *sockaddr_in, len: u32) -> c_int;
+    fn listen(fd: c_int, backlog: c_int) -> c_int;
+    fn accept(fd: c_int, addr: *mut c_void, len: *mut u32) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, arg: c_int) -> c_int;
+    fn poll(fds: *mut pollfd, nfds: c_long, timeout: c_int) -> c_int;
+    fn send(fd: c_int, buf: *c_void, len: size_t, flags: c_int) -> ssize_t;
+    fn recv(fd: c_int, buf: *mut c_void, len: size_t, flags: c_int) -> ssize_t;
+    fn close(fd: c_int) -> c_int;
+  }
+  
+  static afInet: c_int = 2;
+  static sockStream: c_int = 1;
+  static solSocket: c_int = 1;
+  static soReuseAddr: c_int = 2;
+  static fSetFl: c_int = 4;
+  static oNonBlock: c_int = 0x800;
+  static pollIn: c_short = 1;
+  static eAgain: int = 11;
+  static eIntr: int = 4;
+  static msgDontWait: c_int = 0x40;
+  static msgNoSignal: c_int = 0x4000;
+  
+  fn last_error<T>() -> Result<T, ~str> {
+    Err(IoError::last_error().to_str())
+  }
+  
+  // Adds the lines that have arrived on fd to lines, without waiting for more. The start of
+  // a line that hasn't been finished yet is kept in partial. Gives false once the other end
+  // has closed the connection.
+  fn read_lines(fd: c_int, partial: &mut ~str, lines: &mut Vec<~str>) -> bool {
+    let mut open = true;
+    let mut buf = [0u8, ..1024];
+    loop {
+      let n = unsafe { recv(fd, buf.as_mut_ptr() as *mut c_void, buf.len() as size_t, msgDontWait) };
+      if n <= 0 {
+        open = n < 0 && os::errno() == eAgain;
+        break;
       }
+      partial.push_str(str::from_utf8_lossy(buf.slice_to(n as uint)).as_slice());
     }
-
-    fn print_set_blocks(&self) {
-      for row in range(1, 21i8) {
-        for col in range(1, 11i8) {
-          match self.setBlocks.get(row, col) {
-            None        => (),
-            Some(block) => self.display.print_block(block),
-          }
+    loop {
+      match partial.find('\n') {
+        Some(end) => {
+          lines.push(partial.slice_to(end).trim().to_owned());
+          *partial = partial.slice_from(end + 1).to_owned();
         }
+        None => break
       }
-      self.display.flush();
     }
-    
-    fn set_piece(&mut self) {
-      for block in self.piece.blocks.iter() {
-        self.setBlocks.set(*block);
+    open
+  }
+  
+  // sends a line without waiting, or returns false if the player couldn't take it
+  fn send_line(fd: c_int, line: &str) -> bool {
+    let bytes = format!("{}\n", line);
+    unsafe {
+      let sent = send(fd, bytes.as_ptr() as *c_void, bytes.len() as size_t, msgDontWait | msgNoSignal);
+      sent == bytes.len() as ssize_t
+    }
+  }
+  
+  // a player connected to the relay
+  struct Player {
+    fd:       c_int,
+    partial:  ~str,
+    waiting:  bool,          // asked to play and has no opponent yet
+    room:     Option<~str>,  // the room code they're waiting with, None for anyone
+    opponent: Option<c_int>  // the player they're matched with
+  }
+  
+  struct Relay {
+    listener: c_int,
+    players:  Vec<Player>,
+    gone:     Vec<c_int>  // players to disconnect, once the lines that have come in are handled
+  }
+  
+  fn listen_on(port: u16) -> Result<c_int, ~str> {
+    unsafe {
+      let fd = socket(afInet, sockStream, 0);
+      if fd < 0 {
+        return last_error();
+      }
+      let on: c_int = 1;
+      setsockopt(fd, solSocket, soReuseAddr, &on as *c_int as *c_void, mem::size_of::<c_int>() as u32);
+      
+      let addr = sockaddr_in {
+        sin_family: afInet as u16,
+        sin_port:   mem::to_be16(port as i16) as u16,
+        sin_addr:   0,  // any address
+        sin_zero:   [0, ..8]
+      };
+      if bind(fd, &addr, mem::size_of::<sockaddr_in>() as u32) < 0 ||
+         listen(fd, 16) < 0 ||
+         fcntl(fd, fSetFl, oNonBlock) < 0 {
+        let error = last_error();
+        close(fd);
+        return error;
       }
+      Ok(fd)
     }
-    
-    fn clear_row(&mut self, row: i8) {
-      for col in range(1, 11i8) {
-        let mut r = row;
-        while r >= 2 {
-          match self.setBlocks.get(r - 1, col) {
-            None        => self.setBlocks.remove(r, col),
-            Some(block) => self.setBlocks.set(Block{row: r, column: col, color: block.color})
-          }
-          r -= 1;
-        }
+  }
+  
+  // Relays matches on port until the program is stopped, telling how many players are
+  // connected and matches going on whenever that changes. Nothing is drawn and no keys are
+  // read, so it can run without a terminal.
+  pub fn serve(port: u16) -> Result<(), ~str> {
+    let listener = match listen_on(port) {
+      Ok(fd)   => fd,
+      Err(msg) => return Err(strings::fill(strings::get().cantServe, &[port.to_str().as_slice(), msg.as_slice()]))
+    };
+    println!("{}", strings::fill(strings::get().relayListening, &[port.to_str().as_slice()]));
+    let mut relay = Relay{listener: listener, players: Vec::new(), gone: Vec::new()};
+    loop {
+      let mut fds = vec!(pollfd{fd: listener, events: pollIn, revents: 0});
+      for player in relay.players.iter() {
+        fds.push(pollfd{fd: player.fd, events: pollIn, revents: 0});
       }
-      for col in range(1, 11i8) {
-        self.setBlocks.remove(1, col);
+      if unsafe { poll(fds.as_mut_ptr(), fds.len() as c_long, -1) } < 0 && os::errno() != eIntr {
+        return last_error();
+      }
+      
+      let before = relay.counts();
+      let ready: Vec<c_int> = fds.iter().skip(1).filter(|pfd| pfd.revents != 0).map(|pfd| pfd.fd).collect();
+      for fd in ready.iter() {
+        relay.read_from(*fd);
+      }
+      relay.disconnect_gone();
+      relay.accept_players();
+      if relay.counts() != before {
+        let (players, matches) = relay.counts();
+        println!("{}", strings::fill(strings::get().relayStatus, &[players.to_str().as_slice(), matches.to_str().as_slice()]));
+        relay.tell_waiting();
+        relay.disconnect_gone();
       }
     }
-    
-    fn clear_set_rows(&mut self) {
-      let mut row = 20;
+  }
+  
+  impl Relay {
+    fn accept_players(&mut self) {
       loop {
-        while row >= 1 && !self.is_row_set(row) {
-          row -= 1;
-        }
-        if row == 0 {
-          break;
-        } else {
-          self.clear_row(row);      
+        let fd = unsafe { accept(self.listener, ptr::mut_null(), ptr::mut_null()) };
+        if fd < 0 {
+          return;
         }
+        self.players.push(Player{fd: fd, partial: ~"", waiting: false, room: None, opponent: None});
       }
     }
     
-    fn update_piece(&mut self, next: &Piece) {
-      self.display.erase_piece(&self.piece);
-      
-      self.piece = *next;
-      
-      self.display.print_piece(&self.piece);
-    }
-    
-    fn go_to_next_piece(&mut self) {
-        self.set_piece();
-        
-        self.display.erase_next_piece(&self.nextPiece);
-        
-        self.piece = self.nextPiece;
-        self.nextPiece = self.pieceGetter.next_piece();
-        
-        self.display.print_next_piece(&self.nextPiece);
+    // players connected, and matches going on
+    fn counts(&self) -> (uint, uint) {
+      (self.players.len(), self.players.iter().filter(|player| player.opponent.is_some()).count() / 2)
     }
     
-    fn step_fall(&mut self) -> Option<c_int> {
-      match self.can_move_rows(&self.piece, 1) {
-        true  => {
-          let translated = pieces::translate(&self.piece, 1, 0);
-          self.update_piece(&translated);
-          
-          Some(self.scoring.get_time())
-        }
-        false => {
-          if !TetrisGame::all_in_bounds(&self.piece) {
-            self.state = GameOver;
-            return Some(500);
-          }
-          
-          self.go_to_next_piece();
-          
-          let setRows = self.set_row_count();
-          if setRows > 0 {
-            self.erase_set_rows();
-            self.state = Clear;
-          }
-          
-          let s = self.scoring.update(setRows);
-          self.display.print_score(s);
-          
-          Some(1000)
-        }
+    // the players waiting for an opponent see how busy the relay is in their lobby
+    fn tell_waiting(&mut self) {
+      let (players, matches) = self.counts();
+      let waiting: Vec<c_int> = self.players.iter().filter(|player| player.waiting).map(|player| player.fd).collect();
+      for fd in waiting.iter() {
+        self.send(*fd, &Lobby(players, matches));
       }
     }
     
-    fn step_clear(&mut self) -> Option<c_int> {
-      self.erase_all_set_blocks();
-      
-      self.clear_set_rows();
-      
-      self.print_set_blocks();
-      
-      self.state = Fall;
-      
-      Some(1000)
+    fn index_of(&self, fd: c_int) -> Option<uint> {
+      self.players.iter().position(|player| player.fd == fd)
     }
     
-    fn step_game_over(&mut self) -> Option<c_int> {
-      self.scoreKeeper.store_score(&time::now(), self.scoring.get_score());
-      None
+    fn send(&mut self, fd: c_int, message: &Message) {
+      if !send_line(fd, encode(message).as_slice()) {
+        self.gone.push(fd);
+      }
     }
     
-    fn rotate(&mut self, clockwise: bool) {
-      let rotated = if clockwise {
-        pieces::rotate_clockwise(&self.piece)
-      } else {
-        pieces::rotate_counter_clockwise(&self.piece)
+    fn read_from(&mut self, fd: c_int) {
+      let index = match self.index_of(fd) {
+        Some(index) => index,
+        None        => return
       };
-      
-      if !TetrisGame::in_bounds_cols(&rotated) || self.collides_with_set_blocks(&rotated) {
-        return;
+      let mut lines = Vec::new();
+      let open = read_lines(fd, &mut self.players.get_mut(index).partial, &mut lines);
+      for line in lines.iter() {
+        self.handle_line(fd, line.as_slice());
+      }
+      if !open {
+        self.gone.push(fd);
       }
-      
-      self.update_piece(&rotated);
     }
     
-    fn quick_drop(&mut self) {
-      if !self.can_move_rows(&self.piece, 1) {
-        return;
+    // Lines from a player in a match go to their opponent as they are, the relay doesn't
+    // need to understand them. Before that, only asking to play or to join a room means anything.
+    fn handle_line(&mut self, fd: c_int, line: &str) {
+      let index = match self.index_of(fd) {
+        Some(index) => index,
+        None        => return
+      };
+      match (self.players.get(index).opponent, decode(line)) {
+        (Some(opponent), _)      => if !send_line(opponent, line) { self.gone.push(opponent) },
+        (None, Some(Play))       => self.pair(index, None),
+        (None, Some(Join(code))) => self.pair(index, Some(code)),
+        (None, _)                => ()
       }
-      
-      let mut translated = pieces::translate(&self.piece, 1, 0);
-      while self.can_move_rows(&translated, 1) {
-        translated = pieces::translate(&translated, 1, 0);
+    }
+    
+    // Matches the player with whoever has been waiting in the same room (or without one), or
+    // has them wait for the next one
+    fn pair(&mut self, index: uint, room: Option<~str>) {
+      let fd = self.players.get(index).fd;
+      match self.players.iter().position(|other| other.waiting && other.fd != fd && other.room == room) {
+        Some(other) => {
+          let otherFd = self.players.get(other).fd;
+          self.players.get_mut(other).waiting = false;
+          self.players.get_mut(other).opponent = Some(fd);
+          self.players.get_mut(index).waiting = false;
+          self.players.get_mut(index).opponent = Some(otherFd);
+          let seed = rand::random::<u32>() as u64;
+          self.send(otherFd, &Start(seed, 0));
+          self.send(fd, &Start(seed, 1));
+        }
+        None => {
+          self.players.get_mut(index).waiting = true;
+          self.players.get_mut(index).room = room;
+          let (players, matches) = self.counts();
+          self.send(fd, &Wait);
+          self.send(fd, &Lobby(players, matches));
+        }
       }
-      
-      self.update_piece(&translated);
     }
     
-    fn translate_cols(&mut self, columnOffset: i8) {
-      let translated = pieces::translate(&self.piece, 0, columnOffset);
-      
-      if !TetrisGame::in_bounds_cols(&translated) || self.collides_with_set_blocks(&translated) {
-        return;
+    // Closes the connections of the players who went or couldn't keep up. Their opponents
+    // are told, and can ask to play again.
+    fn disconnect_gone(&mut self) {
+      while !self.gone.is_empty() {
+        let fd = self.gone.pop().unwrap();
+        let index = match self.index_of(fd) {
+          Some(index) => index,
+          None        => continue
+        };
+        let player = self.players.remove(index).unwrap();
+        unsafe { close(player.fd); }
+        match player.opponent.and_then(|opponent| self.index_of(opponent)) {
+          Some(other) => {
+            let otherFd = self.players.get(other).fd;
+            self.players.get_mut(other).opponent = None;
+            self.send(otherFd, &Left);
+          }
+          None => ()
+        }
       }
-      
-      self.update_piece(&translated);
     }
   }
-
-  impl<'a> GameHandler for TetrisGame<'a> {
-    fn init(&self) {
-      self.display.print_next_piece(&self.nextPiece);
-      self.display.print_score(self.scoring.get_score());
-      self.display.flush();
+  
+  // A player's connection to the relay. What has arrived is read without waiting, sending
+  // waits (never for long, the lines are short) as remote_scores does.
+  pub struct Connection {
+    fd:      c_int,
+    partial: ~str,
+    open:    bool
+  }
+  
+  pub fn connect(address: &str) -> Result<Connection, ~str> {
+    let socketAddress = try!(spectator::resolve(address));
+    match unsafe { remote_scores::connect_to(socketAddress) } {
+      Ok(fd)   => Ok(Connection{fd: fd, partial: ~"", open: true}),
+      Err(msg) => Err(strings::fill(strings::get().errCantConnect, &[address, msg.as_slice()]))
     }
-    
-    fn handle_step(&mut self) -> Option<c_int> {    
-      let stepTime = 
-      match self.state {
-        Fall     => self.step_fall(),
-        Clear    => self.step_clear(),
-        GameOver => self.step_game_over()
-      };
-      self.display.flush();
-      stepTime
+  }
+  
+  impl Connection {
+    pub fn send(&mut self, message: &Message) {
+      if self.open && unsafe { remote_scores::send_all(self.fd, format!("{}\n", encode(message)).as_bytes()) }.is_err() {
+        self.open = false;
+      }
     }
     
-    fn handle_input(&mut self, input: input_reader::ReadResult) {
-      use input_reader::{Up, Down, Right, Left};
-      match input {
-        Up    => self.rotate(true),
-        Down  => self.quick_drop(),
-        Right => self.translate_cols(1),
-        Left  => self.translate_cols(-1),
-        _     => fail!("unknown direction")
+    // the messages that have arrived since the last call, without waiting for more
+    pub fn receive(&mut self) -> Vec<Message> {
+      let mut lines = Vec::new();
+      if self.open {
+        self.open = read_lines(self.fd, &mut self.partial, &mut lines);
       }
-      self.display.flush();
+      lines.iter().filter_map(|line| decode(line.as_slice())).collect()
     }
     
-    fn handle_quit(&self) {
-      self.scoreKeeper.store_score(&time::now(), self.scoring.get_score());
+    // false once the relay has closed the connection, or it's been lost
+    pub fn is_open(&self) -> bool {
+      self.open
+    }
+  }
+  
+  impl Drop for Connection {
+    fn drop(&mut self) {
+      unsafe {
+        close(self.fd);
+      }
     }
   }
+}
 
-  fn main_loop<T: GameHandler>(handler: &mut T) {
-    use input_reader::{poll_stdin, read_stdin, Other, PollReady, PollTimeout};
-    
-    handler.init();
-    
-    // milliseconds between piece drop steps
-    let mut stepTimeMs: c_int = 1000;
-    
-    // milliseconds for poll timeout
-    let mut pollTimeMs = stepTimeMs;
-    
-    // nanoseconds since the last drop step
-    let mut sinceLastStepNs = 0u64;
-    
-    loop {
-      let t = time::precise_time_ns();
-      match poll_stdin(pollTimeMs) {
-        PollReady   => {
-          match read_stdin() {
-            Other => {
-              handler.handle_quit();
-              break;
-            }
-            input => {
-              sinceLastStepNs += time::precise_time_ns() - t;
-              pollTimeMs = stepTimeMs - ((sinceLastStepNs / 1000000) as c_int);
-              handler.handle_input(input);
-            }
-          }
+mod crowd {
+  use std::libc::{c_char, c_int, c_void, size_t, ssize_t};
+  use std::io::{IoResult, IoError};
+  use std::str;
+  use std::ascii::StrAsciiExt;
+  use std::vec_ng::Vec;
+  use input_reader;
+  use input_reader::{Action, Rotate, MoveRight, MoveLeft, Hint};
+  
+  extern {
+    fn open(path: *c_char, flags: c_int) -> c_int;
+    fn read(fd: c_int, buf: *mut c_void, count: size_t) -> ssize_t;
+    fn close(fd: c_int) -> c_int;
+  }
+  
+  static oRdOnly: c_int = 0;
+  static oNonBlock: c_int = 0x800;
+  
+  // Commands read a line at a time from a named pipe (made with mkfifo), so a chat bot or
+  // anything else can feed votes in with "echo left > PIPE". The pipe is opened so reads
+  // never wait, and it doesn't matter if nothing has it open for writing.
+  pub struct CrowdInput {
+    fd:      c_int,
+    partial: ~str  // the start of a line that hasn't been finished yet
+  }
+  
+  pub fn open_pipe(path: &Path) -> IoResult<CrowdInput> {
+    let fd = path.with_c_str(|p| unsafe { open(p, oRdOnly | oNonBlock) });
+    if fd < 0 {
+      return Err(IoError::last_error());
+    }
+    Ok(CrowdInput{fd: fd, partial: ~""})
+  }
+  
+  pub fn action_from_command(command: &str) -> Option<Action> {
+    match command.trim().to_ascii_lower().as_slice() {
+      "left" | "l"             => Some(MoveLeft),
+      "right" | "r"            => Some(MoveRight),
+      "cw" | "rotate" | "up"   => Some(Rotate),
+      "drop" | "down"          => Some(input_reader::Drop), // not imported, it would hide the Drop trait
+      "hint"                   => Some(Hint),
+      _                        => None
+    }
+  }
+  
+  impl CrowdInput {
+    // every command that has arrived since the last call, without waiting for more
+    pub fn read_commands(&mut self) -> Vec<Action> {
+      let mut buf = [0u8, ..1024];
+      loop {
+        let n = unsafe { read(self.fd, buf.as_mut_ptr() as *mut c_void, buf.len() as size_t) };
+        if n <= 0 {
+          break;
         }
-        PollTimeout => {
-          match handler.handle_step() {
-            None                 => { break; }
-            Some(nextStepTimeMs) => {
-              stepTimeMs = nextStepTimeMs;
-              pollTimeMs = nextStepTimeMs;
-              sinceLastStepNs = 0;
-            }
+        self.partial.push_str(str::from_utf8_lossy(buf.slice_to(n as uint)).as_slice());
+      }
+      
+      let mut actions = Vec::new();
+      loop {
+        let line = match self.partial.find('\n') {
+          Some(end) => {
+            let line = self.partial.slice_to(end).to_owned();
+            self.partial = self.partial.slice_from(end + 1).to_owned();
+            line
           }
+          None => break
+        };
+        match action_from_command(line) {
+          Some(action) => actions.push(action),
+          None         => ()
         }
       }
+      actions
     }
   }
-
-  pub fn run_game(display: &Display) {
-    // the restorer resets the terminal out of raw mode once it's dropped
-    let _restorer = terminal_control::set_terminal_raw_mode();
-    
-    display.init();
-    
-    let mut scoring = scoring::new();
-    
-    let scoreKeeper = score_keeper::get();
-    
-    let mut pieceGetter = piece_getter::new();
-    let firstPiece = pieceGetter.next_piece();
-    let secondPiece = pieceGetter.next_piece();
-
-    display.print_next_piece(&secondPiece);
-    
-    let mut game = TetrisGame{display:     display,
-                              pieceGetter: pieceGetter,
-                              scoring:     scoring,
-                              scoreKeeper: scoreKeeper,
-                              state:       Fall,
-                              piece:       firstPiece,
-                              nextPiece:   secondPiece,
-                              setBlocks:   [None, ..200]};
-
-    main_loop(&mut game);
-    
-    display.close();
+  
+  impl Drop for CrowdInput {
+    fn drop(&mut self) {
+      unsafe {
+        close(self.fd);
+      }
+    }
+  }
+  
+  // The action with the most votes, ties go to whichever got its first vote earliest
+  pub fn winner(votes: &[Action]) -> Option<Action> {
+    let mut best: Option<(Action, uint)> = None;
+    for action in votes.iter() {
+      let count = votes.iter().filter(|a| *a == action).count();
+      match best {
+        Some((_, bestCount)) if bestCount >= count => (),
+        _                                          => best = Some((*action, count))
+      }
+    }
+    best.map(|(action, _)| action)
   }
 }
 
-fn display_help() {
-  println("");
-  println("A simple game of Tetris implemented in Rust");
-  println("");
-  println("Options:");
-  println("--help or -h             |  show this help");
-  println("--scores                 |  show scores");
-  println("--display=double or -d2  |  run in double display mode");
-  println("");
-  println("Controls:");
-  println("left arrow     | move piece left");
-  println("right arrow    | move piece right");
-  println("up arrow       | rotate piece");
-  println("down arrow     | quick drop piece");
-  println("any other key  | exit the game");
-  println("");
-  println("Run this program with no arguments to start a game in standard display mode");
-  println("");
-}
-
-fn display_scores() {
-/*
-High Scores:                   Recent Scores:
-Thu Jan  1 00:00:00 1970       Thu Jan 1 00:00:00 1970
-level: 1                       level: 1
-bonus: 1                       bonus: 1
-score: 1                       score: 1
-
-Thu Jan  1 00:00:00 1970       Thu Jan 1 00:00:00 1970
-level: 1                       level: 1
-bonus: 1                       bonus: 1
-score: 1                       score: 1
-
-Thu Jan  1 00:00:00 1970       Thu Jan 1 00:00:00 1970
-level: 1                       level: 1
-bonus: 1                       bonus: 1
-score: 1                       score: 1
-
-Thu Jan  1 00:00:00 1970       Thu Jan 1 00:00:00 1970
-level: 1                       level: 1
-bonus: 1                       bonus: 1
-score: 1                       score: 1
-
-Thu Jan  1 00:00:00 1970       Thu Jan 1 00:00:00 1970
-level: 1                       level: 1
-bonus: 1                       level: 1
-score: 1                       level: 1
-*/
-
-  fn digits(i: int) -> int {
-    match i {
-      0 .. 9             => 1,
-      10 .. 99           => 2,
-      100 .. 999         => 3,
-      1000 .. 9999       => 4,
-      10000 .. 99999     => 5,
-      100000 .. 999999   => 6,
-      1000000 .. 1000000 => 7,
-      _                  => 8
-    }
+mod replay {
+  use std::io::{BufferedReader, BufferedWriter, File, IoResult};
+  use std::vec_ng::Vec;
+  use input_reader::{Action, Rotate, Drop, MoveRight, MoveLeft, Hint, Stats, Spawn, ClearBoard, StepGame, DumpState, Pause,
+                     FocusLost, FocusGained, Quit};
+  use std::libc::c_int;
+  use std::num;
+  use pieces::{Piece, PieceType, I, J, L, O, S, T, Z};
+  use board;
+  use board::Board;
+  use engine::{GameEngine, State, Fall, Clear, Entry, Finished};
+  use scoring::Score;
+  use saved_game;
+  use time;
+  use modes;
+  use modes::Mode;
+  use strings;
+  
+  // A replay is a text file: a header line "tetris1-replay 3 MODE SEED", followed by one
+  // line per event, "MS CODE", where MS is milliseconds of play since the game started and CODE is
+  // one of r (rotate), d (drop), > (move right), < (move left), h (hint), i (statistics shown or hidden),
+  // 1 to 7 (a sandbox piece), 0 (the sandbox board cleared), n (a step asked for when stepping),
+  // l (the state logged), q (quit) or s (gravity step).
+  // Together with the seed this is enough to play the game again exactly. Pausing isn't
+  // recorded (nothing happens in the game meanwhile), and since version 3 the time spent
+  // paused isn't counted, so the times are the game's own clock, which the modes that end
+  // on time go by.
+  //
+  // Since version 2 there's also a checkpoint line every few seconds, "MS c PIECES STATE STEP
+  // PIECE NEXT LEVEL BONUS SCORE LINES TETRISES BOARD", with the game as it was just after a
+  // piece was set: the pieces set so far, the engine state and milliseconds to its next step,
+  // the falling and next piece, the score, and the board's snapshot in hex. Playback can start
+  // again from a checkpoint instead of from the start of the game. Since version 3 a checkpoint
+  // also ends with the scoring's count of clears on the level, after BOARD.
+  pub static replayVersion: uint = 3;
+  
+  // nanoseconds between checkpoints
+  static checkpointIntervalNs: u64 = 5000000000;
+  
+  pub struct ReplayRecorder {
+    file:    BufferedWriter<File>,
+    startNs: u64,
+    checkpointNs: u64  // when the last checkpoint was written
   }
   
-  fn print_spaces(n: int) {
-    for _ in range(0, n) {
-      print(" ");
-    }
+  // event times are counted from when the recorder is made, until startNs is set to when the game started
+  pub fn new(path: &Path, mode: Mode, seed: u64) -> IoResult<ReplayRecorder> {
+    let mut file = BufferedWriter::new(try!(File::create(path)));
+    try!(file.write_line(format!("tetris1-replay {} {} {}", replayVersion, modes::name(mode), seed)));
+    let now = time::precise_time_ns();
+    Ok(ReplayRecorder{file: file, startNs: now, checkpointNs: now})
   }
   
-  fn max(a: uint, b: uint) -> uint {
-    if a >= b {
-      a
-    } else {
-      b
+  pub fn action_code(action: Action) -> char {
+    match action {
+      Rotate     => 'r',
+      Drop       => 'd',
+      MoveRight  => '>',
+      MoveLeft   => '<',
+      Hint       => 'h',
+      Stats      => 'i',
+      Spawn(ty)  => (0x31 + ty as u8) as char,
+      ClearBoard => '0',
+      StepGame   => 'n',
+      DumpState  => 'l',
+      // like pausing, losing focus stops the game's clock and isn't recorded
+      Pause | FocusLost | FocusGained => 'p',
+      Quit       => 'q'
     }
   }
   
-  println("");
-  println("High Scores:                   Recent Scores:");
+  static stepCode: char = 's';
   
-  let scores = &score_keeper::get().get_scores();  
-  let highScores = scores.highScores.as_slice();
-  let recentScores = scores.recentScores.as_slice();
+  // the sandbox pieces, by their code less one
+  static spawnTypes: [PieceType, ..7] = [I, J, L, O, S, T, Z];
   
-  let n = max(highScores.len(), recentScores.len());
-
-  for i in range(0, n) {
-    if i < highScores.len() && i < recentScores.len() {
-      let (ref highScoreTm, ref highScoreScore) = highScores[i];
-      let (ref recentScoreTm, ref recentScoreScore) = recentScores[i];
-      println!("{}       {}", highScoreTm.ctime(), recentScoreTm.ctime());
-        
-      print!("level: {}", highScoreScore.level);
-      print_spaces(24 - digits(highScoreScore.level as int));
-      println!("level: {}", recentScoreScore.level);
-      
-      print!("bonus: {}", highScoreScore.bonus);
-      print_spaces(24 - digits(highScoreScore.bonus));
-      println!("bonus: {}", recentScoreScore.bonus);
-      
-      print!("score: {}", highScoreScore.score);
-      print_spaces(24 - digits(highScoreScore.score));
-      println!("score: {}", recentScoreScore.score);
-    
-    } else if i < highScores.len() {
-      let (ref highScoreTm, ref highScoreScore) = highScores[i];
-      println!("{}", highScoreTm.ctime());
-      println!("level: {}", highScoreScore.level);
-      println!("bonus: {}", highScoreScore.bonus);
-      println!("score: {}", highScoreScore.score);
-    
-    } else if i < recentScores.len() {
-      let (ref recentScoreTm, ref recentScoreScore) = recentScores[i];
-      println!("                               {}", recentScoreTm.ctime());
-      println!("                               level: {}", recentScoreScore.level);
-      println!("                               bonus: {}", recentScoreScore.bonus);
-      println!("                               score: {}", recentScoreScore.score);
+  pub enum ReplayEvent {
+    PlayerAction(Action),
+    GravityStep
+  }
+  
+  pub struct TimedEvent {
+    timeMs: u64,
+    event:  ReplayEvent
+  }
+  
+  // The game as it was at some point of a replay
+  pub struct Checkpoint {
+    timeMs:    u64,
+    event:     uint,  // index of the first event after it
+    pieces:    uint,
+    state:     State,
+    stepTime:  c_int,
+    piece:     Piece,
+    nextPiece: Piece,
+    score:     Score,
+    count:     int,
+    board:     Board
+  }
+  
+  pub struct Replay {
+    mode:        Mode,
+    seed:        u64,
+    events:      Vec<TimedEvent>,
+    checkpoints: Vec<Checkpoint>
+  }
+  
+  // the engine states, by their number
+  static states: [State, ..4] = [Fall, Clear, Entry, Finished];
+  
+  // the game's engine as a checkpoint, ahead of the given event
+  pub fn checkpoint(timeMs: u64, event: uint, engine: &GameEngine) -> Checkpoint {
+    Checkpoint{timeMs:    timeMs,
+               event:     event,
+               pieces:    engine.pieces,
+               state:     engine.state,
+               stepTime:  engine.stepTime,
+               piece:     engine.piece,
+               nextPiece: engine.nextPiece,
+               score:     engine.scoring.get_score(),
+               count:     engine.scoring.get_count(),
+               board:     engine.board}
+  }
+  
+  fn checkpoint_line(checkpoint: &Checkpoint) -> ~str {
+    let mut board = ~"";
+    for byte in checkpoint.board.snapshot().iter() {
+      board.push_str(format!("{:02x}", *byte));
     }
-    println("");
+    let score = checkpoint.score;
+    format!("{} c {} {} {} {} {} {} {} {} {} {} {} {}", checkpoint.timeMs, checkpoint.pieces, checkpoint.state as uint,
+            checkpoint.stepTime, saved_game::piece_name(&checkpoint.piece), saved_game::piece_name(&checkpoint.nextPiece),
+            score.level, score.bonus, score.score, score.lines, score.tetrises, board, checkpoint.count)
   }
-}
-
-fn main() {
-  let args = os::args();
-
-  // There's always at least one argument (the program's name)
-  // If the program is run with no extra argument's passed by the user, just run the game in standard display mode
-  //
-  // Otherwise there are at least two arguments, handle double display or help argument.
-  // If we don't understand the argument, just show the help
-  match args.len() {
-    1 => tetris::run_game(&graphics::StandardDisplay),
-    _ => {
-      match args[1].as_slice() {
-        "--help" | "-h"            => display_help(),
-        "--score" | "--scores"     => display_scores(),
-        "--display=double" | "-d2" => tetris::run_game(&graphics::DoubleDisplay),
-        _                          => display_help()
+  
+  fn board_from_hex(hex: &str) -> Option<Board> {
+    if hex.len() != board::snapshotBytes * 2 {
+      return None;
+    }
+    let mut bytes = Vec::new();
+    for i in range(0, board::snapshotBytes) {
+      match num::from_str_radix::<u8>(hex.slice(i * 2, i * 2 + 2), 16) {
+        Some(byte) => bytes.push(byte),
+        None       => return None
+      }
+    }
+    board::from_snapshot(bytes.as_slice())
+  }
+  
+  // reads the fields of a checkpoint line after its time and "c"
+  fn checkpoint_from_fields(timeMs: u64, event: uint, fields: &[&str]) -> Option<Checkpoint> {
+    // version 2 checkpoints have no count
+    let count = match fields.len() {
+      11 => Some(0),
+      12 => from_str::<int>(fields[11]),
+      _  => return None
+    };
+    let number = |i: uint| from_str::<int>(fields[i]);
+    match (from_str::<uint>(fields[0]), from_str::<uint>(fields[1]), number(2),
+           saved_game::piece_from_name(fields[3]), saved_game::piece_from_name(fields[4]),
+           from_str::<u16>(fields[5]), number(6), from_str::<i64>(fields[7]), number(8), number(9), board_from_hex(fields[10]), count) {
+      (Some(pieces), Some(state), Some(stepTime), Some(piece), Some(nextPiece),
+       Some(level), Some(bonus), Some(score), Some(lines), Some(tetrises), Some(board), Some(count)) if state < states.len() => {
+        Some(Checkpoint{timeMs:    timeMs,
+                        event:     event,
+                        pieces:    pieces,
+                        state:     states[state],
+                        stepTime:  stepTime as c_int,
+                        piece:     piece,
+                        nextPiece: nextPiece,
+                        score:     Score{level: level, bonus: bonus, score: score, lines: lines, tetrises: tetrises},
+                        count:     count,
+                        board:     board})
       }
+      _ => None
+    }
+  }
+  
+  fn event_from_code(code: &str) -> Option<ReplayEvent> {
+    match code {
+      "r" => Some(PlayerAction(Rotate)),
+      "d" => Some(PlayerAction(Drop)),
+      ">" => Some(PlayerAction(MoveRight)),
+      "<" => Some(PlayerAction(MoveLeft)),
+      "h" => Some(PlayerAction(Hint)),
+      "i" => Some(PlayerAction(Stats)),
+      "0" => Some(PlayerAction(ClearBoard)),
+      "n" => Some(PlayerAction(StepGame)),
+      "l" => Some(PlayerAction(DumpState)),
+      "q" => Some(PlayerAction(Quit)),
+      "s" => Some(GravityStep),
+      _   => from_str::<uint>(code).and_then(|n| if n >= 1 && n <= 7 { Some(PlayerAction(Spawn(spawnTypes[n - 1]))) } else { None })
     }
   }
+  
+  pub fn load(path: &Path) -> Result<Replay, ~str> {
+    let file = match File::open(path) {
+      Ok(file) => file,
+      Err(e)   => return Err(strings::fill(strings::get().errCantRead,
+                                           &[path.display().to_str().as_slice(), e.to_str().as_slice()]))
+    };
+    let mut reader = BufferedReader::new(file);
+    let mut lines = reader.lines();
+    
+    let header = lines.next().unwrap_or(~"");
+    let fields: Vec<&str> = header.words().collect();
+    let (mode, seed) = match fields.as_slice() {
+      ["tetris1-replay", version, mode, seed] if from_str::<uint>(version).map_or(false, |v| v >= 1 && v <= replayVersion) => {
+        match (modes::from_name(mode), from_str::<u64>(seed)) {
+          (Some(mode), Some(seed)) => (mode, seed),
+          _                        => return Err(strings::fill(strings::get().errReplayHeader,
+                                                               &[path.display().to_str().as_slice()]))
+        }
+      }
+      _ => return Err(strings::fill(strings::get().errNotReplay, &[path.display().to_str().as_slice()]))
+    };
+    
+    let mut events = Vec::new();
+    let mut checkpoints = Vec::new();
+    for (i, line) in lines.enumerate() {
+      let fields: Vec<&str> = line.words().collect();
+      match fields.as_slice() {
+        [time, "c", ..rest] => match from_str::<u64>(time).and_then(|time| checkpoint_from_fields(time, events.len(), rest)) {
+          Some(checkpoint) => {
+            checkpoints.push(checkpoint);
+            continue;
+          }
+          None => return Err(strings::fill(strings::get().errBadCheckpoint,
+                                           &[path.display().to_str().as_slice(), (i + 2).to_str().as_slice()]))
+        },
+        _ => ()
+      }
+      let event = match fields.as_slice() {
+        [time, code] => match (from_str::<u64>(time), event_from_code(code)) {
+          (Some(time), Some(event)) => Some(TimedEvent{timeMs: time, event: event}),
+          _                         => None
+        },
+        _ => None
+      };
+      match event {
+        Some(event) => events.push(event),
+        None        => return Err(strings::fill(strings::get().errBadEvent,
+                                                &[path.display().to_str().as_slice(), (i + 2).to_str().as_slice()]))
+      }
+    }
+    Ok(Replay{mode: mode, seed: seed, events: events, checkpoints: checkpoints})
+  }
+  
+  impl ReplayRecorder {
+    fn write_event(&mut self, timeNs: u64, code: char) {
+      let _ = self.file.write_line(format!("{} {}", (timeNs - self.startNs) / 1000000, code));
+    }
+    
+    pub fn record_action(&mut self, timeNs: u64, action: Action) {
+      self.write_event(timeNs, action_code(action));
+    }
+    
+    pub fn record_step(&mut self, timeNs: u64) {
+      self.write_event(timeNs, stepCode);
+    }
+    
+    // the game was paused for pausedNs, which doesn't count towards the event times
+    pub fn record_pause(&mut self, pausedNs: u64) {
+      self.startNs += pausedNs;
+      self.checkpointNs += pausedNs;
+    }
+    
+    // writes a checkpoint of the game, if it's been long enough since the last one
+    pub fn record_checkpoint(&mut self, timeNs: u64, engine: &GameEngine) {
+      if timeNs - self.checkpointNs < checkpointIntervalNs {
+        return;
+      }
+      self.checkpointNs = timeNs;
+      let line = checkpoint_line(&checkpoint((timeNs - self.startNs) / 1000000, 0, engine));
+      let _ = self.file.write_line(line);
+    }
+  }
+}
+
+// Pictures of a replayed game, one file per frame, to make an animation from
+mod frame_export {
+  use std::io::{File, IoResult, Process, UserRWX};
+  use std::io::fs;
+  use strings;
+  
+  // milliseconds of the game between frames, 10 a second
+  pub static frameIntervalMs: u64 = 100;
+  
+  // makes the directory the frames go in, if it's not there yet
+  pub fn create_dir(dir: &Path) -> IoResult<()> {
+    if dir.is_dir() {
+      return Ok(());
+    }
+    fs::mkdir_recursive(dir, UserRWX)
+  }
+  
+  // The file for frame n is frame-00001.ans and so on, counting from 1, so the frames sort
+  // in order. It's plain text with color escapes, cat shows it in a terminal.
+  pub fn frame_path(dir: &Path, n: uint) -> Path {
+    dir.join(format!("frame-{:05u}.ans", n))
+  }
+  
+  // Writes a frame, then runs the converter command on it if there is one. "{}" in the
+  // command is replaced by the frame's path, for example "ansilove -o {}.png {}". Each
+  // command is waited for, so the frames are converted one at a time, in order.
+  pub fn write_frame(dir: &Path, n: uint, picture: &str, command: &Option<~str>) -> Result<(), ~str> {
+    let path = frame_path(dir, n);
+    match File::create(&path).and_then(|mut file| file.write_str(picture)) {
+      Ok(())  => (),
+      Err(e)  => return Err(strings::fill(strings::get().errCantWrite,
+                                          &[path.display().to_str().as_slice(), e.to_str().as_slice()]))
+    }
+    match *command {
+      Some(ref command) => {
+        let command = command.replace("{}", path.display().to_str());
+        match Process::status("sh", [~"-c", command.clone()]) {
+          Ok(status) if status.success() => (),
+          _                              => return Err(strings::fill(strings::get().errCommandFailed, &[command.as_slice()]))
+        }
+      }
+      None => ()
+    }
+    Ok(())
+  }
+}
+
+// The curses backend: instead of writing escape sequences itself, the game draws through
+// ncurses, which looks up how to do everything for the terminal in terminfo and only sends
+// what changed when the screen is refreshed
+mod curses {
+  use std::libc::{c_char, c_int, c_short, c_void};
+  
+  #[link(name = "ncurses")]
+  extern {
+    fn initscr() -> *c_void;
+    fn endwin() -> c_int;
+    fn start_color() -> c_int;
+    fn use_default_colors() -> c_int;
+    fn init_pair(pair: c_short, foreground: c_short, background: c_short) -> c_int;
+    fn curs_set(visibility: c_int) -> c_int;
+    fn noecho() -> c_int;
+    fn attrset(attributes: c_int) -> c_int;
+    #[link_name = "move"]
+    fn move_cursor(row: c_int, col: c_int) -> c_int;
+    fn addstr(text: *c_char) -> c_int;
+    #[link_name = "clear"]
+    fn clear_screen() -> c_int;
+    #[link_name = "refresh"]
+    fn refresh_screen() -> c_int;
+  }
+  
+  static colorWhite: c_short = 7;
+  
+  static mut active: bool = false;
+  
+  pub fn is_active() -> bool {
+    unsafe { active }
+  }
+  
+  // Color pairs 1 to 8 are the block colors as backgrounds, and 9 to 16 the same colors as
+  // text on the terminal's own background (or black, if the terminal can't keep its own).
+  // Only the 8 normal colors are set up, the bright ones are drawn as the normal ones.
+  pub fn background_pair(color: u8) -> c_short {
+    1 + (color % 8) as c_short
+  }
+  
+  pub fn foreground_pair(color: u8) -> c_short {
+    9 + (color % 8) as c_short
+  }
+  
+  // ends curses once it's dropped
+  pub struct Session;
+  
+  impl Drop for Session {
+    fn drop(&mut self) {
+      stop();
+    }
+  }
+  
+  pub fn start() -> Session {
+    unsafe {
+      initscr();
+      noecho();
+      start_color();
+      let background = if use_default_colors() == 0 { -1 } else { 0 };
+      for color in range(0, 8 as c_short) {
+        init_pair(background_pair(color as u8), colorWhite, color);
+        init_pair(foreground_pair(color as u8), color, background);
+      }
+      active = true;
+    }
+    Session
+  }
+  
+  fn stop() {
+    unsafe {
+      if active {
+        endwin();
+        active = false;
+      }
+    }
+  }
+  
+  pub fn set_colors(pair: c_short) {
+    // COLOR_PAIR(pair)
+    unsafe { attrset((pair as c_int) << 8); }
+  }
+  
+  // rows and columns start at 1, like the escape sequences, rather than curses' 0
+  pub fn move_to(row: int, col: int) {
+    unsafe { move_cursor((row - 1) as c_int, (col - 1) as c_int); }
+  }
+  
+  pub fn put(text: &str) {
+    text.with_c_str(|text| unsafe { addstr(text); });
+  }
+  
+  pub fn clear() {
+    unsafe { clear_screen(); }
+  }
+  
+  pub fn show_cursor(show: bool) {
+    unsafe { curs_set(if show { 1 } else { 0 }); }
+  }
+  
+  pub fn refresh() {
+    unsafe { refresh_screen(); }
+  }
+}
+
+mod strings {
+  use std::os;
+  
+  // Everything the game says to the player, in one table for each language. A translation
+  // is another Strings table, and a name for it in from_name. Where a value goes into the
+  // text it's marked with {}, filled in with fill(), so each language can put it where it
+  // reads best.
+  pub struct Strings {
+    // the information area next to the board, also used on the game over box
+    level:         &'static str,
+    bonus:         &'static str,
+    score:         &'static str,
+    lines:         &'static str,
+    tetris:        &'static str,
+    next:          &'static str,
+    tetrises:      &'static str,
+    time:          &'static str,
+    seed:          &'static str,
+    thousands:     &'static str,  // between each 3 digits of a big number
+    
+    // game over headings, centered over the board
+    gameOver:      &'static str,
+    finished:      &'static str,
+    timeUp:        &'static str,
+    winner:        &'static str,
+    victory:       &'static str,
+    opponentLeft:  &'static str,
+    
+    titleScreen:   [&'static str, ..6],
+    replayBanner:  &'static str,
+    demoBanner:    &'static str,
+    botBanner:     &'static str,
+    crowdBanner:   &'static str,
+    firstPlayer:   &'static str,
+    humanPlayer:   &'static str,
+    computerPlayer: &'static str,
+    seriesScore:   &'static str,
+    seriesWon:     &'static str,
+    paused:        &'static str,
+    pauseHelp:     &'static str,
+    nextPiece:     &'static str,
+    spawnDelay:    &'static str,
+    on:            &'static str,
+    off:           &'static str,
+    displayMode:   &'static str,
+    standard:      &'static str,
+    double:        &'static str,
+    idleHelp:      &'static str,
+    resumingIn:    &'static str,
+    tournamentNames:     &'static str,
+    tournamentTooFew:    &'static str,
+    tournamentTurn:      &'static str,
+    tournamentStandings: &'static str,
+    tournamentPlace:     &'static str,
+    secondPlayer:  &'static str,
+    ghost:         &'static str,
+    opponent:      &'static str,
+    waitingForOpponent: &'static str,
+    waitingInRoom: &'static str,
+    waitedFor:     &'static str,
+    lobbyHelp:     &'static str,
+    chatHint:      &'static str,
+    chatYou:       &'static str,
+    chatOpponent:  &'static str,
+    latency:       &'static str,
+    tutorial:      [&'static str, ..5],
+    
+    // the scores and statistics
+    modeHeading:   &'static str,
+    highScores:    &'static str,
+    recentScores:  &'static str,
+    scoreOrders:   [&'static str, ..3],  // headings for --sort score, date and level
+    periodHeadings: [&'static str, ..2], // headings for --period week and month
+    seedScores:    &'static str,
+    scoreColumns:  [&'static str, ..9],  // date, level, bonus, score, lines, tetrises, time, seed, board
+    randomSeed:    &'static str,
+    boardFull:     &'static str,
+    allModes:      &'static str,
+    statLabels:    [&'static str, ..6],  // games, lines, average score, best level, tetris rate, time
+    hudLabels:     [&'static str, ..5],  // pieces per second, keys per piece, tetris rate, I drought, time
+    commentary:    &'static str,
+    dangerLevels:  [&'static str, ..4],  // low, medium, high, critical
+    noGames:       &'static str,
+    graphLabels:   [&'static str, ..3],  // heading, oldest game, newest game
+    
+    // --ai-bench
+    benchLabels:   [&'static str, ..7],  // games, lines, score, best score, pieces, piece limit, time
+    benchSeeds:    &'static str,
+    benchStopped:  &'static str,
+    benchTime:     &'static str,
+    
+    // --ai-tune
+    tuneStart:     &'static str,
+    tuneTry:       &'static str,
+    tuneBetter:    &'static str,
+    tuneLabels:    [&'static str, ..4],  // games per try, best weights, lines, time
+    tuneGames:     &'static str,
+    
+    // messages
+    runHelp:        &'static str,
+    noTerminal:     &'static str,
+    noTwoPlayerText: &'static str,
+    cantReplay:     &'static str,
+    cantResume:     &'static str,
+    savedGame:      &'static str,
+    resumeChoices:  &'static str,
+    unknownMode:    &'static str,
+    cantRace:       &'static str,
+    cantReadVotes:  &'static str,
+    cantRecord:     &'static str,
+    cantSpectate:   &'static str,
+    cantLed:        &'static str,
+    exported:       &'static str,
+    cantExport:     &'static str,
+    framesExported: &'static str,
+    cantFrames:     &'static str,
+    imported:       &'static str,
+    cantImport:     &'static str,
+    configProblem:  &'static str,
+    configExists:   &'static str,
+    wroteConfig:    &'static str,
+    savedWeights:   &'static str,
+    cantWrite:      &'static str,
+    noLeaderboard:  &'static str,
+    cantFetch:      &'static str,
+    cantWatch:      &'static str,
+    cantPlayOnline: &'static str,
+    cantServe:      &'static str,
+    relayListening: &'static str,
+    relayStatus:    &'static str,
+    botFailed:      &'static str,
+    cantLog:        &'static str,
+    cantAnnounce:   &'static str,
+    
+    // problems with files and replays
+    errCantRead:        &'static str,
+    errCantWrite:       &'static str,
+    errNotJson:         &'static str,
+    errNotReplay:       &'static str,
+    errReplayHeader:    &'static str,
+    errBadCheckpoint:   &'static str,
+    errBadEvent:        &'static str,
+    errBadScoreLine:    &'static str,
+    errCommandFailed:   &'static str,
+    errNoSavedGame:     &'static str,
+    errBoardRows:       &'static str,
+    errBoardCells:      &'static str,
+    errBoardCell:       &'static str,
+    
+    // problems on the network
+    errNotHttp:         &'static str,
+    errHostPort:        &'static str,
+    errBadPort:         &'static str,
+    errNoHost:          &'static str,
+    errCantFind:        &'static str,
+    errCantFindBecause: &'static str,
+    errCantConnect:     &'static str,
+    errRelayClosed:     &'static str,
+    errCantReach:       &'static str,
+    errNotIpv4:         &'static str,
+    errTimedOut:        &'static str,
+    errBadResponse:     &'static str,
+    errAnswered:        &'static str,
+    errBadScores:       &'static str,
+    
+    // problems with the bot
+    errCantRun:         &'static str,
+    errBotWrite:        &'static str,
+    errBotSilent:       &'static str,
+    errBotPlacement:    &'static str,
+    
+    // problems in the config file, naming the setting
+    errConfigRead:      &'static str,
+    errConfigJson:      &'static str,
+    errTopLevel:        &'static str,
+    errString:          &'static str,
+    errObject:          &'static str,
+    errList:            &'static str,
+    errBool:            &'static str,
+    errWholeNumber:     &'static str,
+    errOneOf:           &'static str,
+    errUnknownValue:    &'static str,
+    errUnknownIn:       &'static str,
+    errStringIn:        &'static str,
+    errBoolIn:          &'static str,
+    errKeyIn:           &'static str,
+    errNumberIn:        &'static str,
+    errWholeNumberIn:   &'static str,
+    errColorIn:         &'static str,
+    errNoLevels:        &'static str,
+    errTooManyLevels:   &'static str,
+    errLevelObject:     &'static str,
+    errLevelNumber:     &'static str,
+    errLevelMissing:    &'static str,
+    
+    // problems with the command line, naming the option
+    errNoValue:         &'static str,
+    errNeedsValue:      &'static str,
+    errNeedsNumber:     &'static str,
+    errChoice:          &'static str,
+    timeSpans:          &'static str,
+    errAtLeastGames:    &'static str,
+    errAtLeastTries:    &'static str,
+    errGamesRange:      &'static str,
+    errOddGames:        &'static str,
+    errPlayerName:      &'static str,
+    errRoomCode:        &'static str,
+    errAboveZero:       &'static str,
+    errMillis:          &'static str,
+    errUnknownOption:   &'static str,
+    errUnknownCommand:  &'static str,
+    errUnexpected:      &'static str,
+    errNeeds:           &'static str,
+    errExportReplay:    &'static str,
+    errScoresOnly:      &'static str,
+    errPeriodSort:      &'static str,
+    errSeedPeriod:      &'static str,
+    
+    // between the last two of a list of choices
+    or:                 &'static str,
+    
+    // the lines written by --announce
+    announceNew:      &'static str,
+    announceMoved:    &'static str,
+    announceTurned:   &'static str,
+    announceSet:      &'static str,
+    announceCleared:  &'static str,
+    announceLevel:    &'static str,
+    announceGameOver: &'static str,
+    
+    // the score log under the information area
+    logPoints:      &'static str,
+    logTetris:      &'static str,
+    logLevel:       &'static str,
+    
+    // the time between drop steps at this level and the next, under the level
+    speedChange:    &'static str,
+    
+    // the rows left to clear in a sprint
+    goalLeft:       &'static str,
+    
+    help:           &'static str
+  }
+  
+  pub enum Language {
+    English, German
+  }
+  
+  static english: Strings = Strings {
+    level:         "Level:",
+    bonus:         "Bonus:",
+    score:         "Score:",
+    lines:         "Lines:",
+    tetris:        "Tetris:",
+    next:          "Next:",
+    tetrises:      "Tetrises:",
+    time:          "Time:",
+    seed:          "Seed:",
+    thousands:     ",",
+    
+    gameOver:      "GAME  OVER",
+    finished:      "FINISHED",
+    timeUp:        "TIME  UP",
+    winner:        "WINNER",
+    victory:       "YOU WIN",
+    opponentLeft:  "OPPONENT LEFT",
+    
+    titleScreen:   ["T E T R I S",
+                    "",
+                    "Press any key to play",
+                    "or q to quit",
+                    "",
+                    "A demo starts after 30 seconds"],
+    replayBanner:  "REPLAY - ←/→ seek 10s, other keys stop",
+    demoBanner:    "DEMO - press any key",
+    botBanner:     "BOT - press any key to stop",
+    crowdBanner:   "CROWD PLAY - vote left, right, cw or drop",
+    firstPlayer:   "Player 1 (W A S D)",
+    humanPlayer:   "You (arrows)",
+    computerPlayer: "Computer",
+    seriesScore:   "Series {} - {} (best of {})",
+    seriesWon:     "{} wins the series {} - {}",
+    paused:        "PAUSED",
+    pauseHelp:     "P to resume",
+    nextPiece:     "Next piece",
+    spawnDelay:    "Spawn delay",
+    on:            "on",
+    off:           "off",
+    displayMode:   "Display",
+    standard:      "standard",
+    double:        "double",
+    idleHelp:      "Any key: resume",
+    resumingIn:    "Resuming in {}",
+    tournamentNames:     "Player names, one per line (up to {}), then an empty line to start:",
+    tournamentTooFew:    "A tournament needs at least 2 players",
+    tournamentTurn:      "{}'s turn - press a key",
+    tournamentStandings: "Standings ({}, seed {}):",
+    tournamentPlace:     "{}. {}  score {}, lines {}, time {}",
+    secondPlayer:  "Player 2 (arrows)",
+    ghost:         "Ghost",
+    opponent:      "Opponent",
+    waitingForOpponent: "Waiting for an opponent at {}",
+    waitingInRoom: "Room {}: waiting for a friend to join with --join {}",
+    waitedFor:     "Waited {}",
+    lobbyHelp:     "Q: give up",
+    chatHint:      "Enter: chat",
+    chatYou:       "You: {}",
+    chatOpponent:  "Opponent: {}",
+    latency:       "Ping {} ms",
+    tutorial:      ["TUTORIAL 1/4: move the piece with [LEFT] and [RIGHT]",
+                    "TUTORIAL 2/4: turn the piece with [UP]",
+                    "TUTORIAL 3/4: pieces fall by themselves, [DOWN] drops one",
+                    "TUTORIAL 4/4: fill the gap in the bottom row to clear it",
+                    "Well done, that's all there is to it!"],
+    
+    modeHeading:   "{} mode",
+    highScores:    "High Scores:",
+    recentScores:  "Recent Scores:",
+    scoreOrders:   ["Best Scores:", "Latest Games:", "Highest Levels:"],
+    periodHeadings: ["Week of {}:", "Month {}:"],
+    seedScores:    "Best Scores with Seed {}:",
+    scoreColumns:  ["Date", "Level", "Bonus", "Score", "Lines", "Tetrises", "Time", "Seed", "Board"],
+    randomSeed:    "random",
+    boardFull:     "{}% full",
+    allModes:      "all modes",
+    statLabels:    ["games played:", "total lines:", "average score:", "best level:", "tetris rate:", "play time:"],
+    hudLabels:     ["pieces/s", "keys/piece", "tetris %", "no I for", "time"],
+    commentary:    "{} pieces/s   attack {}   danger: {}",
+    dangerLevels:  ["low", "medium", "high", "critical"],
+    noGames:       "No games played yet",
+    graphLabels:   ["Scores of the last {} games:", "older", "newer"],
+    
+    benchLabels:   ["games played:", "average lines:", "average score:", "best score:", "average pieces:",
+                    "piece limit:", "time:"],
+    benchSeeds:    "{} (seeds {} to {})",
+    benchStopped:  "{} games stopped after {} pieces",
+    benchTime:     "{}s ({} games/s)",
+    
+    tuneStart:     "current weights: {} lines",
+    tuneTry:       "try {}: {} lines",
+    tuneBetter:    "try {}: {} lines, the best so far",
+    tuneLabels:    ["games per try:", "best weights:", "average lines:", "time:"],
+    tuneGames:     "{} (seeds {} to {}, at most {} pieces)",
+    
+    runHelp:        "Run with --help to see the commands and options",
+    noTerminal:     "The game reads its keys from a terminal, and standard input isn't one (--ai-bench and --ai-tune play without one)",
+    noTwoPlayerText: "Two player games are only drawn on a terminal, they can't be printed as text",
+    cantReplay:     "Can't play the replay: {}",
+    cantResume:     "Can't resume: {}",
+    savedGame:      "A game was saved when its terminal closed ({} mode, {} played):",
+    resumeChoices:  "R or Enter: resume it   D: discard it   Q: quit",
+    unknownMode:    "unknown mode {}",
+    cantRace:       "Can't race: {}",
+    cantReadVotes:  "can't read votes from {}: {}",
+    cantRecord:     "can't record to {}: {}",
+    cantSpectate:   "can't serve spectators on port {}: {}",
+    cantLed:        "can't send the board to {}: {}",
+    exported:       "Exported {} scores to {}",
+    cantExport:     "Couldn't export scores to {}: {}",
+    framesExported: "Exported {} frames to {}",
+    cantFrames:     "Couldn't export frames: {}",
+    imported:       "Imported {} new scores from {}",
+    cantImport:     "Couldn't import scores: {}",
+    configProblem:  "Problem with {}: {}",
+    configExists:   "{} already exists, move it out of the way to write a new one",
+    wroteConfig:    "Wrote the default settings to {}",
+    savedWeights:   "Wrote the best weights to {}",
+    cantWrite:      "Couldn't write {}: {}",
+    noLeaderboard:  "No leaderboard is set, add a \"leaderboard\" address to {}",
+    cantFetch:      "Couldn't fetch the leaderboard: {}",
+    cantWatch:      "Couldn't watch: {}",
+    cantPlayOnline: "Couldn't play over the network: {}",
+    cantServe:      "Can't relay matches on port {}: {}",
+    relayListening: "Relaying versus matches on port {} (ctrl-c to stop)",
+    relayStatus:    "{} players connected, {} matches",
+    botFailed:      "The bot stopped: {}",
+    cantLog:        "Can't write the log to {}: {}",
+    cantAnnounce:   "Can't write announcements to {}: {}",
+    
+    errCantRead:        "can't read {}: {}",
+    errCantWrite:       "can't write {}: {}",
+    errNotJson:         "{} is not valid JSON: {}",
+    errNotReplay:       "{} is not a tetris1 replay",
+    errReplayHeader:    "{} has an invalid header",
+    errBadCheckpoint:   "{} line {}: not a valid checkpoint",
+    errBadEvent:        "{} line {}: not a valid event",
+    errBadScoreLine:    "{} line {}: not a valid score",
+    errCommandFailed:   "\"{}\" failed",
+    errNoSavedGame:     "there's no saved game",
+    errBoardRows:       "the board must have 20 rows",
+    errBoardCells:      "board row {} must have 10 cells",
+    errBoardCell:       "board row {} has an invalid cell '{}'",
+    
+    errNotHttp:         "{} is not an http:// address",
+    errHostPort:        "{} should be HOST:PORT",
+    errBadPort:         "{} has an invalid port",
+    errNoHost:          "{} has no host",
+    errCantFind:        "can't find {}",
+    errCantFindBecause: "can't find {}: {}",
+    errCantConnect:     "can't connect to {}: {}",
+    errRelayClosed:     "the relay closed the connection",
+    errCantReach:       "can't reach {}: {}",
+    errNotIpv4:         "not an IPv4 address",
+    errTimedOut:        "timed out",
+    errBadResponse:     "{} sent an invalid response",
+    errAnswered:        "{} answered \"{}\"",
+    errBadScores:       "{} sent invalid scores: {}",
+    
+    errCantRun:         "can't run \"{}\": {}",
+    errBotWrite:        "can't write to the bot: {}",
+    errBotSilent:       "the bot didn't answer: {}",
+    errBotPlacement:    "expected \"ROTATIONS COLUMN\" (0 to 3 and 1 to 10), not \"{}\"",
+    
+    errConfigRead:      "can't read file: {}",
+    errConfigJson:      "not valid JSON: {}",
+    errTopLevel:        "expected an object at the top level",
+    errString:          "\"{}\" must be a string",
+    errObject:          "\"{}\" must be an object",
+    errList:            "\"{}\" must be a list",
+    errBool:            "\"{}\" must be true or false",
+    errWholeNumber:     "\"{}\" must be a whole number from {} to {}",
+    errOneOf:           "\"{}\" must be {}",
+    errUnknownValue:    "unknown {} \"{}\"",
+    errUnknownIn:       "unknown \"{}\" in \"{}\", expected {}",
+    errStringIn:        "\"{}\" in \"{}\" must be a string",
+    errBoolIn:          "\"{}\" in \"{}\" must be true or false",
+    errKeyIn:           "\"{}\" in \"{}\" must be a single letter, number or symbol (or \"\" for none)",
+    errNumberIn:        "\"{}\" in \"{}\" must be a number from {} to {}",
+    errWholeNumberIn:   "\"{}\" in \"{}\" must be a whole number from {} to {}",
+    errColorIn:         "\"{}\" in \"{}\" must be a terminal color, {}",
+    errNoLevels:        "\"levels\" must have at least one level",
+    errTooManyLevels:   "\"levels\" can't have more than {} levels",
+    errLevelObject:     "level {} must be an object",
+    errLevelNumber:     "level {}: \"{}\" must be a whole number from {} to {}",
+    errLevelMissing:    "level {}: missing \"{}\"",
+    
+    errNoValue:         "{} doesn't take a value (it was given \"{}\")",
+    errNeedsValue:      "{} needs a value",
+    errNeedsNumber:     "{} needs a number, not \"{}\"",
+    errChoice:          "{} must be {}, not \"{}\"",
+    timeSpans:          "a time like 30m, 12h, 7d or 2w",
+    errAtLeastGames:    "{} needs at least 1 game",
+    errAtLeastTries:    "{} needs at least 1 try",
+    errGamesRange:      "{} needs a number of games from 1 to {}",
+    errOddGames:        "{} needs an odd number of games from 1 to 9",
+    errPlayerName:      "{} needs a name of up to 32 letters, digits, - or _, not \"{}\"",
+    errRoomCode:        "{} needs a room code of 4 letters, not \"{}\"",
+    errAboveZero:       "{} needs a number above 0",
+    errMillis:          "{} needs a number of milliseconds from 0 to {}",
+    errUnknownOption:   "unknown option {}",
+    errUnknownCommand:  "unknown command \"{}\", expected {}",
+    errUnexpected:      "unexpected argument \"{}\" (commands have to come first)",
+    errNeeds:           "{} needs {}",
+    errExportReplay:    "--export-frames and --export-command need a replay to export (replay FILE)",
+    errScoresOnly:      "--since, --sort, --top and --period go with --scores",
+    errPeriodSort:      "--period lists each period's best scores, it can't be given a --sort",
+    errSeedPeriod:      "--scores --seed lists the seed's best scores, it can't be given a --period or --sort",
+    
+    or:                 " or ",
+    
+    announceNew:      "piece {} at column {}, next {}",
+    announceMoved:    "{} at column {}",
+    announceTurned:   "{} turned, at column {}",
+    announceSet:      "{} set at column {}",
+    announceCleared:  "lines cleared: {}, score {}",
+    announceLevel:    "level {}",
+    announceGameOver: "game over, score {}",
+    
+    logPoints:      "+{}",
+    logTetris:      "+{} Tetris",
+    logLevel:       "level {}",
+    
+    speedChange:    "{}ms → {}ms",
+    
+    goalLeft:       "{} to go",
+    
+    help: "
+A simple game of Tetris implemented in Rust
+
+Usage: tetris1 [COMMAND] [OPTIONS]
+
+Commands:
+play                     |  play a game (the default)
+scores                   |  the same as --scores
+stats                    |  the same as --stats
+replay FILE              |  watch the game recorded in FILE being played again
+                         |  (left and right arrows seek back and forward 10 seconds)
+help                     |  show this help
+
+Options (values can also be given as --option=VALUE):
+--help or -h             |  show this help
+--lang LANGUAGE          |  language of the text: en or de (otherwise taken from $LANG)
+--write-default-config   |  write a config file with every setting at its default, to edit
+--scores                 |  show scores (for every mode, or just the one given by --mode)
+--scores --remote        |  show the shared leaderboard set in the config file
+--since TIME             |  with --scores, only games from the last TIME (like 30m, 12h, 7d or 2w)
+--sort ORDER             |  with --scores, list every game kept by score, date or level
+--top N                  |  with --scores, only the first N games of each mode
+--period PERIOD          |  with --scores, the best games of each week or month (3 each, or --top N)
+--scores --seed N        |  show the best games played with seed N, to compare with friends using it
+--stats                  |  show lifetime statistics (for every mode, or just the one given by --mode)
+--stats --graph          |  also draw a bar chart of each mode's scores over the last games
+--games N                |  with --graph, how many of the last games to draw (50 if not given)
+--score-file PATH        |  keep scores in PATH instead of the data directory
+--export-scores FILE     |  save all scores to FILE (as CSV if FILE ends in .csv, otherwise JSON)
+--import-scores FILE     |  add scores from a file made by --export-scores
+--display=double or -d2  |  run in double display mode (--display=standard or -d1 for the
+                         |  standard display, if the config file makes double the default)
+--dumb                   |  print the whole board as plain text after each change, instead of
+                         |  drawing in place (for logs and simple terminals, and what's done when
+                         |  the output isn't a terminal)
+--backend curses         |  draw with ncurses, for terminals that don't handle the escape
+                         |  sequences the game writes itself (the default is --backend escapes),
+                         |  games drawn with curses can't be recorded with --record-cast, captured
+                         |  with --capture, sent to --led or watched
+--record FILE            |  record a replay of the game to FILE (the last game is always
+                         |  recorded to last.replay in the data directory)
+--ai-bench GAMES         |  let the computer play GAMES games without drawing them, and show
+                         |  how well it did (use --seed to play the same games again)
+--ai-tune TRIES          |  look for better weights for the computer player by trying TRIES
+                         |  sets of them without drawing, and show the best one
+--save-weights           |  with --ai-tune, write the best weights to the config file
+--ai-external CMD        |  let the program CMD play, told about each piece on its input
+                         |  and answering where to put it (see the README for how)
+--tutorial               |  learn the controls, one step at a time
+--two-player             |  two players on one keyboard, W A S D against the arrow keys,
+                         |  clearing rows sends garbage to the other player
+--vs-ai LEVEL            |  play against the computer on a second board: easy, medium,
+                         |  hard or inhuman (garbage and --best-of work as with --two-player)
+--best-of N              |  with --two-player, play a series until one player has won most
+                         |  of N games (3, 5, 7...), the result is kept with the scores
+--player NAME            |  keep scores, statistics, saved games and settings separately for
+                         |  NAME, in players/NAME in the data directory ($TETRIS1_PLAYER works too)
+--tournament             |  hot seat tournament for 2 to 8 players: enter the names, then
+                         |  each plays one game with the same pieces, and the standings are
+                         |  shown and kept with the scores
+--spectate-port N        |  let others watch the game by connecting to port N
+                         |  (with a line of commentary for them under the game)
+--watch HOST:PORT        |  watch a game being played with --spectate-port
+--serve PORT             |  relay versus matches over the network on port PORT, without
+                         |  playing (players who can't reach each other meet there)
+--connect HOST:PORT      |  play a versus match over the network against the next player to
+                         |  connect to the relay at HOST:PORT (started with --serve), Enter
+                         |  types a chat line to them
+--join CODE              |  with --connect, play the friend who joins room CODE instead (4
+                         |  letters you agree on), waiting for them in the lobby
+--crowd PIPE             |  play with moves voted for by writing left, right, cw or drop
+                         |  lines to the named pipe PIPE, the most votes each second wins
+--ghost FILE             |  race against the replay in FILE, played on a second board
+                         |  (in the replay's mode, with the same pieces)
+--seed N                 |  use seed N for the sequence of pieces, to play the same game again
+--resume                 |  carry on with a game that was saved when its terminal was closed
+--debug-overlay          |  show rendering and timing statistics while playing
+--no-preview             |  don't show the next piece, for a harder game
+--sandbox                |  practice: keys 1 to 7 swap the falling piece for an I, J, L, O,
+                         |  S, T or Z and 0 clears the board (the score isn't kept)
+--log FILE               |  write a debug log of steps, timers, input and output to FILE,
+                         |  to send along when reporting a problem
+--step                   |  pieces only fall when N is pressed, and L writes the game's
+                         |  state to the debug log, to show exactly how a problem happens
+--announce FILE          |  also write what happens in the game to FILE as short lines of text,
+                         |  for a screen reader or braille display to follow
+--record-cast FILE       |  record the game as an asciinema .cast file
+--capture FILE           |  keep the board as drawn in FILE, written over after every frame, for
+                         |  stream overlays and LED matrices (the layout is in the README)
+--led PATH               |  send the board to an RGB LED matrix on the serial device or UNIX socket
+                         |  PATH whenever it changes (the frame format is in the README)
+--export-frames DIR      |  with replay FILE, write a picture of the game every 0.1 seconds
+                         |  to DIR (frame-00001.ans and on) instead of showing it
+--export-command CMD     |  with --export-frames, run CMD on each picture, {} is its file
+--scoring guideline      |  use Tetris Guideline scoring instead of the standard scoring
+--mode MODE              |  endless (the default), nes (NES Tetris scoring and speeds),
+                         |  sprint (clear 40 lines as fast as you can), ultra (score
+                         |  as much as you can in 2 minutes), pressure (garbage rows
+                         |  are pushed up from the bottom every 10 seconds) or
+                         |  marathon (won on reaching level 15)
+--gravity zen|20g|nes    |  change how fast pieces fall (zen never speeds up,
+                         |  20g drops pieces to the bottom right away)
+--speed X                |  make pieces fall X times as fast at every level
+                         |  (0.5 is half speed, 2 double speed)
+--spawn-delay MS         |  wait MS milliseconds (up to 500) after a piece is set
+                         |  before the next one comes in
+
+Controls:
+left arrow     | move piece left
+right arrow    | move piece right
+up arrow       | rotate piece
+down arrow     | quick drop piece
+h              | show where the computer would put the piece (costs your bonus)
+i              | show or hide more statistics (pieces and keys per piece, tetris rate,
+               | pieces since the last I), next to the game if the terminal is wide enough
+p              | pause, with a menu of settings: up and down pick one, left and right
+               | change it (it's kept in the config file), p again to play on. The display
+               | setting switches between the standard and double displays mid-game
+any other key  | exit the game
+
+Run this program with no arguments to start a game in standard display mode
+The title screen starts a demo game played by the computer after 30 seconds
+
+"
+  };
+  
+  static german: Strings = Strings {
+    level:         "Stufe:",
+    bonus:         "Bonus:",
+    score:         "Punkte:",
+    lines:         "Reihen:",
+    tetris:        "Tetris:",
+    next:          "Danach:",
+    tetrises:      "Tetrisse:",
+    time:          "Zeit:",
+    seed:          "Seed:",
+    thousands:     ".",
+    
+    gameOver:      "SPIELENDE",
+    finished:      "GESCHAFFT",
+    timeUp:        "ZEIT UM",
+    winner:        "GEWONNEN",
+    victory:       "SIEG",
+    opponentLeft:  "GEGNER WEG",
+    
+    titleScreen:   ["T E T R I S",
+                    "",
+                    "Zum Spielen eine Taste drücken",
+                    "oder q zum Beenden",
+                    "",
+                    "Nach 30 Sekunden startet eine Demo"],
+    replayBanner:  "WIEDERHOLUNG - ←/→ 10s spulen, andere Tasten beenden",
+    demoBanner:    "DEMO - eine Taste drücken",
+    botBanner:     "BOT - zum Beenden eine Taste drücken",
+    crowdBanner:   "PUBLIKUM SPIELT - left, right, cw oder drop wählen",
+    firstPlayer:   "Spieler 1 (W A S D)",
+    humanPlayer:   "Du (Pfeile)",
+    computerPlayer: "Computer",
+    seriesScore:   "Serie {} - {} (best of {})",
+    seriesWon:     "{} gewinnt die Serie {} - {}",
+    paused:        "PAUSE",
+    pauseHelp:     "P: weiter",
+    nextPiece:     "Vorschau",
+    spawnDelay:    "Wartezeit",
+    on:            "an",
+    off:           "aus",
+    displayMode:   "Anzeige",
+    standard:      "normal",
+    double:        "doppelt",
+    idleHelp:      "Taste: weiter",
+    resumingIn:    "Weiter in {}",
+    tournamentNames:     "Namen der Spieler, einer pro Zeile (bis zu {}), dann eine leere Zeile zum Starten:",
+    tournamentTooFew:    "Ein Turnier braucht mindestens 2 Spieler",
+    tournamentTurn:      "{} ist dran - eine Taste drücken",
+    tournamentStandings: "Rangliste ({}, Startwert {}):",
+    tournamentPlace:     "{}. {}  Punkte {}, Reihen {}, Zeit {}",
+    secondPlayer:  "Spieler 2 (Pfeile)",
+    ghost:         "Geist",
+    opponent:      "Gegner",
+    waitingForOpponent: "Warten auf einen Gegner bei {}",
+    waitingInRoom: "Raum {}: warten auf einen Freund, der mit --join {} beitritt",
+    waitedFor:     "Gewartet: {}",
+    lobbyHelp:     "Q: aufgeben",
+    chatHint:      "Enter: Chat",
+    chatYou:       "Du: {}",
+    chatOpponent:  "Gegner: {}",
+    latency:       "Ping {} ms",
+    tutorial:      ["ÜBUNG 1/4: den Stein mit [LINKS] und [RECHTS] bewegen",
+                    "ÜBUNG 2/4: den Stein mit [HOCH] drehen",
+                    "ÜBUNG 3/4: Steine fallen von selbst, [RUNTER] lässt sie fallen",
+                    "ÜBUNG 4/4: die Lücke unten füllen, um die Reihe zu löschen",
+                    "Gut gemacht, das war schon alles!"],
+    
+    modeHeading:   "Modus {}",
+    highScores:    "Bestenliste:",
+    recentScores:  "Letzte Spiele:",
+    scoreOrders:   ["Beste Punktestände:", "Neueste Spiele:", "Höchste Stufen:"],
+    periodHeadings: ["Woche ab {}:", "Monat {}:"],
+    seedScores:    "Bestenliste mit Seed {}:",
+    scoreColumns:  ["Datum", "Stufe", "Bonus", "Punkte", "Reihen", "Tetrisse", "Zeit", "Seed", "Feld"],
+    randomSeed:    "zufällig",
+    boardFull:     "{}% voll",
+    allModes:      "alle Modi",
+    statLabels:    ["Spiele:", "Reihen gesamt:", "Punkteschnitt:", "Beste Stufe:", "Tetris-Anteil:", "Spielzeit:"],
+    hudLabels:     ["Steine/s", "Tasten/Stein", "Tetris %", "ohne I seit", "Zeit"],
+    commentary:    "{} Steine/s   Angriff {}   Gefahr: {}",
+    dangerLevels:  ["gering", "mittel", "hoch", "kritisch"],
+    noGames:       "Noch keine Spiele gespielt",
+    graphLabels:   ["Punkte der letzten {} Spiele:", "älter", "neuer"],
+    
+    benchLabels:   ["Spiele:", "Reihen im Schnitt:", "Punkte im Schnitt:", "Beste Punkte:", "Steine im Schnitt:",
+                    "Steinlimit:", "Zeit:"],
+    benchSeeds:    "{} (Seeds {} bis {})",
+    benchStopped:  "{} Spiele nach {} Steinen beendet",
+    benchTime:     "{}s ({} Spiele/s)",
+    
+    tuneStart:     "aktuelle Gewichte: {} Reihen",
+    tuneTry:       "Versuch {}: {} Reihen",
+    tuneBetter:    "Versuch {}: {} Reihen, der beste bisher",
+    tuneLabels:    ["Spiele pro Versuch:", "Beste Gewichte:", "Reihen im Schnitt:", "Zeit:"],
+    tuneGames:     "{} (Seeds {} bis {}, höchstens {} Steine)",
+    
+    runHelp:        "Mit --help werden die Befehle und Optionen angezeigt",
+    noTerminal:     "Das Spiel liest seine Tasten von einem Terminal, und die Standardeingabe ist keins (--ai-bench und --ai-tune spielen ohne)",
+    noTwoPlayerText: "Spiele zu zweit werden nur auf einem Terminal gezeichnet, sie können nicht als Text ausgegeben werden",
+    cantReplay:     "Die Wiederholung kann nicht abgespielt werden: {}",
+    cantResume:     "Fortsetzen nicht möglich: {}",
+    savedGame:      "Beim Schließen des Terminals wurde ein Spiel gespeichert (Modus {}, {} gespielt):",
+    resumeChoices:  "R oder Enter: fortsetzen   D: verwerfen   Q: beenden",
+    unknownMode:    "unbekannter Modus {}",
+    cantRace:       "Rennen nicht möglich: {}",
+    cantReadVotes:  "Stimmen aus {} können nicht gelesen werden: {}",
+    cantRecord:     "Aufnahme in {} nicht möglich: {}",
+    cantSpectate:   "Zuschauer auf Port {} nicht möglich: {}",
+    cantLed:        "Spielfeld kann nicht an {} gesendet werden: {}",
+    exported:       "{} Punktestände nach {} exportiert",
+    cantExport:     "Punktestände konnten nicht nach {} exportiert werden: {}",
+    framesExported: "{} Bilder nach {} exportiert",
+    cantFrames:     "Bilder konnten nicht exportiert werden: {}",
+    imported:       "{} neue Punktestände aus {} importiert",
+    cantImport:     "Punktestände konnten nicht importiert werden: {}",
+    configProblem:  "Problem mit {}: {}",
+    configExists:   "{} gibt es schon, zum Schreiben einer neuen bitte beiseite legen",
+    wroteConfig:    "Standardeinstellungen nach {} geschrieben",
+    savedWeights:   "Beste Gewichte nach {} geschrieben",
+    cantWrite:      "{} konnte nicht geschrieben werden: {}",
+    noLeaderboard:  "Keine Bestenliste eingestellt, eine \"leaderboard\"-Adresse in {} eintragen",
+    cantFetch:      "Die Bestenliste konnte nicht geladen werden: {}",
+    cantWatch:      "Zuschauen nicht möglich: {}",
+    cantPlayOnline: "Spielen über das Netzwerk nicht möglich: {}",
+    cantServe:      "Spiele auf Port {} können nicht vermittelt werden: {}",
+    relayListening: "Vermittle Spiele gegeneinander auf Port {} (Strg-C zum Beenden)",
+    relayStatus:    "{} Spieler verbunden, {} Spiele",
+    botFailed:      "Der Bot wurde beendet: {}",
+    cantLog:        "Das Protokoll kann nicht nach {} geschrieben werden: {}",
+    cantAnnounce:   "Ansagen können nicht nach {} geschrieben werden: {}",
+    
+    errCantRead:        "{} kann nicht gelesen werden: {}",
+    errCantWrite:       "{} kann nicht geschrieben werden: {}",
+    errNotJson:         "{} ist kein gültiges JSON: {}",
+    errNotReplay:       "{} ist keine tetris1-Wiederholung",
+    errReplayHeader:    "{} hat einen ungültigen Kopf",
+    errBadCheckpoint:   "{} Zeile {}: kein gültiger Kontrollpunkt",
+    errBadEvent:        "{} Zeile {}: kein gültiges Ereignis",
+    errBadScoreLine:    "{} Zeile {}: kein gültiger Punktestand",
+    errCommandFailed:   "\"{}\" ist fehlgeschlagen",
+    errNoSavedGame:     "es gibt kein gespeichertes Spiel",
+    errBoardRows:       "das Spielfeld muss 20 Reihen haben",
+    errBoardCells:      "Reihe {} des Spielfelds muss 10 Felder haben",
+    errBoardCell:       "Reihe {} des Spielfelds hat ein ungültiges Feld '{}'",
+    
+    errNotHttp:         "{} ist keine http://-Adresse",
+    errHostPort:        "{} sollte HOST:PORT sein",
+    errBadPort:         "{} hat einen ungültigen Port",
+    errNoHost:          "{} hat keinen Host",
+    errCantFind:        "{} nicht gefunden",
+    errCantFindBecause: "{} nicht gefunden: {}",
+    errCantConnect:     "Verbindung zu {} nicht möglich: {}",
+    errRelayClosed:     "der Vermittler hat die Verbindung getrennt",
+    errCantReach:       "{} nicht erreichbar: {}",
+    errNotIpv4:         "keine IPv4-Adresse",
+    errTimedOut:        "Zeitüberschreitung",
+    errBadResponse:     "{} hat eine ungültige Antwort geschickt",
+    errAnswered:        "{} hat \"{}\" geantwortet",
+    errBadScores:       "{} hat ungültige Punktestände geschickt: {}",
+    
+    errCantRun:         "\"{}\" kann nicht ausgeführt werden: {}",
+    errBotWrite:        "an den Bot kann nicht geschrieben werden: {}",
+    errBotSilent:       "der Bot hat nicht geantwortet: {}",
+    errBotPlacement:    "erwartet wird \"ROTATIONS COLUMN\" (0 bis 3 und 1 bis 10), nicht \"{}\"",
+    
+    errConfigRead:      "Datei kann nicht gelesen werden: {}",
+    errConfigJson:      "kein gültiges JSON: {}",
+    errTopLevel:        "oben muss ein Objekt stehen",
+    errString:          "\"{}\" muss ein String sein",
+    errObject:          "\"{}\" muss ein Objekt sein",
+    errList:            "\"{}\" muss eine Liste sein",
+    errBool:            "\"{}\" muss true oder false sein",
+    errWholeNumber:     "\"{}\" muss eine ganze Zahl von {} bis {} sein",
+    errOneOf:           "\"{}\" muss {} sein",
+    errUnknownValue:    "unbekannter Wert für {}: \"{}\"",
+    errUnknownIn:       "unbekannter Eintrag \"{}\" in \"{}\", erwartet wird {}",
+    errStringIn:        "\"{}\" in \"{}\" muss ein String sein",
+    errBoolIn:          "\"{}\" in \"{}\" muss true oder false sein",
+    errKeyIn:           "\"{}\" in \"{}\" muss ein einzelner Buchstabe, eine Ziffer oder ein Zeichen sein (oder \"\" für keine)",
+    errNumberIn:        "\"{}\" in \"{}\" muss eine Zahl von {} bis {} sein",
+    errWholeNumberIn:   "\"{}\" in \"{}\" muss eine ganze Zahl von {} bis {} sein",
+    errColorIn:         "\"{}\" in \"{}\" muss eine Terminalfarbe sein, {}",
+    errNoLevels:        "\"levels\" muss mindestens eine Stufe haben",
+    errTooManyLevels:   "\"levels\" darf höchstens {} Stufen haben",
+    errLevelObject:     "Stufe {} muss ein Objekt sein",
+    errLevelNumber:     "Stufe {}: \"{}\" muss eine ganze Zahl von {} bis {} sein",
+    errLevelMissing:    "Stufe {}: \"{}\" fehlt",
+    
+    errNoValue:         "{} nimmt keinen Wert (angegeben war \"{}\")",
+    errNeedsValue:      "{} braucht einen Wert",
+    errNeedsNumber:     "{} braucht eine Zahl, nicht \"{}\"",
+    errChoice:          "{} muss {} sein, nicht \"{}\"",
+    timeSpans:          "eine Zeit wie 30m, 12h, 7d oder 2w",
+    errAtLeastGames:    "{} braucht mindestens 1 Spiel",
+    errAtLeastTries:    "{} braucht mindestens 1 Versuch",
+    errGamesRange:      "{} braucht eine Anzahl Spiele von 1 bis {}",
+    errOddGames:        "{} braucht eine ungerade Anzahl Spiele von 1 bis 9",
+    errPlayerName:      "{} braucht einen Namen aus bis zu 32 Buchstaben, Ziffern, - oder _, nicht \"{}\"",
+    errRoomCode:        "{} braucht einen Raumcode aus 4 Buchstaben, nicht \"{}\"",
+    errAboveZero:       "{} braucht eine Zahl über 0",
+    errMillis:          "{} braucht eine Anzahl Millisekunden von 0 bis {}",
+    errUnknownOption:   "unbekannte Option {}",
+    errUnknownCommand:  "unbekannter Befehl \"{}\", erwartet wird {}",
+    errUnexpected:      "unerwartetes Argument \"{}\" (Befehle müssen zuerst kommen)",
+    errNeeds:           "{} braucht {}",
+    errExportReplay:    "--export-frames und --export-command brauchen eine Wiederholung zum Exportieren (replay FILE)",
+    errScoresOnly:      "--since, --sort, --top und --period gehen nur mit --scores",
+    errPeriodSort:      "--period zeigt die besten Punktestände jedes Zeitraums, dazu passt kein --sort",
+    errSeedPeriod:      "--scores --seed zeigt die besten Punktestände des Seeds, dazu passt kein --period oder --sort",
+    
+    or:                 " oder ",
+    
+    announceNew:      "Stein {} in Spalte {}, danach {}",
+    announceMoved:    "{} in Spalte {}",
+    announceTurned:   "{} gedreht, in Spalte {}",
+    announceSet:      "{} abgelegt in Spalte {}",
+    announceCleared:  "Reihen gelöscht: {}, Punkte {}",
+    announceLevel:    "Stufe {}",
+    announceGameOver: "Spielende, Punkte {}",
+    
+    logPoints:      "+{}",
+    logTetris:      "+{} Tetris",
+    logLevel:       "Stufe {}",
+    
+    speedChange:    "{}ms → {}ms",
+    
+    goalLeft:       "noch {}",
+    
+    help: "
+Ein einfaches Tetris, in Rust geschrieben
+
+Aufruf: tetris1 [BEFEHL] [OPTIONEN]
+
+Befehle:
+play                     |  ein Spiel spielen (Standard)
+scores                   |  wie --scores
+stats                    |  wie --stats
+replay FILE              |  das in FILE aufgezeichnete Spiel noch einmal ansehen
+                         |  (Pfeil links und rechts spulen 10 Sekunden zurück und vor)
+help                     |  diese Hilfe anzeigen
+
+Optionen (Werte gehen auch als --option=WERT):
+--help, -h               |  diese Hilfe anzeigen
+--lang SPRACHE           |  Sprache der Texte: en oder de (sonst aus $LANG)
+--write-default-config   |  eine Konfigurationsdatei mit allen Standardwerten zum Bearbeiten schreiben
+--scores                 |  Punktestände anzeigen (für alle Modi, oder nur den mit --mode gewählten)
+--scores --remote        |  die gemeinsame Bestenliste aus der Konfigurationsdatei anzeigen
+--since ZEIT             |  mit --scores nur Spiele der letzten ZEIT (etwa 30m, 12h, 7d oder 2w)
+--sort ORDNUNG           |  mit --scores alle gespeicherten Spiele nach score, date oder level ordnen
+--top N                  |  mit --scores nur die ersten N Spiele jedes Modus
+--period ZEITRAUM        |  mit --scores die besten Spiele jeder Woche (week) oder jedes Monats (month)
+--scores --seed N        |  die besten Spiele mit Seed N zeigen, zum Vergleich mit Freunden
+--stats                  |  Gesamtstatistik anzeigen (für alle Modi, oder nur den mit --mode gewählten)
+--stats --graph          |  dazu die Punkte der letzten Spiele jedes Modus als Balkendiagramm zeigen
+--games N                |  mit --graph, wie viele der letzten Spiele gezeigt werden (sonst 50)
+--score-file PATH        |  Punktestände in PATH statt im Datenverzeichnis speichern
+--export-scores FILE     |  alle Punktestände in FILE speichern (als CSV, wenn FILE auf .csv endet, sonst JSON)
+--import-scores FILE     |  Punktestände aus einer mit --export-scores erstellten Datei hinzufügen
+--display=double, -d2    |  doppelt große Anzeige (--display=standard oder -d1 für die normale
+                         |  Anzeige, wenn die Konfigurationsdatei double zum Standard macht)
+--dumb                   |  das ganze Spielfeld nach jeder Änderung als Text ausgeben, statt an Ort
+                         |  und Stelle zu zeichnen (für Logs und einfache Terminals, und so wird es
+                         |  gemacht, wenn die Ausgabe kein Terminal ist)
+--backend curses         |  mit ncurses zeichnen, für Terminals, die mit den Escape-Sequenzen des
+                         |  Spiels nicht zurechtkommen (Standard ist --backend escapes), mit curses
+                         |  gezeichnete Spiele können nicht mit --record-cast aufgenommen, mit --capture
+                         |  mitgeschrieben, an --led gesendet oder zugeschaut werden
+--record FILE            |  eine Wiederholung des Spiels in FILE aufnehmen (das letzte Spiel wird
+                         |  immer in last.replay im Datenverzeichnis aufgenommen)
+--ai-bench GAMES         |  den Computer GAMES Spiele ohne Anzeige spielen lassen und zeigen,
+                         |  wie gut er war (mit --seed werden dieselben Spiele wiederholt)
+--ai-tune TRIES          |  bessere Gewichte für den Computerspieler suchen, indem TRIES
+                         |  Sätze ohne Anzeige ausprobiert werden, und den besten zeigen
+--save-weights           |  mit --ai-tune die besten Gewichte in die Einstellungsdatei schreiben
+--ai-external CMD        |  das Programm CMD spielen lassen, es erfährt jedes Teil über seine
+                         |  Eingabe und antwortet, wohin es kommt (siehe README)
+--tutorial               |  die Steuerung Schritt für Schritt lernen
+--two-player             |  zwei Spieler an einer Tastatur, W A S D gegen die Pfeiltasten,
+                         |  gelöschte Reihen schicken dem anderen Spieler Müllreihen
+--vs-ai LEVEL            |  auf einem zweiten Brett gegen den Computer spielen: easy, medium,
+                         |  hard oder inhuman (Müllreihen und --best-of wie bei --two-player)
+--best-of N              |  mit --two-player eine Serie spielen, bis ein Spieler die meisten
+                         |  von N Spielen (3, 5, 7...) gewonnen hat, das Ergebnis wird gespeichert
+--player NAME            |  Punkte, Statistiken, gespeicherte Spiele und Einstellungen getrennt
+                         |  für NAME führen, in players/NAME im Datenverzeichnis (oder $TETRIS1_PLAYER)
+--tournament             |  Turnier für 2 bis 8 Spieler an einer Tastatur: Namen eingeben, dann
+                         |  spielt jeder ein Spiel mit denselben Steinen, die Rangliste wird
+                         |  gezeigt und bei den Punkten gespeichert
+--spectate-port N        |  andere über Port N beim Spielen zuschauen lassen
+                         |  (mit einer Zeile Kommentar für sie unter dem Spiel)
+--watch HOST:PORT        |  einem mit --spectate-port gespielten Spiel zuschauen
+--serve PORT             |  Spiele gegeneinander über das Netzwerk auf Port PORT vermitteln, ohne
+                         |  zu spielen (Spieler, die sich nicht erreichen, treffen sich dort)
+--connect HOST:PORT      |  über das Netzwerk gegen den nächsten Spieler spielen, der sich mit
+                         |  dem Vermittler bei HOST:PORT verbindet (mit --serve gestartet),
+                         |  Enter tippt eine Chatzeile an ihn
+--join CODE              |  mit --connect stattdessen gegen den Freund spielen, der Raum CODE
+                         |  beitritt (4 vereinbarte Buchstaben), in der Lobby auf ihn warten
+--crowd PIPE             |  mit Zügen spielen, für die durch Zeilen mit left, right, cw oder drop
+                         |  in die Named Pipe PIPE abgestimmt wird, jede Sekunde gewinnt die Mehrheit
+--ghost FILE             |  gegen die Wiederholung in FILE auf einem zweiten Spielfeld antreten
+                         |  (im Modus der Wiederholung, mit denselben Steinen)
+--seed N                 |  Startwert N für die Steinfolge, um dasselbe Spiel noch einmal zu spielen
+--resume                 |  ein Spiel fortsetzen, das beim Schließen des Terminals gespeichert wurde
+--debug-overlay          |  Zeichen- und Zeitstatistiken während des Spiels anzeigen
+--no-preview             |  den nächsten Stein nicht anzeigen, für ein schwereres Spiel
+--sandbox                |  Üben: die Tasten 1 bis 7 tauschen den fallenden Stein gegen ein
+                         |  I, J, L, O, S, T oder Z, 0 leert das Feld (Punkte werden nicht gespeichert)
+--log FILE               |  ein Debug-Protokoll von Schritten, Timern, Eingaben und Ausgaben in FILE
+                         |  schreiben, zum Mitschicken bei Fehlerberichten
+--step                   |  die Steine fallen nur, wenn N gedrückt wird, und L schreibt den
+                         |  Spielstand ins Debug-Protokoll, um zu zeigen, wie ein Fehler entsteht
+--announce FILE          |  außerdem das Spielgeschehen als kurze Textzeilen in FILE schreiben,
+                         |  zum Mitlesen mit einem Screenreader oder einer Braillezeile
+--record-cast FILE       |  das Spiel als asciinema-.cast-Datei aufnehmen
+--capture FILE           |  das Spielfeld, wie es gezeichnet ist, nach jedem Bild in FILE schreiben,
+                         |  für Stream-Overlays und LED-Matrizen (das Format steht in der README)
+--led PATH               |  das Spielfeld bei jeder Änderung an eine RGB-LED-Matrix am seriellen
+                         |  Gerät oder UNIX-Socket PATH senden (das Format steht in der README)
+--export-frames DIR      |  mit replay FILE alle 0,1 Sekunden ein Bild des Spiels nach DIR
+                         |  schreiben (frame-00001.ans usw.), statt es zu zeigen
+--export-command CMD     |  mit --export-frames CMD für jedes Bild ausführen, {} ist seine Datei
+--scoring guideline      |  Punkte nach der Tetris Guideline statt der normalen Wertung
+--mode MODE              |  endless (Standard), nes (Punkte und Geschwindigkeiten von NES Tetris),
+                         |  sprint (40 Reihen so schnell wie möglich), ultra (so viele Punkte
+                         |  wie möglich in 2 Minuten), pressure (alle 10 Sekunden wird
+                         |  von unten eine Müllreihe hochgeschoben) oder marathon
+                         |  (gewonnen, sobald Stufe 15 erreicht ist)
+--gravity zen|20g|nes    |  ändern, wie schnell die Steine fallen (zen wird nie schneller,
+                         |  20g lässt die Steine sofort ganz nach unten fallen)
+--speed X                |  die Steine auf jeder Stufe X-mal so schnell fallen lassen
+                         |  (0.5 ist halbe, 2 doppelte Geschwindigkeit)
+--spawn-delay MS         |  nach dem Absetzen eines Steins MS Millisekunden (bis 500)
+                         |  warten, bevor der nächste kommt
+
+Steuerung:
+Pfeil links    | Stein nach links
+Pfeil rechts   | Stein nach rechts
+Pfeil hoch     | Stein drehen
+Pfeil runter   | Stein fallen lassen
+h              | zeigen, wo der Computer den Stein hinlegen würde (kostet den Bonus)
+i              | weitere Statistiken (Steine und Tasten pro Stein, Tetris-Anteil, Steine
+               | seit dem letzten I) neben dem Spiel zeigen oder verbergen, wenn das
+               | Terminal breit genug ist
+p              | Pause, mit einem Menü der Einstellungen: hoch und runter wählen, links
+               | und rechts ändern (wird in der Einstellungsdatei gespeichert), p spielt weiter.
+               | Die Anzeige wechselt mitten im Spiel zwischen normaler und doppelter Anzeige
+andere Taste   | Spiel beenden
+
+Ohne Argumente startet das Programm ein Spiel mit der normalen Anzeige
+Der Titelbildschirm startet nach 30 Sekunden ein Demospiel des Computers
+
+"
+  };
+  
+  static mut language: Language = English;
+  
+  pub fn set_language(lang: Language) {
+    unsafe { language = lang; }
+  }
+  
+  pub fn get() -> &'static Strings {
+    match unsafe { language } {
+      English => &english,
+      German  => &german
+    }
+  }
+  
+  // "de", "de_DE.UTF-8" and "de-AT" are all German
+  pub fn from_name(name: &str) -> Option<Language> {
+    match name.split(|c: char| c == '_' || c == '-' || c == '.').next() {
+      Some("en") | Some("C") | Some("POSIX") => Some(English),
+      Some("de")                             => Some(German),
+      _                                      => None
+    }
+  }
+  
+  // the language the environment asks for, English if it's one there's no table for
+  pub fn from_env() -> Language {
+    for name in ["LC_ALL", "LC_MESSAGES", "LANG"].iter() {
+      match os::getenv(*name) {
+        Some(ref value) if value.len() > 0 => return from_name(value.as_slice()).unwrap_or(English),
+        _                                  => ()
+      }
+    }
+    English
+  }
+  
+  // the text with each {} replaced by the next of the values
+  pub fn fill(text: &str, values: &[&str]) -> ~str {
+    let mut filled = ~"";
+    for (i, part) in text.split_str("{}").enumerate() {
+      if i > 0 {
+        filled.push_str(values[i - 1]);
+      }
+      filled.push_str(part);
+    }
+    filled
+  }
+  
+  // the names as a list of choices, like "a, b or c"
+  pub fn one_of(names: &[&str]) -> ~str {
+    match names {
+      []             => ~"",
+      [..rest, last] => {
+        let mut text = rest.connect(", ");
+        if rest.len() > 0 {
+          text.push_str(get().or);
+        }
+        text.push_str(last);
+        text
+      }
+    }
+  }
+  
+  // how many terminal columns the text takes
+  pub fn width(text: &str) -> uint {
+    text.char_len()
+  }
+  
+  // a score with its digits in groups of 3, like 1,234,567
+  pub fn grouped(number: i64) -> ~str {
+    let digits = number.to_str();
+    let mut text = ~"";
+    for (i, digit) in digits.chars().enumerate() {
+      if i > 0 && (digits.len() - i) % 3 == 0 {
+        text.push_str(get().thousands);
+      }
+      text.push_char(digit);
+    }
+    text
+  }
+}
+
+// Text in columns for listings printed to the terminal, each column as wide as its widest
+// cell so nothing has to be counted by hand.
+mod table {
+  use std::vec_ng::Vec;
+  use strings;
+  
+  pub enum Align {
+    Left,
+    Right
+  }
+  
+  // spaces between one column and the next
+  static gap: uint = 2;
+  
+  pub struct Table {
+    aligns: Vec<Align>,
+    rows:   Vec<Vec<~str>>,  // the headings are the first row
+    colors: Vec<Vec<~str>>   // the SGR codes of each cell, "" for plain text
+  }
+  
+  // each value after its label, the values lined up after the longest label
+  pub fn print_labeled(labels: &[&str], values: &[~str]) {
+    let width = labels.iter().fold(0, |widest, label| {
+      let width = strings::width(*label);
+      if width > widest { width } else { widest }
+    });
+    for (label, value) in labels.iter().zip(values.iter()) {
+      println!("{}{} {}", *label, " ".repeat(width - strings::width(*label)), *value);
+    }
+  }
+  
+  pub fn new(columns: &[(&str, Align)]) -> Table {
+    Table {
+      aligns: columns.iter().map(|&(_, align)| align).collect(),
+      rows:   vec!(columns.iter().map(|&(heading, _)| heading.to_owned()).collect()),
+      colors: vec!(Vec::from_elem(columns.len(), ~""))
+    }
+  }
+  
+  impl Table {
+    pub fn add_row(&mut self, cells: Vec<~str>) {
+      assert!(cells.len() == self.aligns.len());
+      self.rows.push(cells);
+      self.colors.push(Vec::from_elem(self.aligns.len(), ~""));
+    }
+    
+    // Colors the last row added (or just its cell in column) with SGR codes like "1;33", on
+    // top of any it has. Only the text is colored, not the spaces lining it up.
+    pub fn color(&mut self, column: Option<uint>, codes: &str) {
+      let row = self.colors.mut_last().unwrap();
+      for (i, cell) in row.mut_iter().enumerate() {
+        if column.is_none() || column == Some(i) {
+          if cell.len() > 0 {
+            cell.push_char(';');
+          }
+          cell.push_str(codes);
+        }
+      }
+    }
+    
+    // the headings and then each row, lined up, without trailing spaces
+    pub fn lines(&self) -> Vec<~str> {
+      let mut widths = Vec::from_elem(self.aligns.len(), 0u);
+      for row in self.rows.iter() {
+        for (i, cell) in row.iter().enumerate() {
+          let width = strings::width(*cell);
+          if width > *widths.get(i) {
+            *widths.get_mut(i) = width;
+          }
+        }
+      }
+      
+      self.rows.iter().zip(self.colors.iter()).map(|(row, colors)| {
+        let mut line = ~"";
+        for (i, (cell, codes)) in row.iter().zip(colors.iter()).enumerate() {
+          if i > 0 {
+            line.push_str(" ".repeat(gap));
+          }
+          let padding = " ".repeat(*widths.get(i) - strings::width(*cell));
+          let text = if codes.len() > 0 { format!("\x1b[{}m{}\x1b[0m", *codes, *cell) } else { cell.clone() };
+          match *self.aligns.get(i) {
+            Left  => { line.push_str(text); line.push_str(padding); }
+            Right => { line.push_str(padding); line.push_str(text); }
+          }
+        }
+        line.trim_right().to_owned()
+      }).collect()
+    }
+  }
+}
+
+mod graphics {
+  use std::str;
+  use std::cell::{Cell, RefCell};
+  use std::comm::Sender;
+  use std::io::stdio;
+  use std::io::print;
+  use std::libc::c_int;
+  use std::vec_ng::Vec;
+  use pieces;
+  use pieces::{Block, Black, Green, White, Piece, PieceType, O, S};
+  use board;
+  use board::Board;
+  use scoring::{Score, ScoreEvent};
+  use score_keeper::format_duration;
+  use saved_game;
+  use curses;
+  use strings;
+  use terminal_control;
+  use input_reader;
+  
+  // Everything is drawn with these few functions. They write escape sequences
+  // straight to the terminal, unless the curses backend has been started.
+  
+  fn csi() {
+    print!("{}[", '\x1B');
+  }
+  
+  fn clear_terminal() {
+    if curses::is_active() {
+      return curses::clear();
+    }
+    csi();
+    print("2J");
+  }
+
+  fn reset_graphics() {
+    if curses::is_active() {
+      return curses::set_colors(0);
+    }
+    csi();
+    print("0m");
+  }
+
+  fn hide_cursor() {
+    if curses::is_active() {
+      return curses::show_cursor(false);
+    }
+    csi();
+    print("?25l");
+  }
+  
+  fn show_cursor() {
+    if curses::is_active() {
+      return curses::show_cursor(true);
+    }
+    csi();
+    print("?25h");
+  }
+  
+  fn osc() {
+    print!("{}]", '\x1B');
+  }
+  
+  // OSC 2 sets the terminal window title, terminated by BEL
+  // (curses has no way to set the title, so there's no title with it)
+  fn set_title(title: &str) {
+    if curses::is_active() {
+      return;
+    }
+    osc();
+    print!("2;{}{}", title, '\x07');
+  }
+  
+  // xterm keeps a stack of window titles, save the user's title before changing it...
+  fn push_title() {
+    if curses::is_active() {
+      return;
+    }
+    csi();
+    print("22;2t");
+  }
+  
+  // ...and put it back when the game is over
+  fn pop_title() {
+    if curses::is_active() {
+      return;
+    }
+    csi();
+    print("23;2t");
+  }
+  
+  // xterm says when the terminal gains or loses focus once it's asked to (there's no asking
+  // through curses, so a curses game isn't paused when it loses focus)
+  pub fn report_focus(on: bool) {
+    if curses::is_active() {
+      return;
+    }
+    csi();
+    print(if on { "?1004h" } else { "?1004l" });
+  }
+  
+  // terminal rows and columns start at 1, and the widest displays go well past 127 columns
+  static maxTerminalCoord: i32 = 0xFFFF;
+  
+  fn move_cursor(rowCol: (i32, i32)) {
+    let (row, col) = rowCol;
+    assert!(row >= 1 && row <= maxTerminalCoord, "terminal row {} out of range", row);
+    assert!(col >= 1 && col <= maxTerminalCoord, "terminal column {} out of range", col);
+    if curses::is_active() {
+      return curses::move_to(row as int, col as int);
+    }
+    csi();
+    print!("{};{}H", row as u16, col as u16);
+  }
+  
+  // The colors blocks are drawn with on a terminal: the terminal color each of the 16 looks
+  // like on it, from the config file's "colorOverrides" and what the terminal says its colors
+  // are, and the terminal color each block color is drawn with, from the config file's "theme"
+  pub struct Colors {
+    lookalikes: [u8, ..16],
+    palette:    [u8, ..8]
+  }
+  
+  // every color as itself, for a terminal nothing is known about
+  pub static plainColors: Colors = Colors{lookalikes: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+                                          palette:    [0, 1, 2, 3, 4, 5, 6, 7]};
+  
+  // An override can name a color that's overridden itself, so each one is followed to the
+  // end (a loop of them is cut short)
+  pub fn colors(overrides: [u8, ..16], theme: [u8, ..8]) -> Colors {
+    let mut lookalikes = overrides;
+    for color in range(0u, 16) {
+      for _ in range(0, 16) {
+        lookalikes[color] = lookalikes[lookalikes[color] as uint];
+      }
+    }
+    Colors{lookalikes: lookalikes, palette: distinct_colors(theme, &lookalikes)}
+  }
+  
+  impl Colors {
+    // the same terminal with another theme
+    pub fn themed(&self, theme: [u8, ..8]) -> Colors {
+      Colors{lookalikes: self.lookalikes, palette: distinct_colors(theme, &self.lookalikes)}
+    }
+  }
+  
+  fn looks_like(lookalikes: &[u8, ..16], a: u8, b: u8) -> bool {
+    lookalikes[a as uint] == lookalikes[b as uint]
+  }
+  
+  // The theme's colors, except that a block color that would look like black or like an
+  // earlier block color is moved to a terminal color that looks like none of the others.
+  // The normal colors are tried before the bright ones, and gray is left to garbage.
+  fn distinct_colors(theme: [u8, ..8], lookalikes: &[u8, ..16]) -> [u8, ..8] {
+    let mut colors = theme;
+    for color in range(1u, 8) {
+      if !range(0, color).any(|other| looks_like(lookalikes, colors[other], colors[color])) {
+        continue;
+      }
+      let unused = range(1u8, 16).find(|&candidate| {
+        candidate != 8 && !colors.iter().any(|&used| looks_like(lookalikes, used, candidate))
+      });
+      match unused {
+        Some(candidate) => colors[color] = candidate,
+        None            => ()
+      }
+    }
+    colors
+  }
+  
+  // how long to wait for the terminal to say what its colors are, a terminal that doesn't
+  // answer holds up the start by this much
+  static colorReplyMs: c_int = 150;
+  
+  // Asks the terminal for each of its 16 colors (OSC 4), and gives the colors it shows
+  // exactly alike the same lookalike, on top of the overrides. Standard input has to be
+  // a terminal, which is put in raw mode so the answers aren't echoed.
+  pub fn detect_lookalikes(overrides: [u8, ..16]) -> [u8, ..16] {
+    let mut colors = overrides;
+    let values = {
+      let _restorer = terminal_control::set_terminal_raw_mode();
+      query_colors()
+    };
+    for color in range(1u, 16) {
+      match values.get(color) {
+        &Some(ref value) => match values.slice_to(color).iter().position(|other| other.as_ref() == Some(value)) {
+          Some(same) if colors[color] as uint == color => colors[color] = same as u8,
+          _                                            => ()
+        },
+        &None => ()
+      }
+    }
+    colors
+  }
+  
+  // Each answer is ESC ] 4 ; N ; rgb:RRRR/GGGG/BBBB, ended by BEL or ESC \, and colors
+  // without one are None
+  fn query_colors() -> Vec<Option<~str>> {
+    for color in range(0, 16) {
+      osc();
+      print!("4;{};?{}", color, '\x07');
+    }
+    stdio::flush();
+    let reply = input_reader::read_reply(colorReplyMs, 16);
+    let text = str::from_utf8_lossy(reply.as_slice()).into_owned();
+    let mut values = Vec::from_elem(16, None);
+    for answer in text.split(|c: char| c == '\x07' || c == '\x1B') {
+      let fields: Vec<&str> = answer.split(';').collect();
+      match fields.as_slice() {
+        ["]4", color, value] => match from_str::<uint>(color) {
+          Some(color) if color < 16 => *values.get_mut(color) = Some(value.to_owned()),
+          _                         => ()
+        },
+        _ => ()
+      }
+    }
+    values
+  }
+  
+  // the SGR code for a terminal color, from the code of black: 0 to 7 are the normal
+  // colors and 8 to 15 the bright ones, which start 60 further on
+  fn color_code(black: uint, offset: u8) -> uint {
+    if offset < 8 { black + offset as uint } else { black + 60 + offset as uint - 8 }
+  }
+  
+  fn set_background_color(offset: u8) {
+    if curses::is_active() {
+      return curses::set_colors(curses::background_pair(offset));
+    }
+    csi();
+    print!("{}m", color_code(40, offset));
+  }
+  
+  // the SGR code for text in a piece's color, for listings printed outside the game
+  pub fn piece_color_code(ty: PieceType, palette: [u8, ..8]) -> ~str {
+    color_code(30, palette[pieces::new(ty).blocks[0].color as uint]).to_str()
+  }
+  
+  fn set_foreground_color(offset: u8) {
+    if curses::is_active() {
+      return curses::set_colors(curses::foreground_pair(offset));
+    }
+    csi();
+    print!("{}m", color_code(30, offset));
+  }
+  
+  // Draws a block at the cursor, width columns wide. Garbage is a gray pattern rather than
+  // a solid color, so it can be told apart from set pieces (curses gets a plain ASCII
+  // pattern, since it's not set up for UTF-8). A dimmed block is a light shade of its color.
+  fn put_block(block: Block, width: uint, dimmed: bool, palette: [u8, ..8]) {
+    if block.color != Black && !block.is_garbage() && dimmed {
+      reset_graphics();
+      set_foreground_color(palette[block.color as uint]);
+      return put((if curses::is_active() { ":" } else { "\u2591" }).repeat(width));
+    }
+    if !block.is_garbage() {
+      set_background_color(palette[block.color as uint]);
+      return put(" ".repeat(width));
+    }
+    reset_graphics();
+    if curses::is_active() {
+      curses::set_colors(curses::foreground_pair(White as u8));
+      return put("#".repeat(width));
+    }
+    csi();
+    print("90m");
+    put("\u2592".repeat(width));
+  }
+  
+  // Draws a cell of the board: like put_block, except that empty cells are dim green dots
+  // instead of blank for the last few rows of a sprint (goalTint)
+  fn put_cell(block: Block, width: uint, dimmed: bool, goalTint: bool, palette: [u8, ..8]) {
+    if block.color != Black || !goalTint {
+      return put_block(block, width, dimmed, palette);
+    }
+    reset_graphics();
+    set_foreground_color(palette[Green as uint]);
+    put(" .".repeat(width / 2));
+  }
+  
+  // writes text at the cursor
+  fn put(text: &str) {
+    if curses::is_active() {
+      return curses::put(text);
+    }
+    print(text);
+  }
+  
+  fn flush_output() {
+    if curses::is_active() {
+      return curses::refresh();
+    }
+    stdio::flush();
+  }
+  
+  fn print_borders(rows: i32, cols: i32, rowOffset: i32, columnOffset: i32) {
+    reset_graphics();
+
+    // sides
+    let mut row = 1;
+    while row <= rows + 1 {
+      move_cursor((row + rowOffset, 1 + columnOffset));
+      put("<!");
+      move_cursor((row + rowOffset, 3 + cols + columnOffset));
+      put("!>");
+      row += 1;
+    }
+    
+    // bottom
+    move_cursor((rows + rowOffset + 1, 3 + columnOffset));
+    let mut col = 1;
+    while col <= cols {
+      put("=");
+      col += 1;
+    }
+    move_cursor((rows + rowOffset + 2, 3 + columnOffset));
+    col = 1;
+    while col <= cols - 1 {
+      put("\\/");
+      col += 2;
+    }
+  }
+  
+  // convert from game level row and column to terminal row/col
+  // (board rows and columns are i8, they're widened before converting so the math can't wrap)
+  trait Converter {
+    fn to_terminal(&self, row: i32, col: i32) -> (i32, i32);
+  }
+  
+  // The player's board as a display has drawn it: a byte for each cell, row by row from the
+  // top, with the block's color (0 for empty, 8 for garbage) and outlineCell added for an
+  // outline. It's sent on to the frame capture and the LED matrix every time the display
+  // is flushed.
+  pub struct DrawnCells {
+    cells:   Cell<[u8, ..200]>,
+    senders: RefCell<Vec<Sender<[u8, ..200]>>>
+  }
+  
+  pub static outlineCell: u8 = 0x80;
+  
+  fn drawn_cells() -> DrawnCells {
+    DrawnCells{cells: Cell::new([0, ..200]), senders: RefCell::new(Vec::new())}
+  }
+  
+  impl DrawnCells {
+    fn track(&self, block: Block, outline: bool) {
+      if block.row > board::height || block.column > board::width {
+        return;
+      }
+      let index = (block.row - 1) as uint * board::width as uint + (block.column - 1) as uint;
+      let color = if outline && block.color != Black { block.color as u8 | outlineCell } else { block.color as u8 };
+      let mut cells = self.cells.get();
+      cells[index] = color;
+      self.cells.set(cells);
+    }
+    
+    fn clear(&self) {
+      self.cells.set([0, ..200]);
+    }
+    
+    // a mirror that's gone away is left alone
+    fn send(&self) {
+      for sender in self.senders.borrow().get().iter() {
+        sender.try_send(self.cells.get());
+      }
+    }
+  }
+  
+  // game level rows for the information area (displaying score info, next piece)
+  static levelRow: i32 = 2;
+  static speedRow: i32 = 3;
+  static bonusRow: i32 = 4;
+  static scoreRow: i32 = 6;
+  static linesRow: i32 = 8;
+  static tetrisRow: i32 = 10;
+  static goalRow: i32 = 11;
+  static nextRow: i32 = 13;
+  static debugRow: i32 = 16;
+  static scoreLogRow: i32 = 16;  // the debug overlay takes the score log's place when it's on
+  
+  // base game level column for the information area
+  // Display implemenations may use an offset from this
+  static baseInfoCol: i32 = 14;
+  
+  // terminal columns from the start of a label in the information area to its value, or to
+  // the next piece (the labels in every language get at least this much)
+  static labelColumns: i32 = 8;
+  
+  // game level columns that the label plus a space take, at columnWidth terminal columns
+  // to a game column
+  fn label_columns(label: &str, columnWidth: i32) -> i32 {
+    let columns = strings::width(label) as i32 + 1;
+    if columns < labelColumns {
+      labelColumns / columnWidth
+    } else {
+      (columns + columnWidth - 1) / columnWidth
+    }
+  }
+  
+  // game level columns from the labels to the values next to them, wide enough for the
+  // longest label in the language being used
+  fn value_offset(columnWidth: i32) -> i32 {
+    let text = strings::get();
+    [text.level, text.bonus, text.score, text.lines, text.tetris].iter()
+      .map(|label| label_columns(*label, columnWidth))
+      .fold(0, |widest, columns| if columns > widest { columns } else { widest })
+  }
+  
+  // how far the next piece has to move right to clear a long "Next:" label
+  fn next_piece_shift(columnWidth: i32) -> i32 {
+    label_columns(strings::get().next, columnWidth) - labelColumns / columnWidth
+  }
+  
+  fn init<T: Converter>(converter: T,
+                        terminalRows: i32,
+                        terminalCols: i32,
+                        terminalRowOffset: i32,
+                        terminalColumnOffset: i32,
+                        infoCol: i32) {
+      clear_terminal();
+      hide_cursor();
+      push_title();
+      print_borders(terminalRows, terminalCols, terminalRowOffset, terminalColumnOffset);
+      
+      let text = strings::get();
+      
+      move_cursor(converter.to_terminal(levelRow, infoCol));
+      put(text.level);
+      
+      move_cursor(converter.to_terminal(bonusRow, infoCol));
+      put(text.bonus);
+      
+      move_cursor(converter.to_terminal(scoreRow, infoCol));
+      put(text.score);
+      
+      move_cursor(converter.to_terminal(linesRow, infoCol));
+      put(text.lines);
+      
+      move_cursor(converter.to_terminal(tetrisRow, infoCol));
+      put(text.tetris);
+      
+      move_cursor(converter.to_terminal(nextRow, infoCol));
+      put(text.next);
+      
+      flush_output();
+  }
+  
+  fn close<T: Converter>(converter: T, cursorMoveGameRow: i32) {
+    reset_graphics();
+    show_cursor();
+    pop_title();
+    move_cursor(converter.to_terminal(cursorMoveGameRow, 1));
+    flush_output();
+  }
+  
+  // Digits 2 terminal rows high, drawn with half blocks, for the score on a display with
+  // room for them under its label
+  static bigDigits: [(&'static str, &'static str), ..10] = [
+    ("\u2588\u2580\u2588", "\u2588\u2584\u2588"),
+    ("\u2580\u2588 ",       "\u2584\u2588\u2584"),
+    ("\u2580\u2580\u2588", "\u2588\u2584\u2584"),
+    ("\u2580\u2588\u2588", "\u2584\u2584\u2588"),
+    ("\u2588 \u2588",       "\u2580\u2580\u2588"),
+    ("\u2588\u2580\u2580", "\u2584\u2584\u2588"),
+    ("\u2588\u2584\u2584", "\u2588\u2584\u2588"),
+    ("\u2580\u2580\u2588", "  \u2588"),
+    ("\u2588\u2580\u2588", "\u2588\u2588\u2588"),
+    ("\u2588\u2580\u2588", "\u2580\u2580\u2588")];
+  
+  // the game level row the big score is drawn on, which is empty otherwise, and the most
+  // digits that fit across the information area (each is 3 columns and a space)
+  static bigScoreRow: i32 = 7;
+  static bigScoreDigits: uint = 5;
+  
+  // Draws number in big digits, right-aligned in room for bigScoreDigits of them, with
+  // its top half at the terminal row and column given
+  fn print_big_number(rowCol: (i32, i32), number: &str) {
+    let (row, col) = rowCol;
+    let mut top = ~"";
+    let mut bottom = ~"";
+    for digit in number.chars().filter_map(|c| c.to_digit(10)) {
+      let (upper, lower) = bigDigits[digit];
+      top.push_str(upper);
+      top.push_char(' ');
+      bottom.push_str(lower);
+      bottom.push_char(' ');
+    }
+    let blank = spaces(bigScoreDigits * 4 - number.len() * 4);
+    move_cursor((row, col));
+    put((blank + top).as_slice());
+    move_cursor((row + 1, col));
+    put((blank + bottom).as_slice());
+  }
+  
+  // The numbers in the information area are right-aligned in fields valueWidth columns
+  // wide, the widest a score is shown in full, so a number that gets shorter (the bonus
+  // going down from 10 to 9) doesn't leave digits of the old one behind, and a long one
+  // doesn't run out of the area. Numbers too long for their field are counted in
+  // thousands, millions and so on.
+  static valueWidth: uint = 11;
+  static valueUnits: [&'static str, ..6] = ["k", "M", "G", "T", "P", "E"];
+  
+  fn number_field(number: i64, width: uint) -> ~str {
+    let mut text = strings::grouped(number);
+    let mut scaled = number;
+    let mut unit = 0;
+    while strings::width(text) > width {
+      scaled /= 1000;
+      text = strings::grouped(scaled) + valueUnits[unit];
+      unit += 1;
+    }
+    text_field(text, width)
+  }
+  
+  // text that isn't a number, like the speed, lined up with the numbers the same way and
+  // cut short if it's too long for the field
+  fn text_field(text: &str, width: uint) -> ~str {
+    if strings::width(text) > width {
+      text.chars().take(width).collect()
+    } else {
+      spaces(width - strings::width(text)) + text
+    }
+  }
+  
+  // The score is drawn in big digits under its label when bigCol (the label's column) is
+  // given, unless it's too long for them or curses is drawing (it isn't set up for UTF-8).
+  fn print_score<T: Converter>(converter: T, infoCol: i32, bigCol: Option<i32>, score: Score) {
+      reset_graphics();
+      
+      move_cursor(converter.to_terminal(levelRow, infoCol));
+      put(number_field(score.level as i64, valueWidth).as_slice());
+      
+      move_cursor(converter.to_terminal(bonusRow, infoCol));
+      put(number_field(score.bonus as i64, valueWidth).as_slice());
+      
+      let digits = score.score.to_str();
+      match bigCol {
+        Some(col) if !curses::is_active() && digits.len() <= bigScoreDigits => {
+          let (row, col) = converter.to_terminal(bigScoreRow, col);
+          print_big_number((row - 1, col), digits);
+        }
+        _ => {
+          move_cursor(converter.to_terminal(scoreRow, infoCol));
+          put(number_field(score.score, valueWidth).as_slice());
+          match bigCol {
+            Some(col) => {
+              let (row, col) = converter.to_terminal(bigScoreRow, col);
+              print_big_number((row - 1, col), "");
+            }
+            None => ()
+          }
+        }
+      }
+      
+      move_cursor(converter.to_terminal(linesRow, infoCol));
+      put(number_field(score.lines as i64, valueWidth).as_slice());
+      
+      move_cursor(converter.to_terminal(tetrisRow, infoCol));
+      put(number_field(score.tetrises as i64, valueWidth).as_slice());
+  }
+  
+  // how fast pieces fall at the level being played and the next one, so a speed up isn't a surprise
+  fn print_speed<T: Converter>(converter: T, infoCol: i32, rowTime: i32, nextRowTime: i32) {
+    reset_graphics();
+    move_cursor(converter.to_terminal(speedRow, infoCol));
+    let speed = strings::fill(strings::get().speedChange, &[rowTime.to_str().as_slice(), nextRowTime.to_str().as_slice()]);
+    put(text_field(speed, valueWidth).as_slice());
+  }
+  
+  // a sprint's empty cells are drawn in green with fewer rows than this left to clear
+  pub static goalTintRows: uint = 10;
+  
+  // The rows a sprint has left to clear, under the tetris count, and a mark left of the
+  // board on the row that many up from the floor once it's on the board
+  fn print_goal<T: Converter>(converter: T, infoCol: i32, remaining: uint) {
+    reset_graphics();
+    move_cursor(converter.to_terminal(goalRow, infoCol));
+    let left = strings::fill(strings::get().goalLeft, &[remaining.to_str().as_slice()]);
+    put(text_field(left, valueWidth).as_slice());
+    for row in range(1, 21i32) {
+      move_cursor(converter.to_terminal(row, -1));
+      put(if remaining as i32 == 21 - row { "\u25B6" } else { " " });
+    }
+  }
+  
+  // game level rows/column of the game over message, drawn over the middle of the board
+  static gameOverRow: i32 = 8;
+  static gameOverCol: i32 = 2;
+  
+  // terminal columns of the game over box, which covers the middle of the board
+  static gameOverWidth: uint = 16;
+  
+  fn spaces(count: uint) -> ~str {
+    " ".repeat(count)
+  }
+  
+  // the text in the middle of a line of the game over box
+  fn centered(text: &str) -> ~str {
+    let width = strings::width(text);
+    if width >= gameOverWidth {
+      return text.to_owned();
+    }
+    let left = (gameOverWidth - width) / 2;
+    format!("{}{}{}", spaces(left), text, spaces(gameOverWidth - width - left))
+  }
+  
+  // a line of the game over box with the label on the left and the value on the right
+  fn box_line(label: &str, value: &str) -> ~str {
+    let used = strings::width(label) + strings::width(value) + 2;
+    let gap = if used < gameOverWidth { gameOverWidth - used } else { 1 };
+    format!(" {}{}{} ", label, spaces(gap), value)
+  }
+  
+  // heading is centered over the board
+  fn print_game_over<T: Converter>(converter: T, heading: &str, score: Score, durationMs: u64, seed: u64) {
+    reset_graphics();
+    
+    let text = strings::get();
+    let lines = [spaces(gameOverWidth),
+                 centered(heading),
+                 spaces(gameOverWidth),
+                 box_line(text.score, strings::grouped(score.score)),
+                 box_line(text.lines, score.lines.to_str()),
+                 box_line(text.tetrises, score.tetrises.to_str()),
+                 box_line(text.time, format_duration(durationMs)),
+                 box_line(text.seed, seed.to_str()),
+                 spaces(gameOverWidth)];
+    let mut row = gameOverRow;
+    for line in lines.iter() {
+      move_cursor(converter.to_terminal(row, gameOverCol));
+      put(*line);
+      row += 1;
+    }
+  }
+  
+  // The pause menu, in the same box as the game over message: each setting with its value,
+  // the selected one marked with an arrow
+  fn print_pause_menu<T: Converter>(converter: T, heading: &str, items: &[(&str, ~str)], selected: uint, help: &str) {
+    reset_graphics();
+    
+    let mut lines = vec!(spaces(gameOverWidth), centered(heading), spaces(gameOverWidth));
+    for (i, &(label, ref value)) in items.iter().enumerate() {
+      let line = box_line(label, value.as_slice());
+      lines.push(if i == selected { "\u25B6" + line.slice_from(1) } else { line });
+    }
+    lines.push(spaces(gameOverWidth));
+    lines.push(centered(help));
+    lines.push(spaces(gameOverWidth));
+    let mut row = gameOverRow;
+    for line in lines.iter() {
+      move_cursor(converter.to_terminal(row, gameOverCol));
+      put(*line);
+      row += 1;
+    }
+  }
+  
+  fn print_debug<T: Converter>(converter: T, infoCol: i32, lines: &[~str]) {
+    reset_graphics();
+    
+    let mut row = debugRow;
+    for line in lines.iter() {
+      move_cursor(converter.to_terminal(row, infoCol));
+      put(format!("{}      ", *line).as_slice());
+      row += 1;
+    }
+  }
+  
+  // where the statistics HUD goes, in terminal rows and columns: a second information
+  // column to the right of the display's area
+  static statsRow: i32 = 3;
+  static statsGap: uint = 2;
+  static statsWidth: uint = 24;
+  
+  fn print_stats(displayWidth: uint, rows: uint, lines: &[~str]) {
+    let col = displayWidth + statsGap;
+    match terminal_control::terminal_size() {
+      Some((columns, _)) if columns >= col + statsWidth => (),
+      _                                                   => return
+    }
+    reset_graphics();
+    for i in range(0, rows) {
+      let line = if i < lines.len() { lines[i].as_slice() } else { "" };
+      let width = strings::width(line);
+      move_cursor((statsRow + i as i32, col as i32 + 1));
+      put(line);
+      if width < statsWidth {
+        put(spaces(statsWidth - width).as_slice());
+      }
+    }
+  }
+  
+  // terminal rows of a miniature board's label, and its top row
+  static miniLabelRow: i32 = 2;
+  static miniTopRow: i32 = 3;
+  
+  // A second board at one terminal column per cell, with a label above it, starting at
+  // terminal column col. It's redrawn whole from the masks of its rows (bit 0 for column 1)
+  // rather than block by block.
+  fn print_mini_board(col: i32, label: &str, rows: &[u16]) {
+    reset_graphics();
+    move_cursor((miniLabelRow, col));
+    put(label);
+    put("    ");
+    for (i, mask) in rows.iter().enumerate() {
+      let mut line = ~"|";
+      for bit in range(0u, 10) {
+        line.push_char(if *mask & (1 << bit) != 0 { '#' } else { ' ' });
+      }
+      line.push_char('|');
+      move_cursor((miniTopRow + i as i32, col));
+      put(line.as_slice());
+    }
+    move_cursor((miniTopRow + rows.len() as i32, col));
+    put("+----------+");
+  }
+  
+  // terminal row of the line under a miniature board, and the columns it's blanked out to
+  static miniCaptionRow: i32 = 24;
+  static miniCaptionWidth: uint = 12;
+  
+  fn print_mini_caption(col: i32, text: &str) {
+    reset_graphics();
+    move_cursor((miniCaptionRow, col));
+    put(text);
+    let width = strings::width(text);
+    if width < miniCaptionWidth {
+      put(spaces(miniCaptionWidth - width).as_slice());
+    }
+  }
+  
+  // The board with the falling piece in it as lines of text with color escapes, between
+  // the same borders as the display, and the score underneath. It isn't drawn, it's for a
+  // picture of the game to keep outside the terminal (like an exported replay frame).
+  pub fn board_picture(setBlocks: &Board, piece: &Piece, score: Score, palette: [u8, ..8]) -> ~str {
+    let mut text = ~"";
+    for row in range(1, board::height + 1) {
+      text.push_str("\x1B[0m<!");
+      for col in range(1, board::width + 1) {
+        let block = match piece.blocks.iter().find(|block| block.row == row && block.column == col) {
+          Some(block) => Some(*block),
+          None        => setBlocks.get(row, col)
+        };
+        match block {
+          Some(block) if block.is_garbage() => text.push_str("\x1B[0;90m\u2592\u2592"),
+          Some(block)                       => text.push_str(format!("\x1B[{}m  ", color_code(40, palette[block.color as uint]))),
+          None                              => text.push_str("\x1B[0m  ")
+        }
+      }
+      text.push_str("\x1B[0m!>\n");
+    }
+    text.push_str("<!====================!>\n");
+    text.push_str("  \\/\\/\\/\\/\\/\\/\\/\\/\\/\\/\n");
+    let labels = strings::get();
+    text.push_str(format!("{} {}  {} {}  {} {}\n", labels.level, score.level, labels.score, score.score,
+                          labels.lines, score.lines));
+    text
+  }
+  
+  // terminal columns each line of the score log is blanked out to
+  static scoreLogWidth: uint = 16;
+  
+  fn print_score_log<T: Converter>(converter: T, infoCol: i32, lines: &[~str]) {
+    reset_graphics();
+    
+    let mut row = scoreLogRow;
+    for line in lines.iter() {
+      move_cursor(converter.to_terminal(row, infoCol));
+      put(line.as_slice());
+      let width = strings::width(*line);
+      if width < scoreLogWidth {
+        put(spaces(scoreLogWidth - width).as_slice());
+      }
+      row += 1;
+    }
+  }
+  
+  // game level rows of the last chat lines of a match over the network, in the score log's
+  // place, and of the line being typed under them
+  static chatLines: uint = 3;
+  static chatEntryRow: i32 = 20;
+  
+  // terminal columns each chat line is blanked out to, up to the opponent's miniature board
+  static chatWidth: uint = 32;
+  
+  // The chat lines, oldest first, and the line the player is typing with a cursor after
+  // it, or how to start typing one
+  fn print_chat<T: Converter>(converter: T, infoCol: i32, lines: &[~str], entry: Option<&str>) {
+    reset_graphics();
+    
+    let shown = lines.slice_from(if lines.len() > chatLines { lines.len() - chatLines } else { 0 });
+    let mut row = scoreLogRow;
+    for line in shown.iter() {
+      move_cursor(converter.to_terminal(row, infoCol));
+      put_padded(line.as_slice(), chatWidth);
+      row += 1;
+    }
+    move_cursor(converter.to_terminal(chatEntryRow, infoCol));
+    match entry {
+      Some(text) => put_padded(format!("> {}_", text).as_slice(), chatWidth),
+      None       => put_padded(strings::get().chatHint, chatWidth)
+    }
+  }
+  
+  // text cut or blanked out to width terminal columns
+  fn put_padded(text: &str, width: uint) {
+    let shown: ~str = text.chars().take(width).collect();
+    put(shown.as_slice());
+    let shownWidth = strings::width(shown.as_slice());
+    if shownWidth < width {
+      put(spaces(width - shownWidth).as_slice());
+    }
+  }
+  
+  // Clears the terminal and draws lines one under the other, each centered in an area of
+  // size (columns, rows) and the block of them in the middle
+  fn print_centered(size: (uint, uint), lines: &[&str]) {
+    let (width, height) = size;
+    reset_graphics();
+    clear_terminal();
+    hide_cursor();
+    let mut row = (height - lines.len()) / 2;
+    for line in lines.iter() {
+      move_cursor((row as i32, ((width - strings::width(*line)) / 2) as i32));
+      put(*line);
+      row += 1;
+    }
+    flush_output();
+  }
+  
+  pub trait Display {
+    fn init(&self);
+    fn close(&self);
+    fn print_score(&self, score: Score);
+    fn print_block(&self, block: Block);
+    fn print_next_piece(&self, piece: &Piece);
+    
+    // an empty cell marked with an outline in the block's color
+    fn print_outline(&self, block: Block);
+    fn print_debug(&self, lines: &[~str]);
+    fn print_game_over(&self, heading: &str, score: Score, durationMs: u64, seed: u64);
+    
+    // the settings that can be changed while the game is paused, over the board
+    fn print_pause_menu(&self, _heading: &str, _items: &[(&str, ~str)], _selected: uint, _help: &str) {
+    }
+    
+    // number of terminal columns and rows the display needs
+    fn size(&self) -> (uint, uint);
+
+    // Called whenever a piece is set. Displays can override this to react to what
+    // happened (rows cleared, level up...), by default the new score is just printed
+    fn print_score_event(&self, event: &ScoreEvent) {
+      self.print_score(event.score);
+    }
+    
+    // the last few things that scored, newest first
+    fn print_score_log(&self, _lines: &[~str]) {
+    }
+    
+    // milliseconds a piece takes to fall a row at the current level and the next
+    fn print_speed(&self, _rowTime: i32, _nextRowTime: i32) {
+    }
+    
+    // the rows left to clear in a sprint, the empty cells are drawn differently once there
+    // are fewer than goalTintRows
+    fn print_goal(&self, _remaining: uint) {
+    }
+    
+    // While a game that's been left alone is paused, blocks are drawn as a light shade of
+    // their color rather than solid, so the board looks dimmed
+    fn set_dimmed(&self, _on: bool) {
+    }
+    
+    // what the terminal colors look like, and the terminal color each block color is drawn with
+    fn colors(&self) -> Colors {
+      plainColors
+    }
+    
+    // the config file's theme changed, blocks drawn from now on are in its colors
+    fn set_theme(&self, _theme: [u8, ..8]) {
+    }
+    
+    // the player's board is sent to cells from now on, every time the display is flushed
+    fn mirror_cells(&self, _cells: Sender<[u8, ..200]>) {
+    }
+    
+    // Whether a full size second board (a SideDisplay) fits next to this display. If it
+    // doesn't the other board is drawn small with print_mini_board.
+    fn has_room_for_side_board(&self) -> bool {
+      false
+    }
+    
+    // another player's board in miniature, from the masks of its 20 rows
+    fn print_mini_board(&self, _label: &str, _rows: &[u16]) {
+    }
+    
+    // a line under the miniature board, like how far behind it is
+    fn print_mini_caption(&self, _text: &str) {
+    }
+    
+    // the chat of a match over the network, and the line being typed if there is one
+    fn print_chat(&self, _lines: &[~str], _entry: Option<&str>) {
+    }
+    
+    // The statistics HUD, next to the display if the terminal is wide enough for it.
+    // It takes up rows lines, any that aren't given are blanked.
+    fn print_stats(&self, rows: uint, lines: &[~str]) {
+      let (width, _) = self.size();
+      print_stats(width, rows, lines);
+    }
+    
+    fn print_title(&self, score: Score) {
+      let text = strings::get();
+      set_title(format!("Tetris - {} {} {} {}", text.level, score.level, text.score, strings::grouped(score.score)).as_slice());
+    }
+    
+    // the screen shown before a game is started, centered in the display's area
+    fn print_title_screen(&self) {
+      print_centered(self.size(), &strings::get().titleScreen);
+    }
+    
+    // what's shown while waiting for an opponent to play over the network, like the title screen
+    fn print_lobby(&self, lines: &[&str]) {
+      print_centered(self.size(), lines);
+    }
+    
+    // a line of text above the board
+    fn print_banner(&self, text: &str) {
+      reset_graphics();
+      move_cursor((1, 4));
+      put(text);
+    }
+
+    fn print_piece(&self, piece: &Piece) {
+      for block in piece.blocks.iter() {
+        self.print_block(*block);
+      }
+    }
+    
+    fn print_piece_outline(&self, piece: &Piece) {
+      for block in piece.blocks.iter() {
+        self.print_outline(*block);
+      }
+    }
+        
+    fn flush(&self) {
+      flush_output();
+    }
+    
+    fn erase_block(&self, row: i8, col: i8) {
+      self.print_block(Block{row: row, column: col, color: Black, ty: None});
+    }
+    
+    fn erase_piece(&self, piece: &Piece) {
+      for block in piece.blocks.iter() {
+        self.erase_block(block.row, block.column);
+      }
+    }
+    
+    fn erase_next_piece(&self, piece: &Piece) {
+      let erase = Piece {
+                    ty:     piece.ty,
+                    rotate: piece.rotate,
+                    blocks: [Block{row:    piece.blocks[0].row,
+                                   column: piece.blocks[0].column,
+                                   color:  Black,
+                                   ty:     None},
+
+                             Block{row:    piece.blocks[1].row,
+                                   column: piece.blocks[1].column,
+                                   color:  Black,
+                                   ty:     None},
+
+                             Block{row:    piece.blocks[2].row,
+                                   column: piece.blocks[2].column,
+                                   color:  Black,
+                                   ty:     None},
+
+                             Block{row:    piece.blocks[3].row,
+                                   column: piece.blocks[3].column,
+                                   color:  Black,
+                                   ty:     None}]
+      };
+      self.print_next_piece(&erase);
+    }
+  }
+  
+  pub struct StandardDisplay {
+    goalTint: Cell<bool>,  // the empty cells are drawn for the end of a sprint, see print_goal
+    dimmed:   Cell<bool>,  // see set_dimmed
+    colors:   Cell<Colors>,
+    drawn:    DrawnCells
+  }
+  
+  pub fn standard_display(colors: Colors) -> StandardDisplay {
+    StandardDisplay{goalTint: Cell::new(false), dimmed: Cell::new(false), colors: Cell::new(colors), drawn: drawn_cells()}
+  }
+
+  // terminal level row/column offsets for everything (Blocks, borders, ...)
+  static stdRowOffset: i32 = 2;
+  static stdColumnOffset: i32 = 3;
+  
+  // terminal level number of columns a left/right border takes
+  static stdBorderColumns: i32 = 2;
+  
+  impl StandardDisplay {
+    #[inline(always)]
+    fn to_terminal(row: i32, col: i32) -> (i32, i32) {
+      (row + stdRowOffset, 2 * col + stdBorderColumns - 1 + stdColumnOffset)
+    }
+  }
+  
+  impl<'a> Converter for &'a StandardDisplay {
+    fn to_terminal(&self, row: i32, col: i32) -> (i32, i32) {
+      StandardDisplay::to_terminal(row, col)
+    }
+  }
+  
+  impl Display for StandardDisplay {
+    fn init(&self) {
+      self.goalTint.set(false);
+      self.drawn.clear();
+      init(self, 20, 20, stdRowOffset, stdColumnOffset, baseInfoCol);
+    }
+
+    fn close(&self) {
+      close(self, 23);
+    }
+    
+    fn print_score(&self, score: Score) {
+      print_score(self, baseInfoCol + value_offset(2), None, score);
+    }
+    
+    fn print_debug(&self, lines: &[~str]) {
+      print_debug(self, baseInfoCol, lines);
+    }
+    
+    fn print_score_log(&self, lines: &[~str]) {
+      print_score_log(self, baseInfoCol, lines);
+    }
+    
+    fn print_speed(&self, rowTime: i32, nextRowTime: i32) {
+      print_speed(self, baseInfoCol + value_offset(2), rowTime, nextRowTime);
+    }
+    
+    fn print_goal(&self, remaining: uint) {
+      self.goalTint.set(remaining < goalTintRows);
+      print_goal(self, baseInfoCol + value_offset(2), remaining);
+    }
+    
+    fn set_dimmed(&self, on: bool) {
+      self.dimmed.set(on);
+    }
+    
+    fn colors(&self) -> Colors {
+      self.colors.get()
+    }
+    
+    fn set_theme(&self, theme: [u8, ..8]) {
+      self.colors.set(self.colors.get().themed(theme));
+    }
+    
+    fn mirror_cells(&self, cells: Sender<[u8, ..200]>) {
+      self.drawn.senders.borrow_mut().get().push(cells);
+    }
+    
+    fn flush(&self) {
+      self.drawn.send();
+      flush_output();
+    }
+    
+    fn has_room_for_side_board(&self) -> bool {
+      true
+    }
+    
+    // right of the information area
+    fn print_mini_board(&self, label: &str, rows: &[u16]) {
+      print_mini_board(66, label, rows);
+    }
+    
+    fn print_mini_caption(&self, text: &str) {
+      print_mini_caption(66, text);
+    }
+    
+    fn print_chat(&self, lines: &[~str], entry: Option<&str>) {
+      print_chat(self, baseInfoCol, lines, entry);
+    }
+    
+    fn print_game_over(&self, heading: &str, score: Score, durationMs: u64, seed: u64) {
+      print_game_over(self, heading, score, durationMs, seed);
+    }
+    
+    fn print_pause_menu(&self, heading: &str, items: &[(&str, ~str)], selected: uint, help: &str) {
+      print_pause_menu(self, heading, items, selected, help);
+    }
+    
+    fn size(&self) -> (uint, uint) {
+      (80, 24)
+    }
+    
+    fn print_block(&self, block: Block) {
+      if block.row < 1 || block.column < 1 {
+        return;
+      }
+      self.drawn.track(block, false);
+      move_cursor(StandardDisplay::to_terminal(block.row as i32, block.column as i32));
+      put_cell(block, 2, self.dimmed.get(), self.goalTint.get(), self.colors.get().palette);
+    }
+    
+    fn print_outline(&self, block: Block) {
+      if block.row < 1 || block.column < 1 {
+        return;
+      }
+      self.drawn.track(block, true);
+      move_cursor(StandardDisplay::to_terminal(block.row as i32, block.column as i32));
+      reset_graphics();
+      set_foreground_color(self.colors.get().palette[block.color as uint]);
+      put("[]");
+    }
+    
+    fn print_next_piece(&self, piece: &Piece) {
+      let colOffset = next_piece_shift(2) + match piece.ty {
+        O | S => 13,
+        _     => 14
+      };
+      for block in piece.blocks.iter() {
+        move_cursor(StandardDisplay::to_terminal(nextRow + block.row as i32, colOffset + block.column as i32));
+        put_block(*block, 2, self.dimmed.get(), self.colors.get().palette);
+      }
+    }
+  }
+  
+  // A second board drawn to the right of a StandardDisplay, for the ghost of a replay being
+  // raced or a second player. There's no information area, just a label, the lines and the
+  // score above the board.
+  pub struct SideDisplay {
+    label:  &'static str,
+    colors: Cell<Colors>
+  }
+  
+  pub fn side_display(label: &'static str, colors: Colors) -> SideDisplay {
+    SideDisplay{label: label, colors: Cell::new(colors)}
+  }
+  
+  static sideColumnOffset: i32 = 50;
+  static sideLabelRow: i32 = 1;
+  static sideScoreRow: i32 = 2;
+  
+  // the score line has narrower fields, to fit in the 30 terminal columns of the side board
+  static sideLinesWidth: uint = 4;
+  static sideScoreWidth: uint = 7;
+  
+  impl SideDisplay {
+    #[inline(always)]
+    fn to_terminal(row: i32, col: i32) -> (i32, i32) {
+      (row + stdRowOffset, 2 * col + stdBorderColumns - 1 + sideColumnOffset)
+    }
+  }
+  
+  impl Converter for SideDisplay {
+    fn to_terminal(&self, row: i32, col: i32) -> (i32, i32) {
+      SideDisplay::to_terminal(row, col)
+    }
+  }
+  
+  impl Display for SideDisplay {
+    // the player's display has already cleared the terminal
+    fn init(&self) {
+      print_borders(20, 20, stdRowOffset, sideColumnOffset);
+      move_cursor((sideLabelRow, 1 + sideColumnOffset));
+      put(self.label);
+    }
+    
+    fn close(&self) {
+    }
+    
+    fn print_score(&self, score: Score) {
+      reset_graphics();
+      move_cursor((sideScoreRow, 1 + sideColumnOffset));
+      let text = strings::get();
+      put(format!("{} {}  {} {}", text.lines, number_field(score.lines as i64, sideLinesWidth), text.score,
+                  number_field(score.score, sideScoreWidth)).as_slice());
+    }
+    
+    fn print_debug(&self, _lines: &[~str]) {
+    }
+    
+    fn print_game_over(&self, heading: &str, score: Score, durationMs: u64, seed: u64) {
+      print_game_over(*self, heading, score, durationMs, seed);
+    }
+    
+    fn size(&self) -> (uint, uint) {
+      (80, 24)
+    }
+    
+    // the window title and the statistics belong to the player
+    fn print_title(&self, _score: Score) {
+    }
+    
+    fn print_stats(&self, _rows: uint, _lines: &[~str]) {
+    }
+    
+    fn colors(&self) -> Colors {
+      self.colors.get()
+    }
+    
+    fn set_theme(&self, theme: [u8, ..8]) {
+      self.colors.set(self.colors.get().themed(theme));
+    }
+    
+    fn print_block(&self, block: Block) {
+      if block.row < 1 || block.column < 1 {
+        return;
+      }
+      move_cursor(SideDisplay::to_terminal(block.row as i32, block.column as i32));
+      put_block(block, 2, false, self.colors.get().palette);
+    }
+    
+    fn print_outline(&self, block: Block) {
+      if block.row < 1 || block.column < 1 {
+        return;
+      }
+      move_cursor(SideDisplay::to_terminal(block.row as i32, block.column as i32));
+      reset_graphics();
+      set_foreground_color(self.colors.get().palette[block.color as uint]);
+      put("[]");
+    }
+    
+    fn print_next_piece(&self, _piece: &Piece) {
+    }
+  }
+  
+  // Draws nothing at all, for games nobody is watching (like the AI benchmark)
+  pub struct NullDisplay;
+  
+  impl Display for NullDisplay {
+    fn init(&self) {
+    }
+    
+    fn close(&self) {
+    }
+    
+    fn print_score(&self, _score: Score) {
+    }
+    
+    fn print_block(&self, _block: Block) {
+    }
+    
+    fn print_next_piece(&self, _piece: &Piece) {
+    }
+    
+    fn print_outline(&self, _block: Block) {
+    }
+    
+    fn print_debug(&self, _lines: &[~str]) {
+    }
+    
+    fn print_game_over(&self, _heading: &str, _score: Score, _durationMs: u64, _seed: u64) {
+    }
+    
+    fn size(&self) -> (uint, uint) {
+      (0, 0)
+    }
+    
+    fn print_title(&self, _score: Score) {
+    }
+    
+    fn print_stats(&self, _rows: uint, _lines: &[~str]) {
+    }
+    
+    fn flush(&self) {
+    }
+  }
+  
+  pub struct DoubleDisplay {
+    goalTint: Cell<bool>,
+    dimmed:   Cell<bool>,
+    colors:   Cell<Colors>,
+    drawn:    DrawnCells
+  }
+  
+  pub fn double_display(colors: Colors) -> DoubleDisplay {
+    DoubleDisplay{goalTint: Cell::new(false), dimmed: Cell::new(false), colors: Cell::new(colors), drawn: drawn_cells()}
+  }
+  
+  static dblRowOffset: i32 = 2;
+  static dblColumnOffset: i32 = 30;
+  static dblBorderColumns: i32 = 2;
+  
+  impl DoubleDisplay {
+    #[inline(always)]
+    fn to_terminal(row: i32, col: i32) -> (i32, i32) {
+      (2 * row + dblRowOffset, 4 * col - 3 + dblBorderColumns + dblColumnOffset)
+    }
+    
+    // each block is two terminal rows high, this is the top one
+    #[inline(always)]
+    fn to_upper_half(row: i32, col: i32) -> (i32, i32) {
+      let (terminalRow, terminalCol) = DoubleDisplay::to_terminal(row, col);
+      (terminalRow - 1, terminalCol)
+    }
+  }
+  
+  impl<'a> Converter for &'a DoubleDisplay {
+    fn to_terminal(&self, row: i32, col: i32) -> (i32, i32) {
+      DoubleDisplay::to_terminal(row, col)
+    }
+  }
+  
+  impl Display for DoubleDisplay {
+    fn init(&self) {
+      self.goalTint.set(false);
+      self.drawn.clear();
+      init(self, 40, 40, dblRowOffset, dblColumnOffset, baseInfoCol - 1);
+    }
+  
+    fn close(&self) {
+      close(self, 22);
+    }
+  
+    fn print_score(&self, score: Score) {
+      print_score(self, baseInfoCol - 1 + value_offset(4), Some(baseInfoCol - 1), score);
+    }
+    
+    fn print_debug(&self, lines: &[~str]) {
+      print_debug(self, baseInfoCol - 1, lines);
+    }
+    
+    fn print_score_log(&self, lines: &[~str]) {
+      print_score_log(self, baseInfoCol - 1, lines);
+    }
+    
+    fn print_speed(&self, rowTime: i32, nextRowTime: i32) {
+      print_speed(self, baseInfoCol - 1 + value_offset(4), rowTime, nextRowTime);
+    }
+    
+    fn print_goal(&self, remaining: uint) {
+      self.goalTint.set(remaining < goalTintRows);
+      print_goal(self, baseInfoCol - 1 + value_offset(4), remaining);
+    }
+    
+    fn set_dimmed(&self, on: bool) {
+      self.dimmed.set(on);
+    }
+    
+    fn colors(&self) -> Colors {
+      self.colors.get()
+    }
+    
+    fn set_theme(&self, theme: [u8, ..8]) {
+      self.colors.set(self.colors.get().themed(theme));
+    }
+    
+    fn mirror_cells(&self, cells: Sender<[u8, ..200]>) {
+      self.drawn.senders.borrow_mut().get().push(cells);
+    }
+    
+    fn flush(&self) {
+      self.drawn.send();
+      flush_output();
+    }
+    
+    // in the space left of the board
+    fn print_mini_board(&self, label: &str, rows: &[u16]) {
+      print_mini_board(4, label, rows);
+    }
+    
+    fn print_mini_caption(&self, text: &str) {
+      print_mini_caption(4, text);
+    }
+    
+    fn print_game_over(&self, heading: &str, score: Score, durationMs: u64, seed: u64) {
+      print_game_over(self, heading, score, durationMs, seed);
+    }
+    
+    fn print_pause_menu(&self, heading: &str, items: &[(&str, ~str)], selected: uint, help: &str) {
+      print_pause_menu(self, heading, items, selected, help);
+    }
+    
+    fn size(&self) -> (uint, uint) {
+      (100, 45)
+    }
+  
+    fn print_block(&self, block: Block) {
+      if block.row < 1 || block.column < 1 {
+        return;
+      }
+      self.drawn.track(block, false);
+      move_cursor(DoubleDisplay::to_terminal(block.row as i32, block.column as i32));
+      put_cell(block, 4, self.dimmed.get(), self.goalTint.get(), self.colors.get().palette);
+      move_cursor(DoubleDisplay::to_upper_half(block.row as i32, block.column as i32));
+      put_cell(block, 4, self.dimmed.get(), self.goalTint.get(), self.colors.get().palette);
+    }
+    
+    fn print_outline(&self, block: Block) {
+      if block.row < 1 || block.column < 1 {
+        return;
+      }
+      self.drawn.track(block, true);
+      reset_graphics();
+      set_foreground_color(self.colors.get().palette[block.color as uint]);
+      move_cursor(DoubleDisplay::to_upper_half(block.row as i32, block.column as i32));
+      put("+--+");
+      move_cursor(DoubleDisplay::to_terminal(block.row as i32, block.column as i32));
+      put("+--+");
+    }
+    
+    fn print_next_piece(&self, piece: &Piece) {
+      let colOffset = next_piece_shift(4) + match piece.ty {
+        O | S => 10,
+        _     => 11
+      };
+      for block in piece.blocks.iter() {
+        move_cursor(DoubleDisplay::to_terminal(nextRow + block.row as i32, colOffset + block.column as i32));
+        put_block(*block, 4, self.dimmed.get(), self.colors.get().palette);
+        move_cursor(DoubleDisplay::to_upper_half(nextRow + block.row as i32, colOffset + block.column as i32));
+        put_block(*block, 4, self.dimmed.get(), self.colors.get().palette);
+      }
+    }
+  }
+  
+  // For logs, CI smoke tests and terminals that can't move the cursor: nothing is drawn in
+  // place, instead a picture of the whole board in plain ASCII is printed line by line
+  // after every frame that changed it, under a line with the score and the next piece.
+  // Messages (the title screen, the pause menu, game over) are printed as lines too.
+  pub struct DumbDisplay {
+    score:   Cell<Score>,
+    next:    Cell<Option<PieceType>>,
+    printed: Cell<Option<[u8, ..200]>>,  // the cells of the last board printed
+    drawn:   DrawnCells
+  }
+  
+  pub fn dumb_display() -> DumbDisplay {
+    DumbDisplay{score:   Cell::new(Score{level: 0, bonus: 0, score: 0, lines: 0, tetrises: 0}),
+                next:    Cell::new(None),
+                printed: Cell::new(None),
+                drawn:   drawn_cells()}
+  }
+  
+  // raw mode doesn't turn a newline into a carriage return as well, so a terminal needs both
+  fn dumb_line(text: &str) {
+    put(text);
+    put(if terminal_control::stdout_is_terminal() { "\r\n" } else { "\n" });
+  }
+  
+  fn dumb_cell(cell: u8) -> &'static str {
+    match cell {
+      0                               => " .",
+      8                               => "##",
+      _ if cell & outlineCell != 0    => "()",
+      _                               => "[]"
+    }
+  }
+  
+  impl Display for DumbDisplay {
+    fn init(&self) {
+      self.drawn.clear();
+      self.printed.set(None);
+    }
+    
+    fn close(&self) {
+      flush_output();
+    }
+    
+    fn print_score(&self, score: Score) {
+      self.score.set(score);
+    }
+    
+    fn print_block(&self, block: Block) {
+      if block.row >= 1 && block.column >= 1 {
+        self.drawn.track(block, false);
+      }
+    }
+    
+    fn print_next_piece(&self, piece: &Piece) {
+      self.next.set(if piece.blocks[0].color == Black { None } else { Some(piece.ty) });
+    }
+    
+    fn print_outline(&self, block: Block) {
+      if block.row >= 1 && block.column >= 1 {
+        self.drawn.track(block, true);
+      }
+    }
+    
+    fn print_debug(&self, _lines: &[~str]) {
+    }
+    
+    fn print_game_over(&self, heading: &str, score: Score, durationMs: u64, seed: u64) {
+      let text = strings::get();
+      self.flush();
+      dumb_line(heading);
+      dumb_line(format!("{} {}  {} {}  {} {}  {} {}  {} {}", text.score, strings::grouped(score.score), text.lines, score.lines,
+                        text.tetrises, score.tetrises, text.time, format_duration(durationMs), text.seed, seed).as_slice());
+      flush_output();
+    }
+    
+    fn print_pause_menu(&self, heading: &str, items: &[(&str, ~str)], selected: uint, help: &str) {
+      dumb_line(heading);
+      for (i, &(label, ref value)) in items.iter().enumerate() {
+        dumb_line(format!("{} {}: {}", if i == selected { ">" } else { " " }, label, *value).as_slice());
+      }
+      dumb_line(help);
+    }
+    
+    fn size(&self) -> (uint, uint) {
+      (24, 22)
+    }
+    
+    fn print_stats(&self, _rows: uint, _lines: &[~str]) {
+    }
+    
+    fn print_title(&self, _score: Score) {
+    }
+    
+    fn print_title_screen(&self) {
+      for line in strings::get().titleScreen.iter() {
+        dumb_line(*line);
+      }
+      flush_output();
+    }
+    
+    fn print_banner(&self, text: &str) {
+      dumb_line(text);
+    }
+    
+    fn mirror_cells(&self, cells: Sender<[u8, ..200]>) {
+      self.drawn.senders.borrow_mut().get().push(cells);
+    }
+    
+    fn flush(&self) {
+      self.drawn.send();
+      let drawn = self.drawn.cells.get();
+      if self.printed.get().map_or(false, |printed| printed.as_slice() == drawn.as_slice()) {
+        return flush_output();
+      }
+      self.printed.set(Some(drawn));
+      let text = strings::get();
+      let score = self.score.get();
+      let next = match self.next.get() {
+        Some(ty) => format!("  {} {}", text.next, saved_game::type_name(ty)),
+        None     => ~""
+      };
+      dumb_line(format!("{} {}  {} {}  {} {}{}", text.level, score.level, text.score, strings::grouped(score.score),
+                        text.lines, score.lines, next).as_slice());
+      for row in drawn.as_slice().chunks(board::width as uint) {
+        let mut line = ~"<!";
+        for &cell in row.iter() {
+          line.push_str(dumb_cell(cell));
+        }
+        line.push_str("!>");
+        dumb_line(line.as_slice());
+      }
+      dumb_line("<!====================!>");
+      flush_output();
+    }
+  }
+}
+
+mod ai {
+  use std::vec_ng::Vec;
+  use pieces;
+  use pieces::Piece;
+  use board::Board;
+  use input_reader::{Action, Rotate, Drop, MoveRight, MoveLeft};
+  use rand::Rng;
+  
+  // How much each feature of the board counts when rating a placement. The first four
+  // defaults are the weights found by Yiyuan Lee's genetic search for the same features,
+  // almostFull gives a little credit for rows that one more block would clear.
+  pub struct Weights {
+    height:     f64,  // total height of all the columns
+    lines:      f64,  // complete lines
+    holes:      f64,  // empty cells with a block somewhere above them
+    bumpiness:  f64,  // total difference in height between neighbouring columns
+    almostFull: f64   // rows missing a single block
+  }
+  
+  pub static defaultWeights: Weights = Weights{height:     -0.510066,
+                                               lines:      0.760666,
+                                               holes:      -0.35663,
+                                               bumpiness:  -0.184483,
+                                               almostFull: 0.1};
+  
+  // each weight moved by up to amount either way, for --ai-tune to try
+  pub fn vary<R: Rng>(weights: &Weights, amount: f64, rng: &mut R) -> Weights {
+    Weights{height:     weights.height + rng.gen_range(-amount, amount),
+            lines:      weights.lines + rng.gen_range(-amount, amount),
+            holes:      weights.holes + rng.gen_range(-amount, amount),
+            bumpiness:  weights.bumpiness + rng.gen_range(-amount, amount),
+            almostFull: weights.almostFull + rng.gen_range(-amount, amount)}
+  }
+  
+  // How well the computer plays as an opponent
+  pub struct Difficulty {
+    reactionMs:    u64,     // milliseconds between its key presses
+    misdropChance: f64,     // how often a piece ends up a column off from where it was meant to go
+    depth:         uint,    // 1 only looks at the falling piece, 2 also at the next one
+    weights:       Weights
+  }
+  
+  // Easy doesn't mind holes much and often drops pieces in the wrong place, hard and
+  // inhuman look a piece ahead and never miss, inhuman as fast as a key every 30ms
+  static difficultyNames: [&'static str, ..4] = ["easy", "medium", "hard", "inhuman"];
+  static difficulties: [Difficulty, ..4] = [
+    Difficulty{reactionMs: 500, misdropChance: 0.25, depth: 1,
+               weights: Weights{height: -0.3, lines: 0.5, holes: -0.1, bumpiness: -0.1, almostFull: 0.0}},
+    Difficulty{reactionMs: 250, misdropChance: 0.08, depth: 1, weights: defaultWeights},
+    Difficulty{reactionMs: 120, misdropChance: 0.0,  depth: 2, weights: defaultWeights},
+    Difficulty{reactionMs: 30,  misdropChance: 0.0,  depth: 2, weights: defaultWeights}
+  ];
+  
+  pub fn difficulty_from_name(name: &str) -> Option<Difficulty> {
+    difficultyNames.iter().position(|n| *n == name).map(|i| difficulties[i])
+  }
+  
+  // Where the AI would put a piece: rotate it clockwise some number of times, then move it
+  // some columns (negative is left), then drop it to end up as piece
+  pub struct Placement {
+    rotations: uint,
+    columns:   i8,
+    piece:     Piece,
+    rating:    f64
+  }
+  
+  // same rules as the game: a piece can rotate or move sideways anywhere it stays between
+  // the walls without overlapping a set block...
+  fn can_place(board: &Board, piece: &Piece) -> bool {
+    piece.blocks.iter().all(|block| block.column >= 1 && block.column <= 10 && !board.has_block(block.row, block.column))
+  }
+  
+  // ...and it falls until the next row down would go through the floor or a set block
+  fn drop_piece(board: &Board, piece: &Piece) -> Piece {
+    let mut dropped = *piece;
+    loop {
+      let next = pieces::translate(&dropped, 1, 0);
+      if next.blocks.iter().any(|block| block.row > 20 || board.has_block(block.row, block.column)) {
+        return dropped;
+      }
+      dropped = next;
+    }
+  }
+  
+  // the board after setting piece and taking out the rows it fills, and how many rows that was
+  fn set_piece(board: &Board, piece: &Piece) -> (Board, uint) {
+    let mut after = *board;
+    for block in piece.blocks.iter() {
+      after.set(*block);
+    }
+    let lines = after.clear_full_rows();
+    (after, lines as uint)
+  }
+  
+  // rates the board after setting piece, or None if the piece would stick out of the top
+  pub fn rate(board: &Board, piece: &Piece, weights: &Weights) -> Option<f64> {
+    if piece.blocks.iter().any(|block| block.row < 1) {
+      return None;
+    }
+    
+    let (after, lines) = set_piece(board, piece);
+    let almostFull = after.almost_full_rows(1).len();
+    
+    let mut heights = [0i8, ..10];
+    let mut holes = 0;
+    for col in range(1, 11i8) {
+      let mut row = 1i8;
+      while row <= 20 && !after.has_block(row, col) {
+        row += 1;
+      }
+      heights[(col - 1) as uint] = 21 - row;
+      while row <= 20 {
+        if !after.has_block(row, col) {
+          holes += 1;
+        }
+        row += 1;
+      }
+    }
+    
+    let height = heights.iter().fold(0, |sum, h| sum + *h as int);
+    let mut bumpiness = 0;
+    for col in range(0, 9u) {
+      bumpiness += (heights[col] as int - heights[col + 1] as int).abs();
+    }
+    
+    Some(weights.height     * height as f64 +
+         weights.lines      * lines as f64 +
+         weights.holes      * holes as f64 +
+         weights.bumpiness  * bumpiness as f64 +
+         weights.almostFull * almostFull as f64)
+  }
+  
+  // Tries every rotation and column the piece can reach from where it is, and picks the best
+  pub fn best_placement(board: &Board, piece: &Piece, weights: &Weights) -> Option<Placement> {
+    search(board, piece, |dropped| rate(board, dropped, weights))
+  }
+  
+  // Like best_placement, but each placement is rated by the best place for the next piece
+  // on the board it leaves, with the rows the first piece clears counted as well
+  pub fn best_placement_ahead(board: &Board, piece: &Piece, next: &Piece, weights: &Weights) -> Option<Placement> {
+    search(board, piece, |dropped| {
+      if dropped.blocks.iter().any(|block| block.row < 1) {
+        return None;
+      }
+      let (after, lines) = set_piece(board, dropped);
+      best_placement(&after, next, weights).map(|placement| placement.rating + weights.lines * lines as f64)
+    })
+  }
+  
+  // where the computer puts a piece when it's playing at the given difficulty (misdrops are
+  // left to whoever makes the moves)
+  pub fn placement_for(board: &Board, piece: &Piece, next: &Piece, difficulty: &Difficulty) -> Option<Placement> {
+    if difficulty.depth > 1 {
+      best_placement_ahead(board, piece, next, &difficulty.weights)
+    } else {
+      best_placement(board, piece, &difficulty.weights)
+    }
+  }
+  
+  // every rotation and column the piece can reach, dropped, rated by rating
+  fn search(board: &Board, piece: &Piece, rating: |&Piece| -> Option<f64>) -> Option<Placement> {
+    let mut best: Option<Placement> = None;
+    
+    let mut rotated = *piece;
+    for rotations in range(0u, 4) {
+      if rotations > 0 {
+        let next = pieces::rotate_clockwise(&rotated);
+        if !can_place(board, &next) {
+          break;
+        }
+        rotated = next;
+      }
+      
+      for &direction in [-1i8, 1].iter() {
+        let mut moved = rotated;
+        let mut columns = 0i8;
+        loop {
+          // the unmoved piece is rated on the way left
+          if columns != 0 || direction < 0 {
+            let dropped = drop_piece(board, &moved);
+            match rating(&dropped) {
+              Some(rating) if best.as_ref().map_or(true, |b| rating > b.rating) => {
+                best = Some(Placement{rotations: rotations, columns: columns, piece: dropped, rating: rating});
+              }
+              _ => ()
+            }
+          }
+          let next = pieces::translate(&moved, 0, direction);
+          if !can_place(board, &next) {
+            break;
+          }
+          moved = next;
+          columns += direction;
+        }
+      }
+    }
+    best
+  }
+  
+  // the key presses that get the piece to the placement
+  pub fn actions(placement: &Placement) -> Vec<Action> {
+    let mut actions = Vec::new();
+    for _ in range(0, placement.rotations) {
+      actions.push(Rotate);
+    }
+    for _ in range(0, placement.columns.abs()) {
+      actions.push(if placement.columns < 0 { MoveLeft } else { MoveRight });
+    }
+    actions.push(Drop);
+    actions
+  }
+}
+
+mod bot {
+  use std::cmp;
+  use std::io::{Process, BufferedReader, PipeStream};
+  use std::vec_ng::Vec;
+  use pieces;
+  use pieces::Piece;
+  use board::Board;
+  use scoring::Score;
+  use ai::Placement;
+  use saved_game;
+  use strings;
+  
+  // A bot is another program playing the game, a line of text at a time each way. The game
+  // runs the command with sh and writes to its standard input:
+  //   "tetris1 1" first, the protocol's name and version
+  //   "piece T next O board ROWS" for each new piece, ROWS being the 20 rows of the board from
+  //     the top, separated by "/", with 10 cells each written as in a saved game ("." is empty)
+  //   "gameover SCORE LINES" once the game is over
+  // For each piece the bot writes back "ROTATIONS COLUMN": how many times to turn the piece
+  // clockwise (0 to 3), then the column (1 to 10) for its leftmost block, and the piece is
+  // dropped there. The game waits for the answer. The bot's input is closed when the game
+  // ends, and the game waits for it to exit.
+  pub static protocolVersion: uint = 1;
+  
+  pub struct Bot {
+    output:  BufferedReader<PipeStream>,
+    process: Process
+  }
+  
+  // anything the bot writes to stderr is thrown away, unless the command sends it somewhere
+  pub fn start(command: &str) -> Result<Bot, ~str> {
+    let mut process = match Process::new("sh", [~"-c", format!("({}) 2>/dev/null", command)]) {
+      Ok(process) => process,
+      Err(e)      => return Err(strings::fill(strings::get().errCantRun, &[command, e.to_str().as_slice()]))
+    };
+    let output = BufferedReader::new(process.stdout.take_unwrap());
+    let mut bot = Bot{output: output, process: process};
+    try!(bot.send(format!("tetris1 {}", protocolVersion)));
+    Ok(bot)
+  }
+  
+  pub fn piece_line(board: &Board, piece: &Piece, next: &Piece) -> ~str {
+    format!("piece {} next {} board {}", saved_game::piece_name(piece), saved_game::piece_name(next),
+            saved_game::encode_board(board).as_slice().connect("/"))
+  }
+  
+  // reads "ROTATIONS COLUMN" as the moves that take the piece there from where it is now
+  pub fn parse_placement(line: &str, piece: &Piece) -> Result<Placement, ~str> {
+    let words: Vec<&str> = line.words().collect();
+    let (rotations, column) = if words.len() == 2 {
+      (from_str::<uint>(*words.get(0)), from_str::<i8>(*words.get(1)))
+    } else {
+      (None, None)
+    };
+    match (rotations, column) {
+      (Some(rotations), Some(column)) if rotations < 4 && column >= 1 && column <= 10 => {
+        let mut rotated = *piece;
+        for _ in range(0, rotations) {
+          rotated = pieces::rotate_clockwise(&rotated);
+        }
+        let left = rotated.blocks.iter().fold(rotated.blocks[0].column, |left, block| cmp::min(left, block.column));
+        let columns = column - left;
+        Ok(Placement{rotations: rotations,
+                     columns:   columns,
+                     piece:     pieces::translate(&rotated, 0, columns),
+                     rating:    0.0})
+      }
+      _ => Err(strings::fill(strings::get().errBotPlacement, &[line.trim()]))
+    }
+  }
+  
+  impl Bot {
+    fn send(&mut self, line: &str) -> Result<(), ~str> {
+      match self.process.stdin.get_mut_ref().write_line(line) {
+        Ok(())  => Ok(()),
+        Err(e)  => Err(strings::fill(strings::get().errBotWrite, &[e.to_str().as_slice()]))
+      }
+    }
+    
+    // asks the bot where the piece goes
+    pub fn place(&mut self, board: &Board, piece: &Piece, next: &Piece) -> Result<Placement, ~str> {
+      try!(self.send(piece_line(board, piece, next)));
+      match self.output.read_line() {
+        Ok(line) => parse_placement(line, piece),
+        Err(e)   => Err(strings::fill(strings::get().errBotSilent, &[e.to_str().as_slice()]))
+      }
+    }
+    
+    // a bot that's already gone doesn't need to hear about the end
+    pub fn finish(&mut self, score: &Score) {
+      let _ = self.send(format!("gameover {} {}", score.score, score.lines));
+    }
+  }
+}
+
+mod paths {
+  use std::io;
+  use std::io::IoResult;
+  use std::io::fs;
+  use std::os;
+  
+  static appDirName: &'static str = "tetris1";
+  
+  // Looks up an XDG base directory variable, falling back to a directory under the
+  // user's home directory (or the current directory if there's no home either)
+  fn base_dir(variable: &str, homeFallback: &str) -> Path {
+    match os::getenv(variable) {
+      Some(ref dir) if dir.len() > 0 => Path::new(dir.as_slice()),
+      _ => match os::homedir() {
+        Some(home) => home.join(homeFallback),
+        None       => Path::new(".")
+      }
+    }
+  }
+  
+  // $XDG_DATA_HOME/tetris1, or ~/.local/share/tetris1
+  pub fn data_dir() -> Path {
+    base_dir("XDG_DATA_HOME", ".local/share").join(appDirName)
+  }
+  
+  // $XDG_CONFIG_HOME/tetris1, or ~/.config/tetris1
+  pub fn config_dir() -> Path {
+    base_dir("XDG_CONFIG_HOME", ".config").join(appDirName)
+  }
+  
+  // A player profile keeps its own scores, saved game, last replay and settings in
+  // players/NAME under the data directory. --player sets this variable for the run, it can
+  // also be set in the shell to stay with one player.
+  static playerVariable: &'static str = "TETRIS1_PLAYER";
+  static maxPlayerName: uint = 32;
+  
+  pub fn set_player(name: &str) {
+    os::setenv(playerVariable, name);
+  }
+  
+  pub fn player() -> Option<~str> {
+    match os::getenv(playerVariable) {
+      Some(ref name) if valid_player_name(name.as_slice()) => Some(name.clone()),
+      _                                                    => None
+    }
+  }
+  
+  // the name becomes a directory, so only letters, digits, - and _
+  pub fn valid_player_name(name: &str) -> bool {
+    name.len() > 0 && name.len() <= maxPlayerName &&
+      name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+  }
+  
+  // the player's own directory, or the data directory when there's no profile
+  pub fn player_dir() -> Path {
+    match player() {
+      Some(name) => data_dir().join("players").join(name),
+      None       => data_dir()
+    }
+  }
+  
+  // the player's own settings if they have any, otherwise the shared ones
+  pub fn config_file() -> Path {
+    let own = own_config_file();
+    if own.exists() { own } else { config_dir().join("config.json") }
+  }
+  
+  // where settings are written, in the player's directory when there's a profile
+  pub fn own_config_file() -> Path {
+    match player() {
+      Some(_) => player_dir().join("config.json"),
+      None    => config_dir().join("config.json")
+    }
+  }
+  
+  // every game is recorded here unless a replay file is given
+  pub fn last_replay_file() -> Path {
+    player_dir().join("last.replay")
+  }
+  
+  // where a game cut short by a hangup is saved so it can be resumed
+  pub fn saved_game_file() -> Path {
+    player_dir().join("saved_game.json")
+  }
+  
+  pub fn scores_file() -> Path {
+    player_dir().join("scores.json")
+  }
+  
+  // Scores used to be written to scores.json in the current working directory. If there's
+  // no scores file in the data directory yet, an old scores.json is copied over to keep it
+  // (players with a profile start their own). main does this once at startup.
+  pub fn copy_legacy_scores() {
+    let path = scores_file();
+    let legacy = Path::new("scores.json");
+    if player().is_none() && !path.exists() && legacy.exists() {
+      let _ = create_parent_dir(&path).and_then(|()| fs::copy(&legacy, &path));
+    }
+  }
+  
+  pub fn create_parent_dir(path: &Path) -> IoResult<()> {
+    let dir = path.dir_path();
+    if dir.exists() {
+      Ok(())
+    } else {
+      fs::mkdir_recursive(&dir, io::UserRWX)
+    }
+  }
+}
+
+mod sound_hooks {
+  use std::io::Process;
+  
+  // Shell commands run when things happen in a game, usually to play a sound,
+  // for example "aplay ~/sounds/clear.wav"
+  #[deriving(Clone)]
+  pub struct SoundHooks {
+    lock:     Option<~str>,  // a piece was set
+    clear:    Option<~str>,  // rows were cleared
+    tetris:   Option<~str>,  // four rows were cleared at once, instead of clear
+    levelUp:  Option<~str>,
+    gameOver: Option<~str>
+  }
+  
+  pub fn none() -> SoundHooks {
+    SoundHooks{lock: None, clear: None, tetris: None, levelUp: None, gameOver: None}
+  }
+  
+  impl SoundHooks {
+    pub fn is_empty(&self) -> bool {
+      self.lock.is_none() && self.clear.is_none() && self.tetris.is_none() &&
+        self.levelUp.is_none() && self.gameOver.is_none()
+    }
+  }
+  
+  // Starts the command in the background, so the game doesn't wait for the sound to finish.
+  // Its output is thrown away so it can't mess up the screen.
+  pub fn play(command: &Option<~str>) {
+    match *command {
+      Some(ref command) => {
+        let _ = Process::new("sh", [~"-c", format!("({}) >/dev/null 2>&1 &", *command)]);
+      }
+      None => ()
+    }
+  }
+}
+
+mod announcer {
+  use std::io::{File, IoResult};
+  use pieces::Piece;
+  use engine::{GameEvent, PieceMoved, PieceLocked, NextPieceChanged, RowsCleared, LevelUp, ScoreChanged, GameOver};
+  use saved_game;
+  use strings;
+  
+  // Writes what happens in a game as short lines of text ("piece T at column 4", "lines
+  // cleared: 2, score 340"), for a screen reader or braille display following the file or
+  // pipe. Drop steps aren't mentioned, only what the player would want to hear about.
+  pub struct Announcer {
+    file:    File,
+    cleared: uint,  // rows cleared by the piece just set, announced along with the new score
+    score:   i64
+  }
+  
+  pub fn new(path: &Path) -> IoResult<Announcer> {
+    let file = try!(File::create(path));
+    Ok(Announcer{file: file, cleared: 0, score: 0})
+  }
+  
+  // the leftmost column the piece covers
+  fn column(piece: &Piece) -> i8 {
+    piece.blocks.iter().fold(piece.blocks[0].column, |left, block| if block.column < left { block.column } else { left })
+  }
+  
+  impl Announcer {
+    fn say(&mut self, text: &str, values: &[&str]) {
+      // a reader that's gone away shouldn't stop the game
+      let _ = self.file.write_line(strings::fill(text, values).as_slice());
+    }
+    
+    pub fn announce(&mut self, event: &GameEvent) {
+      let text = strings::get();
+      match *event {
+        NextPieceChanged(ref piece, ref next) => {
+          self.say(text.announceNew, &[saved_game::piece_name(piece).as_slice(), column(piece).to_str().as_slice(),
+                                       saved_game::piece_name(next).as_slice()]);
+        }
+        PieceMoved(ref from, ref to) if from.rotate != to.rotate => {
+          self.say(text.announceTurned, &[saved_game::piece_name(to).as_slice(), column(to).to_str().as_slice()]);
+        }
+        PieceMoved(ref from, ref to) if column(from) != column(to) => {
+          self.say(text.announceMoved, &[saved_game::piece_name(to).as_slice(), column(to).to_str().as_slice()]);
+        }
+        PieceLocked(ref piece) => {
+          self.say(text.announceSet, &[saved_game::piece_name(piece).as_slice(), column(piece).to_str().as_slice()]);
+        }
+        RowsCleared(ref rows) => self.cleared = rows.len(),
+        ScoreChanged(ref scoreEvent) => {
+          self.score = scoreEvent.score.score;
+          if self.cleared > 0 {
+            let cleared = self.cleared;
+            self.cleared = 0;
+            self.say(text.announceCleared, &[cleared.to_str().as_slice(), self.score.to_str().as_slice()]);
+          }
+        }
+        LevelUp(level) => self.say(text.announceLevel, &[level.to_str().as_slice()]),
+        GameOver => {
+          let score = self.score;
+          self.say(text.announceGameOver, &[score.to_str().as_slice()]);
+        }
+        _ => ()
+      }
+    }
+  }
+}
+
+mod config {
+  use std::ascii::StrAsciiExt;
+  use std::io::File;
+  use std::libc::c_int;
+  use std::vec_ng::Vec;
+  use serialize::json;
+  use scoring;
+  use scoring::Level;
+  use remote_scores;
+  use remote_scores::Url;
+  use sound_hooks;
+  use sound_hooks::SoundHooks;
+  use input_reader;
+  use input_reader::Keys;
+  use gravity;
+  use gravity::GravityKind;
+  use engine;
+  use scoring::ScoringKind;
+  use modes;
+  use modes::{Mode, GarbageRules};
+  use ai;
+  use ai::Weights;
+  use paths;
+  use strings;
+  
+  // Settings read from the config file, anything not in the file keeps its default.
+  // The options given on the command line win over the ones in the file.
+  pub struct Config {
+    levels:       Vec<Level>,
+    bonusDecay:   Option<int>,  // pieces set without clearing before the bonus goes down, None for never
+    leaderboard:  Option<Url>,  // shared leaderboard that finished games are submitted to
+    sounds:       SoundHooks,   // commands run when things happen in the game
+    double:       bool,         // "display": "double" or "standard"
+    curses:       bool,         // "backend": "curses" or "escapes"
+    mode:         Option<Mode>,
+    scoring:      Option<ScoringKind>,
+    gravity:      Option<GravityKind>,
+    debugOverlay: bool,
+    preview:      bool,         // show the next piece
+    spawnDelay:   c_int,        // milliseconds before the next piece comes in after one is set
+    idlePause:    uint,         // minutes without a key pressed before the game pauses itself, 0 for never
+    marathonLevel: u16,         // the level that wins a marathon game
+    garbage:      GarbageRules, // garbage rows sent between two players
+    aiWeights:    Weights,      // how the computer player rates where to put pieces
+    keys:         Keys,         // keys to use as well as the arrow keys
+    theme:        [u8, ..8],    // the terminal color each block color is drawn with
+    colorOverrides: [u8, ..16]  // the terminal color each one looks like, for terminals that show some alike
+  }
+  
+  // the 8 normal terminal colors, which are also the block colors, then the 8 bright ones
+  static colorNames: [&'static str, ..16] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+                                             "gray", "brightRed", "brightGreen", "brightYellow",
+                                             "brightBlue", "brightMagenta", "brightCyan", "brightWhite"];
+  
+  // minutes a game is left alone before it's paused
+  static defaultIdlePause: uint = 5;
+  
+  pub fn default() -> Config {
+    Config {
+      levels:       scoring::default_levels(),
+      bonusDecay:   scoring::defaultBonusDecay,
+      leaderboard:  None,
+      sounds:       sound_hooks::none(),
+      double:       false,
+      curses:       false,
+      mode:         None,
+      scoring:      None,
+      gravity:      None,
+      debugOverlay: false,
+      preview:      true,
+      spawnDelay:   0,
+      idlePause:    defaultIdlePause,
+      marathonLevel: modes::defaultMarathonLevel,
+      garbage:      modes::defaultGarbageRules,
+      aiWeights:    ai::defaultWeights,
+      keys:         input_reader::defaultKeys,
+      theme:        [0, 1, 2, 3, 4, 5, 6, 7],
+      colorOverrides: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+    }
+  }
+  
+  // The config file written by --write-default-config, with every setting at its default
+  pub fn default_text() -> ~str {
+    let theme: Vec<~str> = colorNames.slice(1, 8).iter().map(|name| {
+      format!("    \"{}\": \"{}\"", *name, *name)
+    }).collect();
+    let levels: Vec<~str> = scoring::default_levels().iter().map(|level| {
+      format!("    \\{\"time\": {}, \"score\": {}, \"count\": {}, \"bonusInc\": {}\\}",
+              level.time, level.score, level.count, level.bonusInc)
+    }).collect();
+    
+    let mut text = ~"{\n";
+    text.push_str("  \"display\": \"standard\",\n");
+    text.push_str("  \"backend\": \"escapes\",\n");
+    text.push_str("  \"mode\": \"endless\",\n");
+    text.push_str("  \"scoring\": \"standard\",\n");
+    text.push_str("  \"gravity\": \"levels\",\n");
+    text.push_str("  \"debugOverlay\": false,\n");
+    text.push_str("  \"preview\": true,\n");
+    text.push_str("  \"spawnDelay\": 0,\n");
+    text.push_str(format!("  \"idlePause\": {},\n", defaultIdlePause).as_slice());
+    text.push_str(format!("  \"marathonLevel\": {},\n", modes::defaultMarathonLevel).as_slice());
+    text.push_str(format!("  \"bonusDecay\": {},\n", scoring::defaultBonusDecay.unwrap_or(0)).as_slice());
+    let garbage = modes::defaultGarbageRules;
+    text.push_str(format!("  \"garbage\": \{\"single\": {}, \"double\": {}, \"triple\": {}, \"tetris\": {}, \"cancel\": {}\},\n",
+                          garbage.sent[0], garbage.sent[1], garbage.sent[2], garbage.sent[3], garbage.cancel).as_slice());
+    text.push_str(format!("  \"aiWeights\": {},\n", weights_text(&ai::defaultWeights)).as_slice());
+    text.push_str("  \"keys\": {\"rotate\": \"\", \"drop\": \"\", \"left\": \"\", \"right\": \"\", \"hint\": \"h\", \"stats\": \"i\", \"pause\": \"p\"},\n");
+    text.push_str("  \"theme\": {\n" + theme.connect(",\n") + "\n  },\n");
+    text.push_str("  \"colorOverrides\": {},\n");
+    text.push_str("  \"levels\": [\n" + levels.connect(",\n") + "\n  ]\n");
+    text.push_str("}\n");
+    text
+  }
+  
+  // Reads the config file. It's fine for the file not to exist, but if it does exist it must be valid.
+  //
+  // The level table can be replaced with a "levels" list, for example
+  //   { "levels": [ {"time": 1000, "score": 0, "count": 3, "bonusInc": 1},
+  //                 {"time": 500,  "score": 5, "count": 5, "bonusInc": 2} ] }
+  //
+  // The bonus goes down after "bonusDecay" pieces are set without clearing any rows
+  // (0 keeps the bonus until a hint is used), for example
+  //   { "bonusDecay": 3 }
+  //
+  // A shared leaderboard is set with a "leaderboard" address, for example
+  //   { "leaderboard": "http://example.com:8080/tetris1/scores" }
+  //
+  // The garbage rows a two player game sends for each clear can be changed, and made to
+  // cancel out garbage waiting to come in, with a "garbage" object, for example
+  //   { "garbage": {"single": 0, "double": 1, "triple": 2, "tetris": 4, "cancel": true} }
+  //
+  // The computer player's weights (usually written by --ai-tune) are in "aiWeights", for example
+  //   { "aiWeights": {"height": -0.51, "lines": 0.76, "holes": -0.36, "bumpiness": -0.18, "almostFull": 0.1} }
+  //
+  // Sounds are commands in a "sounds" object, for example
+  //   { "sounds": {"clear": "aplay clear.wav", "gameOver": "aplay over.wav"} }
+  //
+  // The command line options can be given defaults, for example
+  //   { "display": "double", "backend": "curses", "mode": "sprint", "scoring": "guideline",
+  //     "gravity": "nes", "debugOverlay": true, "preview": false, "spawnDelay": 200 }
+  //
+  // A game pauses itself after "idlePause" minutes without a key pressed (0 never does), for example
+  //   { "idlePause": 2 }
+  //
+  // Letter keys can be used as well as the arrow keys (an empty string is no key), and
+  // block colors can be drawn as other terminal colors, for example
+  //   { "keys": {"rotate": "w", "left": "a", "drop": "s", "right": "d", "hint": "h"},
+  //     "theme": {"white": "yellow", "yellow": "white"} }
+  //
+  // A terminal that shows some colors alike can say so, so that blocks of different colors
+  // aren't drawn alike on it (see graphics::colors), for example
+  //   { "colorOverrides": {"magenta": "blue", "brightWhite": "white"} }
+  pub fn load(path: &Path) -> Result<Config, ~str> {
+    let mut config = default();
+    
+    if !path.exists() {
+      return Ok(config);
+    }
+    
+    let root = try!(read_root(path));
+    
+    match root.find(&~"levels") {
+      Some(levels) => config.levels = try!(read_levels(levels)),
+      None         => ()
+    }
+    
+    // 0 turns the bonus decay off
+    match root.find(&~"bonusDecay") {
+      Some(&json::Number(n)) if n == n.floor() && n >= 0.0 && n <= 1000.0 => {
+        config.bonusDecay = if n == 0.0 { None } else { Some(n as int) };
+      }
+      Some(_) => return Err(strings::fill(strings::get().errWholeNumber, &["bonusDecay", "0", "1000"])),
+      None    => ()
+    }
+    
+    match root.find(&~"leaderboard") {
+      Some(&json::String(ref url)) => config.leaderboard = Some(try!(remote_scores::parse_url(*url))),
+      Some(_)                      => return Err(strings::fill(strings::get().errString, &["leaderboard"])),
+      None                         => ()
+    }
+    
+    match root.find(&~"sounds") {
+      Some(&json::Object(ref sounds)) => config.sounds = try!(read_sounds(&**sounds)),
+      Some(_)                         => return Err(strings::fill(strings::get().errObject, &["sounds"])),
+      None                            => ()
+    }
+    
+    match try!(read_name(&*root, "display")) {
+      Some(name) => config.double = match name.as_slice() {
+        "double"   => true,
+        "standard" => false,
+        _          => return Err(strings::fill(strings::get().errOneOf,
+                                               &["display", strings::one_of(["\"standard\"", "\"double\""]).as_slice()]))
+      },
+      None => ()
+    }
+    
+    match try!(read_name(&*root, "backend")) {
+      Some(name) => config.curses = match name.as_slice() {
+        "curses"  => true,
+        "escapes" => false,
+        _         => return Err(strings::fill(strings::get().errOneOf,
+                                              &["backend", strings::one_of(["\"escapes\"", "\"curses\""]).as_slice()]))
+      },
+      None => ()
+    }
+    
+    match try!(read_name(&*root, "mode")) {
+      Some(name) => match modes::from_name(name) {
+        Some(mode) => config.mode = Some(mode),
+        None       => return Err(strings::fill(strings::get().errUnknownValue, &["mode", name]))
+      },
+      None => ()
+    }
+    
+    match try!(read_name(&*root, "scoring")) {
+      Some(name) => match scoring::kind_from_name(name) {
+        Some(kind) => config.scoring = Some(kind),
+        None       => return Err(strings::fill(strings::get().errUnknownValue, &["scoring", name]))
+      },
+      None => ()
+    }
+    
+    match try!(read_name(&*root, "gravity")) {
+      Some(name) => match gravity::kind_from_name(name) {
+        Some(kind) => config.gravity = Some(kind),
+        None       => return Err(strings::fill(strings::get().errUnknownValue, &["gravity", name]))
+      },
+      None => ()
+    }
+    
+    match root.find(&~"debugOverlay") {
+      Some(&json::Boolean(on)) => config.debugOverlay = on,
+      Some(_)                  => return Err(strings::fill(strings::get().errBool, &["debugOverlay"])),
+      None                     => ()
+    }
+    
+    match root.find(&~"preview") {
+      Some(&json::Boolean(on)) => config.preview = on,
+      Some(_)                  => return Err(strings::fill(strings::get().errBool, &["preview"])),
+      None                     => ()
+    }
+    
+    match root.find(&~"spawnDelay") {
+      Some(&json::Number(n)) if n == n.floor() && n >= 0.0 && n <= engine::maxSpawnDelay as f64 => {
+        config.spawnDelay = n as c_int;
+      }
+      Some(_) => return Err(strings::fill(strings::get().errWholeNumber,
+                                          &["spawnDelay", "0", engine::maxSpawnDelay.to_str().as_slice()])),
+      None    => ()
+    }
+    
+    match root.find(&~"idlePause") {
+      Some(&json::Number(n)) if n == n.floor() && n >= 0.0 && n <= 1000.0 => config.idlePause = n as uint,
+      Some(_) => return Err(strings::fill(strings::get().errWholeNumber, &["idlePause", "0", "1000"])),
+      None    => ()
+    }
+    
+    match root.find(&~"marathonLevel") {
+      Some(&json::Number(n)) if n == n.floor() && n >= 1.0 && n <= 1000.0 => config.marathonLevel = n as u16,
+      Some(_) => return Err(strings::fill(strings::get().errWholeNumber, &["marathonLevel", "1", "1000"])),
+      None    => ()
+    }
+    
+    match root.find(&~"garbage") {
+      Some(&json::Object(ref garbage)) => config.garbage = try!(read_garbage(&**garbage)),
+      Some(_)                          => return Err(strings::fill(strings::get().errObject, &["garbage"])),
+      None                             => ()
+    }
+    
+    match root.find(&~"aiWeights") {
+      Some(&json::Object(ref weights)) => config.aiWeights = try!(read_weights(&**weights)),
+      Some(_)                          => return Err(strings::fill(strings::get().errObject, &["aiWeights"])),
+      None                             => ()
+    }
+    
+    match root.find(&~"keys") {
+      Some(&json::Object(ref keys)) => config.keys = try!(read_keys(&**keys)),
+      Some(_)                       => return Err(strings::fill(strings::get().errObject, &["keys"])),
+      None                          => ()
+    }
+    
+    match root.find(&~"theme") {
+      Some(&json::Object(ref theme)) => config.theme = try!(read_theme(&**theme)),
+      Some(_)                        => return Err(strings::fill(strings::get().errObject, &["theme"])),
+      None                           => ()
+    }
+    
+    match root.find(&~"colorOverrides") {
+      Some(&json::Object(ref overrides)) => config.colorOverrides = try!(read_color_overrides(&**overrides)),
+      Some(_)                            => return Err(strings::fill(strings::get().errObject, &["colorOverrides"])),
+      None                           => ()
+    }
+    
+    Ok(config)
+  }
+  
+  fn read_root(path: &Path) -> Result<~json::Object, ~str> {
+    let root = match File::open(path) {
+      Ok(mut file) => match json::from_reader(&mut file) {
+        Ok(root) => root,
+        Err(e)   => return Err(strings::fill(strings::get().errConfigJson, &[e.to_str().as_slice()]))
+      },
+      Err(e) => return Err(strings::fill(strings::get().errConfigRead, &[e.to_str().as_slice()]))
+    };
+    
+    match root {
+      json::Object(root) => Ok(root),
+      _                  => Err(strings::get().errTopLevel.to_owned())
+    }
+  }
+  
+  // Puts one setting in the config file, keeping the others. The file is written again
+  // from what was read, so its settings end up in alphabetical order.
+  pub fn save_setting(path: &Path, name: &str, value: json::Json) -> Result<(), ~str> {
+    let text = if path.exists() {
+      let mut root = try!(read_root(path));
+      root.insert(name.to_owned(), value);
+      json::Object(root).to_pretty_str() + "\n"
+    } else {
+      format!("\\{\n  \"{}\": {}\n\\}\n", name, value.to_str())
+    };
+    
+    let written = paths::create_parent_dir(path).and_then(|()| {
+      File::create(path).and_then(|mut file| file.write_str(text))
+    });
+    written.map_err(|e| e.to_str())
+  }
+  
+  pub fn save_ai_weights(path: &Path, weights: &Weights) -> Result<(), ~str> {
+    save_setting(path, "aiWeights", json::from_str(weights_text(weights).as_slice()).unwrap())
+  }
+  
+  pub fn weights_text(weights: &Weights) -> ~str {
+    format!("\\{\"height\": {:.4f}, \"lines\": {:.4f}, \"holes\": {:.4f}, \"bumpiness\": {:.4f}, \"almostFull\": {:.4f}\\}",
+            weights.height, weights.lines, weights.holes, weights.bumpiness, weights.almostFull)
+  }
+  
+  fn read_name<'a>(object: &'a json::Object, name: &str) -> Result<Option<&'a str>, ~str> {
+    match object.find(&name.to_owned()) {
+      Some(&json::String(ref value)) => Ok(Some(value.as_slice())),
+      Some(_)                        => Err(strings::fill(strings::get().errString, &[name])),
+      None                           => Ok(None)
+    }
+  }
+  
+  fn read_keys(object: &json::Object) -> Result<Keys, ~str> {
+    let mut keys = input_reader::defaultKeys;
+    for (name, key) in object.iter() {
+      let key = match *key {
+        json::String(ref key) if key.len() == 0 => None,
+        json::String(ref key) if key.len() == 1 && key[0] > 0x20 && key[0] < 0x7F => {
+          Some(key.to_ascii_lower()[0])
+        }
+        _ => return Err(strings::fill(strings::get().errKeyIn, &[name.as_slice(), "keys"]))
+      };
+      match name.as_slice() {
+        "rotate" => keys.rotate = key,
+        "drop"   => keys.drop = key,
+        "left"   => keys.moveLeft = key,
+        "right"  => keys.moveRight = key,
+        "hint"   => keys.hint = key,
+        "stats"  => keys.stats = key,
+        "pause"  => keys.pause = key,
+        _        => return Err(unknown("keys", name.as_slice(), ["rotate", "drop", "left", "right", "hint", "stats", "pause"]))
+      }
+    }
+    Ok(keys)
+  }
+  
+  fn color_index(name: &str) -> Option<uint> {
+    colorNames.iter().position(|color| *color == name)
+  }
+  
+  // black isn't in the theme, it's what the board is erased with
+  fn read_theme(object: &json::Object) -> Result<[u8, ..8], ~str> {
+    let mut theme = default().theme;
+    for (name, color) in object.iter() {
+      let from = match color_index(name.as_slice()) {
+        Some(index) if index > 0 && index < 8 => index,
+        _ => return Err(unknown("theme", name.as_slice(), colorNames.slice(1, 8)))
+      };
+      let to = match *color {
+        json::String(ref color) => color_index(color.as_slice()),
+        _                       => None
+      };
+      match to {
+        Some(to) => theme[from] = to as u8,
+        None     => return Err(strings::fill(strings::get().errColorIn, &[name.as_slice(), "theme", color_list().as_slice()]))
+      }
+    }
+    Ok(theme)
+  }
+  
+  // each terminal color named is shown like the one it's given
+  fn read_color_overrides(object: &json::Object) -> Result<[u8, ..16], ~str> {
+    let mut overrides = default().colorOverrides;
+    for (name, color) in object.iter() {
+      let from = match color_index(name.as_slice()) {
+        Some(index) => index,
+        None        => return Err(unknown("colorOverrides", name.as_slice(), colorNames.as_slice()))
+      };
+      let to = match *color {
+        json::String(ref color) => color_index(color.as_slice()),
+        _                       => None
+      };
+      match to {
+        Some(to) => overrides[from] = to as u8,
+        None     => return Err(strings::fill(strings::get().errColorIn,
+                                             &[name.as_slice(), "colorOverrides", color_list().as_slice()]))
+      }
+    }
+    Ok(overrides)
+  }
+  
+  // a name in one of the config's objects that isn't one of the expected names
+  fn unknown(object: &str, name: &str, expected: &[&str]) -> ~str {
+    strings::fill(strings::get().errUnknownIn, &[name, object, strings::one_of(expected).as_slice()])
+  }
+  
+  fn color_list() -> ~str {
+    strings::one_of(colorNames.as_slice())
+  }
+  
+  fn read_sounds(sounds: &json::Object) -> Result<SoundHooks, ~str> {
+    let mut hooks = sound_hooks::none();
+    for (name, command) in sounds.iter() {
+      let command = match *command {
+        json::String(ref command) => Some(command.clone()),
+        _                         => return Err(strings::fill(strings::get().errStringIn, &[name.as_slice(), "sounds"]))
+      };
+      match name.as_slice() {
+        "lock"     => hooks.lock = command,
+        "clear"    => hooks.clear = command,
+        "tetris"   => hooks.tetris = command,
+        "levelUp"  => hooks.levelUp = command,
+        "gameOver" => hooks.gameOver = command,
+        _          => return Err(unknown("sounds", name.as_slice(), ["lock", "clear", "tetris", "levelUp", "gameOver"]))
+      }
+    }
+    Ok(hooks)
+  }
+  
+  fn read_garbage(object: &json::Object) -> Result<GarbageRules, ~str> {
+    let mut rules = modes::defaultGarbageRules;
+    for (name, value) in object.iter() {
+      let clear = match name.as_slice() {
+        "single" => 0,
+        "double" => 1,
+        "triple" => 2,
+        "tetris" => 3,
+        "cancel" => match *value {
+          json::Boolean(on) => {
+            rules.cancel = on;
+            continue;
+          }
+          _ => return Err(strings::fill(strings::get().errBoolIn, &["cancel", "garbage"]))
+        },
+        _        => return Err(unknown("garbage", name.as_slice(), ["single", "double", "triple", "tetris", "cancel"]))
+      };
+      rules.sent[clear] = match *value {
+        json::Number(n) if n == n.floor() && n >= 0.0 && n <= 20.0 => n as int,
+        _ => return Err(strings::fill(strings::get().errWholeNumberIn, &[name.as_slice(), "garbage", "0", "20"]))
+      };
+    }
+    Ok(rules)
+  }
+  
+  fn read_weights(object: &json::Object) -> Result<Weights, ~str> {
+    let mut weights = ai::defaultWeights;
+    for (name, value) in object.iter() {
+      let weight = match *value {
+        json::Number(n) if n >= -100.0 && n <= 100.0 => n,
+        _ => return Err(strings::fill(strings::get().errNumberIn, &[name.as_slice(), "aiWeights", "-100", "100"]))
+      };
+      match name.as_slice() {
+        "height"     => weights.height = weight,
+        "lines"      => weights.lines = weight,
+        "holes"      => weights.holes = weight,
+        "bumpiness"  => weights.bumpiness = weight,
+        "almostFull" => weights.almostFull = weight,
+        _            => return Err(unknown("aiWeights", name.as_slice(), ["height", "lines", "holes", "bumpiness", "almostFull"]))
+      }
+    }
+    Ok(weights)
+  }
+  
+  fn read_levels(levels: &json::Json) -> Result<Vec<Level>, ~str> {
+    let levels = match *levels {
+      json::List(ref levels) => levels,
+      _                      => return Err(strings::fill(strings::get().errList, &["levels"]))
+    };
+    if levels.len() == 0 {
+      return Err(strings::get().errNoLevels.to_owned());
+    }
+    if levels.len() > 1000 {
+      return Err(strings::fill(strings::get().errTooManyLevels, &["1000"]));
+    }
+    
+    let mut result = Vec::new();
+    for (i, level) in levels.iter().enumerate() {
+      let level = match *level {
+        json::Object(ref level) => level,
+        _                       => return Err(strings::fill(strings::get().errLevelObject, &[(i + 1).to_str().as_slice()]))
+      };
+      let time     = try!(read_int(&**level, i, "time",     1, 60000));
+      let score    = try!(read_int(&**level, i, "score",    0, 1000000));
+      let count    = try!(read_int(&**level, i, "count",    0, 1000));
+      let bonusInc = try!(read_int(&**level, i, "bonusInc", 0, 1000));
+      result.push(Level{time: time as c_int, score: score, count: count, bonusInc: bonusInc});
+    }
+    Ok(result)
+  }
+  
+  fn read_int(object: &json::Object, index: uint, name: &str, min: int, max: int) -> Result<int, ~str> {
+    match object.find(&name.to_owned()) {
+      Some(&json::Number(n)) if n == n.floor() && n >= min as f64 && n <= max as f64 => Ok(n as int),
+      Some(_) => Err(strings::fill(strings::get().errLevelNumber,
+                                   &[(index + 1).to_str().as_slice(), name, min.to_str().as_slice(), max.to_str().as_slice()])),
+      None    => Err(strings::fill(strings::get().errLevelMissing, &[(index + 1).to_str().as_slice(), name]))
+    }
+  }
+}
+
+mod cli {
+  use std::from_str::FromStr;
+  use std::vec_ng::Vec;
+  use strings;
+  
+  // Reads the command line one argument at a time. Options take their value from the next
+  // argument ("--seed 42") or after an equals sign ("--seed=42").
+  pub struct Args {
+    args:   Vec<~str>,
+    next:   uint,         // index of the next argument to read
+    option: ~str,         // the option just read, for error messages
+    value:  Option<~str>  // the value given after its equals sign
+  }
+  
+  pub fn new(args: &[~str]) -> Args {
+    Args{args: Vec::from_slice(args), next: 0, option: ~"", value: None}
+  }
+  
+  impl Args {
+    // The next argument, without any "=value" part. An option given a value it didn't use is an error.
+    pub fn next(&mut self) -> Result<Option<~str>, ~str> {
+      match self.value.take() {
+        Some(value) => return Err(strings::fill(strings::get().errNoValue, &[self.option.as_slice(), value.as_slice()])),
+        None        => ()
+      }
+      if self.next >= self.args.len() {
+        return Ok(None);
+      }
+      let arg = self.args.get(self.next).clone();
+      self.next += 1;
+      self.option = match arg.find('=') {
+        Some(i) if arg.starts_with("--") => {
+          self.value = Some(arg.slice_from(i + 1).to_owned());
+          arg.slice_to(i).to_owned()
+        }
+        _ => arg
+      };
+      Ok(Some(self.option.clone()))
+    }
+    
+    // the value of the option just read
+    pub fn value(&mut self) -> Result<~str, ~str> {
+      match self.value.take() {
+        Some(value) => return Ok(value),
+        None        => ()
+      }
+      if self.next >= self.args.len() || self.args.get(self.next).starts_with("--") {
+        return Err(strings::fill(strings::get().errNeedsValue, &[self.option.as_slice()]));
+      }
+      self.next += 1;
+      Ok(self.args.get(self.next - 1).clone())
+    }
+    
+    pub fn number<T: FromStr>(&mut self) -> Result<T, ~str> {
+      let value = try!(self.value());
+      match from_str::<T>(value.as_slice()) {
+        Some(number) => Ok(number),
+        None         => Err(strings::fill(strings::get().errNeedsNumber, &[self.option.as_slice(), value.as_slice()]))
+      }
+    }
+    
+    // the value of the option, which has to be one of the names parse knows, given in expected
+    pub fn choice<T>(&mut self, expected: &[&str], parse: |&str| -> Option<T>) -> Result<T, ~str> {
+      let value = try!(self.value());
+      match parse(value.as_slice()) {
+        Some(choice) => Ok(choice),
+        None         => Err(strings::fill(strings::get().errChoice,
+                                          &[self.option.as_slice(), strings::one_of(expected).as_slice(), value.as_slice()]))
+      }
+    }
+  }
+}
+
+mod modes {
+  // The kinds of game that can be played. Scores are kept separately for each mode.
+  #[deriving(Eq)]
+  pub enum Mode {
+    Endless, NesClassic, Sprint, Ultra, Pressure, Marathon
+  }
+  
+  // sprint: clear this many lines as quickly as possible
+  pub static sprintLines: int = 40;
+  
+  // ultra: score as much as possible in this many milliseconds
+  pub static ultraTimeMs: u64 = 120000;
+  
+  // pressure: a garbage row is pushed up from the bottom this often
+  pub static pressureIntervalMs: u64 = 10000;
+  
+  // marathon: the game is won on reaching this level, unless the config file gives another
+  pub static defaultMarathonLevel: u16 = 15;
+  
+  // two players: the garbage rows sent for clearing 1, 2, 3 or 4 rows at once, and whether
+  // the rows sent first cancel out garbage that's still waiting to come in
+  pub struct GarbageRules {
+    sent:   [int, ..4],
+    cancel: bool
+  }
+  
+  pub static defaultGarbageRules: GarbageRules = GarbageRules{sent: [0, 1, 2, 4], cancel: false};
+  
+  static allModes: [Mode, ..6] = [Endless, NesClassic, Sprint, Ultra, Pressure, Marathon];
+  
+  pub fn name(mode: Mode) -> &'static str {
+    match mode {
+      Endless    => "endless",
+      NesClassic => "nes",
+      Sprint     => "sprint",
+      Ultra      => "ultra",
+      Pressure   => "pressure",
+      Marathon   => "marathon"
+    }
+  }
+  
+  pub fn from_name(name: &str) -> Option<Mode> {
+    allModes.iter().find(|mode| self::name(**mode) == name).map(|mode| *mode)
+  }
+  
+  // sprint games are ranked by how fast the lines were cleared, everything else by score
+  pub fn ranked_by_time(mode: Mode) -> bool {
+    mode == Sprint
+  }
+}
+
+mod score_keeper {
+  use serialize::json;
+  use serialize::{Encodable, Decodable};
+  use scoring::Score;
+  use modes;
+  use modes::Mode;
+  use paths;
+  use std::io::File;
+  use std::vec_ng::Vec;
+  use time;
+  
+  pub trait ScoreKeeper {
+    fn store_score(&self, mode: Mode, entry: ScoreEntry);
+    fn get_scores(&self) -> ScoreStorage;
+    fn save_scores(&self, storage: &ScoreStorage);
+    
+    // the result of a two player series, kept apart from the scores of single games
+    fn store_versus(&self, _result: VersusResult) {
+    }
+    
+    // the standings of a hot seat tournament, also kept apart
+    fn store_tournament(&self, _result: TournamentResult) {
+    }
+  }
+  
+  #[deriving(Encodable, Decodable, Clone)]
+  pub struct ScoreEntry {
+    time:     time::Tm,    // when the game ended
+    score:    Score,
+    duration: u64,         // how long the game lasted, in milliseconds
+    mode:     ~str,        // name of the game mode
+    seed:     Option<u64>, // seed of the piece sequence, if it was seeded
+    density:  f64          // fraction of the board filled with set blocks at the end of the game
+  }
+  
+  // how a two player series ended, a single game is a series of 1
+  #[deriving(Encodable, Decodable, Clone)]
+  pub struct VersusResult {
+    time:       time::Tm,  // when the last game of the series ended
+    mode:       ~str,
+    bestOf:     uint,
+    firstWins:  uint,
+    secondWins: uint
+  }
+  
+  // one player's game in a hot seat tournament
+  #[deriving(Encodable, Decodable, Clone)]
+  pub struct TournamentPlayer {
+    name:  ~str,
+    entry: ScoreEntry
+  }
+  
+  // a hot seat tournament, everyone played the same seed, the standings are first place first
+  #[deriving(Encodable, Decodable, Clone)]
+  pub struct TournamentResult {
+    time:      time::Tm,  // when the last game ended
+    mode:      ~str,
+    seed:      u64,
+    standings: Vec<TournamentPlayer>
+  }
+  
+  // high and recent scores for one game mode
+  #[deriving(Encodable, Decodable)]
+  pub struct ModeScores {
+    mode:         ~str,
+    highScores:   Vec<ScoreEntry>,
+    recentScores: Vec<ScoreEntry>
+  }
+  
+  // the best scores of games played with one seed, so players can race each other on the
+  // same pieces
+  #[deriving(Encodable, Decodable)]
+  pub struct SeedScores {
+    mode:       ~str,
+    seed:       u64,
+    highScores: Vec<ScoreEntry>
+  }
+  
+  // Layout of the scores file, version is bumped whenever the layout changes:
+  //   0: a single list of high and recent (time, score) pairs, all from endless games
+  //      (this version has no version field)
+  //   1: high and recent lists for each mode, entries have time, score and duration
+  //      (this version has no version field either)
+  //   2: entries also have the mode, seed and final board density
+  //   3: a history of every game played, not just the high and recent scores
+  //   4: the results of two player series
+  //   5: the standings of hot seat tournaments
+  //   6: high scores for each seed games were played with
+  #[deriving(Encodable, Decodable)]
+  pub struct ScoreStorage {
+    version:     uint,
+    modes:       Vec<ModeScores>,
+    history:     Vec<ScoreEntry>,
+    versus:      Vec<VersusResult>,
+    tournaments: Vec<TournamentResult>,
+    seeds:       Vec<SeedScores>
+  }
+  
+  static storageVersion: uint = 6;
+  
+  // how many scores are kept for each seed, and for how many seeds (dropping the seed that
+  // was first played longest ago)
+  static maxSeedScores: uint = 10;
+  static maxSeeds: uint = 1000;
+  
+  // the history is trimmed to this many games, dropping the oldest
+  static maxHistory: uint = 10000;
+  
+  impl ScoreStorage {
+    pub fn find_mode<'a>(&'a self, mode: Mode) -> Option<&'a ModeScores> {
+      self.modes.iter().find(|scores| scores.mode.as_slice() == modes::name(mode))
+    }
+    
+    fn mode_scores<'a>(&'a mut self, mode: Mode) -> &'a mut ModeScores {
+      self.named_mode_scores(modes::name(mode))
+    }
+    
+    pub fn named_mode_scores<'a>(&'a mut self, mode: &str) -> &'a mut ModeScores {
+      let index = match self.modes.iter().position(|scores| scores.mode.as_slice() == mode) {
+        Some(index) => index,
+        None        => {
+          self.modes.push(ModeScores{mode:         mode.to_owned(),
+                                     highScores:   Vec::new(),
+                                     recentScores: Vec::new()});
+          self.modes.len() - 1
+        }
+      };
+      self.modes.get_mut(index)
+    }
+    
+    pub fn find_seed<'a>(&'a self, mode: &str, seed: u64) -> Option<&'a SeedScores> {
+      self.seeds.iter().find(|scores| scores.mode.as_slice() == mode && scores.seed == seed)
+    }
+    
+    // puts a game played with a seed in the seed's high scores, if it's good enough
+    fn add_seed_score(&mut self, entry: &ScoreEntry) {
+      let seed = match entry.seed {
+        Some(seed) => seed,
+        None       => return
+      };
+      let index = match self.seeds.iter().position(|scores| scores.mode == entry.mode && scores.seed == seed) {
+        Some(index) => index,
+        None        => {
+          self.seeds.push(SeedScores{mode: entry.mode.clone(), seed: seed, highScores: Vec::new()});
+          if self.seeds.len() > maxSeeds {
+            self.seeds.remove(0);
+          }
+          self.seeds.len() - 1
+        }
+      };
+      let scores = &mut self.seeds.get_mut(index).highScores;
+      scores.push(entry.clone());
+      match modes::from_name(entry.mode.as_slice()) {
+        Some(mode) => scores.sort_by(|e1, e2| better(mode, e1, e2)),
+        None       => scores.sort_by(|e1, e2| e2.score.score.cmp(&e1.score.score))
+      }
+      scores.truncate(maxSeedScores);
+    }
+    
+    // every game in the history and in the high and recent lists of every mode, without
+    // duplicates, in the order of game_key (oldest first)
+    pub fn all_entries(&self) -> Vec<ScoreEntry> {
+      let mut entries: Vec<ScoreEntry> = self.history.clone();
+      for modeScores in self.modes.iter() {
+        entries.push_all(modeScores.highScores.as_slice());
+        entries.push_all(modeScores.recentScores.as_slice());
+      }
+      entries.sort_by(|e1, e2| game_key(e1).cmp(&game_key(e2)));
+      let mut unique: Vec<ScoreEntry> = Vec::new();
+      for entry in entries.move_iter() {
+        if !unique.last().map_or(false, |last| same_game(last, &entry)) {
+          unique.push(entry);
+        }
+      }
+      unique
+    }
+    
+    fn add_to_history(&mut self, entry: ScoreEntry) {
+      self.history.push(entry);
+      if self.history.len() > maxHistory {
+        self.history.remove(0);
+      }
+    }
+    
+    // Adds entries from other storage (like scores exported from another computer), skipping
+    // games that are already here. Returns the number of entries that were new.
+    pub fn merge(&mut self, other: &ScoreStorage) -> uint {
+      // the games here are looked up by game_key, which all_entries is sorted by
+      let existing = self.all_entries();
+      let added: Vec<ScoreEntry> = other.all_entries().move_iter().filter(|entry| {
+        existing.as_slice().bsearch(|e| game_key(e).cmp(&game_key(entry))).is_none()
+      }).collect();
+      
+      // zero scores go in the history only, as store_score keeps them
+      for entry in added.iter() {
+        self.history.push(entry.clone());
+        if entry.score.score > 0 {
+          self.add_seed_score(entry);
+          let scores = self.named_mode_scores(entry.mode.as_slice());
+          scores.highScores.push(entry.clone());
+          scores.recentScores.push(entry.clone());
+        }
+      }
+      
+      // then everything's put back in order once, and what no longer fits is dropped, the
+      // oldest games from the history
+      self.history.sort_by(|e1, e2| e1.time.to_timespec().cmp(&e2.time.to_timespec()));
+      if self.history.len() > maxHistory {
+        self.history = Vec::from_slice(self.history.slice_from(self.history.len() - maxHistory));
+      }
+      for scores in self.modes.mut_iter() {
+        match modes::from_name(scores.mode.as_slice()) {
+          Some(mode) => scores.highScores.sort_by(|e1, e2| better(mode, e1, e2)),
+          None       => scores.highScores.sort_by(|e1, e2| e2.score.score.cmp(&e1.score.score))
+        }
+        scores.highScores.truncate(maxScores);
+        scores.recentScores.sort_by(|e1, e2| e2.time.to_timespec().cmp(&e1.time.to_timespec()));
+        scores.recentScores.truncate(maxScores);
+      }
+      added.len()
+    }
+  }
+  
+  // Calendar periods games can be grouped by, to see the best of each week or month
+  pub enum Period {
+    Week,
+    Month
+  }
+  
+  pub fn period_from_name(name: &str) -> Option<Period> {
+    match name {
+      "week"  => Some(Week),
+      "month" => Some(Month),
+      _       => None
+    }
+  }
+  
+  // The period a game was played in, as the date it starts on (weeks start on Monday, in
+  // local time), so later periods sort after earlier ones
+  pub fn period_start(time: &time::Tm, period: Period) -> ~str {
+    match period {
+      Month => time.strftime("%Y-%m"),
+      Week  => {
+        // counted back from noon, so a daylight saving change can't move it to another day
+        let mut noon = time.clone();
+        noon.tm_hour = 12;
+        noon.tm_min = 0;
+        noon.tm_sec = 0;
+        noon.tm_nsec = 0;
+        let daysBack = ((time.tm_wday + 6) % 7) as i64;
+        time::at(time::Timespec::new(noon.to_timespec().sec - daysBack * 24 * 60 * 60, 0)).strftime("%Y-%m-%d")
+      }
+    }
+  }
+  
+  // The entries in groups by the period they were played in, the newest period first, with
+  // each group's entries in the order they were given
+  pub fn group_by_period(entries: &[ScoreEntry], period: Period) -> Vec<(~str, Vec<ScoreEntry>)> {
+    let mut groups: Vec<(~str, Vec<ScoreEntry>)> = Vec::new();
+    for entry in entries.iter() {
+      let start = period_start(&entry.time, period);
+      match groups.iter().position(|&(ref other, _)| *other == start) {
+        Some(index) => match *groups.get_mut(index) {
+          (_, ref mut group) => group.push(entry.clone())
+        },
+        None => groups.push((start, vec!(entry.clone())))
+      }
+    }
+    groups.sort_by(|&(ref s1, _), &(ref s2, _)| s2.cmp(s1));
+    groups
+  }
+  
+  // what tells games apart, see same_game: when they finished, then the score and mode
+  fn game_key<'a>(entry: &'a ScoreEntry) -> (i64, i64, &'a str) {
+    (entry.time.to_timespec().sec, entry.score.score, entry.mode.as_slice())
+  }
+  
+  // entries are the same game if they finished at the same second with the same score
+  fn same_game(e1: &ScoreEntry, e2: &ScoreEntry) -> bool {
+    e1.mode == e2.mode &&
+    e1.score.score == e2.score.score &&
+    e1.time.to_timespec().sec == e2.time.to_timespec().sec
+  }
+  
+  // minutes, seconds and tenths of a second, like 1:23.4
+  pub fn format_duration(ms: u64) -> ~str {
+    format!("{}:{:02u}.{}", ms / 60000, (ms / 1000) % 60, (ms / 100) % 10)
+  }
+  
+  pub fn new(path: Path) -> ~ScoreKeeper {
+    ~FileScoreKeeper{path: path} as ~ScoreKeeper
+  }
+  
+  // Keeps nothing, for games that don't count (like the ghost of a replay being raced)
+  pub fn null() -> ~ScoreKeeper {
+    ~NullScoreKeeper as ~ScoreKeeper
+  }
+  
+  fn empty_storage() -> ScoreStorage {
+    ScoreStorage {
+      version:     storageVersion,
+      modes:       vec!(),
+      history:     vec!(),
+      versus:      vec!(),
+      tournaments: vec!(),
+      seeds:       vec!()
+    }
+  }
+
+  
+  struct FileScoreKeeper {
+    path: Path
+  }
+  
+  static maxScores : uint = 5;
+  
+  // puts the players of a tournament in order, the same way as the high scores
+  pub fn rank_players(mode: Mode, players: &mut Vec<TournamentPlayer>) {
+    players.sort_by(|p1, p2| better(mode, &p1.entry, &p2.entry));
+  }
+  
+  // Ordering for the high score list: sprint games that cleared all the lines come first,
+  // fastest first, other modes are highest score first
+  pub fn better(mode: Mode, e1: &ScoreEntry, e2: &ScoreEntry) -> Ordering {
+    if modes::ranked_by_time(mode) {
+      let done1 = e1.score.lines >= modes::sprintLines;
+      let done2 = e2.score.lines >= modes::sprintLines;
+      if done1 != done2 {
+        return if done1 { Less } else { Greater };
+      }
+      if done1 {
+        return e1.duration.cmp(&e2.duration);
+      }
+    }
+    e2.score.score.cmp(&e1.score.score)
+  }
+  
+  impl ScoreKeeper for FileScoreKeeper {
+    fn store_score(&self, mode: Mode, entry: ScoreEntry) {
+      let mut storage = self.get_scores();
+      storage.add_to_history(entry.clone());
+      
+      // zero scores aren't worth keeping as high or recent scores
+      if entry.score.score > 0 {
+        storage.add_seed_score(&entry);
+        let scores = storage.mode_scores(mode);
+        
+        scores.highScores.insert(0, entry.clone());
+        scores.highScores.sort_by(|e1, e2| better(mode, e1, e2));
+        if scores.highScores.len() > maxScores {
+          scores.highScores.pop();
+        }
+        
+        scores.recentScores.insert(0, entry);
+        if scores.recentScores.len() > maxScores {
+          scores.recentScores.pop();
+        }
+      }
+      
+      self.save_scores(&storage);
+    }
+    
+    fn store_versus(&self, result: VersusResult) {
+      let mut storage = self.get_scores();
+      storage.versus.push(result);
+      if storage.versus.len() > maxHistory {
+        storage.versus.remove(0);
+      }
+      self.save_scores(&storage);
+    }
+    
+    fn store_tournament(&self, result: TournamentResult) {
+      let mut storage = self.get_scores();
+      storage.tournaments.push(result);
+      if storage.tournaments.len() > maxHistory {
+        storage.tournaments.remove(0);
+      }
+      self.save_scores(&storage);
+    }
+    
+    fn save_scores(&self, storage: &ScoreStorage) {
+      if paths::create_parent_dir(&self.path).is_err() {
+        return;
+      }
+      let mut scoresFile = File::create(&self.path);
+      let mut encoder = json::PrettyEncoder::new(&mut scoresFile);
+      storage.encode(&mut encoder);
+    }
+    
+    fn get_scores(&self) -> ScoreStorage {
+      let storageFile = File::open(&self.path);
+      if storageFile.is_err() {
+        return empty_storage();
+      }
+      
+      let storageObject = json::from_reader(&mut storageFile.unwrap());
+      if storageObject.is_err() {
+        return empty_storage();
+      }
+      
+      decode_storage(storageObject.unwrap())
+    }
+  }
+  
+  struct NullScoreKeeper;
+  
+  impl ScoreKeeper for NullScoreKeeper {
+    fn store_score(&self, _mode: Mode, _entry: ScoreEntry) {
+    }
+    
+    fn get_scores(&self) -> ScoreStorage {
+      empty_storage()
+    }
+    
+    fn save_scores(&self, _storage: &ScoreStorage) {
+    }
+  }
+  
+  // Decodes a scores file of any version, upgrading it to the current version
+  pub fn decode_storage(mut storage: json::Json) -> ScoreStorage {
+    let version = storage_version(&storage);
+    if version == 0 {
+      upgrade_legacy_storage(&mut storage);
+      let mut decoder = json::Decoder::new(storage);
+      let legacy: LegacyScoreStorage = Decodable::decode(&mut decoder);
+      let mut storage = legacy.to_storage();
+      start_history(&mut storage);
+      return storage;
+    }
+    if version == 1 {
+      upgrade_entries_v1(&mut storage);
+    }
+    if version <= 2 {
+      match storage {
+        json::Object(ref mut storage) => add_missing_field(&mut **storage, "history", json::List(~[])),
+        _                             => ()
+      }
+    }
+    if version <= 3 {
+      match storage {
+        json::Object(ref mut storage) => add_missing_field(&mut **storage, "versus", json::List(~[])),
+        _                             => ()
+      }
+    }
+    if version <= 4 {
+      match storage {
+        json::Object(ref mut storage) => add_missing_field(&mut **storage, "tournaments", json::List(~[])),
+        _                             => ()
+      }
+    }
+    if version <= 5 {
+      match storage {
+        json::Object(ref mut storage) => add_missing_field(&mut **storage, "seeds", json::List(~[])),
+        _                             => ()
+      }
+    }
+    let mut decoder = json::Decoder::new(storage);
+    let mut storage: ScoreStorage = Decodable::decode(&mut decoder);
+    if version <= 2 {
+      start_history(&mut storage);
+    }
+    if version <= 5 {
+      start_seed_scores(&mut storage);
+    }
+    storage.version = storageVersion;
+    storage
+  }
+  
+  // Files from before the history was kept only know about the games in the high and
+  // recent lists, those become the start of the history
+  fn start_history(storage: &mut ScoreStorage) {
+    storage.history = storage.all_entries();
+    storage.history.sort_by(|e1, e2| e1.time.to_timespec().cmp(&e2.time.to_timespec()));
+  }
+  
+  // Files from before the seeds had high scores get them from the seeded games in the history
+  fn start_seed_scores(storage: &mut ScoreStorage) {
+    for entry in storage.all_entries().iter() {
+      if entry.score.score > 0 {
+        storage.add_seed_score(entry);
+      }
+    }
+  }
+  
+  fn storage_version(storage: &json::Json) -> uint {
+    match *storage {
+      json::Object(ref storage) => {
+        match storage.find(&~"version") {
+          Some(&json::Number(version))             => version as uint,
+          _ if storage.contains_key(&~"modes")     => 1,
+          _                                        => 0
+        }
+      }
+      _ => storageVersion
+    }
+  }
+  
+  // Version 1 entries don't have mode, seed or density. The mode comes from the list the
+  // entry is in, seeds weren't used yet, and the board density wasn't recorded.
+  fn upgrade_entries_v1(storage: &mut json::Json) {
+    match *storage {
+      json::Object(ref mut storage) => {
+        add_missing_field(&mut **storage, "version", json::Number(1.0));
+        match storage.find_mut(&~"modes") {
+          Some(&json::List(ref mut modeLists)) => {
+            for modeScores in modeLists.mut_iter() {
+              match *modeScores {
+                json::Object(ref mut modeScores) => {
+                  let mode = match modeScores.find(&~"mode") {
+                    Some(&json::String(ref mode)) => mode.clone(),
+                    _                             => modes::name(modes::Endless).to_owned()
+                  };
+                  for key in [~"highScores", ~"recentScores"].iter() {
+                    match modeScores.find_mut(key) {
+                      Some(&json::List(ref mut entries)) => {
+                        for entry in entries.mut_iter() {
+                          match *entry {
+                            json::Object(ref mut entry) => {
+                              add_missing_field(&mut **entry, "mode", json::String(mode.clone()));
+                              add_missing_field(&mut **entry, "seed", json::Null);
+                              add_missing_field(&mut **entry, "density", json::Number(0.0));
+                            }
+                            _ => ()
+                          }
+                        }
+                      }
+                      _ => ()
+                    }
+                  }
+                }
+                _ => ()
+              }
+            }
+          }
+          _ => ()
+        }
+      }
+      _ => ()
+    }
+  }
+  
+  // Before scores were kept per mode, the scores file had a single list of high and recent
+  // (time, score) pairs, all from endless games
+  #[deriving(Decodable)]
+  struct LegacyScoreStorage {
+    highScores:   Vec<(time::Tm, Score)>,
+    recentScores: Vec<(time::Tm, Score)>
+  }
+  
+  impl LegacyScoreStorage {
+    fn to_storage(&self) -> ScoreStorage {
+      ScoreStorage {
+        version:     storageVersion,
+        modes:       vec!(ModeScores{mode:         modes::name(modes::Endless).to_owned(),
+                                     highScores:   self.highScores.iter().map(legacy_entry).collect(),
+                                     recentScores: self.recentScores.iter().map(legacy_entry).collect()}),
+        history:     vec!(),
+        versus:      vec!(),
+        tournaments: vec!(),
+        seeds:       vec!()
+      }
+    }
+  }
+  
+  fn legacy_entry(&(ref tm, score): &(time::Tm, Score)) -> ScoreEntry {
+    ScoreEntry{time:     tm.clone(),
+               score:    score,
+               duration: 0,
+               mode:     modes::name(modes::Endless).to_owned(),
+               seed:     None,
+               density:  0.0}
+  }
+  
+  // Scores files written before lines and tetrises were tracked don't have those fields,
+  // fill them in with zeros so old files still decode
+  fn upgrade_legacy_storage(storage: &mut json::Json) {
+    match *storage {
+      json::Object(ref mut storage) => {
+        for key in [~"highScores", ~"recentScores"].iter() {
+          match storage.find_mut(key) {
+            Some(&json::List(ref mut entries)) => {
+              for entry in entries.mut_iter() {
+                // each entry is a (time, score) tuple, encoded as a two element list
+                match *entry {
+                  json::List(ref mut pair) if pair.len() == 2 => upgrade_score(&mut pair[1]),
+                  _                                          => ()
+                }
+              }
+            }
+            _ => ()
+          }
+        }
+      }
+      _ => ()
+    }
+  }
+  
+  fn upgrade_score(score: &mut json::Json) {
+    match *score {
+      json::Object(ref mut score) => {
+        add_missing_field(&mut **score, "lines", json::Number(0.0));
+        add_missing_field(&mut **score, "tetrises", json::Number(0.0));
+      }
+      _ => ()
+    }
+  }
+  
+  fn add_missing_field(object: &mut json::Object, name: &str, value: json::Json) {
+    let name = name.to_owned();
+    if !object.contains_key(&name) {
+      object.insert(name, value);
+    }
+  }
+}
+
+mod score_transfer {
+  use std::io::{File, BufferedReader, IoResult};
+  use std::vec_ng::Vec;
+  use serialize::json;
+  use serialize::Encodable;
+  use time;
+  use scoring::Score;
+  use modes;
+  use score_keeper;
+  use score_keeper::{ScoreStorage, ScoreEntry};
+  use strings;
+  
+  // files ending in .csv are exported/imported as CSV, everything else as JSON
+  fn is_csv(path: &Path) -> bool {
+    match path.extension_str() {
+      Some(ext) => ext.eq_ignore_ascii_case("csv"),
+      None      => false
+    }
+  }
+  
+  static csvHeader: &'static str =
+    "timestamp,date,mode,score,level,bonus,lines,tetrises,duration_ms,seed,density";
+  
+  pub fn export(storage: &ScoreStorage, path: &Path) -> IoResult<uint> {
+    let mut file = try!(File::create(path));
+    if !is_csv(path) {
+      let mut encoder = json::PrettyEncoder::new(&mut file);
+      storage.encode(&mut encoder);
+      return Ok(storage.all_entries().len());
+    }
+    
+    let entries = storage.all_entries();
+    try!(file.write_line(csvHeader));
+    for entry in entries.iter() {
+      let seed = match entry.seed {
+        Some(seed) => seed.to_str(),
+        None       => ~""
+      };
+      try!(file.write_line(format!("{},{},{},{},{},{},{},{},{},{},{:.3f}",
+                                   entry.time.to_timespec().sec,
+                                   entry.time.strftime("%Y-%m-%d %H:%M:%S"),
+                                   entry.mode,
+                                   entry.score.score,
+                                   entry.score.level,
+                                   entry.score.bonus,
+                                   entry.score.lines,
+                                   entry.score.tetrises,
+                                   entry.duration,
+                                   seed,
+                                   entry.density)));
+    }
+    Ok(entries.len())
+  }
+  
+  pub fn import(path: &Path) -> Result<ScoreStorage, ~str> {
+    if is_csv(path) {
+      return import_csv(path);
+    }
+    let mut file = match File::open(path) {
+      Ok(file) => file,
+      Err(e)   => return Err(strings::fill(strings::get().errCantRead,
+                                           &[path.display().to_str().as_slice(), e.to_str().as_slice()]))
+    };
+    match json::from_reader(&mut file) {
+      Ok(storage) => Ok(score_keeper::decode_storage(storage)),
+      Err(e)      => Err(strings::fill(strings::get().errNotJson, &[path.display().to_str().as_slice(), e.to_str().as_slice()]))
+    }
+  }
+  
+  fn import_csv(path: &Path) -> Result<ScoreStorage, ~str> {
+    let file = match File::open(path) {
+      Ok(file) => file,
+      Err(e)   => return Err(strings::fill(strings::get().errCantRead,
+                                           &[path.display().to_str().as_slice(), e.to_str().as_slice()]))
+    };
+    let mut reader = BufferedReader::new(file);
+    
+    let mut storage = ScoreStorage{version: 0, modes: Vec::new(), history: Vec::new(), versus: Vec::new(),
+                                   tournaments: Vec::new(), seeds: Vec::new()};
+    for (i, line) in reader.lines().enumerate() {
+      let line = line.trim();
+      if line.len() == 0 || line.starts_with("timestamp") {
+        continue;
+      }
+      let entry = match parse_csv_entry(line) {
+        Some(entry) => entry,
+        None        => return Err(strings::fill(strings::get().errBadScoreLine,
+                                                &[path.display().to_str().as_slice(), (i + 1).to_str().as_slice()]))
+      };
+      let mode = entry.mode.clone();
+      storage.named_mode_scores(mode.as_slice()).recentScores.push(entry);
+    }
+    Ok(storage)
+  }
+  
+  fn parse_csv_entry(line: &str) -> Option<ScoreEntry> {
+    let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+    if fields.len() != 11 || modes::from_name(*fields.get(2)).is_none() {
+      return None;
+    }
+    
+    // an empty seed means the game wasn't seeded
+    let seed = if fields.get(9).len() == 0 {
+      Some(None)
+    } else {
+      from_str::<u64>(*fields.get(9)).map(|seed| Some(seed))
+    };
+    
+    match (from_str::<i64>(*fields.get(0)),
+           from_str::<i64>(*fields.get(3)),
+           from_str::<u16>(*fields.get(4)),
+           from_str::<int>(*fields.get(5)),
+           from_str::<int>(*fields.get(6)),
+           from_str::<int>(*fields.get(7)),
+           from_str::<u64>(*fields.get(8)),
+           seed,
+           from_str::<f64>(*fields.get(10))) {
+      (Some(timestamp), Some(score), Some(level), Some(bonus), Some(lines), Some(tetrises),
+       Some(duration), Some(seed), Some(density)) => {
+        Some(ScoreEntry {
+          time:     time::at(time::Timespec::new(timestamp, 0)),
+          score:    Score{level: level, bonus: bonus, score: score, lines: lines, tetrises: tetrises},
+          duration: duration,
+          mode:     fields.get(2).to_owned(),
+          seed:     seed,
+          density:  density
+        })
+      }
+      _ => None
+    }
+  }
+}
+
+mod saved_game {
+  use std::io::{File, IoResult};
+  use std::io::fs;
+  use std::vec_ng::Vec;
+  use serialize::json;
+  use serialize::{Encodable, Decodable};
+  use pieces;
+  use pieces::{Block, Color, Piece, PieceType, Black, Red, Green, Yellow, Blue, Magenta, Cyan, White, Gray};
+  use pieces::{I, J, L, O, S, T, Z};
+  use board;
+  use board::Board;
+  use scoring::Score;
+  use paths;
+  use strings;
+  
+  // A game in progress, saved so it can be picked up again later. The board is kept as
+  // 20 rows of 10 characters, top row first: '.' is an empty cell, a letter is a block of
+  // that piece, and a digit is the color of a block that isn't part of any piece (like
+  // garbage). The falling piece starts again from the top when the game is resumed.
+  #[deriving(Encodable, Decodable)]
+  pub struct SavedGame {
+    mode:      ~str,
+    score:     Score,
+    count:     int,   // the scoring's count of clears on this level, see Scoring::get_count
+    duration:  u64,   // milliseconds played before the game was saved
+    piece:     ~str,
+    nextPiece: ~str,
+    seed:      u64,
+    board:     Vec<~str>
+  }
+  
+  static pieceNames: [&'static str, ..7] = ["I", "J", "L", "O", "S", "T", "Z"];
+  static pieceTypes: [PieceType, ..7] = [I, J, L, O, S, T, Z];
+  static colors: [Color, ..9] = [Black, Red, Green, Yellow, Blue, Magenta, Cyan, White, Gray];
+  
+  pub fn piece_name(piece: &Piece) -> ~str {
+    type_name(piece.ty).to_owned()
+  }
+  
+  pub fn type_name(ty: PieceType) -> &'static str {
+    pieceNames[ty as int]
+  }
+  
+  pub fn piece_from_name(name: &str) -> Option<Piece> {
+    pieceNames.iter().position(|n| *n == name).map(|i| pieces::new(pieceTypes[i]))
+  }
+  
+  pub fn encode_board(setBlocks: &Board) -> Vec<~str> {
+    range(1, board::height + 1).map(|row| {
+      let mut line = ~"";
+      for col in range(1, board::width + 1) {
+        match setBlocks.get(row, col) {
+          Some(Block{ty: Some(ty), ..}) => line.push_str(pieceNames[ty as int]),
+          Some(block)                   => line.push_str((block.color as int).to_str()),
+          None                          => line.push_char('.')
+        }
+      }
+      line
+    }).collect()
+  }
+  
+  // fills setBlocks from the saved board, failing if it isn't 20 rows of 10 valid cells
+  pub fn decode_board(board: &[~str], setBlocks: &mut Board) -> Result<(), ~str> {
+    if board.len() != 20 {
+      return Err(strings::get().errBoardRows.to_owned());
+    }
+    for (row, line) in board.iter().enumerate() {
+      if line.len() != 10 {
+        return Err(strings::fill(strings::get().errBoardCells, &[(row + 1).to_str().as_slice()]));
+      }
+      for (col, cell) in line.chars().enumerate() {
+        match cell {
+          '.'        => setBlocks.remove(row as i8 + 1, col as i8 + 1),
+          '0' .. '8' => setBlocks.set(Block{row:    row as i8 + 1,
+                                            column: col as i8 + 1,
+                                            color:  colors[cell as uint - '0' as uint],
+                                            ty:     None}),
+          'I' | 'J' | 'L' | 'O' | 'S' | 'T' | 'Z' => {
+            let ty = pieceTypes[pieceNames.iter().position(|n| n.char_at(0) == cell).unwrap()];
+            setBlocks.set(Block{row:    row as i8 + 1,
+                                column: col as i8 + 1,
+                                color:  pieces::new(ty).blocks[0].color,
+                                ty:     Some(ty)})
+          }
+          _          => return Err(strings::fill(strings::get().errBoardCell,
+                                                 &[(row + 1).to_str().as_slice(), cell.to_str().as_slice()]))
+        }
+      }
+    }
+    Ok(())
+  }
+  
+  pub fn save(game: &SavedGame) -> IoResult<()> {
+    let path = paths::saved_game_file();
+    try!(paths::create_parent_dir(&path));
+    let mut file = try!(File::create(&path));
+    let mut encoder = json::PrettyEncoder::new(&mut file);
+    game.encode(&mut encoder);
+    Ok(())
+  }
+  
+  pub fn exists() -> bool {
+    paths::saved_game_file().exists()
+  }
+  
+  // Loads the saved game, leaving the file where it is
+  pub fn load() -> Result<SavedGame, ~str> {
+    let path = paths::saved_game_file();
+    if !path.exists() {
+      return Err(strings::get().errNoSavedGame.to_owned());
+    }
+    let root = match File::open(&path) {
+      Ok(mut file) => match json::from_reader(&mut file) {
+        Ok(root) => root,
+        Err(e)   => return Err(strings::fill(strings::get().errNotJson,
+                                             &[path.display().to_str().as_slice(), e.to_str().as_slice()]))
+      },
+      Err(e) => return Err(strings::fill(strings::get().errCantRead,
+                                         &[path.display().to_str().as_slice(), e.to_str().as_slice()]))
+    };
+    // games saved by older versions have no count
+    let root = match root {
+      json::Object(mut fields) => {
+        if !fields.contains_key(&~"count") {
+          fields.insert(~"count", json::Number(0.0));
+        }
+        json::Object(fields)
+      }
+      root => root
+    };
+    let mut decoder = json::Decoder::new(root);
+    Ok(Decodable::decode(&mut decoder))
+  }
+  
+  pub fn discard() {
+    let _ = fs::unlink(&paths::saved_game_file());
+  }
+  
+  // Loads the saved game and removes the file, so the same game can't be resumed twice
+  pub fn take() -> Result<SavedGame, ~str> {
+    let saved = try!(load());
+    discard();
+    Ok(saved)
+  }
+}
+
+mod remote_scores {
+  use std::libc::{c_int, c_long, c_short, c_void, size_t, ssize_t};
+  use std::io::{IoError, MemWriter};
+  use std::io::net::addrinfo;
+  use std::io::net::ip::{SocketAddr, Ipv4Addr, Ipv6Addr};
+  use std::mem;
+  use std::os;
+  use std::str;
+  use serialize::json;
+  use serialize::Encodable;
+  use modes::Mode;
+  use score_keeper;
+  use score_keeper::{ScoreKeeper, ScoreEntry, ScoreStorage, VersusResult, TournamentResult};
+  use terminal_control;
+  use strings;
+  
+  // A shared leaderboard is a plain HTTP endpoint, for example "http://example.com:8080/tetris1/scores".
+  // Finished games are POSTed to it as a JSON score entry, and a GET returns every score
+  // it knows about in the same JSON format as the scores file.
+  #[deriving(Clone)]
+  pub struct Url {
+    host: ~str,
+    port: u16,
+    path: ~str
+  }
+  
+  pub fn parse_url(url: &str) -> Result<Url, ~str> {
+    if !url.starts_with("http://") {
+      return Err(strings::fill(strings::get().errNotHttp, &[url]));
+    }
+    let rest = url.slice_from("http://".len());
+    let (hostPort, path) = match rest.find('/') {
+      Some(i) => (rest.slice_to(i), rest.slice_from(i)),
+      None    => (rest, "/")
+    };
+    let (host, port) = match hostPort.find(':') {
+      Some(i) => match from_str::<u16>(hostPort.slice_from(i + 1)) {
+        Some(port) => (hostPort.slice_to(i), port),
+        None       => return Err(strings::fill(strings::get().errBadPort, &[url]))
+      },
+      None    => (hostPort, 80)
+    };
+    if host.len() == 0 {
+      return Err(strings::fill(strings::get().errNoHost, &[url]));
+    }
+    Ok(Url{host: host.to_owned(), port: port, path: path.to_owned()})
+  }
+  
+  // Sends one HTTP/1.0 request and returns the body of a 2xx response
+  fn request(url: &Url, method: &str, body: &[u8]) -> Result<~[u8], ~str> {
+    let addresses = match addrinfo::get_host_addresses(url.host) {
+      Ok(addresses) => addresses,
+      Err(e)        => return Err(strings::fill(strings::get().errCantFindBecause, &[url.host.as_slice(), e.to_str().as_slice()]))
+    };
+    // only IPv4 is supported, see connect_to
+    let ip = match addresses.iter().find(|ip| match **ip { Ipv4Addr(..) => true, _ => false }) {
+      Some(ip) => *ip,
+      None     => return Err(strings::fill(strings::get().errCantFind, &[url.host.as_slice()]))
+    };
+    
+    let response = send_request(SocketAddr{ip: ip, port: url.port}, url, method, body);
+    let response = match response {
+      Ok(response) => response,
+      Err(e)       => return Err(strings::fill(strings::get().errCantReach, &[url.host.as_slice(), e.as_slice()]))
+    };
+    
+    // the status line is "HTTP/1.x CODE REASON", the body starts after the first blank line
+    let headerEnd = match response.windows(4).position(|w| w == bytes!("\r\n\r\n")) {
+      Some(i) => i,
+      None    => return Err(strings::fill(strings::get().errBadResponse, &[url.host.as_slice()]))
+    };
+    let header = str::from_utf8_lossy(response.slice_to(headerEnd)).into_owned();
+    let statusLine = header.lines().next().unwrap_or("");
+    match statusLine.words().nth(1) {
+      Some(code) if code.starts_with("2") => Ok(response.slice_from(headerEnd + 4).to_owned()),
+      _                                   => Err(strings::fill(strings::get().errAnswered, &[url.host.as_slice(), statusLine]))
+    }
+  }
+  
+  // Linux specific struct sockaddr_in and struct timeval, as in mod spectator
+  #[allow(non_camel_case_types)]
+  struct sockaddr_in {
+    sin_family: u16,
+    sin_port:   u16,
+    sin_addr:   u32,
+    sin_zero:   [u8, ..8]
+  }
+  
+  #[allow(non_camel_case_types)]
+  struct timeval {
+    tv_sec:  i64,
+    tv_usec: i64
+  }
+  
+  #[allow(non_camel_case_types)]
+  struct pollfd {
+    fd:      c_int,
+    events:  c_short,
+    revents: c_short
+  }
+  
+  extern {
+    fn socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int;
+    fn connect(fd: c_int, addr: *sockaddr_in, len: u32) -> c_int;
+    fn setsockopt(fd: c_int, level: c_int, name: c_int, value: *c_void, len: u32) -> c_int;
+    fn getsockopt(fd: c_int, level: c_int, name: c_int, value: *mut c_void, len: *mut u32) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, arg: c_int) -> c_int;
+    fn poll(fds: *mut pollfd, nfds: c_long, timeout: c_int) -> c_int;
+    fn send(fd: c_int, buf: *c_void, len: size_t, flags: c_int) -> ssize_t;
+    fn recv(fd: c_int, buf: *mut c_void, len: size_t, flags: c_int) -> ssize_t;
+    fn close(fd: c_int) -> c_int;
+  }
+  
+  static afInet: c_int = 2;
+  static sockStream: c_int = 1;
+  static solSocket: c_int = 1;
+  static soError: c_int = 4;
+  static soRcvTimeo: c_int = 20;
+  static soSndTimeo: c_int = 21;
+  static fSetFl: c_int = 4;
+  static oNonBlock: c_int = 0x800;
+  static pollOut: c_short = 4;
+  static einProgress: int = 115;
+  static msgNoSignal: c_int = 0x4000;
+  
+  // A leaderboard that doesn't answer mustn't hold up the game (scores are submitted
+  // when a game ends), so connecting, sending and reading each give up after this long
+  static requestTimeoutMs: c_int = 3000;
+  
+  fn last_error<T>() -> Result<T, ~str> {
+    Err(IoError::last_error().to_str())
+  }
+  
+  // Connects to address, without waiting longer than requestTimeoutMs
+  pub unsafe fn connect_to(address: SocketAddr) -> Result<c_int, ~str> {
+    let ip = match address.ip {
+      Ipv4Addr(a, b, c, d) => (a as u32 << 24) | (b as u32 << 16) | (c as u32 << 8) | d as u32,
+      Ipv6Addr(..)         => return Err(strings::get().errNotIpv4.to_owned())
+    };
+    let addr = sockaddr_in {
+      sin_family: afInet as u16,
+      sin_port:   mem::to_be16(address.port as i16) as u16,
+      sin_addr:   mem::to_be32(ip as i32) as u32,
+      sin_zero:   [0, ..8]
+    };
+    let fd = socket(afInet, sockStream, 0);
+    if fd < 0 {
+      return last_error();
+    }
+    
+    // the connect goes on in the background while poll waits for it
+    if fcntl(fd, fSetFl, oNonBlock) < 0 ||
+       (connect(fd, &addr, mem::size_of::<sockaddr_in>() as u32) < 0 && os::errno() != einProgress) {
+      let error = last_error();
+      close(fd);
+      return error;
+    }
+    let mut pfd = pollfd{fd: fd, events: pollOut, revents: 0};
+    let ready = poll(&mut pfd, 1, requestTimeoutMs);
+    if ready <= 0 {
+      let error = if ready == 0 { Err(strings::get().errTimedOut.to_owned()) } else { last_error() };
+      close(fd);
+      return error;
+    }
+    let mut error: c_int = 0;
+    let mut len = mem::size_of::<c_int>() as u32;
+    getsockopt(fd, solSocket, soError, &mut error as *mut c_int as *mut c_void, &mut len);
+    if error != 0 {
+      close(fd);
+      return Err(os::error_string(error as uint));
+    }
+    
+    // connected; sending and reading block again, up to the same timeout
+    let timeout = timeval{tv_sec: (requestTimeoutMs / 1000) as i64, tv_usec: (requestTimeoutMs % 1000) as i64 * 1000};
+    let timeoutPtr = &timeout as *timeval as *c_void;
+    let timeoutLen = mem::size_of::<timeval>() as u32;
+    if fcntl(fd, fSetFl, 0) < 0 ||
+       setsockopt(fd, solSocket, soRcvTimeo, timeoutPtr, timeoutLen) < 0 ||
+       setsockopt(fd, solSocket, soSndTimeo, timeoutPtr, timeoutLen) < 0 {
+      let error = last_error();
+      close(fd);
+      return error;
+    }
+    Ok(fd)
+  }
+  
+  pub unsafe fn send_all(fd: c_int, bytes: &[u8]) -> Result<(), ~str> {
+    let mut sent = 0;
+    while sent < bytes.len() {
+      let rest = bytes.slice_from(sent);
+      let n = send(fd, rest.as_ptr() as *c_void, rest.len() as size_t, msgNoSignal);
+      if n < 0 {
+        return last_error();
+      }
+      sent += n as uint;
+    }
+    Ok(())
+  }
+  
+  // reads until the server closes the connection, as it does after an HTTP/1.0 response
+  unsafe fn read_all(fd: c_int) -> Result<~[u8], ~str> {
+    let mut response = ~[];
+    let mut buffer = [0u8, ..4096];
+    loop {
+      let n = recv(fd, buffer.as_mut_ptr() as *mut c_void, buffer.len() as size_t, 0);
+      if n < 0 {
+        return last_error();
+      }
+      if n == 0 {
+        return Ok(response);
+      }
+      response.push_all(buffer.slice_to(n as uint));
+    }
+  }
+  
+  fn send_request(address: SocketAddr, url: &Url, method: &str, body: &[u8]) -> Result<~[u8], ~str> {
+    let header = format!("{} {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                         method, url.path, url.host, body.len());
+    unsafe {
+      let fd = try!(connect_to(address));
+      let response = send_all(fd, header.as_bytes()).and_then(|_| send_all(fd, body)).and_then(|_| read_all(fd));
+      close(fd);
+      response
+    }
+  }
+  
+  // Fetches the shared leaderboard
+  pub fn fetch(url: &Url) -> Result<ScoreStorage, ~str> {
+    let body = try!(request(url, "GET", []));
+    match json::from_str(str::from_utf8_lossy(body).as_slice()) {
+      Ok(storage) => Ok(score_keeper::decode_storage(storage)),
+      Err(e)      => Err(strings::fill(strings::get().errBadScores, &[url.host.as_slice(), e.to_str().as_slice()]))
+    }
+  }
+  
+  pub fn submit(url: &Url, entry: &ScoreEntry) -> Result<(), ~str> {
+    let mut body = MemWriter::new();
+    {
+      let mut encoder = json::Encoder::new(&mut body);
+      entry.encode(&mut encoder);
+    }
+    try!(request(url, "POST", body.get_ref()));
+    Ok(())
+  }
+  
+  // Keeps scores in the local file as usual, and also submits each finished game to the leaderboard
+  pub fn new(local: ~ScoreKeeper, url: Url) -> ~ScoreKeeper {
+    ~RemoteScoreKeeper{local: local, url: url} as ~ScoreKeeper
+  }
+  
+  struct RemoteScoreKeeper {
+    local: ~ScoreKeeper,
+    url:   Url
+  }
+  
+  impl ScoreKeeper for RemoteScoreKeeper {
+    fn store_score(&self, mode: Mode, entry: ScoreEntry) {
+      // the game is still on screen, so there's nowhere to report a failed submission;
+      // the score is safe in the local file either way. After a hangup the game has to
+      // save itself and exit before it's killed, so it doesn't wait for the network.
+      if !terminal_control::hung_up() {
+        let _ = submit(&self.url, &entry);
+      }
+      self.local.store_score(mode, entry);
+    }
+    
+    fn get_scores(&self) -> ScoreStorage {
+      self.local.get_scores()
+    }
+    
+    fn save_scores(&self, storage: &ScoreStorage) {
+      self.local.save_scores(storage)
+    }
+    
+    fn store_versus(&self, result: VersusResult) {
+      self.local.store_versus(result)
+    }
+    
+    fn store_tournament(&self, result: TournamentResult) {
+      self.local.store_tournament(result)
+    }
+  }
+}
+
+mod debug_overlay {
+  use std::vec_ng::Vec;
+  use output;
+  
+  // nanoseconds between refreshes of the overlay
+  static reportIntervalNs: u64 = 1000000000;
+  
+  // Collects timing and output statistics from the main loop, and once a second
+  // summarizes them as a few short lines of text for the display to show
+  pub struct DebugOverlay {
+    lastReportNs: u64,
+    wakeups:      uint,
+    renderNs:     u64,
+    maxRenderNs:  u64,
+    frameBytes:   uint,
+    maxBytes:     uint,
+    driftMs:      i64,
+    maxDriftMs:   i64
+  }
+  
+  pub fn new(now: u64) -> DebugOverlay {
+    DebugOverlay {
+      lastReportNs: now,
+      wakeups:      0,
+      renderNs:     0,
+      maxRenderNs:  0,
+      frameBytes:   0,
+      maxBytes:     0,
+      driftMs:      0,
+      maxDriftMs:   0
+    }
+  }
+  
+  impl DebugOverlay {
+    // poll returned, either because of input or because it timed out
+    pub fn woke_up(&mut self) {
+      self.wakeups += 1;
+    }
+    
+    // a step or input was handled and flushed to the terminal in renderNs nanoseconds
+    pub fn frame_rendered(&mut self, renderNs: u64) {
+      self.renderNs = renderNs;
+      if renderNs > self.maxRenderNs {
+        self.maxRenderNs = renderNs;
+      }
+      self.frameBytes = output::last_frame_bytes();
+      if self.frameBytes > self.maxBytes {
+        self.maxBytes = self.frameBytes;
+      }
+    }
+    
+    // a drop step happened lateNs after it was due
+    pub fn step_fired(&mut self, lateNs: u64) {
+      self.driftMs = (lateNs / 1000000) as i64;
+      if self.driftMs.abs() > self.maxDriftMs.abs() {
+        self.maxDriftMs = self.driftMs;
+      }
+    }
+    
+    // returns the overlay text once every report interval, and starts a new interval
+    pub fn report(&mut self, now: u64) -> Option<Vec<~str>> {
+      let elapsedNs = now - self.lastReportNs;
+      if elapsedNs < reportIntervalNs {
+        return None;
+      }
+      let wakeupsPerSec = (self.wakeups as u64) * 1000000000 / elapsedNs;
+      let lines = vec!(format!("render: {}us ({}us max)", self.renderNs / 1000, self.maxRenderNs / 1000),
+                       format!("bytes:  {} ({} max)", self.frameBytes, self.maxBytes),
+                       format!("polls:  {}/s", wakeupsPerSec),
+                       format!("drift:  {}ms ({}ms max)", self.driftMs, self.maxDriftMs));
+      self.lastReportNs = now;
+      self.wakeups = 0;
+      self.maxRenderNs = 0;
+      self.maxBytes = 0;
+      Some(lines)
+    }
+  }
+}
+
+mod stats_hud {
+  use std::vec_ng::Vec;
+  use pieces::{Piece, I};
+  use scoring::Score;
+  use score_keeper::format_duration;
+  use strings;
+  
+  // nanoseconds between refreshes of the HUD, so the time and pieces per second keep up
+  pub static refreshNs: u64 = 1000000000;
+  
+  // Counts what the player does for the statistics HUD, toggled during a game: pieces
+  // per second, keys pressed per piece, the tetris rate, how many pieces it's been since
+  // the last I piece and the time played
+  pub struct StatsHud {
+    shown:       bool,
+    refreshedNs: u64,
+    pieces:      uint,  // pieces set
+    keys:        uint,  // moves made, hints and quitting don't count
+    drought:     uint,  // pieces since the last I piece
+    maxDrought:  uint
+  }
+  
+  pub fn new() -> StatsHud {
+    StatsHud{shown: false, refreshedNs: 0, pieces: 0, keys: 0, drought: 0, maxDrought: 0}
+  }
+  
+  // the number of lines the HUD takes up
+  pub fn rows() -> uint {
+    strings::get().hudLabels.len()
+  }
+  
+  impl StatsHud {
+    pub fn key_pressed(&mut self) {
+      self.keys += 1;
+    }
+    
+    pub fn piece_set(&mut self) {
+      self.pieces += 1;
+    }
+    
+    // the piece that just came in to fall
+    pub fn piece_dealt(&mut self, piece: &Piece) {
+      if piece.ty == I {
+        self.drought = 0;
+      } else {
+        self.drought += 1;
+        if self.drought > self.maxDrought {
+          self.maxDrought = self.drought;
+        }
+      }
+    }
+    
+    pub fn lines(&self, score: Score, elapsedMs: u64) -> Vec<~str> {
+      let labels = strings::get().hudLabels;
+      let perSecond = if elapsedMs == 0 { 0.0 } else { self.pieces as f64 * 1000.0 / elapsedMs as f64 };
+      let perPiece = if self.pieces == 0 { 0.0 } else { self.keys as f64 / self.pieces as f64 };
+      let tetrisRate = if score.lines == 0 { 0 } else { score.tetrises * 400 / score.lines };
+      vec!(format!("{} {:.2f}", labels[0], perSecond),
+           format!("{} {:.1f}", labels[1], perPiece),
+           format!("{} {}", labels[2], tetrisRate),
+           format!("{} {} ({})", labels[3], self.drought, self.maxDrought),
+           format!("{} {}", labels[4], format_duration(elapsedMs)))
+    }
+  }
+}
+
+// Keeps an eye on the config file while a game is played, so a theme can be worked on
+// without starting a new game for every change. The file's modification time is looked
+// at every checkNs, and the file is only read again once that's changed.
+mod theme_watch {
+  use std::io::fs;
+  use config;
+  use trace::Log;
+  
+  pub static checkNs: u64 = 1000000000;
+  
+  pub struct ThemeWatch {
+    path:      Path,
+    modified:  u64,       // the file's modification time when it was last read, 0 if it wasn't there
+    theme:     [u8, ..8], // the theme being played with
+    checkedNs: u64
+  }
+  
+  pub fn new(path: Path, theme: [u8, ..8], nowNs: u64) -> ThemeWatch {
+    let modified = modified(&path);
+    ThemeWatch{path: path, modified: modified, theme: theme, checkedNs: nowNs}
+  }
+  
+  fn modified(path: &Path) -> u64 {
+    fs::stat(path).map_or(0, |stat| stat.modified)
+  }
+  
+  impl ThemeWatch {
+    // The theme in the config file, if the file has changed and its theme is different.
+    // A file that doesn't load (it may be half saved) is left until it changes again.
+    pub fn changed_theme(&mut self, nowNs: u64, log: Log) -> Option<[u8, ..8]> {
+      self.checkedNs = nowNs;
+      let modified = modified(&self.path);
+      if modified == self.modified {
+        return None;
+      }
+      self.modified = modified;
+      match config::load(&self.path) {
+        Ok(config) if config.theme.as_slice() != self.theme.as_slice() => {
+          self.theme = config.theme;
+          Some(config.theme)
+        }
+        Ok(_)    => None,
+        Err(msg) => {
+          log.log(format!("theme not reloaded, {}: {}", self.path.display(), msg));
+          None
+        }
+      }
+    }
+  }
+}
+
+mod timers {
+  use std::vec_ng::Vec;
+  
+  // Things in a game that happen on their own schedule rather than on input. Timers that
+  // belong to one player's game carry the player's number (always 0 unless two are playing).
+  #[deriving(Eq)]
+  pub enum Timer {
+    DropStep(uint),   // the player's next drop step
+    HintShown(uint),  // the player's hint has been on the board long enough
+    TrailShown(uint), // the trail of the player's hard drop has been shown
+    TitleClock,       // time to update the window title
+    StatsClock,       // time to update the statistics HUD, while it's shown
+    ThemeCheck,       // time to look for a new theme in the config file
+    IdlePause,        // no key has been pressed for long enough to pause the game
+    Countdown,        // the next second of the countdown before a game carries on
+    NextMove          // the demo, a replay, the crowd or a computer or network opponent has its next move to make
+  }
+  
+  // When each running timer is next due, on the monotonic clock (time::precise_time_ns).
+  // The main loop asks the handler for its timers every time round, sleeps until the
+  // soonest one is due and then hands it to the handler.
+  pub struct Timers {
+    due: Vec<(Timer, u64)>
+  }
+  
+  pub fn new() -> Timers {
+    Timers{due: Vec::new()}
+  }
+  
+  impl Timers {
+    pub fn set(&mut self, timer: Timer, dueNs: u64) {
+      self.due.push((timer, dueNs));
+    }
+    
+    pub fn clear(&mut self) {
+      self.due.clear();
+    }
+    
+    // the timer due soonest, and when
+    pub fn next(&self) -> Option<(Timer, u64)> {
+      let mut next: Option<(Timer, u64)> = None;
+      for &(timer, dueNs) in self.due.iter() {
+        match next {
+          Some((_, nextNs)) if nextNs <= dueNs => (),
+          _                                    => next = Some((timer, dueNs))
+        }
+      }
+      next
+    }
+  }
+  
+  // The next deadline of a timer that repeats every intervalNs. It follows on from the one
+  // that just fired so it doesn't drift, unless that's fallen too far behind to catch up.
+  pub fn following(dueNs: u64, intervalNs: u64, nowNs: u64) -> u64 {
+    if dueNs + intervalNs < nowNs {
+      nowNs + intervalNs
+    } else {
+      dueNs + intervalNs
+    }
+  }
+}
+
+mod tetris {
+  use time;
+  use std::cmp;
+  use std::io;
+  use std::mem;
+  use std::os;
+  use std::io::timer;
+  use std::comm::Sender;
+  use std::vec_ng::Vec;
+  use std::libc::c_int;
+  use std::cell::RefCell;
+  
+  use terminal_control;
+  use input_reader;
+  use pieces;
+  use pieces::{Block, Piece, Gray};
+  use graphics::Display;
+  use piece_getter;
+  use piece_getter::PieceGetter;
+  use scoring;
+  use scoring::{Scoring, ScoreEvent, Score};
+  use gravity;
+  use gravity::Gravity;
+  use score_keeper;
+  use score_keeper::{ScoreKeeper, ScoreEntry};
+  use board;
+  use board::Board;
+  use engine;
+  use engine::{GameEngine, Move, RotateClockwise, ShiftLeft, ShiftRight, HardDrop, Fall, Finished};
+  use engine::{GameEvent, PieceMoved, PieceLocked, NextPieceChanged, RowsCleared, BoardChanged};
+  use engine::{LevelUp, ScoreChanged, GameOver};
+  use output;
+  use output::FrameSink;
+  use cast_recorder;
+  use frame_capture;
+  use led_matrix;
+  use spectator;
+  use debug_overlay;
+  use debug_overlay::DebugOverlay;
+  use stats_hud;
+  use stats_hud::StatsHud;
+  use theme_watch;
+  use theme_watch::ThemeWatch;
+  use sound_hooks;
+  use sound_hooks::SoundHooks;
+  use announcer;
+  use announcer::Announcer;
+  use commentary;
+  use commentary::Commentator;
+  use relay;
+  use crowd;
+  use curses;
+  use trace;
+  use timers;
+  use timers::{Timer, Timers, DropStep, HintShown, TrailShown, TitleClock, StatsClock, ThemeCheck, IdlePause,
+               Countdown, NextMove};
+  use config;
+  use config::Config;
+  use serialize::json;
+  use modes;
+  use modes::{Mode, Endless, NesClassic, Sprint, Ultra, Pressure, Marathon, GarbageRules};
+  use paths;
+  use remote_scores;
+  use saved_game;
+  use saved_game::SavedGame;
+  use replay;
+  use replay::{Replay, ReplayRecorder, Checkpoint, GravityStep, PlayerAction};
+  use frame_export;
+  use graphics;
+  use ai;
+  use bot;
+  use bot::Bot;
+  use input_reader::Action;
+  use rand;
+  use rand::{Rng, SeedableRng, Isaac64Rng};
+  use strings;
+  use table;
+  
+  // settings chosen on the command line that affect how a game is run
+  pub struct Options {
+    debugOverlay: bool,
+    preview:      bool,         // show the next piece, --no-preview hides it for a harder game
+    sandbox:      bool,         // the player picks the pieces, and the game isn't kept
+    stepping:     bool,         // pieces only fall when the player asks for a step
+    castFile:     Option<~str>,
+    captureFile:  Option<Path>, // where to mirror the board after every frame
+    ledFile:      Option<Path>, // serial device or UNIX socket of an LED matrix to send the board to
+    scoring:      scoring::ScoringKind,
+    gravity:      Option<gravity::GravityKind>,
+    speed:        f64,          // multiplies how fast pieces fall, on top of the gravity
+    spawnDelay:   c_int,        // milliseconds before the next piece comes in after one is set
+    mode:         Mode,
+    scoreFile:    Path,
+    resume:       bool,        // carry on with the game saved by a hangup
+    seed:         Option<u64>, // seed for the piece sequence, a random one is picked if not given
+    bestOf:       uint,        // two players: games in the series, the first to win most of them wins
+    vsAi:         Option<ai::Difficulty>, // the computer plays the second game of a two player game
+    replayFile:   Option<Path>, // where to record the replay, instead of the last game's replay file
+    ghostFile:    Option<Path>, // replay to race against
+    spectatePort: Option<u16>,  // serve the game to spectators on this port
+    crowdFile:    Option<Path>, // named pipe to read the crowd's votes from
+    curses:       bool,         // draw with curses instead of escape sequences
+    logFile:      Option<Path>, // where to write the debug log
+    log:          trace::Log,   // the debug log once it's started, trace::off until then
+    announceFile: Option<Path>  // where to write the game's events as text, for a screen reader
+  }
+  
+  impl Options {
+    // the defaults, from the config file
+    pub fn new(config: &Config) -> Options {
+      Options {
+        debugOverlay: config.debugOverlay,
+        preview:      config.preview,
+        sandbox:      false,
+        stepping:     false,
+        castFile:     None,
+        captureFile:  None,
+        ledFile:      None,
+        scoring:      config.scoring.unwrap_or(scoring::Standard),
+        gravity:      config.gravity,
+        speed:        1.0,
+        spawnDelay:   config.spawnDelay,
+        mode:         config.mode.unwrap_or(Endless),
+        scoreFile:    paths::scores_file(),
+        resume:       false,
+        seed:         None,
+        bestOf:       1,
+        vsAi:         None,
+        replayFile:   None,
+        ghostFile:    None,
+        spectatePort: None,
+        crowdFile:    None,
+        curses:       config.curses,
+        logFile:      None,
+        log:          trace::off,
+        announceFile: None
+      }
+    }
+  }
+  
+  trait GameHandler {
+    fn init(&self);
+    
+    // Adds the timers that are running and when they're due. The loop asks again every time
+    // it wakes up, so the handler keeps the deadlines itself.
+    fn set_timers(&self, timers: &mut Timers);
+    
+    // one of the handler's timers is due, returns false to stop the loop
+    fn handle_timer(&mut self, timer: Timer) -> bool;
+    
+    // Returns false if the event was used up by something other than its action (like the key
+    // that ends an idle pause), so a key read as Quit there doesn't end the game
+    fn handle_input(&mut self, event: input_reader::ActionEvent) -> bool;
+    fn handle_quit(&self);
+    fn handle_hangup(&self);
+    fn handle_debug(&self, lines: &[~str]);
+    
+    // the loop stops once this is true
+    fn is_finished(&self) -> bool {
+      false
+    }
+    
+    fn read_input(&self) -> Vec<input_reader::ActionEvent>;
+  }
+
+  // Hears about everything that happens in a game: the input that drives it, and the
+  // events the engine sends back. The game draws the display itself, anything else
+  // that wants to know (the replay recorder, sounds) listens in.
+  trait GameListener {
+    fn on_step(&mut self, _timeNs: u64) {
+    }
+    
+    fn on_input(&mut self, _event: &input_reader::ActionEvent) {
+    }
+    
+    fn on_event(&mut self, _event: &GameEvent) {
+    }
+    
+    // a drop step set a piece, the engine is as it is after the step
+    fn on_piece_set(&mut self, _timeNs: u64, _engine: &GameEngine) {
+    }
+    
+    // the game was resumed after being paused for pausedNs
+    fn on_resume(&mut self, _pausedNs: u64) {
+    }
+  }
+  
+  impl GameListener for ReplayRecorder {
+    fn on_step(&mut self, timeNs: u64) {
+      self.record_step(timeNs);
+    }
+    
+    fn on_piece_set(&mut self, timeNs: u64, engine: &GameEngine) {
+      self.record_checkpoint(timeNs, engine);
+    }
+    
+    fn on_input(&mut self, event: &input_reader::ActionEvent) {
+      self.record_action(event.timeNs, event.action);
+    }
+    
+    fn on_resume(&mut self, pausedNs: u64) {
+      self.record_pause(pausedNs);
+    }
+  }
+  
+  impl GameListener for SoundHooks {
+    fn on_event(&mut self, event: &GameEvent) {
+      match *event {
+        PieceLocked(_)                            => sound_hooks::play(&self.lock),
+        RowsCleared(ref rows) if rows.len() == 4  => sound_hooks::play(&self.tetris),
+        RowsCleared(_)                            => sound_hooks::play(&self.clear),
+        LevelUp(_)                                => sound_hooks::play(&self.levelUp),
+        GameOver                                  => sound_hooks::play(&self.gameOver),
+        _                                         => ()
+      }
+    }
+  }
+  
+  impl GameListener for Announcer {
+    fn on_event(&mut self, event: &GameEvent) {
+      self.announce(event);
+    }
+  }
+  
+  impl GameListener for Commentator {
+    fn on_event(&mut self, event: &GameEvent) {
+      match *event {
+        RowsCleared(ref rows) => self.rows_cleared(rows.len()),
+        _                     => ()
+      }
+    }
+    
+    fn on_piece_set(&mut self, timeNs: u64, engine: &GameEngine) {
+      self.piece_set(timeNs, engine);
+    }
+  }
+
+  struct TetrisGame<'a> {
+    display:     &'a Display,
+    otherDisplay: Option<&'a Display>, // the display the pause menu can switch to, if it can
+    engine:      GameEngine,
+    scoreKeeper: &'a ScoreKeeper,
+    lastTitleNs: u64,
+    mode:        Mode,
+    startNs:     u64,
+    garbageMs:   u64,  // when the last pressure garbage row came up, in milliseconds played
+    endHeading:  &'static str,
+    seed:        u64,
+    garbageRng:  Isaac64Rng,
+    listeners:   Vec<~GameListener>,
+    hint:        Option<Piece>,  // where the AI would put the falling piece, while it's shown
+    hintUntilNs: u64,
+    trail:       Vec<Block>,     // outlined cells the last hard dropped piece fell through, while they're shown
+    trailUntilNs: u64,
+    stepDueNs:   u64,            // when the next drop step is due
+    player:      uint,           // which player's game this is, for its timers
+    outgoingGarbage: int,        // garbage rows earned by clearing rows, for a two player game
+    pendingGarbage:  int,        // garbage rows sent by the other player, not added yet
+    preview:     bool,           // whether the next piece is shown
+    stats:       StatsHud,
+    scoreLog:    Option<Vec<~str>>, // the last few things that scored, newest first, unless it's not shown
+    sandbox:     bool,           // pieces are picked by the player, nothing is kept
+    stepping:    bool,           // gravity waits for the player to ask for each step
+    goalLeft:    Option<uint>,   // the sprint rows left to clear, as last shown
+    pausedAtNs:  Option<u64>,    // when the game was paused, while it is
+    menuItem:    uint,           // the setting picked in the pause menu
+    themeWatch:  Option<ThemeWatch>, // the config file, while its theme is picked up mid-game
+    idlePauseNs: Option<u64>,    // how long without a key pressed before the game pauses itself, if it does
+    lastInputNs: u64,            // when the last key was pressed
+    idle:        bool,           // paused because it was left alone, any key carries on
+    focusPaused: bool,           // paused because the terminal lost focus, it carries on when it's back
+    countdownLeft: uint,         // seconds before the game carries on, while counting down
+    countdownNs: u64,            // when the next second of the countdown is up
+    marathonLevel: u16,          // the level that wins a marathon game
+    garbage:     GarbageRules,   // what's sent to the other player, from the config or an opponent over the network
+    aiWeights:   ai::Weights,    // what the hint and the demo play with, from the config
+    keys:        input_reader::Keys, // keys to use as well as the arrow keys, from the config
+    log:         trace::Log      // the debug log, trace::off without one
+  }
+  
+  // nanoseconds between terminal title updates
+  static titleIntervalNs: u64 = 3000000000;
+  
+  // nanoseconds before the first drop step of a game
+  static firstStepNs: u64 = 1000000000;
+  
+  // nanoseconds a hint stays on the board
+  static hintShownNs: u64 = 1500000000;
+  
+  // nanoseconds the trail of a hard drop stays on the board, about a frame
+  static trailShownNs: u64 = 50000000;
+  
+  // lines kept in the score log
+  static scoreLogLines: uint = 4;
+  
+  // seconds counted down before a game paused by losing focus carries on
+  static countdownSeconds: uint = 3;
+  static countdownStepNs: u64 = 1000000000;
+  
+  // The pause menu has the next piece preview, the spawn delay, which goes up and down
+  // by spawnDelayStep milliseconds, and the display when there's another one to switch to.
+  static spawnDelayStep: int = 50;
+  
+  impl<'a> TetrisGame<'a> {  
+    fn erase_row(&self, row: i8) {
+      for col in range(1, 11i8) {
+        self.display.erase_block(row, col);
+      }
+    }
+    
+    fn erase_all_set_blocks(&self, board: &Board) {
+      for block in board.blocks().iter() {
+        self.display.erase_block(block.row, block.column);
+      }
+    }
+
+    fn print_set_blocks(&self) {
+      for block in self.engine.board.blocks().iter() {
+        self.display.print_block(*block);
+      }
+      self.display.flush();
+    }
+    
+    // Draws the falling piece where it was moved to. Where it was is erased, apart
+    // from any cells the set blocks have since moved into.
+    fn redraw_piece(&self, from: &Piece, to: &Piece) {
+      for block in from.blocks.iter() {
+        if !self.engine.board.has_block(block.row, block.column) {
+          self.display.erase_block(block.row, block.column);
+        }
+      }
+      
+      self.print_hint();
+      self.display.print_piece(to);
+    }
+    
+    // shows what the engine did
+    fn render(&mut self, event: &GameEvent) {
+      match *event {
+        PieceMoved(ref from, ref to)        => self.redraw_piece(from, to),
+        PieceLocked(_)                      => self.clear_hint(),
+        NextPieceChanged(ref old, ref next) if self.preview => {
+          self.display.erase_next_piece(old);
+          self.display.print_next_piece(next);
+        }
+        RowsCleared(ref rows)               => {
+          for row in rows.iter() {
+            self.erase_row(*row);
+          }
+          self.outgoingGarbage += self.garbage.sent[rows.len() - 1];
+        }
+        BoardChanged(ref previous)          => {
+          self.clear_hint();
+          self.erase_all_set_blocks(previous);
+          self.print_set_blocks();
+        }
+        ScoreChanged(ref scoreEvent)        => {
+          self.display.print_score_event(scoreEvent);
+          self.log_score(scoreEvent);
+          let goalLeft = self.sprint_rows_left();
+          if goalLeft != self.goalLeft {
+            self.print_goal(self.goalLeft);
+            self.goalLeft = goalLeft;
+          }
+        }
+        LevelUp(_)                          => self.print_speed(),
+        // the game over screen is shown on the next step
+        NextPieceChanged(..) | GameOver     => ()
+      }
+    }
+    
+    // passes what the engine did on to the display, then the listeners
+    fn publish(&mut self, events: Vec<GameEvent>) {
+      for event in events.iter() {
+        if self.log.is_on() {
+          self.log.log(describe_event(event));
+        }
+        self.render(event);
+        self.count_stats(event);
+        for listener in self.listeners.mut_iter() {
+          listener.on_event(event);
+        }
+      }
+    }
+    
+    // Outlines where the AI would put the falling piece. Asking for a hint costs the bonus.
+    fn show_hint(&mut self) {
+      match ai::best_placement(&self.engine.board, &self.engine.piece, &self.aiWeights) {
+        Some(placement) => {
+          self.clear_hint();
+          self.hint = Some(placement.piece);
+          self.hintUntilNs = time::precise_time_ns() + hintShownNs;
+          self.print_hint();
+          self.display.print_piece(&self.engine.piece);
+          
+          let score = self.engine.scoring.use_hint();
+          self.display.print_score(score);
+        }
+        None => ()
+      }
+    }
+    
+    // which cells of each row are set or under the falling piece, for drawing the board
+    // in miniature
+    fn row_masks(&self) -> [u16, ..20] {
+      let mut rows = [0u16, ..20];
+      for row in range(1, board::height + 1) {
+        rows[(row - 1) as uint] = self.engine.board.row_mask(row);
+      }
+      for block in self.engine.piece.blocks.iter() {
+        if board::in_bounds(block.row, block.column) {
+          rows[(block.row - 1) as uint] |= 1 << (block.column - 1) as uint;
+        }
+      }
+      rows
+    }
+    
+    // the rows left to clear in a sprint game, None in the other modes
+    fn sprint_rows_left(&self) -> Option<uint> {
+      if self.mode != Sprint {
+        return None;
+      }
+      let lines = self.engine.scoring.get_score().lines;
+      Some(if lines >= modes::sprintLines { 0 } else { (modes::sprintLines - lines) as uint })
+    }
+    
+    // Shows the rows left to clear in a sprint. The empty cells are redrawn when the count
+    // drops under goalTintRows, since they're drawn differently from then on.
+    fn print_goal(&self, previous: Option<uint>) {
+      let left = match self.sprint_rows_left() {
+        Some(left) => left,
+        None       => return
+      };
+      self.display.print_goal(left);
+      let tinted = |rows: uint| rows < graphics::goalTintRows;
+      if tinted(left) && !previous.map_or(false, |rows| tinted(rows)) {
+        for row in range(1, board::height + 1) {
+          for col in range(1, board::width + 1) {
+            let underPiece = self.engine.piece.blocks.iter().any(|b| b.row == row && b.column == col);
+            if !underPiece && !self.engine.board.has_block(row, col) {
+              self.display.erase_block(row, col);
+            }
+          }
+        }
+        self.print_hint();
+      }
+    }
+    
+    // the time a piece takes to fall a row, which can be under a frame (and is 0 with 20G)
+    fn row_time(&self, level: u16) -> i32 {
+      (1000.0 / self.engine.gravity.rows_per_second(level)).round() as i32
+    }
+    
+    fn print_speed(&self) {
+      let level = self.engine.scoring.get_score().level;
+      self.display.print_speed(self.row_time(level), self.row_time(level + 1));
+    }
+    
+    fn print_hint(&self) {
+      match self.hint {
+        Some(ref hint) => self.display.print_piece_outline(hint),
+        None           => ()
+      }
+    }
+    
+    // erases the hint outline, leaving any blocks that have since moved into its cells
+    fn clear_hint(&mut self) {
+      match self.hint {
+        Some(hint) => {
+          for block in hint.blocks.iter() {
+            if !self.engine.board.has_block(block.row, block.column) {
+              self.display.erase_block(block.row, block.column);
+            }
+          }
+          self.display.print_piece(&self.engine.piece);
+        }
+        None => ()
+      }
+      self.hint = None;
+    }
+    
+    fn step(&mut self) -> Option<c_int> {
+      let events = self.engine.step();
+      self.publish(events);
+      Some(self.engine.stepTime)
+    }
+    
+    fn elapsed_ms(&self) -> u64 {
+      self.played_ms(time::precise_time_ns())
+    }
+    
+    // milliseconds played up to timeNs, the time of a step or input: going by those rather
+    // than the clock, a replay ends the game at the same step
+    fn played_ms(&self, timeNs: u64) -> u64 {
+      if timeNs > self.startNs { (timeNs - self.startNs) / 1000000 } else { 0 }
+    }
+    
+    fn end_game(&mut self, heading: &'static str) {
+      self.endHeading = heading;
+      let events = self.engine.end();
+      self.publish(events);
+    }
+    
+    // Sprint games end once enough lines are cleared, marathon games once the last level
+    // is reached, ultra games end when the time runs out, and pressure games push up a
+    // garbage row every so often
+    fn check_mode(&mut self, nowNs: u64) {
+      match (self.mode, self.engine.state) {
+        (Sprint, Fall) if self.engine.scoring.get_score().lines >= modes::sprintLines => self.end_game(strings::get().finished),
+        (Marathon, Fall) if self.engine.scoring.get_score().level >= self.marathonLevel => {
+          self.end_game(strings::get().victory)
+        }
+        (Ultra, _) if self.played_ms(nowNs) >= modes::ultraTimeMs => self.end_game(strings::get().timeUp),
+        (Pressure, Fall) => {
+          let playedMs = self.played_ms(nowNs);
+          if playedMs >= self.garbageMs + modes::pressureIntervalMs {
+            self.garbageMs = playedMs;
+            self.add_garbage_row();
+          }
+        }
+        _ => ()
+      }
+    }
+    
+    // pushes the set blocks up one row and fills the bottom row with garbage, leaving one gap
+    fn add_garbage_row(&mut self) {
+      let gap = (self.garbageRng.gen::<uint>() % 10) as i8 + 1;
+      let events = self.engine.add_garbage_row(gap);
+      self.publish(events);
+    }
+    
+    // rows the player sends take away garbage still waiting to come in, before any is sent on
+    fn cancel_garbage(&mut self) {
+      let cancelled = cmp::min(self.outgoingGarbage, self.pendingGarbage);
+      self.outgoingGarbage -= cancelled;
+      self.pendingGarbage -= cancelled;
+    }
+    
+    // adds the garbage rows sent by the other player, unless rows are still being cleared
+    fn receive_garbage(&mut self) {
+      while self.pendingGarbage > 0 {
+        match self.engine.state {
+          Fall => (),
+          _    => return
+        }
+        self.pendingGarbage -= 1;
+        self.add_garbage_row();
+      }
+    }
+    
+    // adds the rows cleared and any level up to the score log (points for drops alone are left out)
+    fn log_score(&mut self, event: &ScoreEvent) {
+      let log = match self.scoreLog {
+        Some(ref mut log) => log,
+        None              => return
+      };
+      let text = strings::get();
+      if event.rows > 0 {
+        let points = event.points.to_str();
+        let line = if event.rows == 4 { text.logTetris } else { text.logPoints };
+        log.insert(0, strings::fill(line, &[points.as_slice()]));
+      }
+      if event.levelUp {
+        log.insert(0, strings::fill(text.logLevel, &[event.score.level.to_str().as_slice()]));
+      }
+      log.truncate(scoreLogLines);
+      self.display.print_score_log(log.as_slice());
+    }
+    
+    fn count_stats(&mut self, event: &GameEvent) {
+      match *event {
+        PieceLocked(_)               => {
+          self.stats.piece_set();
+          if self.stats.shown {
+            self.print_stats();
+          }
+        }
+        NextPieceChanged(ref old, _) => self.stats.piece_dealt(old),
+        _                            => ()
+      }
+    }
+    
+    fn print_stats(&mut self) {
+      let lines = self.stats.lines(self.engine.scoring.get_score(), self.elapsed_ms());
+      self.display.print_stats(stats_hud::rows(), lines.as_slice());
+      self.stats.refreshedNs = time::precise_time_ns();
+    }
+    
+    // Pausing stops the game's clock: the next drop step, the time played and the mode's
+    // timers carry on from where they were once it's resumed.
+    fn toggle_pause(&mut self) {
+      match self.pausedAtNs {
+        Some(pausedAtNs) => {
+          let pausedNs = time::precise_time_ns() - pausedAtNs;
+          self.pausedAtNs = None;
+          self.startNs += pausedNs;
+          self.stepDueNs += pausedNs;
+          for listener in self.listeners.mut_iter() {
+            listener.on_resume(pausedNs);
+          }
+          if self.idle {
+            self.idle = false;
+            self.display.set_dimmed(false);
+          }
+          self.focusPaused = false;
+          self.countdownLeft = 0;
+          self.redraw_board();
+        }
+        None if self.engine.state != Finished => {
+          self.clear_hint();
+          self.clear_trail();
+          self.pausedAtNs = Some(time::precise_time_ns());
+          self.print_pause_menu();
+        }
+        None => ()
+      }
+    }
+    
+    // Pauses a game that's been left alone, with the board dimmed under a box that says
+    // any key carries on. There's nothing to pause once the game is over.
+    fn idle_pause(&mut self) {
+      self.toggle_pause();
+      if self.pausedAtNs.is_none() {
+        return;
+      }
+      self.idle = true;
+      self.display.set_dimmed(true);
+      self.redraw_board();
+      self.print_pause_menu();
+    }
+    
+    // Pauses the game when the terminal loses focus, and counts down to carrying on when
+    // it gets it back. A game paused any other way stays paused, and losing focus again
+    // during the countdown stops it.
+    fn focus_changed(&mut self, gained: bool) {
+      match (gained, self.pausedAtNs) {
+        (false, None) => {
+          self.toggle_pause();
+          self.focusPaused = self.pausedAtNs.is_some();
+        }
+        (false, Some(_)) if self.countdownLeft > 0 => {
+          self.countdownLeft = 0;
+          self.print_pause_menu();
+        }
+        (true, Some(_)) if self.focusPaused => {
+          self.countdownLeft = countdownSeconds;
+          self.countdownNs = time::precise_time_ns() + countdownStepNs;
+          self.print_pause_menu();
+        }
+        _ => ()
+      }
+    }
+    
+    fn count_down(&mut self) {
+      self.countdownLeft -= 1;
+      if self.countdownLeft == 0 {
+        self.toggle_pause();
+      } else {
+        self.countdownNs += countdownStepNs;
+        self.print_pause_menu();
+      }
+    }
+    
+    fn print_pause_menu(&self) {
+      let text = strings::get();
+      if self.idle {
+        return self.display.print_pause_menu(text.paused, &[], 0, text.idleHelp);
+      }
+      if self.countdownLeft > 0 {
+        let help = strings::fill(text.resumingIn, &[self.countdownLeft.to_str().as_slice()]);
+        return self.display.print_pause_menu(text.paused, &[], 0, help.as_slice());
+      }
+      let preview = if self.preview { text.on } else { text.off };
+      let mut items = vec!((text.nextPiece, preview.to_owned()),
+                           (text.spawnDelay, format!("{}ms", self.engine.spawnDelay)));
+      if self.otherDisplay.is_some() {
+        let (width, _) = self.display.size();
+        let (otherWidth, _) = self.otherDisplay.unwrap().size();
+        items.push((text.displayMode, (if width > otherWidth { text.double } else { text.standard }).to_owned()));
+      }
+      self.display.print_pause_menu(text.paused, items.as_slice(), self.menuItem, text.pauseHelp);
+    }
+    
+    fn pause_menu_items(&self) -> uint {
+      if self.otherDisplay.is_some() { 3 } else { 2 }
+    }
+    
+    // up and down pick a setting, left and right change it
+    fn pause_menu_input(&mut self, action: Action) {
+      use input_reader::{Rotate, Drop, MoveRight, MoveLeft};
+      match action {
+        Rotate    => self.menuItem = (self.menuItem + self.pause_menu_items() - 1) % self.pause_menu_items(),
+        Drop      => self.menuItem = (self.menuItem + 1) % self.pause_menu_items(),
+        MoveLeft  => self.change_setting(-1),
+        MoveRight => self.change_setting(1),
+        _         => ()
+      }
+      self.print_pause_menu();
+    }
+    
+    // Changes the setting picked in the pause menu, straight away in this game and in the
+    // config file for the next ones. If the file can't be written, only this game has it.
+    fn change_setting(&mut self, direction: int) {
+      let path = paths::config_file();
+      let saved = match self.menuItem {
+        0 => {
+          self.preview = !self.preview;
+          if self.preview {
+            self.display.print_next_piece(&self.engine.nextPiece);
+          } else {
+            self.display.erase_next_piece(&self.engine.nextPiece);
+          }
+          config::save_setting(&path, "preview", json::Boolean(self.preview))
+        }
+        1 => {
+          let delay = self.engine.spawnDelay as int + direction * spawnDelayStep;
+          self.engine.spawnDelay = cmp::max(0, cmp::min(delay, engine::maxSpawnDelay as int)) as c_int;
+          config::save_setting(&path, "spawnDelay", json::Number(self.engine.spawnDelay as f64))
+        }
+        _ => {
+          let double = self.switch_display();
+          config::save_setting(&path, "display", json::String(if double { ~"double" } else { ~"standard" }))
+        }
+      };
+      match saved {
+        Ok(())   => (),
+        Err(msg) => self.log.log(format!("can't save the setting to {}: {}", path.display(), msg))
+      }
+    }
+    
+    // Swaps the display for the other one and draws the whole game on it, returning
+    // whether it's the double display now (the wider of the two).
+    fn switch_display(&mut self) -> bool {
+      let other = self.otherDisplay.unwrap();
+      self.otherDisplay = Some(self.display);
+      self.display = other;
+      self.redraw_all();
+      let (width, _) = self.display.size();
+      let (otherWidth, _) = self.otherDisplay.unwrap().size();
+      width > otherWidth
+    }
+    
+    // Clears the terminal and draws everything again: the borders and labels, the stack,
+    // the falling piece, the next piece, the score and the HUDs that are shown.
+    fn redraw_all(&mut self) {
+      self.display.init();
+      self.init();
+      if self.engine.state == Fall {
+        self.display.print_piece(&self.engine.piece);
+      }
+      match self.scoreLog {
+        Some(ref log) if !log.is_empty() => self.display.print_score_log(log.as_slice()),
+        _                                => ()
+      }
+      if self.stats.shown {
+        self.print_stats();
+      }
+      self.print_hint();
+    }
+    
+    // everything is drawn again in the new colors when the theme in the config file changes
+    fn check_theme(&mut self) {
+      let theme = match self.themeWatch {
+        Some(ref mut watch) => watch.changed_theme(time::precise_time_ns(), self.log),
+        None                => None
+      };
+      match theme {
+        Some(theme) => {
+          self.display.set_theme(theme);
+          match self.otherDisplay {
+            Some(other) => other.set_theme(theme),
+            None        => ()
+          }
+          self.redraw_all();
+          if self.pausedAtNs.is_some() {
+            self.print_pause_menu();
+          }
+        }
+        None => ()
+      }
+    }
+    
+    // draws the whole board again, after something was drawn over it
+    fn redraw_board(&self) {
+      for row in range(1, 21i8) {
+        self.erase_row(row);
+      }
+      self.print_set_blocks();
+      if self.engine.state == Fall {
+        self.display.print_piece(&self.engine.piece);
+      }
+    }
+    
+    fn toggle_stats(&mut self) {
+      self.stats.shown = !self.stats.shown;
+      if self.stats.shown {
+        self.print_stats();
+      } else {
+        self.display.print_stats(stats_hud::rows(), &[]);
+      }
+    }
+    
+    fn update_title(&mut self) {
+      let now = time::precise_time_ns();
+      if now - self.lastTitleNs >= titleIntervalNs {
+        self.display.print_title(self.engine.scoring.get_score());
+        self.lastTitleNs = now;
+      }
+    }
+    
+    // sandbox games don't count, their scores and boards aren't kept
+    fn score_entry(&self) -> ScoreEntry {
+      ScoreEntry{time:     time::now(),
+                 score:    self.engine.scoring.get_score(),
+                 duration: self.elapsed_ms(),
+                 mode:     modes::name(self.mode).to_owned(),
+                 seed:     Some(self.seed),
+                 density:  self.engine.board_density()}
+    }
+    
+    fn store_score(&self) {
+      if self.sandbox {
+        return;
+      }
+      self.scoreKeeper.store_score(self.mode, self.score_entry());
+    }
+    
+    fn save_game(&self) {
+      if self.sandbox {
+        return;
+      }
+      let game = SavedGame{mode:      modes::name(self.mode).to_owned(),
+                           score:     self.engine.scoring.get_score(),
+                           count:     self.engine.scoring.get_count(),
+                           duration:  self.elapsed_ms(),
+                           piece:     saved_game::piece_name(&self.engine.piece),
+                           nextPiece: saved_game::piece_name(&self.engine.nextPiece),
+                           seed:      self.seed,
+                           board:     saved_game::encode_board(&self.engine.board)};
+      let _ = saved_game::save(&game);
+    }
+    
+    fn step_game_over(&mut self) -> Option<c_int> {
+      self.display.print_game_over(self.endHeading, self.engine.scoring.get_score(), self.elapsed_ms(), self.seed);
+      self.store_score();
+      None
+    }
+    
+    fn move_piece(&mut self, action: Move) {
+      let from = self.engine.piece;
+      let events = self.engine.apply_action(action);
+      self.publish(events);
+      if action == HardDrop {
+        self.show_trail(&from);
+      }
+    }
+    
+    // Outlines the cells a hard dropped piece fell through, in each of its columns from
+    // where it started to just above where it landed
+    fn show_trail(&mut self, from: &Piece) {
+      self.clear_trail();
+      let to = self.engine.piece;
+      let rows = to.blocks[0].row - from.blocks[0].row;
+      for block in from.blocks.iter() {
+        // only the top block of each column starts a streak
+        if from.blocks.iter().any(|other| other.column == block.column && other.row < block.row) {
+          continue;
+        }
+        for row in range(block.row, block.row + rows) {
+          if row >= 1 {
+            self.trail.push(Block{row: row, column: block.column, color: block.color, ty: None});
+          }
+        }
+      }
+      for block in self.trail.iter() {
+        self.display.print_outline(*block);
+      }
+      self.trailUntilNs = time::precise_time_ns() + trailShownNs;
+    }
+    
+    // erases the trail, leaving any blocks that have since moved into its cells
+    fn clear_trail(&mut self) {
+      for block in self.trail.iter() {
+        let underPiece = self.engine.piece.blocks.iter().any(|b| b.row == block.row && b.column == block.column);
+        if !underPiece && !self.engine.board.has_block(block.row, block.column) {
+          self.display.erase_block(block.row, block.column);
+        }
+      }
+      self.trail.clear();
+      self.print_hint();
+    }
+    
+    // the timers of the hint and the drop trail, while they're shown
+    fn set_shown_timers(&self, timers: &mut Timers) {
+      if self.hint.is_some() {
+        timers.set(HintShown(self.player), self.hintUntilNs);
+      }
+      if !self.trail.is_empty() {
+        timers.set(TrailShown(self.player), self.trailUntilNs);
+      }
+    }
+    
+    fn swap_piece(&mut self, ty: pieces::PieceType) {
+      self.clear_hint();
+      let events = self.engine.swap_piece(ty);
+      self.publish(events);
+    }
+    
+    fn clear_board(&mut self) {
+      let events = self.engine.clear_board();
+      self.publish(events);
+    }
+    
+    // Puts the game back as it was at a replay checkpoint. The pieces come from the seed,
+    // so the piece getter is made again and the pieces dealt so far are skipped.
+    fn restore_checkpoint(&mut self, checkpoint: &Checkpoint) {
+      let mut pieceGetter = piece_getter::new(self.seed);
+      for _ in range(0, checkpoint.pieces + 2) {
+        pieceGetter.next_piece();
+      }
+      self.engine.pieceGetter = pieceGetter;
+      self.engine.scoring.restore(checkpoint.score, checkpoint.count);
+      self.engine.state = checkpoint.state;
+      self.engine.stepTime = checkpoint.stepTime;
+      self.engine.piece = checkpoint.piece;
+      self.engine.nextPiece = checkpoint.nextPiece;
+      self.engine.board = checkpoint.board;
+      self.engine.pieces = checkpoint.pieces;
+      self.hint = None;
+      self.trail = Vec::new();
+      self.goalLeft = self.sprint_rows_left();
+    }
+  }
+
+  impl<'a> TetrisGame<'a> {
+    // The drop step at now (the clock, or the step's time in a replay): the piece falls, or
+    // the game moves on to its next state. Returns how long until the next step, or None
+    // once the game is over and has been shown.
+    fn step_gravity(&mut self, now: u64) -> Option<c_int> {
+      for listener in self.listeners.mut_iter() {
+        listener.on_step(now);
+      }
+      self.check_mode(now);
+      let before = self.engine.state;
+      let piecesBefore = self.engine.pieces;
+      let stepTime = match self.engine.state {
+        Finished => self.step_game_over(),
+        _        => self.step()
+      };
+      if self.engine.pieces != piecesBefore {
+        for listener in self.listeners.mut_iter() {
+          listener.on_piece_set(now, &self.engine);
+        }
+      }
+      if self.log.is_on() {
+        self.log.log(format!("step {:?} -> {:?}, next step in {:?} ms", before, self.engine.state, stepTime));
+      }
+      self.display.flush();
+      stepTime
+    }
+    
+    // a drop step asked for by the player while stepping through the game, the game over
+    // screen is left to the timer as usual
+    fn step_by_hand(&mut self) {
+      if self.engine.state == Finished {
+        return;
+      }
+      self.step_gravity(time::precise_time_ns());
+      self.stepDueNs = time::precise_time_ns();
+    }
+    
+    // writes everything the engine knows to the debug log, to go with a report of a rule bug
+    fn dump_state(&self) {
+      if !self.log.is_on() {
+        return;
+      }
+      let engine = &self.engine;
+      let score = engine.scoring.get_score();
+      self.log.log(format!("state {:?}, {} pieces set, next step in {} ms", engine.state, engine.pieces, engine.stepTime));
+      self.log.log(format!("piece {:?} rotation {} at {:?}", engine.piece.ty, engine.piece.rotate,
+                           engine.piece.blocks.iter().map(|b| (b.row, b.column)).collect::<Vec<(i8, i8)>>().as_slice()));
+      self.log.log(format!("next piece {:?}", engine.nextPiece.ty));
+      self.log.log(format!("level {}, bonus {}, score {}, lines {}, tetrises {}",
+                           score.level, score.bonus, score.score, score.lines, score.tetrises));
+      for line in saved_game::encode_board(&engine.board).iter() {
+        self.log.log(format!("board {}", *line));
+      }
+    }
+  }
+
+  impl<'a> GameHandler for TetrisGame<'a> {
+    fn init(&self) {
+      self.print_set_blocks();
+      if self.preview {
+        self.display.print_next_piece(&self.engine.nextPiece);
+      }
+      self.display.print_score(self.engine.scoring.get_score());
+      self.print_speed();
+      self.print_goal(None);
+      self.display.print_title(self.engine.scoring.get_score());
+      self.display.flush();
+    }
+    
+    // the window title is only kept by the first player's game, and when stepping through
+    // a game there are no drop steps until it's over
+    fn set_timers(&self, timers: &mut Timers) {
+      match self.themeWatch {
+        Some(ref watch) => timers.set(ThemeCheck, watch.checkedNs + theme_watch::checkNs),
+        None            => ()
+      }
+      if self.countdownLeft > 0 {
+        timers.set(Countdown, self.countdownNs);
+      }
+      if self.pausedAtNs.is_some() {
+        return;
+      }
+      match self.idlePauseNs {
+        Some(idleNs) if self.engine.state != Finished => timers.set(IdlePause, self.lastInputNs + idleNs),
+        _                                             => ()
+      }
+      if !self.stepping || self.engine.state == Finished {
+        timers.set(DropStep(self.player), self.stepDueNs);
+      }
+      self.set_shown_timers(timers);
+      if self.player == 0 {
+        timers.set(TitleClock, self.lastTitleNs + titleIntervalNs);
+      }
+      if self.player == 0 && self.stats.shown {
+        timers.set(StatsClock, self.stats.refreshedNs + stats_hud::refreshNs);
+      }
+    }
+    
+    fn handle_timer(&mut self, timer: Timer) -> bool {
+      match timer {
+        DropStep(_)   => match self.step_gravity(time::precise_time_ns()) {
+          Some(stepMs) => {
+            self.stepDueNs = timers::following(self.stepDueNs, stepMs as u64 * 1000000, time::precise_time_ns());
+          }
+          None => return false
+        },
+        HintShown(_)  => self.clear_hint(),
+        TrailShown(_) => self.clear_trail(),
+        TitleClock    => self.update_title(),
+        StatsClock    => self.print_stats(),
+        ThemeCheck    => self.check_theme(),
+        IdlePause     => self.idle_pause(),
+        Countdown     => self.count_down(),
+        NextMove      => ()
+      }
+      self.display.flush();
+      true
+    }
+    
+    fn handle_input(&mut self, event: input_reader::ActionEvent) -> bool {
+      use input_reader::{Rotate, Drop, MoveRight, MoveLeft, Hint, Stats, Spawn, ClearBoard, StepGame, DumpState, Pause,
+                         FocusLost, FocusGained, Quit};
+      match event.action {
+        FocusLost | FocusGained => {
+          self.focus_changed(event.action == FocusGained);
+          self.display.flush();
+          return true;
+        }
+        _ => ()
+      }
+      self.lastInputNs = time::precise_time_ns();
+      // the pause menu isn't part of the game, so listeners (like the replay) don't hear about it
+      match (event.action, self.pausedAtNs) {
+        // any key carries on, whatever it's for
+        (_, Some(_)) if self.idle => {
+          self.toggle_pause();
+          self.display.flush();
+          return false;
+        }
+        (Pause, _) => {
+          self.toggle_pause();
+          self.display.flush();
+          return true;
+        }
+        // The player's back, so the game stays paused until they say. The key that stops
+        // the countdown only brings up the menu.
+        (_, Some(_)) if self.focusPaused || self.countdownLeft > 0 => {
+          self.focusPaused = false;
+          self.countdownLeft = 0;
+          self.print_pause_menu();
+          self.display.flush();
+          return false;
+        }
+        (_, Some(_)) => {
+          self.pause_menu_input(event.action);
+          self.display.flush();
+          return true;
+        }
+        (_, None) => ()
+      }
+      for listener in self.listeners.mut_iter() {
+        listener.on_input(&event);
+      }
+      match event.action {
+        Rotate | Drop | MoveRight | MoveLeft => self.stats.key_pressed(),
+        _                                    => ()
+      }
+      match event.action {
+        Rotate    => self.move_piece(RotateClockwise),
+        Drop      => self.move_piece(HardDrop),
+        MoveRight => self.move_piece(ShiftRight),
+        MoveLeft  => self.move_piece(ShiftLeft),
+        Hint      => self.show_hint(),
+        Stats     => self.toggle_stats(),
+        Spawn(ty) if self.sandbox  => self.swap_piece(ty),
+        ClearBoard if self.sandbox => self.clear_board(),
+        Spawn(_) | ClearBoard      => (),
+        StepGame if self.stepping  => self.step_by_hand(),
+        DumpState if self.stepping => self.dump_state(),
+        StepGame | DumpState       => (),
+        Pause | FocusLost | FocusGained | Quit => ()
+      }
+      self.display.flush();
+      true
+    }
+    
+    fn handle_quit(&self) {
+      self.store_score();
+    }
+    
+    // The terminal is gone, so there's no one to show anything to. A game that's still
+    // going is saved so it can be picked up again with --resume, and its score is stored
+    // when it finally ends; a game that's over only has its score stored.
+    fn handle_hangup(&self) {
+      match self.engine.state {
+        Finished => self.store_score(),
+        _        => self.save_game()
+      }
+    }
+    
+    fn handle_debug(&self, lines: &[~str]) {
+      self.display.print_debug(lines);
+      self.display.flush();
+    }
+    
+    fn read_input(&self) -> Vec<input_reader::ActionEvent> {
+      input_reader::read_actions(&self.keys, self.sandbox, self.stepping, self.log)
+    }
+  }
+  
+
+  // what the debug log says about an event from the engine
+  fn describe_event(event: &GameEvent) -> ~str {
+    match *event {
+      PieceMoved(from, to)          => format!("piece moved from row {}, column {} to row {}, column {}",
+                                               from.blocks[0].row, from.blocks[0].column,
+                                               to.blocks[0].row, to.blocks[0].column),
+      PieceLocked(_)                => ~"piece locked",
+      NextPieceChanged(_, next)     => format!("next piece {:?}", next.ty),
+      RowsCleared(ref rows)         => format!("rows cleared {:?}", rows.as_slice()),
+      BoardChanged(_)               => ~"board changed",
+      LevelUp(level)                => format!("level up to {}", level),
+      ScoreChanged(scoreEvent)      => format!("score {} (+{})", scoreEvent.score.score, scoreEvent.points),
+      GameOver                      => ~"game over"
+    }
+  }
+  
+  // milliseconds from now until deadlineNs, rounded up so poll doesn't wake up just
+  // before it, and never negative (poll waits forever for a negative timeout)
+  fn ms_until(deadlineNs: u64, nowNs: u64) -> c_int {
+    if deadlineNs <= nowNs {
+      0
+    } else {
+      ((deadlineNs - nowNs + 999999) / 1000000) as c_int
+    }
+  }
+  
+  fn main_loop<T: GameHandler>(handler: &mut T, debug: &mut Option<DebugOverlay>, log: trace::Log) {
+    use input_reader::{poll_stdin, Quit, PollReady, PollTimeout, PollInterrupted};
+    
+    handler.init();
+    let mut timers = timers::new();
+    
+    loop {
+      timers.clear();
+      handler.set_timers(&mut timers);
+      let now = time::precise_time_ns();
+      let sleepMs = match (timers.next(), output::held_frame_due()) {
+        (Some((_, dueNs)), Some(frameNs)) if frameNs < dueNs => ms_until(frameNs, now),
+        (Some((_, dueNs)), _)                                => ms_until(dueNs, now),
+        (None, Some(frameNs))                                => ms_until(frameNs, now),
+        (None, None)                                         => -1
+      };
+      
+      let pollResult = poll_stdin(sleepMs);
+      let woke = time::precise_time_ns();
+      if log.is_on() {
+        let result = match pollResult {
+          PollReady       => "input",
+          PollTimeout     => "timeout",
+          PollInterrupted => "interrupted"
+        };
+        log.log(format!("poll for {} ms: {} after {} ms", sleepMs, result, (woke - now) / 1000000));
+      }
+      match *debug {
+        Some(ref mut overlay) => overlay.woke_up(),
+        None                  => ()
+      }
+      if terminal_control::hung_up() {
+        handler.handle_hangup();
+        break;
+      }
+      match pollResult {
+        PollReady => {
+          let events = handler.read_input();
+          let mut quit = false;
+          for event in events.iter() {
+            if handler.handle_input(*event) && event.action == Quit {
+              quit = true;
+              break;
+            }
+          }
+          if quit {
+            handler.handle_quit();
+            break;
+          }
+        }
+        // a timer is due, or some other signal woke the loop up
+        PollTimeout | PollInterrupted => ()
+      }
+      
+      // every timer that's due by now, soonest first (handling one can move the others)
+      let mut stopped = false;
+      loop {
+        timers.clear();
+        handler.set_timers(&mut timers);
+        let now = time::precise_time_ns();
+        let (timer, dueNs) = match timers.next() {
+          Some((timer, dueNs)) if dueNs <= now => (timer, dueNs),
+          _                                    => break
+        };
+        if timer == DropStep(0) {
+          match *debug {
+            Some(ref mut overlay) => overlay.step_fired(now - dueNs),
+            None                  => ()
+          }
+          if log.is_on() {
+            log.log(format!("step fired {} ms late", (now - dueNs) / 1000000));
+          }
+        }
+        if !handler.handle_timer(timer) {
+          stopped = true;
+          break;
+        }
+      }
+      if stopped || handler.is_finished() {
+        break;
+      }
+      output::flush_held();
+      match *debug {
+        Some(ref mut overlay) => {
+          let now = time::precise_time_ns();
+          overlay.frame_rendered(now - woke);
+          match overlay.report(now) {
+            Some(lines) => handler.handle_debug(lines.as_slice()),
+            None        => ()
+          }
+        }
+        None => ()
+      }
+    }
+  }
+
+  // Races the player's game against the ghost of a recorded game, which is played back
+  // on its own board as its recorded events come due
+  struct GhostRace<'a, 'b, 'c> {
+    player:  &'a mut TetrisGame<'b>,
+    ghost:   &'a mut TetrisGame<'c>,
+    replay:  Replay,
+    next:    uint,  // index of the next replay event to play
+    startNs: u64,
+    mini:    bool   // the ghost is drawn in miniature on the player's display, rather than on its own
+  }
+  
+  impl<'a, 'b, 'c> GhostRace<'a, 'b, 'c> {
+    fn print_mini_ghost(&self) {
+      if self.mini {
+        let label = format!("{} {}", strings::get().ghost, self.ghost.engine.scoring.get_score().lines);
+        let rows = self.ghost.row_masks();
+        self.player.display.print_mini_board(label.as_slice(), rows.as_slice());
+        self.player.display.flush();
+      }
+    }
+  }
+  
+  // Plays the replay's events that are due by now on the game, next is the index of the
+  // first one still to play
+  fn play_replay_events(game: &mut TetrisGame, replay: &Replay, next: &mut uint, startNs: u64) {
+    let elapsed = (time::precise_time_ns() - startNs) / 1000000;
+    play_replay_events_until(game, replay, next, elapsed);
+  }
+  
+  // Plays the replay's events up to elapsed milliseconds into the game. Each happens at its
+  // recorded time on the game's clock, so the game plays out the same as it was recorded.
+  fn play_replay_events_until(game: &mut TetrisGame, replay: &Replay, next: &mut uint, elapsed: u64) {
+    while *next < replay.events.len() && replay.events.get(*next).timeMs <= elapsed {
+      let event = replay.events.get(*next).event;
+      let timeNs = game.startNs + replay.events.get(*next).timeMs * 1000000;
+      *next += 1;
+      match event {
+        // once the game is over and shown, there's nothing more to play
+        GravityStep => if game.step_gravity(timeNs).is_none() {
+          *next = replay.events.len();
+        },
+        PlayerAction(input_reader::Quit) => *next = replay.events.len(),
+        PlayerAction(action)             => {
+          game.handle_input(input_reader::ActionEvent{action: action, timeNs: timeNs, player: 0});
+        }
+      }
+    }
+    game.display.flush();
+  }
+  
+  // when the replay's next event is due, if there are any left
+  fn next_replay_event_ns(replay: &Replay, next: uint, startNs: u64) -> Option<u64> {
+    if next < replay.events.len() {
+      Some(startNs + replay.events.get(next).timeMs * 1000000)
+    } else {
+      None
+    }
+  }
+  
+  impl<'a, 'b, 'c> GameHandler for GhostRace<'a, 'b, 'c> {
+    fn init(&self) {
+      self.ghost.display.init();
+      self.ghost.init();
+      self.player.init();
+      self.print_mini_ghost();
+    }
+    
+    // the ghost's steps come from the replay, so only its hint and drop trail have timers of their own
+    fn set_timers(&self, timers: &mut Timers) {
+      self.player.set_timers(timers);
+      self.ghost.set_shown_timers(timers);
+      match next_replay_event_ns(&self.replay, self.next, self.startNs) {
+        Some(dueNs) => timers.set(NextMove, dueNs),
+        None        => ()
+      }
+    }
+    
+    fn handle_timer(&mut self, timer: Timer) -> bool {
+      match timer {
+        NextMove => {
+          play_replay_events(self.ghost, &self.replay, &mut self.next, self.startNs);
+          self.print_mini_ghost();
+          true
+        }
+        HintShown(player) | TrailShown(player) if player == self.ghost.player => self.ghost.handle_timer(timer),
+        _        => self.player.handle_timer(timer)
+      }
+    }
+    
+    // the ghost can't be paused, so neither can the race
+    fn handle_input(&mut self, event: input_reader::ActionEvent) -> bool {
+      event.action == input_reader::Pause || self.player.handle_input(event)
+    }
+    
+    fn handle_quit(&self) {
+      self.player.handle_quit();
+    }
+    
+    fn handle_hangup(&self) {
+      self.player.handle_hangup();
+    }
+    
+    fn handle_debug(&self, lines: &[~str]) {
+      self.player.handle_debug(lines);
+    }
+    
+    fn read_input(&self) -> Vec<input_reader::ActionEvent> {
+      self.player.read_input()
+    }
+  }
+  
+  // Plays a recorded game back on its own, as its recorded events come due, until a key other
+  // than left or right is pressed. Left and right seek back and forward.
+  struct ReplayPlayback<'a, 'b> {
+    game:    &'a mut TetrisGame<'b>,
+    replay:  Replay,
+    start:   Checkpoint,  // the game before its first event, to seek back to
+    next:    uint,  // index of the next replay event to play
+    startNs: u64,
+    stopped: bool
+  }
+  
+  // milliseconds a seek key moves a replay back or forward
+  static replaySeekMs: i64 = 10000;
+  
+  impl<'a, 'b> ReplayPlayback<'a, 'b> {
+    // Moves playback offsetMs back or forward: the game goes back to the last checkpoint
+    // before then, and the events from there on are played straight away to catch up
+    fn seek(&mut self, offsetMs: i64) {
+      let now = time::precise_time_ns();
+      let elapsed = ((now - self.startNs) / 1000000) as i64;
+      let target = if elapsed + offsetMs > 0 { (elapsed + offsetMs) as u64 } else { 0 };
+      let checkpoint = self.replay.checkpoints.iter().filter(|c| c.timeMs <= target).last().unwrap_or(&self.start);
+      self.game.restore_checkpoint(checkpoint);
+      self.next = checkpoint.event;
+      
+      self.game.display.init();
+      self.init();
+      self.game.display.print_piece(&self.game.engine.piece);
+      
+      self.startNs = now - target * 1000000;
+      self.game.startNs = self.startNs;
+      play_replay_events(self.game, &self.replay, &mut self.next, self.startNs);
+    }
+  }
+  
+  impl<'a, 'b> GameHandler for ReplayPlayback<'a, 'b> {
+    fn init(&self) {
+      self.game.init();
+      self.game.display.print_banner(strings::get().replayBanner);
+      self.game.display.flush();
+    }
+    
+    // gravity steps come from the replay, not the game's own timer
+    fn set_timers(&self, timers: &mut Timers) {
+      self.game.set_shown_timers(timers);
+      match next_replay_event_ns(&self.replay, self.next, self.startNs) {
+        Some(dueNs) => timers.set(NextMove, dueNs),
+        None        => ()
+      }
+    }
+    
+    fn handle_timer(&mut self, timer: Timer) -> bool {
+      match timer {
+        NextMove => {
+          play_replay_events(self.game, &self.replay, &mut self.next, self.startNs);
+          true
+        }
+        _        => self.game.handle_timer(timer)
+      }
+    }
+    
+    fn handle_input(&mut self, event: input_reader::ActionEvent) -> bool {
+      match event.action {
+        input_reader::MoveLeft  => self.seek(-replaySeekMs),
+        input_reader::MoveRight => self.seek(replaySeekMs),
+        _                       => self.stopped = true
+      }
+      true
+    }
+    
+    fn handle_quit(&self) {
+    }
+    
+    fn handle_hangup(&self) {
+    }
+    
+    fn handle_debug(&self, lines: &[~str]) {
+      self.game.handle_debug(lines);
+    }
+    
+    fn is_finished(&self) -> bool {
+      self.stopped
+    }
+    
+    fn read_input(&self) -> Vec<input_reader::ActionEvent> {
+      self.game.read_input()
+    }
+  }
+  
+  // Lets the AI play a game for the attract mode demo, until a key is pressed
+  struct AiPlayer<'a, 'b> {
+    game:         &'a mut TetrisGame<'b>,
+    actions:      Vec<Action>,  // actions still to do for the current piece, last one first
+    lastActionNs: u64,
+    bot:          Option<Bot>,  // another program deciding where pieces go, instead of the AI
+    failure:      Option<~str>, // what went wrong with the bot, if it stopped the game
+    stopped:      bool
+  }
+  
+  // nanoseconds between the AI's key presses, slow enough to watch
+  static aiActionIntervalNs: u64 = 150000000;
+  
+  impl<'a, 'b> AiPlayer<'a, 'b> {
+    // plans where to put a piece once it appears at the top of the board
+    fn plan(&mut self) {
+      match self.game.engine.state {
+        Fall if self.game.engine.piece.blocks.iter().any(|block| block.row < 1) => (),
+        _                                                                => return
+      }
+      let engine = &self.game.engine;
+      let placement = match self.bot {
+        Some(ref mut bot) => match bot.place(&engine.board, &engine.piece, &engine.nextPiece) {
+          Ok(placement) => Some(placement),
+          Err(msg)      => {
+            self.failure = Some(msg);
+            self.stopped = true;
+            None
+          }
+        },
+        None => ai::best_placement(&engine.board, &engine.piece, &self.game.aiWeights)
+      };
+      match placement {
+        Some(placement) => {
+          self.actions = ai::actions(&placement);
+          self.actions.as_mut_slice().reverse();
+        }
+        None => ()
+      }
+    }
+    
+    // makes the next planned move, if there is one, and waits a while before the next
+    fn next_move(&mut self) {
+      let now = time::precise_time_ns();
+      self.lastActionNs = now;
+      if self.game.engine.state == Finished {
+        match self.bot.take() {
+          Some(mut bot) => bot.finish(&self.game.engine.scoring.get_score()),
+          None          => ()
+        }
+        return;
+      }
+      if self.actions.len() == 0 {
+        self.plan();
+      }
+      match self.actions.pop() {
+        Some(action) => { self.game.handle_input(input_reader::ActionEvent{action: action, timeNs: now, player: 0}); }
+        None         => ()
+      }
+    }
+  }
+  
+  impl<'a, 'b> GameHandler for AiPlayer<'a, 'b> {
+    fn init(&self) {
+      self.game.init();
+      let text = strings::get();
+      self.game.display.print_banner(if self.bot.is_some() { text.botBanner } else { text.demoBanner });
+      self.game.display.flush();
+    }
+    
+    fn set_timers(&self, timers: &mut Timers) {
+      self.game.set_timers(timers);
+      timers.set(NextMove, self.lastActionNs + aiActionIntervalNs);
+    }
+    
+    fn handle_timer(&mut self, timer: Timer) -> bool {
+      match timer {
+        NextMove => {
+          self.next_move();
+          true
+        }
+        _        => self.game.handle_timer(timer)
+      }
+    }
+    
+    fn handle_input(&mut self, _event: input_reader::ActionEvent) -> bool {
+      self.stopped = true;
+      true
+    }
+    
+    // the demo's score doesn't count, and there's nothing to save
+    fn handle_quit(&self) {
+    }
+    
+    fn handle_hangup(&self) {
+    }
+    
+    fn handle_debug(&self, lines: &[~str]) {
+      self.game.handle_debug(lines);
+    }
+    
+    fn is_finished(&self) -> bool {
+      self.stopped
+    }
+    
+    fn read_input(&self) -> Vec<input_reader::ActionEvent> {
+      self.game.read_input()
+    }
+  }
+  
+  // Plays the game with commands voted in through a pipe: every window, the command
+  // with the most votes is done. The keyboard still works as well.
+  struct CrowdPlay<'a, 'b> {
+    game:        &'a mut TetrisGame<'b>,
+    input:       crowd::CrowdInput,
+    votes:       Vec<Action>,
+    windowEndNs: u64
+  }
+  
+  // nanoseconds votes are collected for before the winner is done
+  static crowdVoteWindowNs: u64 = 1000000000;
+  
+  impl<'a, 'b> GameHandler for CrowdPlay<'a, 'b> {
+    fn init(&self) {
+      self.game.init();
+      self.game.display.print_banner(strings::get().crowdBanner);
+      self.game.display.flush();
+    }
+    
+    fn set_timers(&self, timers: &mut Timers) {
+      self.game.set_timers(timers);
+      timers.set(NextMove, self.windowEndNs);
+    }
+    
+    fn handle_timer(&mut self, timer: Timer) -> bool {
+      match timer {
+        NextMove => {
+          self.count_votes();
+          true
+        }
+        _        => self.game.handle_timer(timer)
+      }
+    }
+    
+    fn handle_input(&mut self, event: input_reader::ActionEvent) -> bool {
+      self.game.handle_input(event)
+    }
+    
+    fn handle_quit(&self) {
+      self.game.handle_quit();
+    }
+    
+    fn handle_hangup(&self) {
+      self.game.handle_hangup();
+    }
+    
+    fn handle_debug(&self, lines: &[~str]) {
+      self.game.handle_debug(lines);
+    }
+    
+    fn read_input(&self) -> Vec<input_reader::ActionEvent> {
+      self.game.read_input()
+    }
+  }
+  
+  impl<'a, 'b> CrowdPlay<'a, 'b> {
+    // Makes the move with the most votes once the window closes. The votes wait in the
+    // pipe until then.
+    fn count_votes(&mut self) {
+      let commands = self.input.read_commands();
+      self.votes.push_all_move(commands);
+      
+      let now = time::precise_time_ns();
+      match crowd::winner(self.votes.as_slice()) {
+        Some(action) => { self.game.handle_input(input_reader::ActionEvent{action: action, timeNs: now, player: 0}); }
+        None         => ()
+      }
+      self.votes.clear();
+      self.windowEndNs = timers::following(self.windowEndNs, crowdVoteWindowNs, now);
+    }
+  }
+  
+  // the parts of the tutorial, in order
+  #[deriving(Eq)]
+  enum Lesson {
+    MoveLesson = 0, RotateLesson, DropLesson, ClearLesson, TutorialDone
+  }
+  
+  fn lesson_prompt(lesson: Lesson) -> &'static str {
+    strings::get().tutorial[lesson as uint]
+  }
+  
+  // nanoseconds the last message stays up before the tutorial ends
+  static tutorialEndNs: u64 = 3000000000;
+  
+  // Walks a new player through the controls one lesson at a time, on a game with a
+  // scripted sequence of pieces. The piece doesn't fall until moving and turning it have
+  // been tried, and the last lesson is played on a board prepared for clearing a row.
+  struct Tutorial<'a, 'b> {
+    game:         &'a mut TetrisGame<'b>,
+    lesson:       Lesson,
+    movedLeft:    bool,
+    movedRight:   bool,
+    rotated:      bool,
+    lessonPieces: uint,  // pieces set when the current lesson started
+    doneNs:       u64    // when the tutorial ends, once every lesson is done
+  }
+  
+  impl<'a, 'b> Tutorial<'a, 'b> {
+    fn print_prompt(&self) {
+      self.game.display.print_banner(format!("{:<60s}", lesson_prompt(self.lesson)).as_slice());
+      self.game.display.flush();
+    }
+    
+    fn start_lesson(&mut self, lesson: Lesson) {
+      self.lesson = lesson;
+      self.lessonPieces = self.game.engine.pieces;
+      match lesson {
+        DropLesson   => self.game.stepDueNs = time::precise_time_ns() + firstStepNs,
+        ClearLesson  => self.prepare_clear(),
+        TutorialDone => self.doneNs = time::precise_time_ns() + tutorialEndNs,
+        _            => ()
+      }
+      self.print_prompt();
+    }
+    
+    // swaps the board for one with a bottom row that the falling I piece can finish off
+    fn prepare_clear(&mut self) {
+      let previous = self.game.engine.board;
+      let mut prepared = board::new();
+      for col in range(1, board::width - 3) {
+        prepared.set(Block{row: board::height, column: col, color: Gray, ty: None});
+      }
+      self.game.engine.board = prepared;
+      self.game.publish(vec!(BoardChanged(previous)));
+    }
+    
+    // moves on to the next lesson once the current one has been done
+    fn check_progress(&mut self) {
+      let next = match self.lesson {
+        MoveLesson if self.movedLeft && self.movedRight                    => RotateLesson,
+        RotateLesson if self.rotated                                       => DropLesson,
+        DropLesson if self.game.engine.pieces > self.lessonPieces          => ClearLesson,
+        ClearLesson if self.game.engine.scoring.get_score().lines > 0      => TutorialDone,
+        _                                                                  => return
+      };
+      self.start_lesson(next);
+    }
+  }
+  
+  impl<'a, 'b> GameHandler for Tutorial<'a, 'b> {
+    fn init(&self) {
+      self.game.init();
+      self.print_prompt();
+    }
+    
+    fn set_timers(&self, timers: &mut Timers) {
+      match self.lesson {
+        MoveLesson | RotateLesson => self.game.set_shown_timers(timers),
+        DropLesson | ClearLesson  => self.game.set_timers(timers),
+        TutorialDone              => timers.set(NextMove, self.doneNs)
+      }
+    }
+    
+    fn handle_timer(&mut self, timer: Timer) -> bool {
+      match timer {
+        // the last message has been up long enough
+        NextMove => false,
+        _        => {
+          let running = self.game.handle_timer(timer);
+          self.check_progress();
+          running
+        }
+      }
+    }
+    
+    fn handle_input(&mut self, event: input_reader::ActionEvent) -> bool {
+      use input_reader::{Rotate, MoveLeft, MoveRight};
+      let used = self.game.handle_input(event);
+      match (self.lesson, event.action) {
+        (MoveLesson, MoveLeft)  => self.movedLeft = true,
+        (MoveLesson, MoveRight) => self.movedRight = true,
+        (RotateLesson, Rotate)  => self.rotated = true,
+        _                       => ()
+      }
+      self.check_progress();
+      used
+    }
+    
+    // nothing from the tutorial is kept
+    fn handle_quit(&self) {
+    }
+    
+    fn handle_hangup(&self) {
+    }
+    
+    fn handle_debug(&self, lines: &[~str]) {
+      self.game.handle_debug(lines);
+    }
+    
+    fn read_input(&self) -> Vec<input_reader::ActionEvent> {
+      self.game.read_input()
+    }
+  }
+  
+  fn run_demo(display: &Display, options: &Options, config: &Config) {
+    let seed = rand::random::<u32>() as u64;
+    let pieceGetter = piece_getter::new(seed);
+    let scoring = new_scoring(Endless, options, config);
+    let gravity = new_gravity(Endless, options, config);
+    let scoreKeeper = score_keeper::null();
+    let mut game = new_game(display, pieceGetter, scoring, gravity, scoreKeeper, Endless, seed, time::precise_time_ns(), config, options.log);
+    
+    display.init();
+    let mut demo = AiPlayer{game:         &mut game,
+                            actions:      Vec::new(),
+                            lastActionNs: time::precise_time_ns(),
+                            bot:          None,
+                            failure:      None,
+                            stopped:      false};
+    main_loop(&mut demo, &mut None, options.log);
+  }
+  
+  // Lets another program play, started with command and told about each piece as in the
+  // bot module. It plays with the mode and seed options, and its games aren't kept with the scores.
+  pub fn run_bot(display: &Display, command: &str, options: &Options, config: &Config) {
+    let text = strings::get();
+    let bot = match bot::start(command) {
+      Ok(bot)  => bot,
+      Err(msg) => {
+        println!("{}", strings::fill(text.botFailed, &[msg.as_slice()]));
+        os::set_exit_status(1);
+        return;
+      }
+    };
+    let seed = match options.seed {
+      Some(seed) => seed,
+      None       => rand::random::<u32>() as u64
+    };
+    
+    let failure = {
+      // the restorer resets the terminal out of raw mode once it's dropped
+      let _restorer = terminal_control::set_terminal_raw_mode();
+      let _curses = if options.curses { Some(curses::start()) } else { None };
+      
+      terminal_control::catch_hangup();
+      
+      output::install(Vec::new(), options.log);
+      
+      let pieceGetter = piece_getter::new(seed);
+      let scoring = new_scoring(options.mode, options, config);
+      let gravity = new_gravity(options.mode, options, config);
+      let scoreKeeper = score_keeper::null();
+      let mut game = new_game(display, pieceGetter, scoring, gravity, scoreKeeper, options.mode, seed, time::precise_time_ns(), config, options.log);
+      game.preview = options.preview;
+      game.engine.spawnDelay = options.spawnDelay;
+      
+      display.init();
+      let mut player = AiPlayer{game:         &mut game,
+                                actions:      Vec::new(),
+                                lastActionNs: time::precise_time_ns(),
+                                bot:          Some(bot),
+                                failure:      None,
+                                stopped:      false};
+      main_loop(&mut player, &mut None, options.log);
+      
+      if !terminal_control::hung_up() {
+        display.close();
+      }
+      output::uninstall();
+      player.failure.take()
+    };
+    
+    match failure {
+      Some(msg) => {
+        println!("{}", strings::fill(text.botFailed, &[msg.as_slice()]));
+        os::set_exit_status(1);
+      }
+      None => ()
+    }
+  }
+  
+  enum TitleChoice {
+    Play, QuitGame, Idle
+  }
+  
+  // milliseconds on the title screen before the demo starts
+  static attractDelayMs: c_int = 30000;
+  
+  // Shows the saved game, its board and score, and asks whether to carry on with it (Some(true)),
+  // throw it away and start a new game (Some(false)) or quit (None). A saved game that can't be
+  // read is left for --resume to report.
+  fn ask_resume(palette: [u8, ..8]) -> Option<bool> {
+    use input_reader::{try_read_stdin, Key};
+    
+    let saved = match saved_game::load() {
+      Ok(saved) => saved,
+      Err(_)    => return Some(false)
+    };
+    let mut setBlocks = board::new();
+    if saved_game::decode_board(saved.board.as_slice(), &mut setBlocks).is_err() {
+      return Some(false);
+    }
+    let piece = match saved_game::piece_from_name(saved.piece) {
+      Some(piece) => piece,
+      None        => return Some(false)
+    };
+    
+    let text = strings::get();
+    println!("");
+    println!("{}", strings::fill(text.savedGame, &[saved.mode.as_slice(), score_keeper::format_duration(saved.duration).as_slice()]));
+    print!("{}", graphics::board_picture(&setBlocks, &piece, saved.score, palette));
+    println!("{}", text.resumeChoices);
+    io::stdio::flush();
+    
+    let _restorer = terminal_control::set_terminal_raw_mode();
+    loop {
+      match try_read_stdin() {
+        // r or enter
+        Ok(Key(0x72)) | Ok(Key(0x52)) | Ok(Key(0x0D)) => return Some(true),
+        // d
+        Ok(Key(0x64)) | Ok(Key(0x44))                 => {
+          saved_game::discard();
+          return Some(false);
+        }
+        // q, escape or ctrl-c
+        Ok(Key(0x71)) | Ok(Key(0x51)) | Ok(Key(0x1B)) | Ok(Key(0x03)) => return None,
+        // with no answer to be had, the saved game is kept and a new one started
+        Err(_)                                        => return Some(false),
+        _                                             => ()
+      }
+    }
+  }
+  
+  fn title_screen(display: &Display) -> TitleChoice {
+    use input_reader::{poll_stdin, read_stdin, Key, PollReady, PollTimeout, PollInterrupted};
+    
+    display.print_title_screen();
+    output::flush_now();
+    loop {
+      match poll_stdin(attractDelayMs) {
+        PollReady       => return match read_stdin() {
+          // q, escape or ctrl-c
+          Key(0x71) | Key(0x51) | Key(0x1B) | Key(0x03) => QuitGame,
+          _                                             => Play
+        },
+        PollTimeout     => return Idle,
+        PollInterrupted => if terminal_control::hung_up() { return QuitGame; }
+      }
+    }
+  }
+  
+  // Two people playing side by side on one keyboard, each game with its own timers.
+  // Clearing two or more rows at once sends garbage rows to the other player's board.
+  // The first to top out loses.
+  struct TwoPlayer<'a, 'b> {
+    first:    &'a mut TetrisGame<'b>,
+    second:   &'a mut TetrisGame<'b>,
+    opponent: Option<Opponent>,  // the computer, when it plays the second game
+    winner:   Option<uint>,  // which player won, once the game is over
+    finished: bool
+  }
+  
+  // The computer as the second player, pressing keys about as fast and as well as its difficulty
+  struct Opponent {
+    difficulty:   ai::Difficulty,
+    actions:      Vec<Action>,   // actions still to do for the current piece, last one first
+    plannedFor:   Option<uint>,  // the pieces set when the actions were planned
+    lastActionNs: u64,
+    rng:          Isaac64Rng
+  }
+  
+  impl Opponent {
+    fn next_action_ns(&self) -> u64 {
+      self.lastActionNs + self.difficulty.reactionMs * 1000000
+    }
+    
+    // plans where to put each new piece, then makes one of the moves to get it there
+    fn next_move(&mut self, game: &mut TetrisGame) {
+      let now = time::precise_time_ns();
+      self.lastActionNs = now;
+      if game.engine.state != Fall {
+        return;
+      }
+      if self.plannedFor != Some(game.engine.pieces) {
+        self.plannedFor = Some(game.engine.pieces);
+        self.actions = self.plan(game);
+      }
+      match self.actions.pop() {
+        Some(action) => { game.handle_input(input_reader::ActionEvent{action: action, timeNs: now, player: 1}); }
+        None         => ()
+      }
+    }
+    
+    fn plan(&mut self, game: &TetrisGame) -> Vec<Action> {
+      let engine = &game.engine;
+      match ai::placement_for(&engine.board, &engine.piece, &engine.nextPiece, &self.difficulty) {
+        Some(mut placement) => {
+          if self.rng.gen::<f64>() < self.difficulty.misdropChance {
+            placement.columns += if self.rng.gen::<bool>() { 1 } else { -1 };
+          }
+          let mut actions = ai::actions(&placement);
+          actions.as_mut_slice().reverse();
+          actions
+        }
+        None => Vec::new()
+      }
+    }
+  }
+  
+  impl<'a, 'b> TwoPlayer<'a, 'b> {
+    // Both games run here on the same clock, so garbage can be handed over right after
+    // every timer, rather than held back until its game time comes as in a match over the
+    // network. Both games have the same rules.
+    fn exchange_garbage(&mut self) {
+      if self.first.garbage.cancel {
+        self.first.cancel_garbage();
+        self.second.cancel_garbage();
+      }
+      self.second.pendingGarbage += self.first.outgoingGarbage;
+      self.first.pendingGarbage += self.second.outgoingGarbage;
+      self.first.outgoingGarbage = 0;
+      self.second.outgoingGarbage = 0;
+      self.first.receive_garbage();
+      self.second.receive_garbage();
+    }
+    
+    fn declare_winner(winner: &TetrisGame) {
+      winner.display.print_game_over(strings::get().winner, winner.engine.scoring.get_score(), winner.elapsed_ms(), winner.seed);
+      winner.display.flush();
+    }
+  }
+  
+  impl<'a, 'b> GameHandler for TwoPlayer<'a, 'b> {
+    fn init(&self) {
+      self.second.display.init();
+      self.second.init();
+      self.first.init();
+      let banner = if self.opponent.is_some() { strings::get().humanPlayer } else { strings::get().firstPlayer };
+      self.first.display.print_banner(banner);
+      self.first.display.flush();
+    }
+    
+    fn set_timers(&self, timers: &mut Timers) {
+      self.first.set_timers(timers);
+      self.second.set_timers(timers);
+      match self.opponent {
+        Some(ref opponent) => timers.set(NextMove, opponent.next_action_ns()),
+        None               => ()
+      }
+    }
+    
+    // the other player wins once a game is over
+    fn handle_timer(&mut self, timer: Timer) -> bool {
+      if timer == NextMove {
+        match self.opponent {
+          Some(ref mut opponent) => opponent.next_move(self.second),
+          None                   => ()
+        }
+        self.exchange_garbage();
+        return true;
+      }
+      let secondPlayers = match timer {
+        DropStep(1) | HintShown(1) | TrailShown(1) => true,
+        _                                          => false
+      };
+      let carryOn = if secondPlayers { self.second.handle_timer(timer) } else { self.first.handle_timer(timer) };
+      if !carryOn {
+        TwoPlayer::declare_winner(if secondPlayers { &*self.first } else { &*self.second });
+        self.winner = Some(if secondPlayers { 0 } else { 1 });
+        self.finished = true;
+        return false;
+      }
+      self.exchange_garbage();
+      true
+    }
+    
+    fn handle_input(&mut self, event: input_reader::ActionEvent) -> bool {
+      // one player can't stop the clock on the other
+      match event.player {
+        _ if event.action == input_reader::Pause => true,
+        0 => self.first.handle_input(event),
+        _ => self.second.handle_input(event)
+      }
+    }
+    
+    // two player games don't keep scores, and there's nothing to save
+    fn handle_quit(&self) {
+    }
+    
+    fn handle_hangup(&self) {
+    }
+    
+    fn handle_debug(&self, lines: &[~str]) {
+      self.first.handle_debug(lines);
+    }
+    
+    fn is_finished(&self) -> bool {
+      self.finished
+    }
+    
+    // against the computer the player has the usual keys to themselves
+    fn read_input(&self) -> Vec<input_reader::ActionEvent> {
+      if self.opponent.is_some() {
+        self.first.read_input()
+      } else {
+        input_reader::read_two_player_actions(self.first.log)
+      }
+    }
+  }
+  
+  // milliseconds the series score is shown between the games of a series
+  static seriesPauseMs: u64 = 3000;
+  
+  // Plays a two player series of options.bestOf games (just one game by default), until one
+  // player has won most of them. Both players get the same pieces, a new sequence each game.
+  // With options.vsAi the computer plays the second game.
+  pub fn run_two_player(display: &Display, options: &Options, config: &Config) {
+    let text = strings::get();
+    let names = match options.vsAi {
+      Some(_) => [text.humanPlayer, text.computerPlayer],
+      None    => [text.firstPlayer, text.secondPlayer]
+    };
+    let sideDisplay = graphics::side_display(names[1], display.colors());
+    let seed = match options.seed {
+      Some(seed) => seed,
+      None       => rand::random::<u32>() as u64
+    };
+    
+    // the restorer resets the terminal out of raw mode once it's dropped
+    let _restorer = terminal_control::set_terminal_raw_mode();
+    let _curses = if options.curses { Some(curses::start()) } else { None };
+    
+    terminal_control::catch_hangup();
+    
+    output::install(Vec::new(), options.log);
+    
+    let mut wins = [0u, 0u];
+    let mut round = 0;
+    while !series_decided(wins, options.bestOf) {
+      let seed = seed + round as u64;
+      let startNs = time::precise_time_ns();
+      let firstPieces = piece_getter::new(seed);
+      let firstScoring = new_scoring(options.mode, options, config);
+      let firstGravity = new_gravity(options.mode, options, config);
+      let firstKeeper = score_keeper::null();
+      let mut first = new_game(display, firstPieces, firstScoring, firstGravity, firstKeeper,
+                               options.mode, seed, startNs, config, options.log);
+      first.preview = options.preview;
+      first.engine.spawnDelay = options.spawnDelay;
+      
+      let secondPieces = piece_getter::new(seed);
+      let secondScoring = new_scoring(options.mode, options, config);
+      let secondGravity = new_gravity(options.mode, options, config);
+      let secondKeeper = score_keeper::null();
+      let mut second = new_game(&sideDisplay as &Display, secondPieces, secondScoring, secondGravity, secondKeeper,
+                                options.mode, seed, startNs, config, options.log);
+      second.player = 1;
+      second.preview = options.preview;
+      second.engine.spawnDelay = options.spawnDelay;
+      
+      display.init();
+      
+      let opponent = options.vsAi.map(|difficulty| Opponent{difficulty:   difficulty,
+                                                            actions:      Vec::new(),
+                                                            plannedFor:   None,
+                                                            lastActionNs: startNs,
+                                                            rng:          SeedableRng::from_seed(&[seed, 2])});
+      let mut game = TwoPlayer{first:    &mut first,
+                               second:   &mut second,
+                               opponent: opponent,
+                               winner:   None,
+                               finished: false};
+      main_loop(&mut game, &mut None, options.log);
+      
+      // a quit or a hangup ends the series with the game
+      match game.winner {
+        Some(winner) => wins[winner] += 1,
+        None         => break
+      }
+      round += 1;
+      if options.bestOf > 1 {
+        show_series_score(display, names, wins, options.bestOf, options.log);
+      }
+    }
+    
+    if series_decided(wins, options.bestOf) {
+      let result = score_keeper::VersusResult{time:       time::now(),
+                                              mode:       modes::name(options.mode).to_owned(),
+                                              bestOf:     options.bestOf,
+                                              firstWins:  wins[0],
+                                              secondWins: wins[1]};
+      score_keeper::new(options.scoreFile.clone()).store_versus(result);
+    }
+    
+    // there's no terminal left to clean up after a hangup
+    if !terminal_control::hung_up() {
+      display.close();
+    }
+    
+    output::uninstall();
+  }
+  
+  // a player has won most of the games in the series
+  fn series_decided(wins: [uint, ..2], bestOf: uint) -> bool {
+    wins[0] * 2 > bestOf || wins[1] * 2 > bestOf
+  }
+  
+  // Shows the games each player has won so far over the first player's board for a moment,
+  // or who won the series once it's decided. Keys pressed meanwhile are thrown away, so they
+  // don't move the first pieces of the next game.
+  fn show_series_score(display: &Display, names: [&'static str, ..2], wins: [uint, ..2], bestOf: uint, log: trace::Log) {
+    let text = strings::get();
+    let score = [wins[0].to_str(), wins[1].to_str()];
+    let banner = if series_decided(wins, bestOf) {
+      let winner = if wins[0] > wins[1] { names[0] } else { names[1] };
+      strings::fill(text.seriesWon, &[winner, score[0].as_slice(), score[1].as_slice()])
+    } else {
+      strings::fill(text.seriesScore, &[score[0].as_slice(), score[1].as_slice(), bestOf.to_str().as_slice()])
+    };
+    display.print_banner(banner.as_slice());
+    display.flush();
+    timer::sleep(seriesPauseMs);
+    input_reader::read_two_player_actions(log);
+  }
+  
+  // A versus match against a player on another computer, through a relay (--serve). Only
+  // the player's game is played here: the opponent's board comes in over the connection and
+  // is drawn in miniature, along with the garbage they send and the end of their game.
+  //
+  // What the opponent sends comes with the game time it happened at, and is held back until
+  // that time plus the lag, in this game's time. Each event then lands the same time after
+  // it happened in the opponent's game however long it took to arrive, and in the order
+  // they happened, rather than garbage turning up in the past of a game that's moved on.
+  struct NetVersus<'a, 'b> {
+    game:        &'a mut TetrisGame<'b>,
+    connection:  relay::Connection,
+    sentRows:    [u16, ..20],  // the board as the opponent was last sent it
+    readDueNs:   u64,          // when the connection is next read
+    held:        Vec<HeldEvent>,  // the opponent's events still to come, oldest first
+    roundTripMs: Option<u64>,  // how long a ping takes to be answered, once one has been
+    offsetMs:    i64,          // how far the opponent's game time is ahead of this game's
+    pingDueMs:   u64,          // the game time to measure the lag again
+    chat:        RefCell<Chat>,
+    finished:    bool
+  }
+  
+  // nanoseconds between reads of the connection
+  static netReadIntervalNs: u64 = 20000000;
+  
+  // milliseconds between pings, and held back on top of the lag so events that come a
+  // little later than usual still land in time
+  static pingIntervalMs: u64 = 1000;
+  static lagMarginMs: u64 = 50;
+  
+  // an event the opponent sent, and the game time it's due to be applied at
+  struct HeldEvent {
+    dueMs:   u64,
+    message: relay::Message
+  }
+  
+  // The chat of a match over the network. Enter starts a line, and the keys go into it
+  // rather than to the game until it's sent with Enter again or given up on.
+  struct Chat {
+    lines:  Vec<~str>,     // what's been said, oldest first
+    typing: Option<~str>,  // the line the player is typing
+    typed:  Vec<~str>      // lines the player has finished that haven't been sent yet
+  }
+  
+  // chat lines kept to be drawn again, the display shows the last few that fit
+  static chatKeptLines: uint = 8;
+  
+  impl Chat {
+    fn add_line(&mut self, line: ~str) {
+      self.lines.push(line);
+      if self.lines.len() > chatKeptLines {
+        self.lines.remove(0);
+      }
+    }
+    
+    fn print(&self, display: &Display) {
+      display.print_chat(self.lines.as_slice(), self.typing.as_ref().map(|line| line.as_slice()));
+    }
+  }
+  
+  // What a key does to the chat line being typed: the line with the key's character added
+  // (or the last one taken off by backspace), or None once it's finished with Enter (and
+  // added to typed) or given up on with escape or ctrl-c
+  fn type_chat_key(line: ~str, key: input_reader::ReadResult, typed: &mut Vec<~str>) -> Option<~str> {
+    match key {
+      input_reader::Key(0x0D)                           => {
+        if line.len() > 0 {
+          typed.push(line);
+        }
+        None
+      }
+      input_reader::Key(0x1B) | input_reader::Key(0x03) => None,
+      input_reader::Key(0x7F) | input_reader::Key(0x08) => {
+        Some(if line.len() > 0 { line.slice_to(line.len() - 1).to_owned() } else { line })
+      }
+      input_reader::Key(b) if b >= 0x20 && b <= 0x7E && line.len() < relay::chatMaxLength => {
+        let mut line = line;
+        line.push_char(b as char);
+        Some(line)
+      }
+      _                                                 => Some(line)
+    }
+  }
+  
+  impl<'a, 'b> NetVersus<'a, 'b> {
+    // Sends the opponent the garbage rows the player's clears have earned, the board if it's
+    // changed and the chat lines the player has typed, then adds the garbage they've sent
+    fn exchange(&mut self) {
+      if self.game.garbage.cancel {
+        self.game.cancel_garbage();
+      }
+      let nowMs = self.game.elapsed_ms();
+      if self.game.outgoingGarbage > 0 {
+        self.connection.send(&relay::Garbage(nowMs, self.game.outgoingGarbage));
+        self.game.outgoingGarbage = 0;
+      }
+      self.game.receive_garbage();
+      let rows = self.game.row_masks();
+      if rows.as_slice() != self.sentRows.as_slice() {
+        self.connection.send(&relay::Board(nowMs, Vec::from_slice(rows.as_slice())));
+        self.sentRows = rows;
+      }
+      
+      let mut chat = self.chat.borrow_mut();
+      let chat = chat.get();
+      if !chat.typed.is_empty() {
+        let typed = mem::replace(&mut chat.typed, Vec::new());
+        for line in typed.move_iter() {
+          self.connection.send(&relay::Chat(line.clone()));
+          chat.add_line(strings::fill(strings::get().chatYou, &[line.as_slice()]));
+        }
+        chat.print(self.game.display);
+      }
+    }
+    
+    // Handles what the opponent has sent, and applies their events that are due. False once
+    // the match is over, because they've topped out or gone.
+    fn read_connection(&mut self) -> bool {
+      let nowMs = self.game.elapsed_ms();
+      for message in self.connection.receive().move_iter() {
+        match message {
+          relay::Board(ms, rows)   => self.hold(ms, relay::Board(ms, rows)),
+          relay::Garbage(ms, rows) => self.hold(ms, relay::Garbage(ms, rows)),
+          relay::Over(ms)          => self.hold(ms, relay::Over(ms)),
+          relay::Ping(ms)          => self.connection.send(&relay::Pong(ms, nowMs)),
+          relay::Pong(ms, theirs)  => self.measure_lag(ms, theirs, nowMs),
+          relay::Chat(text)        => {
+            let mut chat = self.chat.borrow_mut();
+            chat.get().add_line(strings::fill(strings::get().chatOpponent, &[text.as_slice()]));
+            chat.get().print(self.game.display);
+          }
+          relay::Left              => return self.end_match(strings::get().opponentLeft),
+          _                        => ()
+        }
+      }
+      if !self.connection.is_open() {
+        return self.end_match(strings::get().opponentLeft);
+      }
+      while !self.held.is_empty() && self.held.get(0).dueMs <= nowMs {
+        match self.held.remove(0).unwrap().message {
+          relay::Board(_, rows)   => self.game.display.print_mini_board(strings::get().opponent, rows.as_slice()),
+          relay::Garbage(_, rows) => self.game.pendingGarbage += rows,
+          relay::Over(_)          => return self.end_match(strings::get().winner),
+          _                       => ()
+        }
+      }
+      if nowMs >= self.pingDueMs {
+        self.connection.send(&relay::Ping(nowMs));
+        self.pingDueMs = nowMs + pingIntervalMs;
+      }
+      self.exchange();
+      self.game.display.flush();
+      self.readDueNs = timers::following(self.readDueNs, netReadIntervalNs, time::precise_time_ns());
+      true
+    }
+    
+    // Holds back an event the opponent's game had at game time ms until it's due here. It's
+    // never due before the ones that came before it, even if the lag has gone down since.
+    fn hold(&mut self, ms: u64, message: relay::Message) {
+      let localMs = cmp::max(ms as i64 - self.offsetMs, 0) as u64;
+      let lagMs = self.roundTripMs.map_or(0, |roundTrip| roundTrip / 2);
+      let earliestMs = self.held.last().map_or(0, |event| event.dueMs);
+      self.held.push(HeldEvent{dueMs: cmp::max(localMs + lagMs + lagMarginMs, earliestMs), message: message});
+    }
+    
+    // A pong for the ping sent at game time ms, answered at the opponent's game time theirs.
+    // The answer is taken to have been given halfway through the round trip, which gives how
+    // far apart the two games' times are. The round trip shown is evened out over a few pings.
+    fn measure_lag(&mut self, ms: u64, theirs: u64, nowMs: u64) {
+      if ms > nowMs {
+        return;
+      }
+      let roundTrip = nowMs - ms;
+      self.offsetMs = theirs as i64 - (ms + roundTrip / 2) as i64;
+      let shown = match self.roundTripMs {
+        Some(before) => (before * 3 + roundTrip) / 4,
+        None         => roundTrip
+      };
+      self.roundTripMs = Some(shown);
+      self.game.display.print_mini_caption(strings::fill(strings::get().latency, &[shown.to_str().as_slice()]).as_slice());
+    }
+    
+    fn end_match(&mut self, heading: &str) -> bool {
+      let game = &*self.game;
+      game.display.print_game_over(heading, game.engine.scoring.get_score(), game.elapsed_ms(), game.seed);
+      game.display.flush();
+      self.finished = true;
+      false
+    }
+  }
+  
+  impl<'a, 'b> GameHandler for NetVersus<'a, 'b> {
+    fn init(&self) {
+      self.game.init();
+      self.game.display.print_banner(strings::get().humanPlayer);
+      self.game.display.print_mini_board(strings::get().opponent, &[0u16, ..20]);
+      self.chat.borrow().get().print(self.game.display);
+      self.game.display.flush();
+    }
+    
+    fn set_timers(&self, timers: &mut Timers) {
+      self.game.set_timers(timers);
+      timers.set(NextMove, self.readDueNs);
+    }
+    
+    // the opponent wins once the player's game is over
+    fn handle_timer(&mut self, timer: Timer) -> bool {
+      if timer == NextMove {
+        return self.read_connection();
+      }
+      if !self.game.handle_timer(timer) {
+        self.connection.send(&relay::Over(self.game.elapsed_ms()));
+        self.finished = true;
+        return false;
+      }
+      self.exchange();
+      true
+    }
+    
+    fn handle_input(&mut self, event: input_reader::ActionEvent) -> bool {
+      // the player can't stop the clock on the opponent
+      if event.action == input_reader::Pause {
+        return true;
+      }
+      let handled = self.game.handle_input(event);
+      self.exchange();
+      handled
+    }
+    
+    // there's no score to keep for a match, and closing the connection tells the opponent
+    fn handle_quit(&self) {
+    }
+    
+    fn handle_hangup(&self) {
+    }
+    
+    fn handle_debug(&self, lines: &[~str]) {
+      self.game.handle_debug(lines);
+    }
+    
+    fn is_finished(&self) -> bool {
+      self.finished
+    }
+    
+    // keys go to the chat line while the player is typing one, and to the game otherwise
+    fn read_input(&self) -> Vec<input_reader::ActionEvent> {
+      let game = &*self.game;
+      let timeNs = time::precise_time_ns();
+      let mut chat = self.chat.borrow_mut();
+      let chat = chat.get();
+      let mut events = Vec::new();
+      let mut chatChanged = false;
+      for key in input_reader::read_keys(game.log).move_iter() {
+        match chat.typing.take() {
+          Some(line) => {
+            chat.typing = type_chat_key(line, key, &mut chat.typed);
+            chatChanged = true;
+          }
+          None => match key {
+            input_reader::Key(0x0D) => {
+              chat.typing = Some(~"");
+              chatChanged = true;
+            }
+            _                       => {
+              let action = input_reader::key_action(key, &game.keys, false, false);
+              events.push(input_reader::ActionEvent{action: action, timeNs: timeNs, player: 0});
+            }
+          }
+        }
+      }
+      if chatChanged {
+        chat.print(game.display);
+        game.display.flush();
+      }
+      events
+    }
+  }
+  
+  // milliseconds between looks at the keyboard and the connection while waiting for an opponent
+  static lobbyPollMs: c_int = 100;
+  
+  // The lobby: who the player is waiting for (anyone, or a friend joining their room), how
+  // busy the relay is once it's said, and how long they've waited
+  fn print_lobby(display: &Display, address: &str, room: Option<&str>, relayCounts: Option<(uint, uint)>, seconds: u64) {
+    let text = strings::get();
+    let waiting = match room {
+      Some(code) => strings::fill(text.waitingInRoom, &[code, code]),
+      None       => strings::fill(text.waitingForOpponent, &[address])
+    };
+    let busy = match relayCounts {
+      Some((players, matches)) => strings::fill(text.relayStatus, &[players.to_str().as_slice(), matches.to_str().as_slice()]),
+      None                     => ~""
+    };
+    let waited = strings::fill(text.waitedFor, &[format!("{}:{:02u}", seconds / 60, seconds % 60).as_slice()]);
+    display.print_lobby(&[waiting.as_slice(), busy.as_slice(), waited.as_slice(), "", text.lobbyHelp]);
+  }
+  
+  // Shows the lobby until the relay has matched the player with an opponent, and gives the
+  // seed of the match and its garbage rules, or None if the player gives up (q, escape or
+  // ctrl-c) or the terminal has gone away. It's drawn again when the relay says how busy it
+  // is, and every second. The player who waited sends the opponent their rules, and the
+  // other keeps waiting until they've come.
+  fn wait_for_opponent(display: &Display, connection: &mut relay::Connection, address: &str,
+                       room: Option<&str>, rules: GarbageRules) -> Result<Option<(u64, GarbageRules)>, ~str> {
+    use input_reader::{poll_stdin, read_stdin, Key, PollReady, PollTimeout, PollInterrupted};
+    
+    let startNs = time::precise_time_ns();
+    let mut relayCounts = None;
+    let mut shownSeconds = None;
+    let mut started = None;
+    loop {
+      let mut changed = false;
+      for message in connection.receive().move_iter() {
+        match message {
+          relay::Start(seed, 0)          => {
+            connection.send(&relay::Rules(rules));
+            return Ok(Some((seed, rules)));
+          }
+          relay::Start(seed, _)          => started = Some(seed),
+          relay::Rules(theirs)           => match started {
+            Some(seed) => return Ok(Some((seed, theirs))),
+            None       => ()
+          },
+          // the opponent went before their rules came, so it's back to waiting for another
+          relay::Left                    => {
+            started = None;
+            match room {
+              Some(code) => connection.send(&relay::Join(code.to_owned())),
+              None       => connection.send(&relay::Play)
+            }
+          }
+          relay::Lobby(players, matches) => {
+            relayCounts = Some((players, matches));
+            changed = true;
+          }
+          _                              => ()
+        }
+      }
+      if !connection.is_open() {
+        return Err(strings::get().errRelayClosed.to_owned());
+      }
+      let seconds = (time::precise_time_ns() - startNs) / 1000000000;
+      if changed || shownSeconds != Some(seconds) {
+        print_lobby(display, address, room, relayCounts, seconds);
+        shownSeconds = Some(seconds);
+      }
+      match poll_stdin(lobbyPollMs) {
+        PollReady       => match read_stdin() {
+          // q, escape or ctrl-c
+          Key(0x71) | Key(0x51) | Key(0x1B) | Key(0x03) => return Ok(None),
+          _                                             => ()
+        },
+        PollTimeout     => (),
+        PollInterrupted => if terminal_control::hung_up() { return Ok(None); }
+      }
+    }
+  }
+  
+  // Plays a versus match over the network against the next player to connect to the relay
+  // at address, or the one who joins the same room. Both games get the same pieces, and
+  // clears send garbage rows to the other board as in a two player game, by the rules of
+  // the player who waited.
+  pub fn run_net_versus(display: &Display, address: &str, room: Option<&str>, options: &Options, config: &Config) -> Result<(), ~str> {
+    let mut connection = try!(relay::connect(address));
+    match room {
+      Some(code) => connection.send(&relay::Join(code.to_owned())),
+      None       => connection.send(&relay::Play)
+    }
+    
+    // the restorer resets the terminal out of raw mode once it's dropped
+    let _restorer = terminal_control::set_terminal_raw_mode();
+    let _curses = if options.curses { Some(curses::start()) } else { None };
+    
+    terminal_control::catch_hangup();
+    
+    output::install(Vec::new(), options.log);
+    
+    let result = match wait_for_opponent(display, &mut connection, address, room, config.garbage) {
+      Ok(Some((seed, rules))) => {
+        play_net_match(display, connection, seed, rules, options, config);
+        Ok(())
+      }
+      Ok(None) => Ok(()),
+      Err(msg) => Err(msg)
+    };
+    
+    // there's no terminal left to clean up after a hangup
+    if !terminal_control::hung_up() {
+      display.close();
+    }
+    
+    output::uninstall();
+    result
+  }
+  
+  fn play_net_match(display: &Display, connection: relay::Connection, seed: u64, rules: GarbageRules,
+                    options: &Options, config: &Config) {
+    let startNs = time::precise_time_ns();
+    let pieceGetter = piece_getter::new(seed);
+    let scoring = new_scoring(options.mode, options, config);
+    let gravity = new_gravity(options.mode, options, config);
+    let scoreKeeper = score_keeper::null();
+    let mut game = new_game(display, pieceGetter, scoring, gravity, scoreKeeper,
+                            options.mode, seed, startNs, config, options.log);
+    game.preview = options.preview;
+    game.engine.spawnDelay = options.spawnDelay;
+    game.scoreLog = None;  // the chat goes in its place
+    game.garbage = rules;
+    
+    display.init();
+    
+    let mut versus = NetVersus{game:        &mut game,
+                               connection:  connection,
+                               sentRows:    [0u16, ..20],
+                               readDueNs:   startNs,
+                               held:        Vec::new(),
+                               roundTripMs: None,
+                               offsetMs:    0,
+                               pingDueMs:   0,
+                               chat:        RefCell::new(Chat{lines: Vec::new(), typing: None, typed: Vec::new()}),
+                               finished:    false};
+    main_loop(&mut versus, &mut None, options.log);
+  }
+  
+  // a hot seat tournament has 2 to 8 players, names are cut short to fit in the banner
+  static maxTournamentPlayers: uint = 8;
+  static maxNameLength: uint = 16;
+  
+  // asks for the players' names before the game takes over the terminal, one per line
+  // until an empty line, or until there are as many players as there can be
+  fn read_player_names() -> Vec<~str> {
+    println!("{}", strings::fill(strings::get().tournamentNames, &[maxTournamentPlayers.to_str().as_slice()]));
+    let mut input = io::stdin();
+    let mut names = Vec::new();
+    while names.len() < maxTournamentPlayers {
+      let name = match input.read_line() {
+        Ok(line) => line.trim().to_owned(),
+        Err(_)   => break
+      };
+      if name.len() == 0 {
+        break;
+      }
+      names.push(name.slice_chars(0, cmp::min(name.char_len(), maxNameLength)).to_owned());
+    }
+    names
+  }
+  
+  // Shows whose turn it is over an empty board and waits for them to press a key. False if
+  // they'd rather stop the tournament (q, escape or ctrl-c) or the terminal has gone away.
+  fn wait_for_turn(display: &Display, name: &str) -> bool {
+    use input_reader::{poll_stdin, read_stdin, Key, PollReady, PollTimeout, PollInterrupted};
+    
+    display.init();
+    display.print_banner(strings::fill(strings::get().tournamentTurn, &[name]).as_slice());
+    display.flush();
+    loop {
+      match poll_stdin(-1) {
+        PollReady       => return match read_stdin() {
+          Key(0x71) | Key(0x51) | Key(0x1B) | Key(0x03) => false,
+          _                                             => true
+        },
+        PollTimeout     => (),
+        PollInterrupted => if terminal_control::hung_up() { return false; }
+      }
+    }
+  }
+  
+  // Hot seat score attack: the players pass the keyboard around, each playing one game of
+  // the same mode and seed. Once everyone has played, the standings are shown and kept with
+  // the scores. A game quit early counts with the score it got to, but stopping at a turn
+  // (or a hangup) ends the tournament without standings.
+  pub fn run_tournament(display: &Display, options: &Options, config: &Config) {
+    let text = strings::get();
+    let names = read_player_names();
+    if names.len() < 2 {
+      println!("{}", text.tournamentTooFew);
+      os::set_exit_status(1);
+      return;
+    }
+    let seed = match options.seed {
+      Some(seed) => seed,
+      None       => rand::random::<u32>() as u64
+    };
+    
+    let mut standings = Vec::new();
+    {
+      // the restorer resets the terminal out of raw mode once it's dropped
+      let _restorer = terminal_control::set_terminal_raw_mode();
+      let _curses = if options.curses { Some(curses::start()) } else { None };
+      
+      terminal_control::catch_hangup();
+      
+      output::install(Vec::new(), options.log);
+      
+      for name in names.iter() {
+        if !wait_for_turn(display, name.as_slice()) {
+          break;
+        }
+        let pieceGetter = piece_getter::new(seed);
+        let scoring = new_scoring(options.mode, options, config);
+        let gravity = new_gravity(options.mode, options, config);
+        let scoreKeeper = score_keeper::null();
+        let mut game = new_game(display, pieceGetter, scoring, gravity, scoreKeeper, options.mode, seed, time::precise_time_ns(), config, options.log);
+        game.preview = options.preview;
+        game.engine.spawnDelay = options.spawnDelay;
+        
+        display.init();
+        main_loop(&mut game, &mut None, options.log);
+        if terminal_control::hung_up() {
+          break;
+        }
+        standings.push(score_keeper::TournamentPlayer{name: name.clone(), entry: game.score_entry()});
+      }
+      
+      // there's no terminal left to clean up after a hangup
+      if !terminal_control::hung_up() {
+        display.close();
+      }
+      output::uninstall();
+    }
+    if standings.len() < names.len() {
+      return;
+    }
+    
+    score_keeper::rank_players(options.mode, &mut standings);
+    println!("{}", strings::fill(text.tournamentStandings, &[modes::name(options.mode), seed.to_str().as_slice()]));
+    for (place, player) in standings.iter().enumerate() {
+      let score = player.entry.score;
+      println!("{}", strings::fill(text.tournamentPlace, &[(place + 1).to_str().as_slice(),
+                                                          format!("{:16s}", player.name).as_slice(),
+                                                          strings::grouped(score.score).as_slice(),
+                                                          score.lines.to_str().as_slice(),
+                                                          score_keeper::format_duration(player.entry.duration).as_slice()]));
+    }
+    let result = score_keeper::TournamentResult{time:      time::now(),
+                                                mode:      modes::name(options.mode).to_owned(),
+                                                seed:      seed,
+                                                standings: standings};
+    score_keeper::new(options.scoreFile.clone()).store_tournament(result);
+  }
+  
+  fn new_game<'a>(display:     &'a Display,
+                  pieceGetter: ~PieceGetter,
+                  scoring:     ~Scoring,
+                  gravity:     ~Gravity,
+                  scoreKeeper: &'a ScoreKeeper,
+                  mode:        Mode,
+                  seed:        u64,
+                  startNs:     u64,
+                  config:      &Config,
+                  log:         trace::Log) -> TetrisGame<'a> {
+    TetrisGame{display:     display,
+               otherDisplay: None,
+               engine:      engine::new(pieceGetter, scoring, gravity),
+               scoreKeeper: scoreKeeper,
+               lastTitleNs: startNs,
+               mode:        mode,
+               startNs:     startNs,
+               garbageMs:   0,
+               endHeading:  strings::get().gameOver,
+               seed:        seed,
+               // garbage rows get their own generator so they don't change the piece sequence
+               garbageRng:  SeedableRng::from_seed(&[seed, 1]),
+               listeners:   Vec::new(),
+               hint:        None,
+               hintUntilNs: 0,
+               trail:       Vec::new(),
+               trailUntilNs: 0,
+               stepDueNs:   startNs + firstStepNs,
+               player:      0,
+               outgoingGarbage: 0,
+               pendingGarbage:  0,
+               preview:     true,
+               stats:       stats_hud::new(),
+               scoreLog:    Some(Vec::new()),
+               sandbox:     false,
+               stepping:    false,
+               goalLeft:    None,
+               pausedAtNs:  None,
+               menuItem:    0,
+               themeWatch:  None,
+               idlePauseNs: None,
+               lastInputNs: startNs,
+               idle:        false,
+               focusPaused: false,
+               countdownLeft: 0,
+               countdownNs: 0,
+               marathonLevel: config.marathonLevel,
+               garbage:     config.garbage,
+               aiWeights:   config.aiWeights,
+               keys:        config.keys,
+               log:         log}
+  }
+  
+  fn new_scoring(mode: Mode, options: &Options, config: &Config) -> ~Scoring {
+    // NES mode always uses NES scoring, and NES gravity unless some other gravity was chosen.
+    // Marathon uses the guideline scoring, where the level goes up every 10 lines with no
+    // last level to get stuck on.
+    match mode {
+      NesClassic => scoring::new(scoring::Nes, config.levels.clone(), config.bonusDecay),
+      Marathon   => scoring::new(scoring::Guideline, config.levels.clone(), config.bonusDecay),
+      _          => scoring::new(options.scoring, config.levels.clone(), config.bonusDecay)
+    }
+  }
+  
+  fn new_gravity(mode: Mode, options: &Options, config: &Config) -> ~Gravity {
+    let gravity = match (options.gravity, mode) {
+      (Some(kind), _)    => gravity::new(kind, config.levels.as_slice()),
+      (None, NesClassic) => gravity::new(gravity::NesCurve, config.levels.as_slice()),
+      (None, _)          => gravity::new(gravity::LevelTable, config.levels.as_slice())
+    };
+    if options.speed == 1.0 { gravity } else { gravity::scaled(gravity, options.speed) }
+  }
+  
+  // plays the ghost of the replay alongside the player's game
+  // (in miniature, if the player's display has no room for a second board)
+  fn race(player: &mut TetrisGame, replay: Replay, options: &Options, config: &Config, debug: &mut Option<DebugOverlay>) {
+    let sideDisplay = graphics::side_display(strings::get().ghost, player.display.colors());
+    let nullDisplay = graphics::NullDisplay;
+    let mini = !player.display.has_room_for_side_board();
+    let display = if mini { &nullDisplay as &Display } else { &sideDisplay as &Display };
+    let pieceGetter = piece_getter::new(replay.seed);
+    let scoring = new_scoring(replay.mode, options, config);
+    let gravity = new_gravity(replay.mode, options, config);
+    let scoreKeeper = score_keeper::null();
+    // the ghost's clock starts with the race, which its events are timed from
+    let startNs = time::precise_time_ns();
+    let mut ghost = new_game(display, pieceGetter, scoring, gravity, scoreKeeper,
+                             replay.mode, replay.seed, startNs, config, options.log);
+    ghost.player = 1;
+    
+    let mut race = GhostRace{player:  player,
+                             ghost:   &mut ghost,
+                             replay:  replay,
+                             next:    0,
+                             startNs: startNs,
+                             mini:    mini};
+    main_loop(&mut race, debug, options.log);
+  }
+  
+  // lets the crowd vote on the moves of the player's game
+  fn crowd_play(game: &mut TetrisGame, input: crowd::CrowdInput, debug: &mut Option<DebugOverlay>) {
+    let log = game.log;
+    let mut crowdPlay = CrowdPlay{game:        game,
+                                  input:       input,
+                                  votes:       Vec::new(),
+                                  windowEndNs: time::precise_time_ns() + crowdVoteWindowNs};
+    main_loop(&mut crowdPlay, debug, log);
+  }
+  
+  // a game in the benchmark is stopped after this many pieces, in case the AI never loses
+  static benchMaxPieces: uint = 10000;
+  
+  // each set of weights --ai-tune tries plays this many games, on the same seeds, with
+  // fewer pieces than the benchmark so that many sets can be tried
+  static tuneGames: uint = 5;
+  static tuneMaxPieces: uint = 2000;
+  
+  // Plays a game with the AI as fast as possible, without drawing anything, until it's over
+  // or maxPieces have been set. Gives the final score and the number of pieces set.
+  fn play_ai_game(seed: u64, weights: &ai::Weights, maxPieces: uint, options: &Options, config: &Config) -> (Score, uint) {
+    let display = graphics::NullDisplay;
+    let pieceGetter = piece_getter::new(seed);
+    let scoring = new_scoring(options.mode, options, config);
+    let gravity = new_gravity(options.mode, options, config);
+    let scoreKeeper = score_keeper::null();
+    let mut game = new_game(&display as &Display, pieceGetter, scoring, gravity, scoreKeeper,
+                            options.mode, seed, time::precise_time_ns(), config, options.log);
+    
+    // plan and make the moves for each new piece, then let gravity take over
+    let mut plannedFor = None;
+    while game.engine.pieces < maxPieces {
+      match game.engine.state {
+        Fall if plannedFor != Some(game.engine.pieces) => {
+          plannedFor = Some(game.engine.pieces);
+          match ai::best_placement(&game.engine.board, &game.engine.piece, weights) {
+            Some(placement) => {
+              for action in ai::actions(&placement).iter() {
+                game.handle_input(input_reader::ActionEvent{action: *action, timeNs: 0, player: 0});
+              }
+            }
+            None => ()
+          }
+        }
+        _ => ()
+      }
+      if game.step_gravity(time::precise_time_ns()).is_none() {
+        break;
+      }
+    }
+    (game.engine.scoring.get_score(), game.engine.pieces)
+  }
+  
+  // Plays games with the AI as fast as possible, without drawing anything, and
+  // prints how well it did. Game i is played with seed + i so runs can be repeated.
+  pub fn run_ai_bench(games: uint, options: &Options, config: &Config) {
+    let baseSeed = match options.seed {
+      Some(seed) => seed,
+      None       => rand::random::<u32>() as u64
+    };
+    let weights = config.aiWeights;
+    
+    let mut totalLines = 0;
+    let mut totalScore = 0;
+    let mut totalPieces = 0;
+    let mut bestScore = 0;
+    let mut stopped = 0;
+    let startNs = time::precise_time_ns();
+    
+    for i in range(0, games) {
+      let (score, pieces) = play_ai_game(baseSeed + i as u64, &weights, benchMaxPieces, options, config);
+      if pieces >= benchMaxPieces {
+        stopped += 1;
+      }
+      
+      totalLines += score.lines;
+      totalScore += score.score;
+      totalPieces += pieces;
+      if score.score > bestScore {
+        bestScore = score.score;
+      }
+    }
+    
+    let seconds = (time::precise_time_ns() - startNs) as f64 / 1000000000.0;
+    let text = strings::get();
+    let values = [strings::fill(text.benchSeeds, &[games.to_str().as_slice(), baseSeed.to_str().as_slice(),
+                                                   (baseSeed + games as u64 - 1).to_str().as_slice()]),
+                  format!("{:.1f}", totalLines as f64 / games as f64),
+                  format!("{:.1f}", totalScore as f64 / games as f64),
+                  bestScore.to_str(),
+                  format!("{:.1f}", totalPieces as f64 / games as f64),
+                  strings::fill(text.benchStopped, &[stopped.to_str().as_slice(), benchMaxPieces.to_str().as_slice()]),
+                  strings::fill(text.benchTime, &[format!("{:.2f}", seconds).as_slice(),
+                                                  format!("{:.1f}", games as f64 / seconds).as_slice()])];
+    table::print_labeled(text.benchLabels.as_slice(), values.as_slice());
+  }
+  
+  // average lines cleared over the tuning games
+  fn tune_lines(weights: &ai::Weights, baseSeed: u64, options: &Options, config: &Config) -> f64 {
+    let mut lines = 0;
+    for i in range(0, tuneGames) {
+      let (score, _) = play_ai_game(baseSeed + i as u64, weights, tuneMaxPieces, options, config);
+      lines += score.lines;
+    }
+    lines as f64 / tuneGames as f64
+  }
+  
+  // Looks for better AI weights by trying sets of them without drawing anything: starting
+  // from the current weights, each new set is the best one so far with every weight moved a
+  // little (odd tries) or a lot (even tries), and is kept if it clears more lines on the
+  // same games. Prints the best set, and with save also writes it to the config file.
+  pub fn run_ai_tune(tries: uint, save: bool, options: &Options, config: &Config) {
+    let baseSeed = match options.seed {
+      Some(seed) => seed,
+      None       => rand::random::<u32>() as u64
+    };
+    let mut rng: Isaac64Rng = SeedableRng::from_seed(&[baseSeed, 3]);
+    let startNs = time::precise_time_ns();
+    
+    let text = strings::get();
+    let mut best = config.aiWeights;
+    let mut bestLines = tune_lines(&best, baseSeed, options, config);
+    println!("{}", strings::fill(text.tuneStart, &[format!("{:.1f}", bestLines).as_slice()]));
+    for i in range(1, tries + 1) {
+      let amount = if i % 2 == 1 { 0.05 } else { 0.3 };
+      let weights = ai::vary(&best, amount, &mut rng);
+      let lines = tune_lines(&weights, baseSeed, options, config);
+      let line = if lines > bestLines { text.tuneBetter } else { text.tuneTry };
+      println!("{}", strings::fill(line, &[i.to_str().as_slice(), format!("{:.1f}", lines).as_slice()]));
+      if lines > bestLines {
+        best = weights;
+        bestLines = lines;
+      }
+    }
+    
+    let seconds = (time::precise_time_ns() - startNs) as f64 / 1000000000.0;
+    let values = [strings::fill(text.tuneGames, &[tuneGames.to_str().as_slice(), baseSeed.to_str().as_slice(),
+                                                  (baseSeed + tuneGames as u64 - 1).to_str().as_slice(),
+                                                  tuneMaxPieces.to_str().as_slice()]),
+                  config::weights_text(&best),
+                  format!("{:.1f}", bestLines),
+                  format!("{:.2f}s", seconds)];
+    table::print_labeled(text.tuneLabels.as_slice(), values.as_slice());
+    
+    if save {
+      let path = paths::config_file();
+      match config::save_ai_weights(&path, &best) {
+        Ok(())   => println!("{}", strings::fill(text.savedWeights, &[path.display().to_str().as_slice()])),
+        Err(msg) => {
+          println!("{}", strings::fill(text.cantWrite, &[path.display().to_str().as_slice(), msg.as_slice()]));
+          os::set_exit_status(1);
+        }
+      }
+    }
+  }
+  
+  // shows a recorded game being played again, in the mode it was recorded in
+  pub fn run_replay(display: &Display, path: &Path, options: &Options, config: &Config) {
+    let replay = match replay::load(path) {
+      Ok(replay) => replay,
+      Err(msg)   => {
+        println!("{}", strings::fill(strings::get().cantReplay, &[msg.as_slice()]));
+        return;
+      }
+    };
+    
+    // the restorer resets the terminal out of raw mode once it's dropped
+    let _restorer = terminal_control::set_terminal_raw_mode();
+    let _curses = if options.curses { Some(curses::start()) } else { None };
+    
+    terminal_control::catch_hangup();
+    
+    output::install(Vec::new(), options.log);
+    
+    let pieceGetter = piece_getter::new(replay.seed);
+    let scoring = new_scoring(replay.mode, options, config);
+    let gravity = new_gravity(replay.mode, options, config);
+    let scoreKeeper = score_keeper::null();
+    let startNs = time::precise_time_ns();
+    let mut game = new_game(display, pieceGetter, scoring, gravity, scoreKeeper,
+                            replay.mode, replay.seed, startNs, config, options.log);
+    
+    let start = replay::checkpoint(0, 0, &game.engine);
+    
+    display.init();
+    let mut playback = ReplayPlayback{game:    &mut game,
+                                      replay:  replay,
+                                      start:   start,
+                                      next:    0,
+                                      startNs: startNs,
+                                      stopped: false};
+    main_loop(&mut playback, &mut None, options.log);
+    
+    if !terminal_control::hung_up() {
+      display.close();
+    }
+    
+    output::uninstall();
+  }
+  
+  // Plays a replay as fast as it can without drawing anything, writing a picture of the
+  // game to dir every frame interval of game time, until the replay runs out of events
+  pub fn export_replay_frames(path: &Path, dir: &Path, command: &Option<~str>, options: &Options, config: &Config) {
+    let text = strings::get();
+    let replay = match replay::load(path) {
+      Ok(replay) => replay,
+      Err(msg)   => {
+        println!("{}", strings::fill(text.cantReplay, &[msg.as_slice()]));
+        return;
+      }
+    };
+    match frame_export::create_dir(dir) {
+      Ok(()) => (),
+      Err(e) => {
+        println!("{}", strings::fill(text.cantFrames, &[format!("{}: {}", dir.display(), e).as_slice()]));
+        return;
+      }
+    }
+    
+    let display = graphics::NullDisplay;
+    let pieceGetter = piece_getter::new(replay.seed);
+    let scoring = new_scoring(replay.mode, options, config);
+    let gravity = new_gravity(replay.mode, options, config);
+    let scoreKeeper = score_keeper::null();
+    let mut game = new_game(&display as &Display, pieceGetter, scoring, gravity, scoreKeeper,
+                            replay.mode, replay.seed, time::precise_time_ns(), config, options.log);
+    // the frames are looked at away from this terminal
+    let palette = graphics::plainColors.themed(config.theme).palette;
+    
+    let mut next = 0;
+    let mut frames = 0;
+    loop {
+      let elapsed = frames as u64 * frame_export::frameIntervalMs;
+      play_replay_events_until(&mut game, &replay, &mut next, elapsed);
+      
+      frames += 1;
+      let picture = graphics::board_picture(&game.engine.board, &game.engine.piece, game.engine.scoring.get_score(), palette);
+      match frame_export::write_frame(dir, frames, picture.as_slice(), command) {
+        Ok(()) => (),
+        Err(msg) => {
+          println!("{}", strings::fill(text.cantFrames, &[msg.as_slice()]));
+            return;
+        }
+      }
+      if next >= replay.events.len() {
+        break;
+      }
+    }
+    println!("{}", strings::fill(text.framesExported, &[frames.to_str().as_slice(), dir.display().to_str().as_slice()]));
+  }
+  
+  // teaches the controls, on a game of its own that doesn't keep a score
+  pub fn run_tutorial(display: &Display, options: &Options, config: &Config) {
+    // the restorer resets the terminal out of raw mode once it's dropped
+    let _restorer = terminal_control::set_terminal_raw_mode();
+    let _curses = if options.curses { Some(curses::start()) } else { None };
+    
+    terminal_control::catch_hangup();
+    
+    output::install(Vec::new(), options.log);
+    
+    // a T to practice with, then I pieces for the row that's left to clear
+    let pieceGetter = piece_getter::scripted(vec!(pieces::T, pieces::I));
+    let scoring = new_scoring(Endless, options, config);
+    let gravity = new_gravity(Endless, options, config);
+    let scoreKeeper = score_keeper::null();
+    let mut game = new_game(display, pieceGetter, scoring, gravity, scoreKeeper,
+                            Endless, 0, time::precise_time_ns(), config, options.log);
+    // the first piece starts on the board instead of above it, so it can be seen before it falls
+    game.engine.piece = pieces::translate(&game.engine.piece, 2, 0);
+    
+    display.init();
+    let mut tutorial = Tutorial{game:         &mut game,
+                                lesson:       MoveLesson,
+                                movedLeft:    false,
+                                movedRight:   false,
+                                rotated:      false,
+                                lessonPieces: 0,
+                                doneNs:       0};
+    main_loop(&mut tutorial, &mut None, options.log);
+    
+    if !terminal_control::hung_up() {
+      display.close();
+    }
+    
+    output::uninstall();
+  }
+  
+  // the player's board goes to cells from whichever display the game is on
+  fn mirror_cells(display: &Display, otherDisplay: Option<&Display>, cells: Sender<[u8, ..200]>) {
+    match otherDisplay {
+      Some(other) => other.mirror_cells(cells.clone()),
+      None        => ()
+    }
+    display.mirror_cells(cells);
+  }
+  
+  // The pause menu can switch to otherDisplay mid-game, unless there's a side board for a
+  // race or the frames are being recorded or watched at the size the game started with.
+  pub fn run_game(display: &Display, otherDisplay: Option<&Display>, options: &Options, config: &Config) {
+    // A game saved by a hangup is offered before a new one starts, unless this one is a race
+    // or played by a crowd (or there's no terminal to ask on)
+    let resume = if options.resume {
+      true
+    } else if saved_game::exists() && options.ghostFile.is_none() && options.crowdFile.is_none() &&
+              terminal_control::stdin_is_terminal() && terminal_control::stdout_is_terminal() {
+      match ask_resume(display.colors().palette) {
+        Some(resume) => resume,
+        None         => return
+      }
+    } else {
+      false
+    };
+    
+    let saved = if resume {
+      match saved_game::take() {
+        Ok(saved) => Some(saved),
+        Err(msg)  => {
+          println!("{}", strings::fill(strings::get().cantResume, &[msg.as_slice()]));
+          return;
+        }
+      }
+    } else {
+      None
+    };
+    
+    let ghost = match options.ghostFile {
+      Some(ref path) if saved.is_none() => match replay::load(path) {
+        Ok(ghost) => Some(ghost),
+        Err(msg)  => {
+          println!("{}", strings::fill(strings::get().cantRace, &[msg.as_slice()]));
+          return;
+        }
+      },
+      _ => None
+    };
+    
+    // the crowd plays the player's own game, so there's no crowd in a race
+    let crowdInput = match options.crowdFile {
+      Some(ref path) if ghost.is_none() => match crowd::open_pipe(path) {
+        Ok(input) => Some(input),
+        Err(e)    => {
+          println!("{}", strings::fill(strings::get().cantReadVotes,
+                                       &[path.display().to_str().as_slice(), e.to_str().as_slice()]));
+          return;
+        }
+      },
+      _ => None
+    };
+    
+    // a resumed game carries on in the mode it was saved in, and a race is
+    // played in the mode of the game being raced
+    let mode = match (&saved, &ghost) {
+      (&Some(ref saved), _) => match modes::from_name(saved.mode) {
+        Some(mode) => mode,
+        None       => {
+          let text = strings::get();
+          println!("{}", strings::fill(text.cantResume, &[strings::fill(text.unknownMode, &[saved.mode.as_slice()]).as_slice()]));
+          return;
+        }
+      },
+      (&None, &Some(ref ghost)) => ghost.mode,
+      (&None, &None)            => options.mode
+    };
+    
+    let mut setBlocks = board::new();
+    match saved {
+      Some(ref saved) => match saved_game::decode_board(saved.board.as_slice(), &mut setBlocks) {
+        Ok(())   => (),
+        Err(msg) => {
+          println!("{}", strings::fill(strings::get().cantResume, &[msg.as_slice()]));
+          return;
+        }
+      },
+      None => ()
+    }
+    
+    // Every game has a seed so it can be played again. Random seeds are kept small
+    // enough to be easy to type back in. Races use the same pieces as the game being raced.
+    let seed = match (&saved, &ghost, options.seed) {
+      (&Some(ref saved), _, _)     => saved.seed,
+      (&None, &Some(ref ghost), _) => ghost.seed,
+      (&None, &None, Some(seed))   => seed,
+      (&None, &None, None)         => rand::random::<u32>() as u64
+    };
+    
+    // Resumed games aren't recorded, since their replay couldn't start from the seed alone,
+    // and neither are sandbox games. Every other game is recorded to the last game's replay
+    // file unless another file was given.
+    let mut replay = None;
+    if saved.is_none() && !options.sandbox {
+      let path = match options.replayFile {
+        Some(ref path) => path.clone(),
+        None           => paths::last_replay_file()
+      };
+      let recorder = paths::create_parent_dir(&path).and_then(|()| replay::new(&path, mode, seed));
+      match (recorder, &options.replayFile) {
+        (Ok(recorder), _)  => replay = Some(recorder),
+        (Err(e), &Some(_)) => {
+          println!("{}", strings::fill(strings::get().cantRecord, &[path.display().to_str().as_slice(), e.to_str().as_slice()]));
+          return;
+        }
+        (Err(_), &None)    => ()
+      }
+    }
+    
+    let announcer = match options.announceFile {
+      Some(ref path) => {
+        match announcer::new(path) {
+          Ok(announcer) => Some(announcer),
+          Err(e)        => {
+            println!("{}", strings::fill(strings::get().cantAnnounce,
+                                         &[path.display().to_str().as_slice(), e.to_str().as_slice()]));
+            return;
+          }
+        }
+      }
+      None => None
+    };
+    
+    let mut sinks: Vec<~FrameSink> = Vec::new();
+    let mut remarks = None;  // where the commentator sends its remarks, when there are spectators
+    match options.castFile {
+      Some(ref path) => {
+        match cast_recorder::new(&Path::new(path.as_slice()), display.size()) {
+          Ok(recorder) => sinks.push(~recorder as ~FrameSink),
+          Err(e)       => {
+            println!("{}", strings::fill(strings::get().cantRecord, &[path.as_slice(), e.to_str().as_slice()]));
+            return;
+          }
+        }
+      }
+      None => ()
+    }
+    match options.spectatePort {
+      Some(port) => {
+        let (_, rows) = display.size();
+        let (sender, receiver) = channel();
+        match spectator::new(port, rows, receiver) {
+          Ok(server) => {
+            sinks.push(~server as ~FrameSink);
+            remarks = Some(sender);
+          }
+          Err(e)     => {
+            println!("{}", strings::fill(strings::get().cantSpectate, &[port.to_str().as_slice(), e.to_str().as_slice()]));
+            return;
+          }
+        }
+      }
+      None => ()
+    }
+    
+    let switchable = ghost.is_none() && sinks.is_empty();
+    
+    // the captured board and the LED matrix don't depend on the display's size, so they're
+    // no reason not to switch
+    match options.captureFile {
+      Some(ref path) => {
+        let (sender, receiver) = channel();
+        match frame_capture::new(path, receiver) {
+          Ok(capture) => {
+            sinks.push(~capture as ~FrameSink);
+            mirror_cells(display, otherDisplay, sender);
+          }
+          Err(e)      => {
+            println!("{}", strings::fill(strings::get().cantRecord,
+                                         &[path.display().to_str().as_slice(), e.to_str().as_slice()]));
+            return;
+          }
+        }
+      }
+      None => ()
+    }
+    match options.ledFile {
+      Some(ref path) => {
+        let (sender, receiver) = channel();
+        match led_matrix::new(path, receiver, options.log) {
+          Ok(matrix) => {
+            sinks.push(~matrix as ~FrameSink);
+            mirror_cells(display, otherDisplay, sender);
+          }
+          Err(e)     => {
+            println!("{}", strings::fill(strings::get().cantLed, &[path.display().to_str().as_slice(), e.to_str().as_slice()]));
+            return;
+          }
+        }
+      }
+      None => ()
+    }
+    
+    // the restorer resets the terminal out of raw mode once it's dropped
+    let _restorer = terminal_control::set_terminal_raw_mode();
+    let _curses = if options.curses { Some(curses::start()) } else { None };
+    
+    terminal_control::catch_hangup();
+    
+    output::install(sinks, options.log);
+    
+    // a resumed or crowd played game goes straight to playing, otherwise wait on the
+    // title screen, showing the demo whenever it's left alone for long enough
+    if saved.is_none() && crowdInput.is_none() {
+      loop {
+        match title_screen(display) {
+          Play     => break,
+          Idle     => run_demo(display, options, config),
+          QuitGame => {
+            display.close();
+            output::uninstall();
+            return;
+          }
+        }
+      }
+    }
+    
+    display.init();
+    
+    let scoring = new_scoring(mode, options, config);
+    let gravity = new_gravity(mode, options, config);
+    
+    let scoreKeeper = match config.leaderboard {
+      Some(ref url) => remote_scores::new(score_keeper::new(options.scoreFile.clone()), url.clone()),
+      None          => score_keeper::new(options.scoreFile.clone())
+    };
+    
+    let pieceGetter = piece_getter::new(seed);
+    
+    let startNs = time::precise_time_ns();
+    let mut game = new_game(display, pieceGetter, scoring, gravity, scoreKeeper, mode, seed, startNs, config, options.log);
+    game.preview = options.preview;
+    game.engine.spawnDelay = options.spawnDelay;
+    if switchable {
+      game.otherDisplay = otherDisplay;
+    }
+    if ghost.is_none() {
+      game.themeWatch = Some(theme_watch::new(paths::config_file(), config.theme, startNs));
+    }
+    // gravity doesn't run on its own when stepping, and the moves of a race's ghost or
+    // the crowd don't wait for the player
+    if config.idlePause > 0 && ghost.is_none() && crowdInput.is_none() && !options.stepping {
+      game.idlePauseNs = Some(config.idlePause as u64 * 60 * 1000000000);
+    }
+    if options.sandbox {
+      game.sandbox = true;
+    }
+    if options.stepping {
+      game.stepping = true;
+    }
+    if options.debugOverlay {
+      game.scoreLog = None;
+    }
+    
+    match replay {
+      Some(mut replay) => {
+        replay.startNs = startNs;
+        game.listeners.push(~replay as ~GameListener);
+      }
+      None => ()
+    }
+    if !config.sounds.is_empty() {
+      game.listeners.push(~config.sounds.clone() as ~GameListener);
+    }
+    match announcer {
+      Some(announcer) => game.listeners.push(~announcer as ~GameListener),
+      None            => ()
+    }
+    match remarks {
+      Some(remarks) => game.listeners.push(~commentary::new(startNs, config.garbage, remarks) as ~GameListener),
+      None          => ()
+    }
+    
+    match saved {
+      Some(ref saved) => {
+        game.engine.scoring.restore(saved.score, saved.count);
+        game.startNs -= saved.duration * 1000000;
+        game.engine.board = setBlocks;
+        game.engine.piece = saved_game::piece_from_name(saved.piece).unwrap_or(game.engine.piece);
+        game.engine.nextPiece = saved_game::piece_from_name(saved.nextPiece).unwrap_or(game.engine.nextPiece);
+      }
+      None => ()
+    }
+
+    let mut debug = if options.debugOverlay {
+      Some(debug_overlay::new(time::precise_time_ns()))
+    } else {
+      None
+    };
+
+    // a race can't be paused, so it isn't paused when the terminal loses focus either
+    let focusReported = ghost.is_none() && terminal_control::stdout_is_terminal();
+    if focusReported {
+      graphics::report_focus(true);
+    }
+    
+    match (ghost, crowdInput) {
+      (Some(ghost), _)    => race(&mut game, ghost, options, config, &mut debug),
+      (None, Some(input)) => crowd_play(&mut game, input, &mut debug),
+      (None, None)        => main_loop(&mut game, &mut debug, options.log)
+    }
+    
+    // a game quit while it was left alone mustn't leave the next one dimmed
+    game.display.set_dimmed(false);
+    
+    // there's no terminal left to clean up after a hangup, and the game may have
+    // switched displays
+    if !terminal_control::hung_up() {
+      if focusReported {
+        graphics::report_focus(false);
+      }
+      game.display.close();
+    }
+    
+    output::uninstall();
+  }
+}
+
+fn display_help() {
+  print(strings::get().help);
+}
+
+// How the games listed by --scores are picked when --since, --sort or --top is given: out of
+// every game kept, not just the high and recent scores
+enum ScoreOrder {
+  ByScore,
+  ByDate,
+  ByLevel
+}
+
+struct ScoreQuery {
+  since:  Option<u64>,   // only games that ended this many seconds ago or less
+  order:  ScoreOrder,
+  top:    Option<uint>,  // only the first this many games of each mode (or of each period)
+  period: Option<score_keeper::Period>,  // the best games of each week or month
+  seed:   Option<u64>   // the best games played with this seed
+}
+
+// how many games --period shows for each week or month if --top doesn't say
+static defaultPeriodBest: uint = 3;
+
+fn score_order_from_name(name: &str) -> Option<ScoreOrder> {
+  match name {
+    "score" => Some(ByScore),
+    "date"  => Some(ByDate),
+    "level" => Some(ByLevel),
+    _       => None
+  }
+}
+
+// a time span like 30m, 12h, 7d or 2w, in seconds
+fn parse_span(text: &str) -> Option<u64> {
+  let units = [("m", 60u64), ("h", 60 * 60), ("d", 24 * 60 * 60), ("w", 7 * 24 * 60 * 60)];
+  for &(unit, seconds) in units.iter() {
+    if text.ends_with(unit) {
+      return from_str::<u64>(text.slice_to(text.len() - 1)).and_then(|n| n.checked_mul(&seconds));
+    }
+  }
+  None
+}
+
+// The games of a mode that the query picks, in its order (all of them, --top is left to the
+// caller). By score they're ranked the way the mode ranks its high scores, so finished
+// sprints come first, fastest first.
+fn picked_scores(entries: &[score_keeper::ScoreEntry], mode: &str, query: &ScoreQuery) -> Vec<score_keeper::ScoreEntry> {
+  let now = time::get_time().sec;
+  let mut picked: Vec<score_keeper::ScoreEntry> = entries.iter().filter(|entry| {
+    entry.mode.as_slice() == mode && match query.since {
+      Some(seconds) => now - entry.time.to_timespec().sec <= seconds as i64,
+      None          => true
+    }
+  }).map(|entry| entry.clone()).collect();
+  match (query.order, modes::from_name(mode)) {
+    (ByScore, Some(mode)) => picked.sort_by(|e1, e2| score_keeper::better(mode, e1, e2)),
+    (ByScore, None)       => picked.sort_by(|e1, e2| e2.score.score.cmp(&e1.score.score)),
+    (ByDate, _)           => picked.sort_by(|e1, e2| e2.time.to_timespec().cmp(&e1.time.to_timespec())),
+    (ByLevel, _)          => picked.sort_by(|e1, e2| (e2.score.level, e2.score.score).cmp(&(e1.score.level, e1.score.score)))
+  }
+  picked
+}
+
+// show the scores for one mode, or every mode if filter is None, as the high and recent
+// scores or picked by query
+fn display_scores(storage: &score_keeper::ScoreStorage, filter: Option<modes::Mode>, query: Option<ScoreQuery>,
+                  palette: [u8, ..8]) {
+/*
+endless mode
+
+High Scores:
+Date              Level  Bonus  Score  Lines  Tetrises    Time  Seed    Board
+1970-01-01 00:00      3      2  1,024     25         2  4:42.1  random  35% full
+1970-01-01 00:00      1      1     12      1         0  0:42.1  12345   8% full
+
+Recent Scores:
+...
+*/
+
+  fn format_seed(seed: Option<u64>) -> ~str {
+    match seed {
+      Some(seed) => seed.to_str(),
+      None       => strings::get().randomSeed.to_owned()
+    }
+  }
+  
+  fn format_density(density: f64) -> ~str {
+    strings::fill(strings::get().boardFull, &[((density * 100.0).round() as int).to_str().as_slice()])
+  }
+  
+  // On a terminal the first three of a ranked list are gold, silver and bronze, games older
+  // than oldScoreDays are dimmed, and each level is shown in the color of a piece (the next
+  // piece along for each level, in the order I, J, L, O, S, T, Z)
+  static medalColors: [&'static str, ..3] = ["1;93", "1;37", "33"];
+  static oldScoreDays: i64 = 30;
+  static levelPieces: [pieces::PieceType, ..7] = [pieces::I, pieces::J, pieces::L, pieces::O,
+                                                  pieces::S, pieces::T, pieces::Z];
+  
+  // the scores under a heading, a row each in the order they're kept
+  fn print_scores(heading: &str, entries: &[score_keeper::ScoreEntry], ranked: bool, palette: [u8, ..8]) {
+    let colored = terminal_control::stdout_is_terminal();
+    let now = time::get_time().sec;
+    let c = strings::get().scoreColumns;
+    let mut scores = table::new([(c[0], table::Left), (c[1], table::Right), (c[2], table::Right),
+                                 (c[3], table::Right), (c[4], table::Right), (c[5], table::Right),
+                                 (c[6], table::Right), (c[7], table::Left), (c[8], table::Left)]);
+    for (place, entry) in entries.iter().enumerate() {
+      scores.add_row(vec!(entry.time.strftime("%Y-%m-%d %H:%M"),
+                          entry.score.level.to_str(),
+                          entry.score.bonus.to_str(),
+                          strings::grouped(entry.score.score),
+                          entry.score.lines.to_str(),
+                          entry.score.tetrises.to_str(),
+                          score_keeper::format_duration(entry.duration),
+                          format_seed(entry.seed),
+                          format_density(entry.density)));
+      if colored {
+        if ranked && place < medalColors.len() {
+          scores.color(None, medalColors[place]);
+        } else if now - entry.time.to_timespec().sec > oldScoreDays * 24 * 60 * 60 {
+          scores.color(None, "2");
+        }
+        let level = entry.score.level as uint;
+        scores.color(Some(1), graphics::piece_color_code(levelPieces[(level + 6) % 7], palette).as_slice());
+      }
+    }
+    
+    println(heading);
+    if entries.len() > 0 {
+      for line in scores.lines().iter() {
+        println(*line);
+      }
+    }
+    println("");
+  }
+  
+  fn first(entries: &[score_keeper::ScoreEntry], count: Option<uint>) -> &[score_keeper::ScoreEntry] {
+    match count {
+      Some(count) if count < entries.len() => entries.slice_to(count),
+      _                                    => entries
+    }
+  }
+  
+  let text = strings::get();
+  let entries = if query.is_some() { storage.all_entries() } else { Vec::new() };
+  for modeScores in storage.modes.iter() {
+    let mode = modes::from_name(modeScores.mode.as_slice());
+    if filter.is_some() && mode != filter {
+      continue;
+    }
+    
+    match query {
+      Some(ref query) => {
+        let seedScores = query.seed.and_then(|seed| storage.find_seed(modeScores.mode.as_slice(), seed));
+        let picked = match seedScores {
+          // picked_scores ranks the seed's high scores the way the mode ranks them
+          Some(scores) => picked_scores(scores.highScores.as_slice(), modeScores.mode.as_slice(), query),
+          None if query.seed.is_some() => Vec::new(),
+          None                         => picked_scores(entries.as_slice(), modeScores.mode.as_slice(), query)
+        };
+        if picked.len() == 0 {
+          continue;
+        }
+        println("");
+        println!("{}", strings::fill(text.modeHeading, &[modeScores.mode.as_slice()]));
+        println("");
+        match (query.seed, query.period) {
+          (Some(seed), _) => {
+            let heading = strings::fill(text.seedScores, &[seed.to_str().as_slice()]);
+            print_scores(heading.as_slice(), first(picked.as_slice(), query.top), true, palette);
+          }
+          (None, Some(period)) => {
+            let best = Some(query.top.unwrap_or(defaultPeriodBest));
+            let groups = score_keeper::group_by_period(picked.as_slice(), period);
+            for &(ref start, ref group) in groups.iter() {
+              let heading = strings::fill(text.periodHeadings[period as uint], &[start.as_slice()]);
+              print_scores(heading.as_slice(), first(group.as_slice(), best), true, palette);
+            }
+          }
+          (None, None) => {
+            let ranked = match query.order { ByDate => false, ByScore | ByLevel => true };
+            print_scores(text.scoreOrders[query.order as uint], first(picked.as_slice(), query.top), ranked, palette);
+          }
+        }
+      }
+      None => {
+        println("");
+        println!("{}", strings::fill(text.modeHeading, &[modeScores.mode.as_slice()]));
+        println("");
+        print_scores(text.highScores, modeScores.highScores.as_slice(), true, palette);
+        print_scores(text.recentScores, modeScores.recentScores.as_slice(), false, palette);
+      }
+    }
+  }
+}
+
+// how many of the last games --graph draws if --games doesn't say, and the most it can
+static defaultGraphGames: uint = 50;
+static maxGraphGames: uint = 200;
+
+// show lifetime totals over every game in the history, for all modes and then for each mode
+// (or just the mode given by filter), with a graph of each mode's last graphGames scores
+fn display_stats(scoreFile: &Path, filter: Option<modes::Mode>, graphGames: Option<uint>) {
+  struct Stats {
+    games:    uint,
+    lines:    int,
+    tetrises: int,
+    score:    i64,
+    level:    u16,
+    duration: u64,
+    scores:   Vec<i64>  // of each game, oldest first
+  }
+  
+  fn empty_stats() -> Stats {
+    Stats{games: 0, lines: 0, tetrises: 0, score: 0, level: 0, duration: 0, scores: Vec::new()}
+  }
+  
+  fn add(stats: &mut Stats, entry: &score_keeper::ScoreEntry) {
+    stats.games += 1;
+    stats.lines += entry.score.lines;
+    stats.tetrises += entry.score.tetrises;
+    stats.score = stats.score.checked_add(&entry.score.score).unwrap_or(std::i64::MAX);
+    if entry.score.level > stats.level {
+      stats.level = entry.score.level;
+    }
+    stats.duration += entry.duration;
+    stats.scores.push(entry.score.score);
+  }
+  
+  fn print_stats(heading: &str, stats: &Stats) {
+    // the share of all cleared lines that were cleared by tetrises
+    let tetrisRate = if stats.lines > 0 {
+      (stats.tetrises * 4) as f64 * 100.0 / stats.lines as f64
+    } else {
+      0.0
+    };
+    let seconds = stats.duration / 1000;
+    
+    let values = [stats.games.to_str(),
+                  stats.lines.to_str(),
+                  strings::grouped(stats.score / stats.games as i64),
+                  stats.level.to_str(),
+                  format!("{:.1f}%", tetrisRate),
+                  format!("{}:{:02u}:{:02u}", seconds / 3600, (seconds / 60) % 60, seconds % 60)];
+    
+    println("");
+    println(heading);
+    table::print_labeled(strings::get().statLabels.as_slice(), values.as_slice());
+  }
+  
+  // rows the graph's bars can be
+  static graphHeight: uint = 8;
+  
+  // The scores as a bar chart, with a column for each game from the oldest on the left. Bars
+  // are rounded up, so a game with any points at all shows.
+  fn print_graph(scores: &[i64]) {
+    let labels = strings::get().graphLabels;
+    let top = scores.iter().fold(0i64, |top, &score| if score > top { score } else { top });
+    let topLabel = strings::grouped(top);
+    let labelWidth = strings::width(topLabel);
+    let bars: Vec<uint> = scores.iter().map(|&score| {
+      if top > 0 { (score as f64 * graphHeight as f64 / top as f64).ceil() as uint } else { 0 }
+    }).collect();
+    
+    println("");
+    println(strings::fill(labels[0], &[scores.len().to_str().as_slice()]));
+    for row in range(0, graphHeight).rev() {
+      let label = if row == graphHeight - 1 { topLabel.as_slice() } else { "" };
+      let line: ~str = bars.iter().map(|&bar| if bar > row { '#' } else { ' ' }).collect();
+      println!("{}{} |{}", " ".repeat(labelWidth - strings::width(label)), label, line.trim_right());
+    }
+    println!("{}0 +{}", " ".repeat(labelWidth - 1), "-".repeat(scores.len()));
+    let gap = scores.len() as int - strings::width(labels[1]) as int - strings::width(labels[2]) as int;
+    println!("{}{}{}{}", " ".repeat(labelWidth + 2), labels[1], " ".repeat(if gap > 1 { gap as uint } else { 1 }), labels[2]);
+  }
+  
+  let storage = score_keeper::new(scoreFile.clone()).get_scores();
+  
+  let mut total = empty_stats();
+  let mut perMode: Vec<(~str, Stats)> = Vec::new();
+  for entry in storage.history.iter() {
+    if filter.is_some() && modes::from_name(entry.mode.as_slice()) != filter {
+      continue;
+    }
+    add(&mut total, entry);
+    
+    let index = match perMode.iter().position(|&(ref mode, _)| *mode == entry.mode) {
+      Some(index) => index,
+      None        => {
+        perMode.push((entry.mode.clone(), empty_stats()));
+        perMode.len() - 1
+      }
+    };
+    match *perMode.get_mut(index) {
+      (_, ref mut stats) => add(stats, entry)
+    }
+  }
+  
+  if total.games == 0 {
+    println(strings::get().noGames);
+    return;
+  }
+  
+  if filter.is_none() {
+    print_stats(strings::get().allModes, &total);
+  }
+  for &(ref mode, ref stats) in perMode.iter() {
+    print_stats(strings::fill(strings::get().modeHeading, &[mode.as_slice()]).as_slice(), stats);
+    match graphGames {
+      Some(games) => {
+        let first = if stats.scores.len() > games { stats.scores.len() - games } else { 0 };
+        print_graph(stats.scores.slice_from(first));
+      }
+      None => ()
+    }
+  }
+  println("");
+}
+
+fn export_scores(scoreFile: &Path, path: &Path) {
+  let storage = score_keeper::new(scoreFile.clone()).get_scores();
+  match score_transfer::export(&storage, path) {
+    Ok(count) => println!("{}", strings::fill(strings::get().exported, &[count.to_str().as_slice(),
+                                                                        path.display().to_str().as_slice()])),
+    Err(e)    => {
+      println!("{}", strings::fill(strings::get().cantExport, &[path.display().to_str().as_slice(), e.to_str().as_slice()]));
+      os::set_exit_status(1);
+    }
+  }
+}
+
+fn import_scores(scoreFile: &Path, path: &Path) {
+  let scoreKeeper = score_keeper::new(scoreFile.clone());
+  match score_transfer::import(path) {
+    Ok(imported) => {
+      let mut storage = scoreKeeper.get_scores();
+      let added = storage.merge(&imported);
+      scoreKeeper.save_scores(&storage);
+      println!("{}", strings::fill(strings::get().imported, &[added.to_str().as_slice(), path.display().to_str().as_slice()]));
+    }
+    Err(msg) => {
+      println!("{}", strings::fill(strings::get().cantImport, &[msg.as_slice()]));
+      os::set_exit_status(1);
+    }
+  }
+}
+
+// reports a problem with the config file, for anything that needs the config
+fn config_ok(configError: &Option<~str>) -> bool {
+  match *configError {
+    Some(ref msg) => {
+      println!("{}", strings::fill(strings::get().configProblem, &[paths::config_file().display().to_str().as_slice(),
+                                                                  msg.as_slice()]));
+      os::set_exit_status(1);
+      false
+    }
+    None => true
+  }
+}
+
+// writes a config file with every setting at its default, to be edited (a player with
+// a profile gets their own)
+fn write_default_config() {
+  let path = paths::own_config_file();
+  if path.exists() {
+    println!("{}", strings::fill(strings::get().configExists, &[path.display().to_str().as_slice()]));
+    os::set_exit_status(1);
+    return;
+  }
+  let written = paths::create_parent_dir(&path).and_then(|()| {
+    File::create(&path).and_then(|mut file| file.write_str(config::default_text()))
+  });
+  match written {
+    Ok(()) => println!("{}", strings::fill(strings::get().wroteConfig, &[path.display().to_str().as_slice()])),
+    Err(e) => {
+      println!("{}", strings::fill(strings::get().cantWrite, &[path.display().to_str().as_slice(), e.to_str().as_slice()]));
+      os::set_exit_status(1);
+    }
+  }
+}
+
+// What the command line asked for
+struct CommandLine {
+  options:      tetris::Options,
+  help:         bool,
+  language:     Option<strings::Language>,
+  writeConfig:  bool,
+  double:       bool,
+  dumb:         bool,          // print the board as plain text after each change
+  showScores:   bool,
+  showStats:    bool,
+  remote:       bool,
+  since:        Option<u64>,   // --scores options, see ScoreQuery
+  sort:         Option<ScoreOrder>,
+  top:          Option<uint>,
+  period:       Option<score_keeper::Period>,
+  graph:        bool,
+  graphGames:   Option<uint>,
+  modeGiven:    bool,
+  benchGames:   Option<uint>,
+  tuneTries:    Option<uint>,  // weight sets for --ai-tune to try
+  saveWeights:  bool,
+  botCommand:   Option<~str>,  // another program to play the game
+  twoPlayer:    bool,
+  tournament:   bool,
+  tutorial:     bool,
+  watchAddress: Option<~str>,
+  servePort:    Option<u16>,   // relay versus matches on this port
+  connectAddress: Option<~str>, // the relay to play a versus match through
+  roomCode:     Option<~str>,  // the room to meet a friend in on the relay, upper case
+  playReplay:   Option<Path>,  // replay to show being played again
+  framesDir:    Option<Path>,  // where to export the replay's frames instead of showing it
+  framesCommand: Option<~str>, // run on each exported frame
+  exportFile:   Option<Path>,
+  importFile:   Option<Path>
+}
+
+// The name given with --player, if it's one that can be used (parse_args reports it otherwise)
+fn player_arg(args: &[~str]) -> Option<~str> {
+  let mut name = None;
+  for (i, arg) in args.iter().enumerate() {
+    if arg.as_slice() == "--player" && i + 1 < args.len() {
+      name = Some(args[i + 1].clone());
+    } else if arg.starts_with("--player=") {
+      name = Some(arg.slice_from("--player=".len()).to_owned());
+    }
+  }
+  name.filtered(|name| paths::valid_player_name(name.as_slice()))
+}
+
+// Reads the arguments after the program's name. The first one can be a command (play, scores,
+// stats, replay FILE or help), the rest are options in any order, starting from the defaults
+// in the config file.
+fn parse_args(args: &[~str], config: &config::Config) -> Result<CommandLine, ~str> {
+  let mut commandLine = CommandLine{options:      tetris::Options::new(config),
+                                    help:         false,
+                                    language:     None,
+                                    writeConfig:  false,
+                                    double:       config.double,
+                                    dumb:         false,
+                                    showScores:   false,
+                                    showStats:    false,
+                                    remote:       false,
+                                    since:        None,
+                                    sort:         None,
+                                    top:          None,
+                                    period:       None,
+                                    graph:        false,
+                                    graphGames:   None,
+                                    modeGiven:    false,
+                                    benchGames:   None,
+                                    tuneTries:    None,
+                                    saveWeights:  false,
+                                    botCommand:   None,
+                                    twoPlayer:    false,
+                                    tournament:   false,
+                                    tutorial:     false,
+                                    watchAddress: None,
+                                    servePort:    None,
+                                    connectAddress: None,
+                                    roomCode:     None,
+                                    playReplay:   None,
+                                    framesDir:    None,
+                                    framesCommand: None,
+                                    exportFile:   None,
+                                    importFile:   None};
+  let mut args = cli::new(args);
+  let mut first = true;
+  loop {
+    let arg = match try!(args.next()) {
+      Some(arg) => arg,
+      None      => break
+    };
+    let options = &mut commandLine.options;
+    match arg.as_slice() {
+      "play" if first            => (),
+      "scores" if first          => commandLine.showScores = true,
+      "stats" if first           => commandLine.showStats = true,
+      "replay" if first          => commandLine.playReplay = Some(Path::new(try!(args.value()))),
+      "--replay"                 => commandLine.playReplay = Some(Path::new(try!(args.value()))),
+      "help" if first            => commandLine.help = true,
+      "--help" | "-h"            => commandLine.help = true,
+      "--lang"                   => {
+        commandLine.language = Some(try!(args.choice(["en", "de"], |name| strings::from_name(name))));
+      }
+      "--write-default-config"   => commandLine.writeConfig = true,
+      "--score" | "--scores"     => commandLine.showScores = true,
+      "--stats"                  => commandLine.showStats = true,
+      "--remote"                 => commandLine.remote = true,
+      "--since"                  => {
+        commandLine.since = Some(try!(args.choice([strings::get().timeSpans], |text| parse_span(text))));
+      }
+      "--sort"                   => {
+        commandLine.sort = Some(try!(args.choice(["score", "date", "level"], |name| score_order_from_name(name))));
+      }
+      "--top"                    => match try!(args.number::<uint>()) {
+        0   => return Err(strings::fill(strings::get().errAtLeastGames, &["--top"])),
+        top => commandLine.top = Some(top)
+      },
+      "--period"                 => {
+        commandLine.period = Some(try!(args.choice(["week", "month"], |name| score_keeper::period_from_name(name))));
+      }
+      "--graph"                  => commandLine.graph = true,
+      "--games"                  => match try!(args.number::<uint>()) {
+        games if games >= 1 && games <= maxGraphGames => commandLine.graphGames = Some(games),
+        _ => return Err(strings::fill(strings::get().errGamesRange, &["--games", maxGraphGames.to_str().as_slice()]))
+      },
+      "--two-player"             => commandLine.twoPlayer = true,
+      "--vs-ai"                  => {
+        options.vsAi = Some(try!(args.choice(["easy", "medium", "hard", "inhuman"], |name| ai::difficulty_from_name(name))));
+        commandLine.twoPlayer = true;
+      }
+      "--tournament"             => commandLine.tournament = true,
+      "--player"                 => {
+        let name = try!(args.value());
+        if !paths::valid_player_name(name.as_slice()) {
+          return Err(strings::fill(strings::get().errPlayerName, &["--player", name.as_slice()]));
+        }
+      }
+      "--tutorial"               => commandLine.tutorial = true,
+      "--resume"                 => options.resume = true,
+      "--record"                 => options.replayFile = Some(Path::new(try!(args.value()))),
+      "--ai-bench"               => match try!(args.number::<uint>()) {
+        0     => return Err(strings::fill(strings::get().errAtLeastGames, &["--ai-bench"])),
+        games => commandLine.benchGames = Some(games)
+      },
+      "--ai-tune"                => match try!(args.number::<uint>()) {
+        0     => return Err(strings::fill(strings::get().errAtLeastTries, &["--ai-tune"])),
+        tries => commandLine.tuneTries = Some(tries)
+      },
+      "--save-weights"           => commandLine.saveWeights = true,
+      "--ai-external"            => commandLine.botCommand = Some(try!(args.value())),
+      "--spectate-port"          => options.spectatePort = Some(try!(args.number())),
+      "--watch"                  => commandLine.watchAddress = Some(try!(args.value())),
+      "--serve"                  => commandLine.servePort = Some(try!(args.number())),
+      "--connect"                => commandLine.connectAddress = Some(try!(args.value())),
+      "--join"                   => {
+        let code = try!(args.value());
+        if !relay::valid_room_code(code.as_slice()) {
+          return Err(strings::fill(strings::get().errRoomCode, &["--join", code.as_slice()]));
+        }
+        commandLine.roomCode = Some(code.to_ascii_upper());
+      }
+      "--crowd"                  => options.crowdFile = Some(Path::new(try!(args.value()))),
+      "--ghost"                  => options.ghostFile = Some(Path::new(try!(args.value()))),
+      "--seed"                   => options.seed = Some(try!(args.number())),
+      "--best-of"                => match try!(args.number::<uint>()) {
+        games if games % 2 == 1 && games <= 9 => options.bestOf = games,
+        _ => return Err(strings::fill(strings::get().errOddGames, &["--best-of"]))
+      },
+      "--display"                => {
+        commandLine.double = try!(args.choice(["standard", "double"], |name| match name {
+          "double"   => Some(true),
+          "standard" => Some(false),
+          _          => None
+        }));
+      }
+      "-d2"                      => commandLine.double = true,
+      "-d1"                      => commandLine.double = false,
+      "--dumb"                   => commandLine.dumb = true,
+      "--backend"                => {
+        options.curses = try!(args.choice(["escapes", "curses"], |name| match name {
+          "curses"  => Some(true),
+          "escapes" => Some(false),
+          _         => None
+        }));
+      }
+      "--debug-overlay"          => options.debugOverlay = true,
+      "--no-preview"             => options.preview = false,
+      "--sandbox"                => options.sandbox = true,
+      "--step"                   => options.stepping = true,
+      "--log"                    => options.logFile = Some(Path::new(try!(args.value()))),
+      "--announce"               => options.announceFile = Some(Path::new(try!(args.value()))),
+      "--record-cast"            => options.castFile = Some(try!(args.value())),
+      "--capture"                => options.captureFile = Some(Path::new(try!(args.value()))),
+      "--led"                    => options.ledFile = Some(Path::new(try!(args.value()))),
+      "--scoring"                => {
+        options.scoring = try!(args.choice(["standard", "guideline", "nes"], |name| scoring::kind_from_name(name)));
+      }
+      "--gravity"                => {
+        options.gravity = Some(try!(args.choice(["levels", "nes", "zen", "20g"], |name| gravity::kind_from_name(name))));
+      }
+      "--speed"                  => match try!(args.number::<f64>()) {
+        speed if speed > 0.0 => options.speed = speed,
+        _                    => return Err(strings::fill(strings::get().errAboveZero, &["--speed"]))
+      },
+      "--spawn-delay"            => match try!(args.number::<i32>()) {
+        ms if ms >= 0 && ms <= engine::maxSpawnDelay => options.spawnDelay = ms,
+        _ => return Err(strings::fill(strings::get().errMillis, &["--spawn-delay", engine::maxSpawnDelay.to_str().as_slice()]))
+      },
+      "--export-scores"          => commandLine.exportFile = Some(Path::new(try!(args.value()))),
+      "--export-frames"          => commandLine.framesDir = Some(Path::new(try!(args.value()))),
+      "--export-command"         => commandLine.framesCommand = Some(try!(args.value())),
+      "--import-scores"          => commandLine.importFile = Some(Path::new(try!(args.value()))),
+      "--score-file"             => options.scoreFile = Path::new(try!(args.value())),
+      "--mode"                   => {
+        options.mode = try!(args.choice(["endless", "nes", "sprint", "ultra", "pressure", "marathon"], |name| modes::from_name(name)));
+        commandLine.modeGiven = true;
+      }
+      _ if arg.starts_with("-")  => return Err(strings::fill(strings::get().errUnknownOption, &[arg.as_slice()])),
+      _ if first                 => return Err(strings::fill(strings::get().errUnknownCommand,
+                                                             &[arg.as_slice(), strings::one_of(["play", "scores", "stats", "replay", "help"]).as_slice()])),
+      _                          => return Err(strings::fill(strings::get().errUnexpected, &[arg.as_slice()]))
+    }
+    first = false;
+  }
+  if (commandLine.framesDir.is_some() || commandLine.framesCommand.is_some()) && commandLine.playReplay.is_none() {
+    return Err(strings::get().errExportReplay.to_owned());
+  }
+  if commandLine.framesCommand.is_some() && commandLine.framesDir.is_none() {
+    return Err(strings::fill(strings::get().errNeeds, &["--export-command", "--export-frames DIR"]));
+  }
+  if commandLine.saveWeights && commandLine.tuneTries.is_none() {
+    return Err(strings::fill(strings::get().errNeeds, &["--save-weights", "--ai-tune TRIES"]));
+  }
+  if (commandLine.since.is_some() || commandLine.sort.is_some() || commandLine.top.is_some() ||
+      commandLine.period.is_some()) && !commandLine.showScores {
+    return Err(strings::get().errScoresOnly.to_owned());
+  }
+  if commandLine.period.is_some() && commandLine.sort.is_some() {
+    return Err(strings::get().errPeriodSort.to_owned());
+  }
+  if commandLine.showScores && commandLine.options.seed.is_some() &&
+     (commandLine.period.is_some() || commandLine.sort.is_some()) {
+    return Err(strings::get().errSeedPeriod.to_owned());
+  }
+  if commandLine.graph && !commandLine.showStats {
+    return Err(strings::fill(strings::get().errNeeds, &["--graph", "--stats"]));
+  }
+  if commandLine.graphGames.is_some() && !commandLine.graph {
+    return Err(strings::fill(strings::get().errNeeds, &["--games", "--graph"]));
+  }
+  if commandLine.roomCode.is_some() && commandLine.connectAddress.is_none() {
+    return Err(strings::fill(strings::get().errNeeds, &["--join", "--connect HOST:PORT"]));
+  }
+  Ok(commandLine)
+}
+
+fn main() {
+  // There's always at least one argument (the program's name)
+  // If the program is run with no extra argument's passed by the user, just run the game in standard display mode
+  //
+  // The config file gives the defaults that the arguments change. A problem with it is only
+  // reported by the things that need it, so the scores can still be shown. The player's
+  // profile decides which config file that is, so it's picked out of the arguments first.
+  match player_arg(os::args().tail()) {
+    Some(name) => paths::set_player(name),
+    None       => ()
+  }
+  paths::copy_legacy_scores();
+  // before the config is read, so a problem with it is told in the player's language
+  strings::set_language(strings::from_env());
+  let (config, configError) = match config::load(&paths::config_file()) {
+    Ok(config) => (config, None),
+    Err(msg)   => (config::default(), Some(msg))
+  };
+  
+  let commandLine = match parse_args(os::args().tail(), &config) {
+    Ok(commandLine) => commandLine,
+    Err(msg)        => {
+      println!("{}", msg);
+      println(strings::get().runHelp);
+      os::set_exit_status(1);
+      return;
+    }
+  };
+  match commandLine.language {
+    Some(language) => strings::set_language(language),
+    None           => ()
+  }
+  let mut options = commandLine.options;
+  let double = commandLine.double;
+  let modeGiven = commandLine.modeGiven;
+  
+  // Games are printed as plain text when asked to, or when the output isn't going to a
+  // terminal (so it can be logged), and then there are no escape sequences, curses' included
+  let dumb = commandLine.dumb || !terminal_control::stdout_is_terminal();
+  if dumb {
+    options.curses = false;
+  }
+  
+  if commandLine.help {
+    display_help();
+    return;
+  }
+  
+  if commandLine.writeConfig {
+    write_default_config();
+    return;
+  }
+  
+  match commandLine.exportFile {
+    Some(path) => {
+      export_scores(&options.scoreFile, &path);
+      return;
+    }
+    None => ()
+  }
+  
+  match commandLine.importFile {
+    Some(path) => {
+      import_scores(&options.scoreFile, &path);
+      return;
+    }
+    None => ()
+  }
+  
+  if commandLine.showScores {
+    let palette = graphics::plainColors.themed(config.theme).palette;
+    let filter = if modeGiven { Some(options.mode) } else { None };
+    // without an order the best scores come first
+    let query = if commandLine.since.is_some() || commandLine.sort.is_some() || commandLine.top.is_some() ||
+                   commandLine.period.is_some() || options.seed.is_some() {
+      Some(ScoreQuery{since:  commandLine.since,
+                      order:  commandLine.sort.unwrap_or(ByScore),
+                      top:    commandLine.top,
+                      period: commandLine.period,
+                      seed:   options.seed})
+    } else {
+      None
+    };
+    if !commandLine.remote {
+      display_scores(&score_keeper::new(options.scoreFile.clone()).get_scores(), filter, query, palette);
+      return;
+    }
+    
+    if !config_ok(&configError) {
+      return;
+    }
+    let url = match config.leaderboard {
+      Some(ref url) => url,
+      None          => {
+        println!("{}", strings::fill(strings::get().noLeaderboard, &[paths::config_file().display().to_str().as_slice()]));
+        os::set_exit_status(1);
+        return;
+      }
+    };
+    match remote_scores::fetch(url) {
+      Ok(storage) => display_scores(&storage, filter, query, palette),
+      Err(msg)    => {
+        println!("{}", strings::fill(strings::get().cantFetch, &[msg.as_slice()]));
+        os::set_exit_status(1);
+      }
+    }
+    return;
+  }
+  
+  match commandLine.watchAddress {
+    Some(address) => {
+      match spectator::watch(address.as_slice()) {
+        Ok(())   => (),
+        Err(msg) => {
+          println!("{}", strings::fill(strings::get().cantWatch, &[msg.as_slice()]));
+          os::set_exit_status(1);
+        }
+      }
+      return;
+    }
+    None => ()
+  }
+  
+  match commandLine.servePort {
+    Some(port) => {
+      match relay::serve(port) {
+        Ok(())   => (),
+        Err(msg) => {
+          println!("{}", msg);
+          os::set_exit_status(1);
+        }
+      }
+      return;
+    }
+    None => ()
+  }
+  
+  if commandLine.showStats {
+    let graphGames = if commandLine.graph { Some(commandLine.graphGames.unwrap_or(defaultGraphGames)) } else { None };
+    display_stats(&options.scoreFile, if modeGiven { Some(options.mode) } else { None }, graphGames);
+    return;
+  }
+  
+  if !config_ok(&configError) {
+    return;
+  }
+  
+  // blocks that would look alike on this terminal are drawn with other colors, and curses
+  // draws the bright colors as the normal ones
+  let mut lookalikes = if !dumb && terminal_control::terminal_size().is_some() {
+    graphics::detect_lookalikes(config.colorOverrides)
+  } else {
+    config.colorOverrides
+  };
+  if options.curses {
+    for color in range(8u, 16) {
+      lookalikes[color] = lookalikes[color - 8];
+    }
+  }
+  let colors = graphics::colors(lookalikes, config.theme);
+  
+  let logFile = match options.logFile {
+    Some(ref path) => match trace::start(path) {
+      Ok(file) => Some(file),
+      Err(e)   => {
+        println!("{}", strings::fill(strings::get().cantLog, &[path.display().to_str().as_slice(), e.to_str().as_slice()]));
+        os::set_exit_status(1);
+        return;
+      }
+    },
+    None => None
+  };
+  options.log = logFile.as_ref().map_or(trace::off, |file| file.log);
+  
+  match commandLine.benchGames {
+    Some(games) => {
+      tetris::run_ai_bench(games, &options, &config);
+      return;
+    }
+    None => ()
+  }
+  
+  match commandLine.tuneTries {
+    Some(tries) => {
+      tetris::run_ai_tune(tries, commandLine.saveWeights, &options, &config);
+      return;
+    }
+    None => ()
+  }
+  
+  match (&commandLine.playReplay, &commandLine.framesDir) {
+    (&Some(ref path), &Some(ref dir)) => {
+      tetris::export_replay_frames(path, dir, &commandLine.framesCommand, &options, &config);
+      return;
+    }
+    _ => ()
+  }
+  
+  // everything from here on reads keys from the terminal
+  if !terminal_control::stdin_is_terminal() {
+    println(strings::get().noTerminal);
+    os::set_exit_status(1);
+    return;
+  }
+  
+  // a game printed as text has no other display to switch to
+  let standardDisplay = graphics::standard_display(colors);
+  let doubleDisplay = graphics::double_display(colors);
+  let dumbDisplay = graphics::dumb_display();
+  let (display, otherDisplay) = if dumb {
+    (&dumbDisplay as &graphics::Display, None)
+  } else if double {
+    (&doubleDisplay as &graphics::Display, Some(&standardDisplay as &graphics::Display))
+  } else {
+    (&standardDisplay as &graphics::Display, Some(&doubleDisplay as &graphics::Display))
+  };
+  
+  match commandLine.botCommand {
+    Some(command) => {
+      tetris::run_bot(display, command.as_slice(), &options, &config);
+      return;
+    }
+    None => ()
+  }
+  
+  match commandLine.playReplay {
+    Some(path) => {
+      // replays are always shown on the standard display, unless they're printed as text
+      let replayDisplay = if otherDisplay.is_some() { &standardDisplay as &graphics::Display } else { display };
+      tetris::run_replay(replayDisplay, &path, &options, &config);
+      return;
+    }
+    None => ()
+  }
+  
+  if commandLine.twoPlayer {
+    if dumb {
+      println(strings::get().noTwoPlayerText);
+      os::set_exit_status(1);
+    } else {
+      tetris::run_two_player(&standardDisplay, &options, &config);
+    }
+    return;
+  }
+  
+  match commandLine.connectAddress {
+    Some(address) => {
+      if dumb {
+        println(strings::get().noTwoPlayerText);
+        os::set_exit_status(1);
+        return;
+      }
+      let room = commandLine.roomCode.as_ref().map(|code| code.as_slice());
+      match tetris::run_net_versus(&standardDisplay, address.as_slice(), room, &options, &config) {
+        Ok(())   => (),
+        Err(msg) => {
+          println!("{}", strings::fill(strings::get().cantPlayOnline, &[msg.as_slice()]));
+          os::set_exit_status(1);
+        }
+      }
+      return;
+    }
+    None => ()
+  }
+  
+  if commandLine.tournament {
+    tetris::run_tournament(display, &options, &config);
+    return;
+  }
+  
+  if commandLine.tutorial {
+    tetris::run_tutorial(display, &options, &config);
+    return;
+  }
+  
+  tetris::run_game(display, otherDisplay, &options, &config);
 }
@@ -0,0 +1,1541 @@
+// The rules of the game, without any terminal handling: pieces, the board of set
+// blocks, piece sequences, scoring, gravity and the game engine that ties them together.
+// The terminal game in tetris1.rs is built on top of this, other frontends can use the
+// C interface in the web module.
+
+#[crate_id = "tetris_engine#0.1"];
+#[crate_type = "lib"];
+
+extern crate rand;
+extern crate serialize;
+extern crate sync;
+
+pub mod pieces {
+  use sync::one::{Once, ONCE_INIT};
+  
+  // Gray is only used for garbage rows pushed up from the bottom, which aren't part of any piece
+  #[deriving(Eq)]
+  pub enum Color {
+    Black = 0, Red, Green, Yellow, Blue, Magenta, Cyan, White, Gray
+  }
+
+  #[deriving(Clone, Eq)]
+  pub enum PieceType {
+    I = 0, J, L, O, S, T, Z
+  }
+
+  // A block keeps the type of the piece it came from once it's set, so it can still be drawn
+  // as part of that piece (garbage and erased blocks have no type).
+  pub struct Block {
+    row:    i8,
+    column: i8,
+    color:  Color,
+    ty:     Option<PieceType>
+  }
+  
+  impl Block {
+    pub fn is_garbage(&self) -> bool {
+      self.color == Gray
+    }
+  }
+
+  pub struct Piece {
+    ty:     PieceType,
+    rotate: u8,
+    blocks: [Block, ..4]
+  }
+
+
+  static pieceInitial: [Piece, ..7] = 
+  [
+    Piece{ty:     I,
+          rotate: 0,
+          blocks: [Block{row: 0, column: 4, color: Cyan, ty: Some(I)},
+                   Block{row: 0, column: 5, color: Cyan, ty: Some(I)},
+                   Block{row: 0, column: 6, color: Cyan, ty: Some(I)},
+                   Block{row: 0, column: 7, color: Cyan, ty: Some(I)}]},
+    
+    Piece{ty:     J,
+          rotate: 0,
+          blocks: [Block{row: -1, column: 4, color: Blue, ty: Some(J)},
+                   Block{row:  0, column: 4, color: Blue, ty: Some(J)},
+                   Block{row:  0, column: 5, color: Blue, ty: Some(J)},
+                   Block{row:  0, column: 6, color: Blue, ty: Some(J)}]},
+    
+    Piece{ty:     L,
+          rotate: 0,
+          blocks: [Block{row:  0, column: 4, color: White, ty: Some(L)},
+                   Block{row:  0, column: 5, color: White, ty: Some(L)},
+                   Block{row:  0, column: 6, color: White, ty: Some(L)},
+                   Block{row: -1, column: 6, color: White, ty: Some(L)}]},
+
+    Piece{ty:     O,
+          rotate: 0,
+          blocks: [Block{row: -1, column: 5, color: Yellow, ty: Some(O)},
+                   Block{row: -1, column: 6, color: Yellow, ty: Some(O)},
+                   Block{row:  0, column: 5, color: Yellow, ty: Some(O)},
+                   Block{row:  0, column: 6, color: Yellow, ty: Some(O)}]},
+    
+    Piece{ty:     S,
+          rotate: 0,
+          blocks: [Block{row:  0, column: 5, color: Green, ty: Some(S)},
+                   Block{row:  0, column: 6, color: Green, ty: Some(S)},
+                   Block{row: -1, column: 6, color: Green, ty: Some(S)},
+                   Block{row: -1, column: 7, color: Green, ty: Some(S)}]},
+    
+    Piece{ty:     T,
+          rotate: 0,
+          blocks: [Block{row:  0, column: 4, color: Magenta, ty: Some(T)},
+                   Block{row:  0, column: 5, color: Magenta, ty: Some(T)},
+                   Block{row:  0, column: 6, color: Magenta, ty: Some(T)},
+                   Block{row: -1, column: 5, color: Magenta, ty: Some(T)}]},
+
+    Piece{ty:     Z,
+          rotate: 0,
+          blocks: [Block{row: -1, column: 4, color: Red, ty: Some(Z)},
+                   Block{row: -1, column: 5, color: Red, ty: Some(Z)},
+                   Block{row:  0, column: 5, color: Red, ty: Some(Z)},
+                   Block{row:  0, column: 6, color: Red, ty: Some(Z)}]}
+  ];
+
+  pub fn new(ty: PieceType) -> Piece {
+    pieceInitial[ty as int]
+  }
+  
+  // The four rotations of every piece, as 4x4 grids with each block's number in its cell.
+  // Turning clockwise takes a piece from one grid to the next (and back to the first after
+  // the last), every block moving by the difference between where its number is in the two
+  // grids. The blocks of pieceInitial are numbered the same way as the first grid.
+  static pieceShapes: [[[&'static str, ..4], ..4], ..7] =
+  [
+    // I
+    [["....", "....", "....", "0123"],
+     [".0..", ".1..", ".2..", ".3.."],
+     ["....", "....", "....", "3210"],
+     [".3..", ".2..", ".1..", ".0.."]],
+    
+    // J
+    [["....", "....", "0...", "123."],
+     ["....", ".10.", ".2..", ".3.."],
+     ["....", "....", "321.", "..0."],
+     ["....", ".3..", ".2..", "01.."]],
+    
+    // L
+    [["....", "....", "..3.", "012."],
+     ["....", "0...", "1...", "23.."],
+     ["....", "....", "210.", "3..."],
+     ["....", "32..", ".1..", ".0.."]],
+    
+    // O
+    [["....", "....", "01..", "23.."],
+     ["....", "....", "01..", "23.."],
+     ["....", "....", "01..", "23.."],
+     ["....", "....", "01..", "23.."]],
+    
+    // S
+    [["....", "....", ".23.", "01.."],
+     ["....", "0...", "12..", ".3.."],
+     ["....", "....", ".10.", "32.."],
+     ["....", "3...", "21..", ".0.."]],
+    
+    // T
+    [["....", "....", ".3..", "012."],
+     ["....", "0...", "13..", "2..."],
+     ["....", "....", "210.", ".3.."],
+     ["....", ".2..", "31..", ".0.."]],
+    
+    // Z
+    [["....", "....", "01..", ".23."],
+     ["....", ".0..", "21..", "3..."],
+     ["....", "....", "32..", ".10."],
+     ["....", ".3..", "12..", "0..."]]
+  ];
+  
+  // the row and column of block number n in a rotation's grid
+  fn block_position(grid: &[&'static str, ..4], n: uint) -> (i8, i8) {
+    let digit = '0' as u8 + n as u8;
+    for (row, line) in grid.iter().enumerate() {
+      match line.as_bytes().iter().position(|c| *c == digit) {
+        Some(col) => return (row as i8, col as i8),
+        None      => ()
+      }
+    }
+    fail!("block {} is missing from a piece's shape", n);
+  }
+  
+  // how far each block moves turning clockwise from the given rotation to the next one
+  fn shape_offsets(shapes: &[[&'static str, ..4], ..4], rotate: uint) -> [(i8, i8), ..4] {
+    let mut offsets = [(0i8, 0i8), ..4];
+    for n in range(0u, 4) {
+      let (fromRow, fromCol) = block_position(&shapes[rotate], n);
+      let (toRow, toCol) = block_position(&shapes[(rotate + 1) % 4], n);
+      offsets[n] = (toRow - fromRow, toCol - fromCol);
+    }
+    offsets
+  }
+  
+  // The offsets of every piece and rotation, worked out from pieceShapes the first time a
+  // piece turns so the grids aren't scanned again on every turn
+  static mut offsetsTable: [[[(i8, i8), ..4], ..4], ..7] = [[[(0, 0), ..4], ..4], ..7];
+  static mut offsetsWorkedOut: Once = ONCE_INIT;
+  
+  fn rotation_offsets(ty: PieceType, rotate: u8) -> [(i8, i8), ..4] {
+    unsafe {
+      offsetsWorkedOut.doit(|| {
+        for (i, shapes) in pieceShapes.iter().enumerate() {
+          for turn in range(0u, 4) {
+            offsetsTable[i][turn] = shape_offsets(shapes, turn);
+          }
+        }
+      });
+      offsetsTable[ty as int][rotate]
+    }
+  }
+
+  trait Offset {
+    fn row(self) -> i8;
+    fn col(self) -> i8;
+  }
+
+  impl Offset for (i8, i8) {
+    fn row(self) -> i8 {
+      let (row, _) = self;
+      row
+    }
+    
+    fn col(self) -> i8 {
+      let (_, col) = self;
+      col
+    }
+  }
+  
+  fn transform_blocks(clockwise: bool, blocks: &[Block, ..4], transform: [(i8, i8), ..4]) -> [Block, ..4] {
+    let s = if clockwise { 1i8 } else { -1i8 };
+    [
+      Block{row:    blocks[0].row    + s * transform[0].row(),
+            column: blocks[0].column + s * transform[0].col(),
+            color:  blocks[0].color,
+            ty:     blocks[0].ty},
+    
+      Block{row:    blocks[1].row    + s * transform[1].row(),
+            column: blocks[1].column + s * transform[1].col(),
+            color:  blocks[1].color,
+            ty:     blocks[1].ty},
+    
+      Block{row:    blocks[2].row    + s * transform[2].row(),
+            column: blocks[2].column + s * transform[2].col(),
+            color:  blocks[2].color,
+            ty:     blocks[2].ty},
+    
+      Block{row:    blocks[3].row    + s * transform[3].row(),
+            column: blocks[3].column + s * transform[3].col(),
+            color:  blocks[3].color,
+            ty:     blocks[3].ty}
+    ]
+  }
+
+  pub fn rotate_clockwise(piece: &Piece) -> Piece {
+    Piece {
+      ty:     piece.ty,
+      rotate: (piece.rotate + 1) % 4,
+      blocks: transform_blocks(true, &piece.blocks, rotation_offsets(piece.ty, piece.rotate))
+    }
+  }
+
+  pub fn rotate_counter_clockwise(piece: &Piece) -> Piece {
+    Piece {
+      ty:     piece.ty,
+      rotate: (piece.rotate + 3) % 4,
+      blocks: transform_blocks(false, &piece.blocks, rotation_offsets(piece.ty, (piece.rotate + 3) % 4))
+    }
+  }
+  
+  pub fn translate(piece: &Piece, rowOffset: i8, columnOffset: i8) -> Piece {
+    Piece {
+      ty:     piece.ty,
+      rotate: piece.rotate,
+      blocks: [Block{row:    piece.blocks[0].row    + rowOffset,
+                     column: piece.blocks[0].column + columnOffset,
+                     color:  piece.blocks[0].color,
+                     ty:     piece.blocks[0].ty},
+                     
+               Block{row:    piece.blocks[1].row    + rowOffset,
+                     column: piece.blocks[1].column + columnOffset,
+                     color:  piece.blocks[1].color,
+                     ty:     piece.blocks[1].ty},
+                     
+               Block{row:    piece.blocks[2].row    + rowOffset,
+                     column: piece.blocks[2].column + columnOffset,
+                     color:  piece.blocks[2].color,
+                     ty:     piece.blocks[2].ty},
+               
+               Block{row:    piece.blocks[3].row + rowOffset,
+                     column: piece.blocks[3].column + columnOffset,
+                     color:  piece.blocks[3].color,
+                     ty:     piece.blocks[3].ty}]
+    }
+  }
+}
+
+pub mod board {
+  use std::vec_ng::Vec;
+  use pieces;
+  use pieces::{Block, Color, Piece, PieceType, I, J, L, O, S, T, Z};
+  use pieces::{Black, Red, Green, Yellow, Blue, Magenta, Cyan, White, Gray};
+  
+  pub static height: i8 = 20;
+  pub static width: i8 = 10;
+  
+  // a row with every cell set
+  pub static fullRow: u16 = 0x3FF;
+  
+  // The blocks set on the board. Rows go from 1 at the top to 20 at the bottom, and
+  // columns from 1 on the left to 10 on the right.
+  //
+  // Each row is kept as a bit mask of which cells are set (bit 0 for column 1), which is
+  // all collisions need, so the AI can try placements quickly, along with a count of its
+  // set cells for full and almost full rows. The colors and piece types of the blocks are
+  // kept alongside for drawing.
+  pub struct Board {
+    masks:  [u16, ..20],
+    counts: [u8, ..20],
+    cells:  [Option<Color>, ..200],
+    types:  [Option<PieceType>, ..200]
+  }
+  
+  pub fn new() -> Board {
+    Board{masks: [0, ..20], counts: [0, ..20], cells: [None, ..200], types: [None, ..200]}
+  }
+  
+  pub fn in_bounds(row: i8, col: i8) -> bool {
+    row >= 1 && row <= height && col >= 1 && col <= width
+  }
+  
+  #[inline(always)]
+  fn bit(col: i8) -> u16 {
+    1 << (col - 1) as uint
+  }
+  
+  #[inline(always)]
+  fn index(row: i8, col: i8) -> uint {
+    (width as uint) * ((row as uint) - 1) + (col as uint) - 1
+  }
+  
+  // A snapshot is a compact copy of a board, for saving it or sending it somewhere. It's the
+  // mask of each row from the top down, low byte first, then a 4 bit code for each cell, two
+  // cells to a byte with the cell on the left in the low bits. A code is 0 for an empty cell,
+  // 1 to 7 for a block of piece type I to Z, and 8 to 15 for a block of no piece type in
+  // color Red to Gray.
+  pub static snapshotBytes: uint = 140;
+  
+  static snapshotTypes: [PieceType, ..7] = [I, J, L, O, S, T, Z];
+  static snapshotColors: [Color, ..9] = [Black, Red, Green, Yellow, Blue, Magenta, Cyan, White, Gray];
+  
+  // the board a snapshot was made of, or None if the bytes aren't a snapshot
+  pub fn from_snapshot(bytes: &[u8]) -> Option<Board> {
+    if bytes.len() != snapshotBytes {
+      return None;
+    }
+    let cells = bytes.slice_from(2 * height as uint);
+    let mut board = new();
+    for i in range(0u, width as uint * height as uint) {
+      let code = if i % 2 == 0 { cells[i / 2] & 0xF } else { cells[i / 2] >> 4 };
+      let row = (i / width as uint) as i8 + 1;
+      let col = (i % width as uint) as i8 + 1;
+      match code {
+        0      => (),
+        1 .. 7 => {
+          let ty = snapshotTypes[code - 1];
+          board.set(Block{row: row, column: col, color: pieces::new(ty).blocks[0].color, ty: Some(ty)});
+        }
+        _      => board.set(Block{row: row, column: col, color: snapshotColors[code - 7], ty: None})
+      }
+    }
+    // the masks have to agree with the cells
+    for row in range(0u, height as uint) {
+      if (bytes[2 * row] as u16 | (bytes[2 * row + 1] as u16 << 8)) != board.masks[row] {
+        return None;
+      }
+    }
+    Some(board)
+  }
+  
+  // the rows of a board from top to bottom, each as a slice of its cells from left to right
+  pub struct Rows<'a> {
+    board: &'a Board,
+    row:   i8
+  }
+  
+  impl<'a> Iterator<&'a [Option<Color>]> for Rows<'a> {
+    fn next(&mut self) -> Option<&'a [Option<Color>]> {
+      if self.row > height {
+        return None;
+      }
+      self.row += 1;
+      Some(self.board.row(self.row - 1))
+    }
+  }
+  
+  impl Board {
+    // false for any cell outside the board
+    pub fn has_block(&self, row: i8, col: i8) -> bool {
+      in_bounds(row, col) && self.masks[(row - 1) as uint] & bit(col) != 0
+    }
+    
+    // None for any cell outside the board
+    pub fn get(&self, row: i8, col: i8) -> Option<Block> {
+      if !in_bounds(row, col) {
+        return None;
+      }
+      let i = index(row, col);
+      self.cells[i].map(|color| Block{row: row, column: col, color: color, ty: self.types[i]})
+    }
+    
+    pub fn set(&mut self, block: Block) {
+      if !in_bounds(block.row, block.column) {
+        fail!("can't set a block outside the board");
+      }
+      if !self.has_block(block.row, block.column) {
+        self.counts[(block.row - 1) as uint] += 1;
+      }
+      self.masks[(block.row - 1) as uint] |= bit(block.column);
+      let i = index(block.row, block.column);
+      self.cells[i] = Some(block.color);
+      self.types[i] = block.ty;
+    }
+    
+    // does nothing for a cell outside the board
+    pub fn remove(&mut self, row: i8, col: i8) {
+      if in_bounds(row, col) {
+        if self.has_block(row, col) {
+          self.counts[(row - 1) as uint] -= 1;
+        }
+        self.masks[(row - 1) as uint] &= !bit(col);
+        self.cells[index(row, col)] = None;
+        self.types[index(row, col)] = None;
+      }
+    }
+    
+    // which cells of the row are set, bit 0 for column 1 (0 for a row outside the board)
+    pub fn row_mask(&self, row: i8) -> u16 {
+      if row < 1 || row > height {
+        return 0;
+      }
+      self.masks[(row - 1) as uint]
+    }
+    
+    // how many cells of the row are set (0 for a row outside the board)
+    pub fn row_count(&self, row: i8) -> uint {
+      if row < 1 || row > height {
+        return 0;
+      }
+      self.counts[(row - 1) as uint] as uint
+    }
+    
+    pub fn row<'a>(&'a self, row: i8) -> &'a [Option<Color>] {
+      if row < 1 || row > height {
+        fail!("row {} is outside the board", row);
+      }
+      let start = index(row, 1);
+      self.cells.slice(start, start + width as uint)
+    }
+    
+    pub fn rows<'a>(&'a self) -> Rows<'a> {
+      Rows{board: self, row: 1}
+    }
+    
+    // all the set blocks, from the top row down
+    pub fn blocks(&self) -> Vec<Block> {
+      let mut blocks = Vec::new();
+      for row in range(1, height + 1) {
+        for col in range(1, width + 1) {
+          match self.get(row, col) {
+            Some(block) => blocks.push(block),
+            None        => ()
+          }
+        }
+      }
+      blocks
+    }
+    
+    // a snapshot of the board, see from_snapshot
+    pub fn snapshot(&self) -> Vec<u8> {
+      let mut bytes = Vec::with_capacity(snapshotBytes);
+      for mask in self.masks.iter() {
+        bytes.push((*mask & 0xFF) as u8);
+        bytes.push((*mask >> 8) as u8);
+      }
+      for i in range(0u, width as uint * height as uint / 2) {
+        bytes.push(self.snapshot_code(2 * i) | (self.snapshot_code(2 * i + 1) << 4));
+      }
+      bytes
+    }
+    
+    fn snapshot_code(&self, i: uint) -> u8 {
+      match (self.cells[i], self.types[i]) {
+        (None, _)           => 0,
+        (Some(_), Some(ty)) => ty as u8 + 1,
+        (Some(color), None) => color as u8 + 7
+      }
+    }
+    
+    pub fn block_count(&self) -> uint {
+      self.counts.iter().fold(0, |count, rowCount| count + *rowCount as uint)
+    }
+    
+    // whether any block of the piece is on a set block (blocks outside the board don't count)
+    pub fn collides(&self, piece: &Piece) -> bool {
+      piece.blocks.iter().any(|block| self.has_block(block.row, block.column))
+    }
+    
+    pub fn is_row_full(&self, row: i8) -> bool {
+      self.row_count(row) == width as uint
+    }
+    
+    // the full rows, from the top down
+    pub fn full_rows(&self) -> Vec<i8> {
+      range(1, height + 1).filter(|row| self.is_row_full(*row)).collect()
+    }
+    
+    // the rows missing at most the given number of cells, but not full, from the top down
+    pub fn almost_full_rows(&self, missing: uint) -> Vec<i8> {
+      range(1, height + 1).filter(|row| {
+        let count = self.row_count(*row);
+        count < width as uint && count + missing >= width as uint
+      }).collect()
+    }
+    
+    // takes out the row, the rows above it drop down one and the top row is left empty
+    pub fn clear_row(&mut self, row: i8) {
+      if row < 1 || row > height {
+        return;
+      }
+      let mut r = row;
+      while r >= 2 {
+        self.copy_row(r - 1, r);
+        r -= 1;
+      }
+      self.empty_row(1);
+    }
+    
+    // takes out every full row, and returns how many there were
+    pub fn clear_full_rows(&mut self) -> int {
+      let mut cleared = 0;
+      let mut row = height;
+      while row >= 1 {
+        if self.is_row_full(row) {
+          self.clear_row(row);
+          cleared += 1;
+        } else {
+          row -= 1;
+        }
+      }
+      cleared
+    }
+    
+    // Moves everything up one row, leaving the bottom row empty. Returns whether
+    // that pushed any blocks off the top.
+    pub fn push_up(&mut self) -> bool {
+      let pushedOff = self.masks[0] != 0;
+      for row in range(1, height) {
+        self.copy_row(row + 1, row);
+      }
+      self.empty_row(height);
+      pushedOff
+    }
+    
+    fn copy_row(&mut self, from: i8, to: i8) {
+      self.masks[(to - 1) as uint] = self.masks[(from - 1) as uint];
+      self.counts[(to - 1) as uint] = self.counts[(from - 1) as uint];
+      for col in range(1, width + 1) {
+        self.cells[index(to, col)] = self.cells[index(from, col)];
+        self.types[index(to, col)] = self.types[index(from, col)];
+      }
+    }
+    
+    fn empty_row(&mut self, row: i8) {
+      self.masks[(row - 1) as uint] = 0;
+      self.counts[(row - 1) as uint] = 0;
+      for col in range(1, width + 1) {
+        self.cells[index(row, col)] = None;
+        self.types[index(row, col)] = None;
+      }
+    }
+  }
+}
+
+pub mod piece_getter {
+  use std::vec_ng::Vec;
+  use pieces;
+  use pieces::{Piece, PieceType, I, J, L, O, S, T, Z};
+  use rand::{Rng, SeedableRng, Isaac64Rng};
+
+  pub trait PieceGetter {
+    fn next_piece(&mut self) -> Piece;
+  }
+  
+  // The same seed always gives the same sequence of pieces
+  pub fn new(seed: u64) -> ~PieceGetter {
+    let rng: Isaac64Rng = SeedableRng::from_seed(&[seed]);
+    return ~RandomPieceGetter{rng: rng} as ~PieceGetter;
+  }
+  
+  struct RandomPieceGetter<R> {
+    rng: R
+  }
+  
+  impl<R: Rng> PieceGetter for RandomPieceGetter<R> {
+    fn next_piece(&mut self) -> Piece {
+      let pieceType = self.rng.choose(&[I, J, L, O, S, T, Z]);
+      return pieces::new(pieceType);
+    }
+  }
+  
+  // Deals the given pieces in order, then keeps dealing the last one, for a game that's
+  // been set up ahead of time (like the tutorial)
+  pub fn scripted(types: Vec<PieceType>) -> ~PieceGetter {
+    if types.len() == 0 {
+      fail!("a scripted piece sequence needs at least one piece");
+    }
+    ~ScriptedPieceGetter{types: types, next: 0} as ~PieceGetter
+  }
+  
+  struct ScriptedPieceGetter {
+    types: Vec<PieceType>,
+    next:  uint
+  }
+  
+  impl PieceGetter for ScriptedPieceGetter {
+    fn next_piece(&mut self) -> Piece {
+      let ty = *self.types.get(self.next);
+      if self.next + 1 < self.types.len() {
+        self.next += 1;
+      }
+      pieces::new(ty)
+    }
+  }
+}
+
+pub mod scoring {
+  use std::i64;
+  use std::libc::c_int;
+  use std::vec_ng::Vec;
+
+  // milliseconds to fall a row, score added for each clear, number of clears before
+  // going to the next level, and bonus increment when going to the next level
+  #[deriving(Clone)]
+  pub struct Level {
+    time:     c_int,
+    score:    int,
+    count:    int,
+    bonusInc: int
+  }
+  
+  static levels : [Level, ..11] = [Level{time: 1000, score: 0,  count: 3, bonusInc: 1},
+                                   Level{time: 900,  score: 5,  count: 3, bonusInc: 1},
+                                   Level{time: 800,  score: 10, count: 3, bonusInc: 2},
+                                   Level{time: 700,  score: 15, count: 3, bonusInc: 2},
+                                   Level{time: 600,  score: 20, count: 3, bonusInc: 3},
+                                   Level{time: 500,  score: 30, count: 4, bonusInc: 4},
+                                   Level{time: 400,  score: 40, count: 4, bonusInc: 4},
+                                   Level{time: 350,  score: 45, count: 5, bonusInc: 5},
+                                   Level{time: 300,  score: 50, count: 6, bonusInc: 10},
+                                   Level{time: 250,  score: 60, count: 6, bonusInc: 20},
+                                   Level{time: 200,  score: 70, count: 4, bonusInc: 20}];
+  
+  // the level table used when the config file doesn't have one
+  pub fn default_levels() -> Vec<Level> {
+    Vec::from_slice(levels)
+  }
+  
+  // The table is carried on past its last level by a formula, up to maxLevel: each level
+  // after it is a tenth faster than the one before, until pieces fall a row a millisecond
+  // (which is as good as all at once), and clears are worth extendedScoreStep more points.
+  // Level 0 (NES style) is played like level 1.
+  pub static maxLevel: u16 = 999;
+  static extendedSpeedUp: f64 = 0.9;
+  static extendedScoreStep: int = 10;
+  
+  pub fn get_level(levels: &[Level], level: u16) -> Level {
+    let count = levels.len() as u16;
+    if level == 0 {
+      return levels[0];
+    }
+    if level <= count {
+      return levels[level - 1];
+    }
+    let last = levels[count - 1];
+    let extra = level - count;
+    let time = (last.time as f64 * extendedSpeedUp.powi(extra as i32)) as c_int;
+    Level{time:     if time < 1 { 1 } else { time },
+          score:    last.score + extendedScoreStep * extra as int,
+          count:    last.count,
+          bonusInc: last.bonusInc}
+  }
+  
+  #[deriving(Encodable, Decodable)]
+  pub struct Score {
+    level:    u16,
+    bonus:    int,
+    score:    i64,
+    lines:    int,
+    tetrises: int
+  }
+  
+  // What happened to the score when a piece was set
+  pub struct ScoreEvent {
+    score:       Score, // the score after the update
+    points:      i64,   // points added to the score
+    rows:        int,   // rows completed by the piece
+    levelUp:     bool,  // moved on to the next level
+    bonusChange: int    // how much the bonus went up (or down, if negative)
+  }
+  
+  // Points times a multiplier (the bonus or the level), added to a score. Long games can
+  // run up scores too big for an i64, so they stop at the biggest one rather than
+  // wrapping round to a negative score.
+  pub fn add_points(score: i64, points: int, times: int) -> i64 {
+    (points as i64).checked_mul(&(times as i64))
+      .and_then(|points| score.checked_add(&points))
+      .unwrap_or(i64::MAX)
+  }
+  
+  pub fn score_event(before: Score, after: Score, rows: int) -> ScoreEvent {
+    ScoreEvent {
+      score:       after,
+      points:      after.score - before.score,
+      rows:        rows,
+      levelUp:     after.level > before.level,
+      bonusChange: after.bonus - before.bonus
+    }
+  }
+  
+  pub trait Scoring {
+    fn get_score(&self) -> Score;
+    fn update(&mut self, setRows: int) -> ScoreEvent;
+    
+    // the falling piece was dropped by the player rather than by gravity
+    fn update_drop(&mut self, _rows: int, _hard: bool) -> Score {
+      self.get_score()
+    }
+    
+    // how many times rows have been cleared on this level, for scoring that goes up
+    // a level after a number of clears rather than lines (it isn't part of the score)
+    fn get_count(&self) -> int {
+      0
+    }
+    
+    // carry on from a score and count saved by an earlier game
+    fn restore(&mut self, score: Score, count: int);
+    
+    // the player asked for a hint, so the bonus is lost
+    fn use_hint(&mut self) -> Score {
+      self.get_score()
+    }
+  }
+  
+  pub enum ScoringKind {
+    Standard, Guideline, Nes
+  }
+  
+  pub fn kind_from_name(name: &str) -> Option<ScoringKind> {
+    match name {
+      "standard" | "std" => Some(Standard),
+      "guideline"        => Some(Guideline),
+      "nes"              => Some(Nes),
+      _                  => None
+    }
+  }
+  
+  // How many pieces can be set without completing any rows before the standard scoring's
+  // bonus goes down by one, or None for a bonus that never goes down
+  pub static defaultBonusDecay: Option<int> = Some(1);
+  
+  pub fn new(kind: ScoringKind, levels: Vec<Level>, bonusDecay: Option<int>) -> ~Scoring {
+    match kind {
+      Standard  => ~StdScoring{levels:     levels,
+                               level:      1,
+                               score:      0,
+                               bonus:      1,
+                               count:      0,
+                               bonusDecay: bonusDecay,
+                               bonusDrop:  bonusDecay.unwrap_or(0),
+                               lines:      0,
+                               tetrises:   0} as ~Scoring,
+      
+      Guideline => ~GuidelineScoring{level:    1,
+                                     score:    0,
+                                     lines:    0,
+                                     tetrises: 0} as ~Scoring,
+      
+      Nes       => ~NesScoring{level:    0,
+                               score:    0,
+                               lines:    0,
+                               tetrises: 0} as ~Scoring
+    }
+  }
+  
+  struct StdScoring {
+    levels:     Vec<Level>,
+    level:      u16,
+    score:      i64,
+    bonus:      int,
+    count:      int,
+    bonusDecay: Option<int>,
+    bonusDrop:  int,  // pieces left to set without completing rows before the bonus goes down
+    lines:      int,
+    tetrises:   int
+  }
+  
+  impl StdScoring {
+    fn reset_bonus_drop(&mut self) {
+      self.bonusDrop = self.bonusDecay.unwrap_or(0);
+    }
+    
+    fn update_some_set_rows(&mut self, setRows: int) -> Score {
+      let level = get_level(self.levels.as_slice(), self.level);
+      
+      let baseScore = 10 * (1 << (setRows - 1));
+      let levelScore = level.score;
+      
+      self.score = add_points(self.score, baseScore + levelScore, self.bonus);
+      
+      self.lines += setRows;
+      if setRows == 4 {
+        self.tetrises += 1;
+      }
+      
+      self.count += 1;
+      
+      // add to the bonus when a level is cleared
+      let bonusInc = if self.count > level.count { level.bonusInc } else { 0 };
+      
+      if self.count > level.count {
+        if self.level < maxLevel {
+          self.level += 1;
+        }
+        self.count = 0;
+      }
+      
+      if self.bonus == 1 {
+        self.bonus = 2 * setRows;
+      } else {
+        self.bonus += 2 * setRows;
+      }
+      self.bonus += bonusInc;
+      
+      self.reset_bonus_drop();
+      
+      self.get_score()
+    }
+    
+    fn update_no_set_rows(&mut self) -> Score {
+      if self.bonus > 1 && self.bonusDecay.is_some() {
+        self.bonusDrop -= 1;
+        if self.bonusDrop == 0 {
+          self.bonus -= 1;
+          self.reset_bonus_drop();
+        }
+      }
+      self.get_score()
+    }
+  }
+  
+  impl Scoring for StdScoring {
+    fn get_score(&self) -> Score {
+      Score{level:    self.level,
+            bonus:    self.bonus,
+            score:    self.score,
+            lines:    self.lines,
+            tetrises: self.tetrises}
+    }
+    
+    fn update(&mut self, setRows: int) -> ScoreEvent {
+      let before = self.get_score();
+      let after = if setRows > 0 {
+        self.update_some_set_rows(setRows)
+      } else {
+        self.update_no_set_rows()
+      };
+      score_event(before, after, setRows)
+    }
+    
+    fn use_hint(&mut self) -> Score {
+      self.bonus = 1;
+      self.reset_bonus_drop();
+      self.get_score()
+    }
+    
+    fn get_count(&self) -> int {
+      self.count
+    }
+    
+    fn restore(&mut self, score: Score, count: int) {
+      self.level = score.level;
+      self.score = score.score;
+      self.bonus = score.bonus;
+      self.lines = score.lines;
+      self.tetrises = score.tetrises;
+      self.count = count;
+      self.reset_bonus_drop();
+    }
+  }
+  
+  // Scoring following the Tetris Guideline: line clears are worth 100/300/500/800 points
+  // times the level, dropping a piece is worth 1 point per row (soft) or 2 points per row (hard),
+  // and the level goes up every 10 lines.
+  struct GuidelineScoring {
+    level:    u16,
+    score:    i64,
+    lines:    int,
+    tetrises: int
+  }
+  
+  static guidelineLineScores: [int, ..5] = [0, 100, 300, 500, 800];
+  static guidelineLinesPerLevel: int = 10;
+  
+  impl Scoring for GuidelineScoring {
+    fn get_score(&self) -> Score {
+      Score{level:    self.level,
+            bonus:    1,
+            score:    self.score,
+            lines:    self.lines,
+            tetrises: self.tetrises}
+    }
+    
+    fn update(&mut self, setRows: int) -> ScoreEvent {
+      let before = self.get_score();
+      if setRows > 0 {
+        self.score = add_points(self.score, guidelineLineScores[setRows], self.level as int);
+        self.lines += setRows;
+        if setRows == 4 {
+          self.tetrises += 1;
+        }
+        self.level = 1 + (self.lines / guidelineLinesPerLevel) as u16;
+      }
+      score_event(before, self.get_score(), setRows)
+    }
+    
+    fn update_drop(&mut self, rows: int, hard: bool) -> Score {
+      self.score = add_points(self.score, rows, if hard { 2 } else { 1 });
+      self.get_score()
+    }
+    
+    fn restore(&mut self, score: Score, _count: int) {
+      self.level = score.level;
+      self.score = score.score;
+      self.lines = score.lines;
+      self.tetrises = score.tetrises;
+    }
+  }
+  
+  // Scoring from NES Tetris: levels start at 0, line clears are worth
+  // 40/100/300/1200 points times (level + 1), and the level goes up every 10 lines.
+  struct NesScoring {
+    level:    u16,
+    score:    i64,
+    lines:    int,
+    tetrises: int
+  }
+  
+  static nesLineScores: [int, ..5] = [0, 40, 100, 300, 1200];
+  static nesLinesPerLevel: int = 10;
+  
+  impl Scoring for NesScoring {
+    fn get_score(&self) -> Score {
+      Score{level:    self.level,
+            bonus:    1,
+            score:    self.score,
+            lines:    self.lines,
+            tetrises: self.tetrises}
+    }
+    
+    fn update(&mut self, setRows: int) -> ScoreEvent {
+      let before = self.get_score();
+      if setRows > 0 {
+        self.score = add_points(self.score, nesLineScores[setRows], self.level as int + 1);
+        self.lines += setRows;
+        if setRows == 4 {
+          self.tetrises += 1;
+        }
+        self.level = (self.lines / nesLinesPerLevel) as u16;
+      }
+      score_event(before, self.get_score(), setRows)
+    }
+    
+    fn restore(&mut self, score: Score, _count: int) {
+      self.level = score.level;
+      self.score = score.score;
+      self.lines = score.lines;
+      self.tetrises = score.tetrises;
+    }
+  }
+}
+
+pub mod gravity {
+  use std::f64;
+  use std::libc::c_int;
+  use std::vec_ng::Vec;
+  use board;
+  use scoring;
+  use scoring::Level;
+  
+  // the shortest time between drop steps, about a frame at 60 frames per second
+  pub static minStepTime: c_int = 16;
+  
+  // How fast pieces fall at a given level, in rows per second. The engine steps the game
+  // every step_time milliseconds and moves the piece rows_per_step rows each time: a row
+  // per step when that's no faster than a frame, otherwise a step every frame that moves
+  // the piece more than one row. The part of a row that's left over is carried on to the
+  // next step, so speeds between whole rows per frame come out right on average.
+  pub trait Gravity {
+    fn rows_per_second(&self, level: u16) -> f64;
+    
+    fn step_time(&self, level: u16) -> c_int {
+      let time = 1000.0 / self.rows_per_second(level);
+      if time < minStepTime as f64 { minStepTime } else { time.round() as c_int }
+    }
+    
+    fn rows_per_step(&self, level: u16) -> f64 {
+      let time = 1000.0 / self.rows_per_second(level);
+      if time < minStepTime as f64 { minStepTime as f64 / time } else { 1.0 }
+    }
+  }
+  
+  pub enum GravityKind {
+    LevelTable, NesCurve, Zen, TwentyG
+  }
+  
+  pub fn kind_from_name(name: &str) -> Option<GravityKind> {
+    match name {
+      "levels" => Some(LevelTable),
+      "nes"    => Some(NesCurve),
+      "zen"    => Some(Zen),
+      "20g"    => Some(TwentyG),
+      _        => None
+    }
+  }
+  
+  pub fn new(kind: GravityKind, levels: &[Level]) -> ~Gravity {
+    let levels = Vec::from_slice(levels);
+    match kind {
+      LevelTable => ~LevelGravity{levels: levels} as ~Gravity,
+      NesCurve   => ~NesGravity as ~Gravity,
+      Zen        => ~FixedGravity{time: zenStepTime} as ~Gravity,
+      TwentyG    => ~InstantGravity{levels: LevelGravity{levels: levels}} as ~Gravity
+    }
+  }
+  
+  // Another gravity sped up or slowed down by a multiplier, whatever the level: a speed
+  // of 2.0 drops pieces twice as often, 0.5 half as often
+  pub fn scaled(gravity: ~Gravity, speed: f64) -> ~Gravity {
+    ~ScaledGravity{gravity: gravity, speed: speed} as ~Gravity
+  }
+  
+  struct ScaledGravity {
+    gravity: ~Gravity,
+    speed:   f64
+  }
+  
+  // the steps are sped up until they're a frame apart, then the piece falls further on each one
+  impl Gravity for ScaledGravity {
+    fn rows_per_second(&self, level: u16) -> f64 {
+      self.gravity.rows_per_second(level) * self.speed
+    }
+    
+    fn step_time(&self, level: u16) -> c_int {
+      let time = (self.gravity.step_time(level) as f64 / self.speed) as c_int;
+      if time < minStepTime { minStepTime } else { time }
+    }
+    
+    fn rows_per_step(&self, level: u16) -> f64 {
+      let stepTime = self.gravity.step_time(level) as f64 / self.speed;
+      self.gravity.rows_per_step(level) * self.step_time(level) as f64 / stepTime
+    }
+  }
+  
+  // Speeds taken from the level table, and carried on past its end by scoring::get_level
+  struct LevelGravity {
+    levels: Vec<Level>
+  }
+  
+  // a level's time is how long a piece takes to fall a row, so a table can be made faster
+  // than a row a frame with times under minStepTime
+  impl Gravity for LevelGravity {
+    fn rows_per_second(&self, level: u16) -> f64 {
+      1000.0 / scoring::get_level(self.levels.as_slice(), level).time as f64
+    }
+  }
+  
+  // NES gravity is counted in frames per row, at the NTSC frame rate of 60.0988 frames per second
+  struct NesGravity;
+  
+  static nesFrameMs: f64 = 16.639;
+  
+  // frames per row for levels 0 to 29, level 29 (the kill screen) drops a row every frame
+  static nesFrames: [u8, ..30] = [48, 43, 38, 33, 28, 23, 18, 13, 8, 6,
+                                  5,  5,  5,  4,  4,  4,  3,  3,  3, 2,
+                                  2,  2,  2,  2,  2,  2,  2,  2,  2, 1];
+  
+  // past the kill screen pieces fall another row a frame every nesLevelsPerRow levels
+  static nesLevelsPerRow: u16 = 10;
+  
+  impl Gravity for NesGravity {
+    fn rows_per_second(&self, level: u16) -> f64 {
+      let killScreen = nesFrames.len() as u16 - 1;
+      if level > killScreen {
+        let rows = 1 + (level - killScreen) / nesLevelsPerRow;
+        return rows as f64 * 1000.0 / nesFrameMs;
+      }
+      1000.0 / (nesFrames[level] as f64 * nesFrameMs)
+    }
+  }
+  
+  // Zen gravity never speeds up, whatever the level
+  struct FixedGravity {
+    time: c_int
+  }
+  
+  static zenStepTime: c_int = 1000;
+  
+  impl Gravity for FixedGravity {
+    fn rows_per_second(&self, _level: u16) -> f64 {
+      1000.0 / self.time as f64
+    }
+  }
+  
+  // 20G: the piece falls all the way down on every step, with the steps as far apart as
+  // the level table says, so there's time to move it before it's set
+  struct InstantGravity {
+    levels: LevelGravity
+  }
+  
+  impl Gravity for InstantGravity {
+    fn rows_per_second(&self, _level: u16) -> f64 {
+      f64::INFINITY
+    }
+    
+    fn step_time(&self, level: u16) -> c_int {
+      self.levels.step_time(level)
+    }
+    
+    fn rows_per_step(&self, _level: u16) -> f64 {
+      board::height as f64
+    }
+  }
+}
+
+pub mod engine {
+  use std::libc::c_int;
+  use std::vec_ng::Vec;
+  use pieces;
+  use pieces::{Block, Piece, PieceType, Gray};
+  use board;
+  use board::Board;
+  use piece_getter::PieceGetter;
+  use scoring;
+  use scoring::{Scoring, ScoreEvent};
+  use gravity::Gravity;
+
+  // Entry is the wait after a piece is set (and any rows taken out) before the next one
+  // comes in, when there's a spawn delay
+  #[deriving(Eq)]
+  pub enum State {
+    Fall = 0, Clear, Entry, Finished
+  }
+  
+  // the longest spawn delay, in milliseconds
+  pub static maxSpawnDelay: c_int = 500;
+  
+  // what can be done with the falling piece
+  #[deriving(Eq)]
+  pub enum Move {
+    RotateClockwise, RotateCounterClockwise, ShiftLeft, ShiftRight, HardDrop
+  }
+  
+  // What changed in the game, in the order it happened, for the display and anything
+  // else listening in (sounds, bots, ...)
+  pub enum GameEvent {
+    PieceMoved(Piece, Piece),                // the falling piece moved from the first place to the second
+    PieceLocked(Piece),                      // the falling piece was set into the board
+    NextPieceChanged(Piece, Piece),          // the old next piece came in, and the new one is next
+    RowsCleared(Vec<i8>),                    // these rows were filled, the rows above drop down on the next step
+    BoardChanged(Board),                     // the set blocks moved, this is where they were before
+    LevelUp(u16),                            // the game moved on to this level
+    ScoreChanged(ScoreEvent),                // the score changed, from setting or dropping a piece
+    GameOver                                 // the board filled up, or the mode's goal was reached
+  }
+  
+  // The rules of the game and nothing else: no drawing, and no clock. Whoever uses it
+  // steps it when stepTime is up, and shows the events it gets back.
+  pub struct GameEngine {
+    pieceGetter: ~PieceGetter,
+    scoring:     ~Scoring,
+    gravity:     ~Gravity,
+    state:       State,
+    piece:       Piece,
+    nextPiece:   Piece,
+    board:       Board,
+    pieces:      uint,  // pieces set so far
+    stepTime:    c_int, // milliseconds until the next step is due
+    spawnDelay:  c_int, // milliseconds of Entry after a piece is set, 0 for none
+    fallCarry:   f64    // the part of a row the falling piece has fallen but not moved yet
+  }
+  
+  pub fn new(mut pieceGetter: ~PieceGetter, scoring: ~Scoring, gravity: ~Gravity) -> GameEngine {
+    let piece = pieceGetter.next_piece();
+    let nextPiece = pieceGetter.next_piece();
+    let stepTime = gravity.step_time(scoring.get_score().level);
+    GameEngine{pieceGetter: pieceGetter,
+               scoring:     scoring,
+               gravity:     gravity,
+               state:       Fall,
+               piece:       piece,
+               nextPiece:   nextPiece,
+               board:       board::new(),
+               pieces:      0,
+               stepTime:    stepTime,
+               spawnDelay:  0,
+               fallCarry:   0.0}
+  }
+  
+  impl GameEngine {
+    fn in_bounds_bottom_row(piece: &Piece) -> bool {
+      piece.blocks.iter().all(|block| block.row <= 20)
+    }
+    
+    fn in_bounds_cols(piece: &Piece) -> bool {
+      piece.blocks.iter().all(|block| block.column >= 1 && block.column <= 10)
+    }
+    
+    fn all_in_bounds(piece: &Piece) -> bool {
+      piece.blocks.iter().all(|block| block.row >= 1 && block.row <= 20 && block.column >= 1 && block.column <= 10)
+    }
+    
+    fn can_move_rows(&self, piece: &Piece, rowOffset: i8) -> bool {
+      let moved =  pieces::translate(piece, rowOffset, 0);
+      return GameEngine::in_bounds_bottom_row(&moved) && !self.board.collides(&moved);
+    }
+
+    // fraction of the board covered by set blocks
+    pub fn board_density(&self) -> f64 {
+      self.board.block_count() as f64 / (board::height as f64 * board::width as f64)
+    }
+    
+    fn set_piece(&mut self) {
+      for block in self.piece.blocks.iter() {
+        self.board.set(*block);
+      }
+    }
+    
+    // The rows the piece falls on this step, at least one, with the part of a row left
+    // over kept for the next step
+    fn rows_to_fall(&mut self, level: u16) -> i8 {
+      let fall = self.fallCarry + self.gravity.rows_per_step(level);
+      if fall < 1.0 {
+        self.fallCarry = 0.0;
+        return 1;
+      }
+      if fall >= board::height as f64 {
+        self.fallCarry = 0.0;
+        return board::height;
+      }
+      self.fallCarry = fall - fall.floor();
+      fall.floor() as i8
+    }
+    
+    // The falling piece drops as far as gravity takes it in one move, or is set if it
+    // can't drop at all
+    fn step_fall(&mut self) -> Vec<GameEvent> {
+      let level = self.scoring.get_score().level;
+      if self.can_move_rows(&self.piece, 1) {
+        let fall = self.rows_to_fall(level);
+        let mut translated = pieces::translate(&self.piece, 1, 0);
+        let mut rows = 1;
+        while rows < fall && self.can_move_rows(&translated, 1) {
+          translated = pieces::translate(&translated, 1, 0);
+          rows += 1;
+        }
+        let from = self.piece;
+        self.piece = translated;
+        self.stepTime = self.gravity.step_time(level);
+        return vec!(PieceMoved(from, translated));
+      }
+      
+      if !GameEngine::all_in_bounds(&self.piece) {
+        self.state = Finished;
+        self.stepTime = 500;
+        return vec!(GameOver);
+      }
+      
+      let mut events = Vec::new();
+      self.set_piece();
+      self.pieces += 1;
+      self.fallCarry = 0.0;
+      events.push(PieceLocked(self.piece));
+      
+      let oldNext = self.nextPiece;
+      self.piece = self.nextPiece;
+      self.nextPiece = self.pieceGetter.next_piece();
+      events.push(NextPieceChanged(oldNext, self.nextPiece));
+      
+      let setRows = self.board.full_rows();
+      let count = setRows.len() as int;
+      if count > 0 {
+        self.state = Clear;
+        events.push(RowsCleared(setRows));
+      }
+      let scoreEvent = self.scoring.update(count);
+      events.push(ScoreChanged(scoreEvent));
+      if scoreEvent.levelUp {
+        events.push(LevelUp(scoreEvent.score.level));
+      }
+      self.stepTime = 1000;
+      if count == 0 {
+        self.enter();
+      }
+      events
+    }
+    
+    // with a spawn delay, waits that long before the next piece comes in
+    fn enter(&mut self) {
+      if self.spawnDelay > 0 {
+        self.state = Entry;
+        self.stepTime = self.spawnDelay;
+      }
+    }
+    
+    // Moves the game on by one step: the falling piece drops, or is set once it can't,
+    // or the rows filled by the last piece are taken out, or the spawn delay is over and
+    // the next piece comes straight in
+    pub fn step(&mut self) -> Vec<GameEvent> {
+      match self.state {
+        Fall     => self.step_fall(),
+        Clear    => {
+          let previous = self.board;
+          self.board.clear_full_rows();
+          self.state = Fall;
+          self.stepTime = 1000;
+          self.enter();
+          vec!(BoardChanged(previous))
+        }
+        Entry    => {
+          self.state = Fall;
+          self.step_fall()
+        }
+        Finished => Vec::new()
+      }
+    }
+    
+    fn hard_drop(&mut self) -> Vec<GameEvent> {
+      if !self.can_move_rows(&self.piece, 1) {
+        return Vec::new();
+      }
+      
+      let mut translated = pieces::translate(&self.piece, 1, 0);
+      let mut rows = 1;
+      while self.can_move_rows(&translated, 1) {
+        translated = pieces::translate(&translated, 1, 0);
+        rows += 1;
+      }
+      let from = self.piece;
+      self.piece = translated;
+      let before = self.scoring.get_score();
+      let after = self.scoring.update_drop(rows, true);
+      vec!(PieceMoved(from, translated), ScoreChanged(scoring::score_event(before, after, 0)))
+    }
+    
+    // Moves the falling piece, if there's room for it. Nothing happens if there isn't,
+    // or while waiting for the piece to come in.
+    pub fn apply_action(&mut self, action: Move) -> Vec<GameEvent> {
+      if self.state == Finished || self.state == Entry {
+        return Vec::new();
+      }
+      let moved = match action {
+        RotateClockwise        => pieces::rotate_clockwise(&self.piece),
+        RotateCounterClockwise => pieces::rotate_counter_clockwise(&self.piece),
+        ShiftLeft              => pieces::translate(&self.piece, 0, -1),
+        ShiftRight             => pieces::translate(&self.piece, 0, 1),
+        HardDrop               => return self.hard_drop()
+      };
+      
+      if !GameEngine::in_bounds_cols(&moved) || self.board.collides(&moved) {
+        return Vec::new();
+      }
+      let from = self.piece;
+      self.piece = moved;
+      vec!(PieceMoved(from, moved))
+    }
+    
+    // Pushes the set blocks up one row and fills the bottom row with garbage, leaving a gap
+    // in column gap. The game is over if that pushes blocks off the top.
+    pub fn add_garbage_row(&mut self, gap: i8) -> Vec<GameEvent> {
+      let previous = self.board;
+      let toppedOut = self.board.push_up();
+      
+      for col in range(1, board::width + 1) {
+        if col != gap {
+          self.board.set(Block{row: board::height, column: col, color: Gray, ty: None});
+        }
+      }
+      
+      let mut events = vec!(BoardChanged(previous));
+      
+      // the falling piece gets pushed up along with everything else if it's in the way
+      if self.board.collides(&self.piece) {
+        let from = self.piece;
+        self.piece = pieces::translate(&self.piece, -1, 0);
+        events.push(PieceMoved(from, self.piece));
+      }
+      
+      if toppedOut {
+        self.state = Finished;
+        events.push(GameOver);
+      }
+      events
+    }
+    
+    // Swaps the falling piece for a new one of type ty, back at the top of the board,
+    // for practicing with the pieces of your choice
+    pub fn swap_piece(&mut self, ty: PieceType) -> Vec<GameEvent> {
+      if self.state != Fall {
+        return Vec::new();
+      }
+      let from = self.piece;
+      self.piece = pieces::new(ty);
+      vec!(PieceMoved(from, self.piece))
+    }
+    
+    // takes every set block off the board
+    pub fn clear_board(&mut self) -> Vec<GameEvent> {
+      if self.state == Finished {
+        return Vec::new();
+      }
+      let previous = self.board;
+      self.board = board::new();
+      vec!(BoardChanged(previous))
+    }
+    
+    // ends the game early, for modes that finish on something other than topping out
+    pub fn end(&mut self) -> Vec<GameEvent> {
+      if self.state == Finished {
+        return Vec::new();
+      }
+      self.state = Finished;
+      vec!(GameOver)
+    }
+  }
+}
+
+// A C interface to the engine, for frontends that aren't written in Rust, like a web page
+// drawing the game in an xterm.js terminal with this library compiled for the browser:
+// the page keeps the game from tetris_new, calls tetris_tick from a timer with the
+// milliseconds gone by, passes key presses to tetris_input and reads the board back
+// with tetris_cell. Nothing in the library touches the terminal or the clock (the
+// termios, poll and signal handling all stay in tetris1.rs), so this is all it needs.
+pub mod web {
+  use std::cast;
+  use std::i32;
+  use std::libc::c_int;
+  use board;
+  use piece_getter;
+  use scoring;
+  use gravity;
+  use engine;
+  use engine::{GameEngine, Finished, RotateClockwise, RotateCounterClockwise, ShiftLeft, ShiftRight, HardDrop};
+
+  pub struct WebGame {
+    engine:  GameEngine,
+    untilMs: c_int  // milliseconds left until the next step
+  }
+  
+  // starts a game with the standard scoring and speeds
+  #[no_mangle]
+  pub extern "C" fn tetris_new(seed: u64) -> *mut WebGame {
+    let levels = scoring::default_levels();
+    let gravity = gravity::new(gravity::LevelTable, levels.as_slice());
+    let engine = engine::new(piece_getter::new(seed), scoring::new(scoring::Standard, levels, scoring::defaultBonusDecay), gravity);
+    let untilMs = engine.stepTime;
+    unsafe { cast::transmute(~WebGame{engine: engine, untilMs: untilMs}) }
+  }
+  
+  #[no_mangle]
+  pub extern "C" fn tetris_free(game: *mut WebGame) {
+    let _game: ~WebGame = unsafe { cast::transmute(game) };
+  }
+  
+  // Steps the game for the time gone by since the last call. Returns 1 if anything
+  // changed and the board needs drawing again, 0 if not.
+  #[no_mangle]
+  pub extern "C" fn tetris_tick(game: *mut WebGame, elapsedMs: c_int) -> c_int {
+    let game = unsafe { &mut *game };
+    let mut changed = 0;
+    game.untilMs -= elapsedMs;
+    while game.untilMs <= 0 && game.engine.state != Finished {
+      if !game.engine.step().is_empty() {
+        changed = 1;
+      }
+      game.untilMs += game.engine.stepTime;
+    }
+    changed
+  }
+  
+  // Key codes: 0 rotates clockwise, 1 counter-clockwise, 2 moves left, 3 right and 4 drops.
+  // Returns 1 if the piece moved, 0 if it couldn't (or the key isn't one of these).
+  #[no_mangle]
+  pub extern "C" fn tetris_input(game: *mut WebGame, key: c_int) -> c_int {
+    let action = match key {
+      0 => RotateClockwise,
+      1 => RotateCounterClockwise,
+      2 => ShiftLeft,
+      3 => ShiftRight,
+      4 => HardDrop,
+      _ => return 0
+    };
+    let game = unsafe { &mut *game };
+    if game.engine.apply_action(action).is_empty() {0} else {1}
+  }
+  
+  // What to draw at a cell (rows 1 to 20 from the top, columns 1 to 10), counting the
+  // falling piece: 0 if it's empty, otherwise 1 + the block's Color (so 9 is garbage)
+  #[no_mangle]
+  pub extern "C" fn tetris_cell(game: *WebGame, row: c_int, col: c_int) -> c_int {
+    if row < 1 || row > board::height as c_int || col < 1 || col > board::width as c_int {
+      return 0;
+    }
+    let game = unsafe { &*game };
+    let (row, col) = (row as i8, col as i8);
+    for block in game.engine.piece.blocks.iter() {
+      if block.row == row && block.column == col {
+        return 1 + block.color as c_int;
+      }
+    }
+    match game.engine.board.get(row, col) {
+      Some(block) => 1 + block.color as c_int,
+      None        => 0
+    }
+  }
+  
+  // scores too big for a C int come back as the biggest one
+  #[no_mangle]
+  pub extern "C" fn tetris_score(game: *WebGame) -> c_int {
+    let score = unsafe { (*game).engine.scoring.get_score().score };
+    if score > i32::MAX as i64 { i32::MAX } else { score as c_int }
+  }
+  
+  #[no_mangle]
+  pub extern "C" fn tetris_lines(game: *WebGame) -> c_int {
+    unsafe { (*game).engine.scoring.get_score().lines as c_int }
+  }
+  
+  #[no_mangle]
+  pub extern "C" fn tetris_level(game: *WebGame) -> c_int {
+    unsafe { (*game).engine.scoring.get_score().level as c_int }
+  }
+  
+  // 1 once the board has filled up
+  #[no_mangle]
+  pub extern "C" fn tetris_is_over(game: *WebGame) -> c_int {
+    unsafe { if (*game).engine.state == Finished {1} else {0} }
+  }
+}